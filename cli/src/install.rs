@@ -12,8 +12,8 @@ use reqwest::Client;
 use serde_json::Value;
 use tracing::{debug, info, trace};
 use url::Url;
-use pact_plugin_driver::download::{download_json_from_github, download_plugin_executable};
-use pact_plugin_driver::repository::fetch_repository_index;
+use pact_plugin_driver::download::{download_plugin_executable_from, fetch_plugin_manifest};
+use pact_plugin_driver::repository::{fetch_repository_index, mirrored_plugin_url};
 
 use crate::{find_plugin, resolve_plugin_dir};
 use crate::repository::{APP_USER_AGENT, DEFAULT_INDEX};
@@ -38,7 +38,11 @@ pub fn install_plugin(
 
     let install_url = Url::parse(source.as_str());
     if let Ok(install_url) = install_url {
-      install_plugin_from_url(&http_client, install_url.as_str(), override_prompt, skip_if_installed,skip_load).await
+      if install_url.scheme() == "file" {
+        install_plugin_from_mirror(&http_client, install_url.as_str(), override_prompt, skip_if_installed, skip_load).await
+      } else {
+        install_plugin_from_url(&http_client, install_url.as_str(), override_prompt, skip_if_installed,skip_load).await
+      }
     } else {
       install_known_plugin(&http_client, source.as_str(), override_prompt, skip_if_installed, version, skip_load).await
     }
@@ -67,7 +71,11 @@ async fn install_known_plugin(
       entry.latest_version.as_str()
     };
     if let Some(version_entry) = entry.versions.iter().find(|v| v.version == version) {
-      install_plugin_from_url(http_client, version_entry.source.value().as_str(), override_prompt, skip_if_installed,skip_load).await
+      if let Some(url) = mirrored_plugin_url(name, version) {
+        install_plugin_from_mirror(http_client, url.as_str(), override_prompt, skip_if_installed, skip_load).await
+      } else {
+        install_plugin_from_url(http_client, version_entry.source.value().as_str(), override_prompt, skip_if_installed,skip_load).await
+      }
     } else {
       Err(anyhow!("'{}' is not a valid version for plugin '{}'", version, name))
     }
@@ -94,36 +102,12 @@ async fn install_plugin_from_url(
         let suffix = format!("/tag/{}", tag);
         source_url.strip_suffix(suffix.as_str()).unwrap_or(source_url)
       };
-      let manifest_json = download_json_from_github(http_client, url, &tag, "pact-plugin.json")
+      let location = format!("{}/download/{}", url, tag);
+      let manifest = fetch_plugin_manifest(http_client, location.as_str())
         .await.context("Downloading manifest file from GitHub")?;
-      let manifest: PactPluginManifest = serde_json::from_value(manifest_json)
-        .context("Parsing JSON manifest file from GitHub")?;
       debug!(?manifest, "Loaded manifest from GitHub");
 
-      if !skip_if_installed || !already_installed(&manifest) {
-        println!("Installing plugin {} version {}", manifest.name, manifest.version);
-        let plugin_dir = create_plugin_dir(&manifest, override_prompt)
-          .context("Creating plugins directory")?;
-        download_plugin_executable(&manifest, &plugin_dir, http_client, url, &tag, true).await?;
-
-        env::set_var("pact_do_not_track", "true");
-        if !skip_load {
-          load_plugin(&manifest.as_dependency())
-          .await
-          .and_then(|plugin| {
-            println!("Installed plugin {} version {} OK", manifest.name, manifest.version);
-            if let Some(plugin) = lookup_plugin(&plugin.manifest.as_dependency()) {
-              plugin.kill();
-            }
-            Ok(())
-          })
-        } else {
-          Ok(())
-        }
-      } else {
-        println!("Skipping installing plugin {} version {} as it is already installed", manifest.name, manifest.version);
-        Ok(())
-      }
+      install_manifest(http_client, &manifest, location.as_str(), override_prompt, skip_if_installed, skip_load).await
     } else {
       bail!("GitHub release page does not have a valid tag_name attribute");
     }
@@ -132,6 +116,57 @@ async fn install_plugin_from_url(
   }
 }
 
+/// Installs a plugin from a directory of a plugin repository mirror. The URL can be http(s) or file.
+async fn install_plugin_from_mirror(
+  http_client: &Client,
+  url: &str,
+  override_prompt: bool,
+  skip_if_installed: bool,
+  skip_load: bool
+) -> anyhow::Result<()> {
+  let url = url.trim_end_matches('/');
+  let manifest = fetch_plugin_manifest(http_client, url).await?;
+  debug!(?manifest, "Loaded manifest from {}", url);
+
+  install_manifest(http_client, &manifest, url, override_prompt, skip_if_installed, skip_load).await
+}
+
+/// Installs the plugin files from the location (URL of the directory holding them), and then loads
+/// the plugin to check it
+async fn install_manifest(
+  http_client: &Client,
+  manifest: &PactPluginManifest,
+  location: &str,
+  override_prompt: bool,
+  skip_if_installed: bool,
+  skip_load: bool
+) -> anyhow::Result<()> {
+  if !skip_if_installed || !already_installed(manifest) {
+    println!("Installing plugin {} version {}", manifest.name, manifest.version);
+    let plugin_dir = create_plugin_dir(manifest, override_prompt)
+      .context("Creating plugins directory")?;
+    download_plugin_executable_from(manifest, &plugin_dir, http_client, location, true).await?;
+
+    env::set_var("pact_do_not_track", "true");
+    if !skip_load {
+      load_plugin(&manifest.as_dependency())
+      .await
+      .and_then(|plugin| {
+        println!("Installed plugin {} version {} OK", manifest.name, manifest.version);
+        if let Some(plugin) = lookup_plugin(&plugin.manifest.as_dependency()) {
+          plugin.kill();
+        }
+        Ok(())
+      })
+    } else {
+      Ok(())
+    }
+  } else {
+    println!("Skipping installing plugin {} version {} as it is already installed", manifest.name, manifest.version);
+    Ok(())
+  }
+}
+
 pub(crate) async fn fetch_json_from_url(source: &str, http_client: &Client) -> anyhow::Result<Value> {
   info!(%source, "Fetching root document for source");
   let response: Value = http_client.get(source)
@@ -9,6 +9,7 @@ use comfy_table::presets::UTF8_FULL;
 use comfy_table::Table;
 use itertools::Itertools;
 use pact_plugin_driver::plugin_models::PactPluginManifest;
+use pact_plugin_driver::repository::{repository_url, set_repository_url};
 use requestty::OnEsc;
 use tracing::{error, Level};
 use tracing_subscriber::FmtSubscriber;
@@ -18,6 +19,7 @@ use crate::list::{list_plugins, plugin_list};
 mod install;
 mod repository;
 mod list;
+mod lock;
 
 #[derive(Parser, Debug)]
 #[clap(about, version)]
@@ -80,7 +82,12 @@ enum Commands {
 
     #[clap(long,env="PACT_PLUGIN_CLI_SKIP_LOAD")]
     /// Skip auto-loading of plugin
-    skip_load: bool
+    skip_load: bool,
+
+    #[clap(long,env="PACT_PLUGIN_REPOSITORY_URL")]
+    /// URL of the plugin repository to install known plugins from. This can be an internal HTTP
+    /// server or a file:// URL of a directory mirroring the repository.
+    repository_url: Option<String>
   },
 
   /// Remove a plugin
@@ -114,6 +121,20 @@ enum Commands {
     version: Option<String>
   },
 
+  /// Create or update the plugin lock file
+  ///
+  /// The lock file records the exact version of the plugins used, so that the plugin driver will
+  /// load those versions. Each plugin already in the lock file is updated to the highest compatible
+  /// version that is installed.
+  Lock {
+    #[clap(short, long, env = "PACT_PLUGIN_LOCK_FILE", default_value = "pact-plugins.lock")]
+    /// Lock file to update
+    file: PathBuf,
+
+    /// Plugins to add to the lock file, as the plugin name and optionally the required version (i.e. csv:0.0.3)
+    plugins: Vec<String>
+  },
+
   /// Sub-commands for dealing with a plugin repository
   #[command(subcommand)]
   Repository(RepositoryCommands)
@@ -243,12 +264,16 @@ pub fn handle_matches(cli: &Cli) -> Result<(), ExitCode> {
   let result = match &cli.command {
     Commands::List(command) => list_plugins(command),
     Commands::Env => print_env(),
-    Commands::Install { yes, skip_if_installed, source, source_type, version, skip_load } => {
+    Commands::Install { yes, skip_if_installed, source, source_type, version, skip_load, repository_url } => {
+      if let Some(url) = repository_url {
+        set_repository_url(url);
+      }
       install::install_plugin(source, source_type, *yes || cli.yes, *skip_if_installed, version, *skip_load)
     },
     Commands::Remove { yes, name, version } => remove_plugin(name, version, *yes || cli.yes),
     Commands::Enable { name, version } => enable_plugin(name, version),
     Commands::Disable { name, version } => disable_plugin(name, version),
+    Commands::Lock { file, plugins } => lock::update_lock_file(file, plugins),
     Commands::Repository(command) => repository::handle_command(command)
   };
 
@@ -366,11 +391,16 @@ fn print_env() -> anyhow::Result<()> {
   let mut table = Table::new();
 
   let (plugin_src, plugin_dir) = resolve_plugin_dir();
+  let repository_src = match env::var_os("PACT_PLUGIN_REPOSITORY_URL") {
+    Some(url) if !url.is_empty() => "$PACT_PLUGIN_REPOSITORY_URL",
+    _ => "default"
+  };
 
   table
     .load_style(UTF8_FULL)
     .set_header(vec!["Configuration", "Source", "Value"])
-    .add_row(vec!["Plugin Directory", plugin_src.as_str(), plugin_dir.as_str()]);
+    .add_row(vec!["Plugin Directory", plugin_src.as_str(), plugin_dir.as_str()])
+    .add_row(vec!["Plugin Repository", repository_src, repository_url().as_str()]);
 
   println!("{table}");
 
@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::Table;
+use pact_plugin_driver::lockfile::PluginLockFile;
+use pact_plugin_driver::plugin_models::PluginDependency;
+
+/// Creates or updates the lock file, locking each plugin already in it, and any given ones, to
+/// the highest compatible version that is installed
+pub fn update_lock_file(file: &PathBuf, plugins: &[String]) -> anyhow::Result<()> {
+  let mut lock_file = PluginLockFile::load(file)?;
+  let additional = plugins.iter().map(|plugin| parse_dependency(plugin)).collect::<Vec<_>>();
+  lock_file.update(&additional)?;
+  lock_file.write(file)?;
+
+  let mut table = Table::new();
+  table
+    .load_style(UTF8_FULL)
+    .set_header(vec!["Name", "Requirement", "Locked Version"]);
+  for plugin in &lock_file.plugins {
+    table.add_row(vec![
+      plugin.name.as_str(),
+      plugin.requirement.as_deref().unwrap_or("*"),
+      plugin.version.as_str()
+    ]);
+  }
+  println!("Updated {}", file.display());
+  println!("{table}");
+
+  Ok(())
+}
+
+/// Parses a plugin given as `name` or `name:version`
+fn parse_dependency(plugin: &str) -> PluginDependency {
+  let (name, version) = match plugin.split_once(':') {
+    Some((name, version)) => (name, Some(version.to_string())),
+    None => (plugin, None)
+  };
+  PluginDependency {
+    name: name.to_string(),
+    version,
+    dependency_type: Default::default()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use super::parse_dependency;
+
+  #[test]
+  fn parse_dependency_test() {
+    expect!(parse_dependency("csv").name).to(be_equal_to("csv"));
+    expect!(parse_dependency("csv").version).to(be_none());
+    expect!(parse_dependency("csv:0.0.3").name).to(be_equal_to("csv"));
+    expect!(parse_dependency("csv:0.0.3").version).to(be_some().value("0.0.3"));
+  }
+}
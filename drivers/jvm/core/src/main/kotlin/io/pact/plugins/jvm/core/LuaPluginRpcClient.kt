@@ -18,9 +18,14 @@ import java.nio.ByteBuffer
  */
 class LuaPluginRpcClient(private val engine: LuaEngine) : PactPluginRpcClient {
   override fun initPlugin(request: PluginInitRequest): PluginInitResponse {
-    val result = engine.callFunction("init", listOf(request.implementation, request.version))
+    val result = engine.callFunction("init", listOf(request.implementation, request.version, request.hostCapabilities))
+    // init can return just the catalogue entries, or { catalogue = {...}, capabilities = {...} } to
+    // also declare the plugin's capabilities. An empty table reads back as an empty Map, which is
+    // an empty catalogue either way.
     @Suppress("UNCHECKED_CAST")
-    val items = result as? List<Any?> ?: emptyList()
+    val declared = result as? Map<String, Any?>
+    val items = (declared?.get("catalogue") ?: result) as? List<Any?> ?: emptyList()
+    val capabilities = (declared?.get("capabilities") as? List<Any?>)?.map { it.toString() } ?: emptyList()
     val entries = items.map { item ->
       @Suppress("UNCHECKED_CAST")
       val map = item as Map<String, Any?>
@@ -38,7 +43,7 @@ class LuaPluginRpcClient(private val engine: LuaEngine) : PactPluginRpcClient {
         .putAllValues(values)
         .build()
     }
-    return PluginInitResponse(entries)
+    return PluginInitResponse(entries, capabilities)
   }
 
   override fun updateCatalogue(request: Plugin.Catalogue) {
@@ -71,6 +71,58 @@ class LuaPactPluginTest {
     }
   }
 
+  @Test
+  fun `init can declare capabilities based on the host capabilities`() {
+    val pluginDir = kotlin.io.path.createTempDirectory("lua-plugin-test").toFile()
+    File(pluginDir, "entry.lua").writeText(
+      """
+        function init(implementation, version, host_capabilities)
+          local capabilities = {}
+          for _, capability in ipairs(host_capabilities) do
+            if capability == "interaction/message" then
+              table.insert(capabilities, capability)
+            end
+          end
+          return {
+            catalogue = { { entryType = "TRANSPORT", key = "capabilities-test" } },
+            capabilities = capabilities
+          }
+        end
+      """.trimIndent()
+    )
+
+    val manifest = DefaultPactPluginManifest(
+      pluginDir = pluginDir,
+      pluginInterfaceVersion = 2,
+      name = "capabilities-test",
+      version = "0.0.0",
+      executableType = "lua",
+      minimumRequiredVersion = null,
+      entryPoint = "entry.lua",
+      entryPoints = emptyMap(),
+      args = emptyList(),
+      dependencies = emptyList(),
+      pluginConfig = emptyMap()
+    )
+
+    val plugin = LuaPactPlugin(manifest)
+    try {
+      val response = plugin.withRpcClient {
+        it.initPlugin(PluginInitRequest(
+          implementation = "test",
+          version = "0.0.0",
+          hostCapabilities = listOf("interaction/request-response", "interaction/message")
+        ))
+      }
+      assertEquals(1, response.catalogueEntries.size)
+      assertEquals("capabilities-test", response.catalogueEntries[0].key)
+      assertEquals(listOf("interaction/message"), response.pluginCapabilities)
+    } finally {
+      plugin.shutdown()
+      pluginDir.deleteRecursively()
+    }
+  }
+
   @Test
   fun `configure_interaction then compareContents round trip`() {
     val plugin = LuaPactPlugin(jwtManifest())
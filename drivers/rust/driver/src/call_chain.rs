@@ -9,13 +9,12 @@
 //! absolute deadline (`pact-deadline-ms` gRPC metadata) so a callback can never outlive the
 //! request that triggered it.
 
-use std::collections::HashMap;
-use std::sync::Mutex;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use lazy_static::lazy_static;
 use uuid::Uuid;
 
+use crate::driver::PluginDriver;
+
 /// gRPC metadata key carrying the call-chain ID on driver<->plugin callback requests.
 pub const CALL_CHAIN_ID_METADATA_KEY: &str = "pact-call-chain-id";
 /// gRPC metadata key carrying the absolute deadline (Unix epoch milliseconds) on driver<->plugin
@@ -26,14 +25,26 @@ pub const DEADLINE_METADATA_KEY: &str = "pact-deadline-ms";
 /// arrives with no deadline metadata (defensive default for a non-conforming plugin).
 pub const DEFAULT_CALL_CHAIN_TIMEOUT: Duration = Duration::from_secs(30);
 
-lazy_static! {
-  static ref CALL_CHAINS: Mutex<HashMap<String, Vec<String>>> = Mutex::new(HashMap::new());
-}
-
 /// Generate a new call-chain ID for the root of a driver -> plugin call that may trigger
-/// callbacks.
+/// callbacks. Chains started by a driver other than the default one are prefixed with the driver
+/// ID, so the callbacks are handled by the same driver (see [`driver_for_call_chain`]).
 pub fn new_call_chain_id() -> String {
-  Uuid::new_v4().to_string()
+  let driver = PluginDriver::current();
+  if driver.is_default() {
+    Uuid::new_v4().to_string()
+  } else {
+    format!("{}:{}", driver.id(), Uuid::new_v4())
+  }
+}
+
+/// The driver that started the call chain, falling back to the default driver if the chain ID
+/// has no driver ID prefix. Callbacks for a chain whose driver has since been dropped are handled
+/// by the default driver.
+pub(crate) fn driver_for_call_chain(chain_id: &str) -> PluginDriver {
+  chain_id.split_once(':')
+    .and_then(|(id, _)| id.parse::<u64>().ok())
+    .and_then(PluginDriver::find)
+    .unwrap_or_else(PluginDriver::default_driver)
 }
 
 /// Current time as Unix epoch milliseconds.
@@ -67,13 +78,14 @@ pub fn remaining(deadline_ms: u64) -> Duration {
 /// popped back off. Returned by [`push_call`].
 #[derive(Debug)]
 pub struct CallChainGuard {
+  driver: PluginDriver,
   chain_id: String,
   entry_key: String,
 }
 
 impl Drop for CallChainGuard {
   fn drop(&mut self) {
-    pop_call(&self.chain_id, &self.entry_key);
+    pop_call(&self.driver, &self.chain_id, &self.entry_key);
   }
 }
 
@@ -85,19 +97,22 @@ impl Drop for CallChainGuard {
 /// already on the stack - the same capability being invoked again before an earlier invocation of
 /// it has returned, i.e. a cycle - and dispatch should not proceed.
 pub fn push_call(chain_id: &str, entry_key: &str) -> Result<CallChainGuard, String> {
-  let mut chains = CALL_CHAINS.lock().expect("CALL_CHAINS mutex poisoned");
-  let stack = chains.entry(chain_id.to_string()).or_default();
-  if stack.iter().any(|key| key == entry_key) {
-    return Err(format!(
-      "Cycle detected calling '{}': already in call chain {:?}", entry_key, stack
-    ));
+  let driver = PluginDriver::current();
+  {
+    let mut chains = driver.inner.call_chains.lock().expect("CALL_CHAINS mutex poisoned");
+    let stack = chains.entry(chain_id.to_string()).or_default();
+    if stack.iter().any(|key| key == entry_key) {
+      return Err(format!(
+        "Cycle detected calling '{}': already in call chain {:?}", entry_key, stack
+      ));
+    }
+    stack.push(entry_key.to_string());
   }
-  stack.push(entry_key.to_string());
-  Ok(CallChainGuard { chain_id: chain_id.to_string(), entry_key: entry_key.to_string() })
+  Ok(CallChainGuard { driver, chain_id: chain_id.to_string(), entry_key: entry_key.to_string() })
 }
 
-fn pop_call(chain_id: &str, entry_key: &str) {
-  let mut chains = CALL_CHAINS.lock().expect("CALL_CHAINS mutex poisoned");
+fn pop_call(driver: &PluginDriver, chain_id: &str, entry_key: &str) {
+  let mut chains = driver.inner.call_chains.lock().expect("CALL_CHAINS mutex poisoned");
   if let Some(stack) = chains.get_mut(chain_id) {
     if let Some(pos) = stack.iter().rposition(|key| key == entry_key) {
       stack.remove(pos);
@@ -147,6 +162,23 @@ mod tests {
     expect!(push_call("chain-b-push_call_allows_the_same_entry_key_in_different_chains", key)).to(be_ok());
   }
 
+  #[test]
+  fn call_chains_started_by_a_driver_are_routed_back_to_it() {
+    let chain_id = new_call_chain_id();
+    expect!(driver_for_call_chain(&chain_id).is_default()).to(be_true());
+
+    let driver = PluginDriver::new(Default::default());
+    let chain_id = {
+      let _guard = driver.enter();
+      new_call_chain_id()
+    };
+    expect!(chain_id.starts_with(&format!("{}:", driver.id()))).to(be_true());
+    expect!(driver_for_call_chain(&chain_id)).to(be_equal_to(driver.clone()));
+
+    drop(driver);
+    expect!(driver_for_call_chain(&chain_id).is_default()).to(be_true());
+  }
+
   #[test]
   fn deadline_helpers_compute_expiry_and_remaining_budget() {
     let future_deadline = deadline_from(Duration::from_secs(60));
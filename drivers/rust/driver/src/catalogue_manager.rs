@@ -1,27 +1,26 @@
 //! Manages the catalogue of features provided by plugins
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
 use std::fmt::{self, Display, Formatter};
-use std::sync::Mutex;
 
+use anyhow::anyhow;
 use itertools::Itertools;
-use lazy_static::lazy_static;
 use maplit::hashset;
 use pact_models::content_types::ContentType;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::sync::broadcast;
 use tracing::{debug, error, instrument, trace, warn};
 
 use crate::content::{ContentGenerator, ContentMatcher};
+use crate::driver::PluginDriver;
 use crate::plugin_models::PactPluginManifest;
 use crate::proto::catalogue_entry::EntryType;
 use crate::proto::CatalogueEntry as ProtoCatalogueEntry;
 use crate::proto_v2::catalogue_entry::EntryType as EntryTypeV2;
 
-lazy_static! {
-  static ref CATALOGUE_REGISTER: Mutex<HashMap<String, CatalogueEntry>> = Mutex::new(HashMap::new());
-}
-
 /// Type of catalogue entry
 #[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
 #[allow(non_camel_case_types)]
@@ -191,11 +190,65 @@ pub struct CatalogueEntry {
   pub values: HashMap<String, String>
 }
 
-/// Register the entries in the global catalogue
+/// Change made to the catalogue, sent to the subscribers (see [`subscribe_to_catalogue_changes`])
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CatalogueEvent {
+  /// An entry was added to the catalogue
+  Added {
+    /// Key the entry is registered under in the catalogue (i.e. `plugin/protobuf/content-matcher/protobuf`)
+    catalogue_key: String,
+    /// The entry that was added
+    entry: CatalogueEntry
+  },
+  /// An entry was removed from the catalogue
+  Removed {
+    /// Key the entry was registered under in the catalogue
+    catalogue_key: String,
+    /// The entry that was removed
+    entry: CatalogueEntry
+  }
+}
+
+/// Number of events kept for each subscriber that has not received them yet. A subscriber that
+/// falls further behind than this misses the oldest events (see [`subscribe_to_catalogue_changes`]).
+pub const CATALOGUE_EVENT_CAPACITY: usize = 256;
+
+/// Subscribe to the changes made to the catalogue of the current driver, for example entries
+/// added when a plugin is loaded or removed when it is shut down. Replacing an entry with a
+/// different one is sent as the old entry being removed and then the new one added.
+///
+/// Events are only sent to the receivers subscribed at the time of the change. A receiver that
+/// does not keep up gets a `RecvError::Lagged` error telling it how many events it missed, after
+/// which it can re-read the catalogue with [`all_entries`].
+pub fn subscribe_to_catalogue_changes() -> broadcast::Receiver<CatalogueEvent> {
+  PluginDriver::current().inner.catalogue_events.subscribe()
+}
+
+fn publish_catalogue_events(driver: &PluginDriver, events: Vec<CatalogueEvent>) {
+  for event in events {
+    // An error just means there are no subscribers
+    let _ = driver.inner.catalogue_events.send(event);
+  }
+}
+
+impl CatalogueEntry {
+  /// Key the entry is registered under in the catalogue, i.e. `core/content-matcher/json` or
+  /// `plugin/protobuf/content-matcher/protobuf`
+  pub fn catalogue_key(&self) -> String {
+    match (&self.provider_type, &self.plugin) {
+      (CatalogueEntryProviderType::PLUGIN, Some(plugin)) => format!("plugin/{}/{}/{}", plugin.name, self.entry_type, self.key),
+      _ => format!("core/{}/{}", self.entry_type, self.key)
+    }
+  }
+}
+
+/// Register the entries in the catalogue of the current driver
 pub fn register_plugin_entries(plugin: &PactPluginManifest, catalogue_list: &Vec<ProtoCatalogueEntry>) {
   trace!("register_plugin_entries({:?}, {:?})", plugin, catalogue_list);
 
-  let mut guard = CATALOGUE_REGISTER.lock().unwrap();
+  let driver = PluginDriver::current();
+  let mut guard = driver.inner.catalogue_register.lock().unwrap();
+  let mut events = vec![];
 
   for entry in catalogue_list {
     // Deliberately reading the raw field rather than prost's `entry.r#type()` accessor: the
@@ -213,36 +266,57 @@ pub fn register_plugin_entries(plugin: &PactPluginManifest, catalogue_list: &Vec
       }
     };
     let key = format!("plugin/{}/{}/{}", plugin.name, entry_type, entry.key);
-    guard.insert(key.clone(), CatalogueEntry {
+    let entry = CatalogueEntry {
       entry_type,
       provider_type: CatalogueEntryProviderType::PLUGIN,
       plugin: Some(plugin.clone()),
       key: entry.key.clone(),
       values: entry.values.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
-    });
+    };
+    compile_content_type_patterns(&driver, &key, &entry);
+    match guard.insert(key.clone(), entry.clone()) {
+      Some(previous) if previous == entry => {}
+      Some(previous) => {
+        events.push(CatalogueEvent::Removed { catalogue_key: key.clone(), entry: previous });
+        events.push(CatalogueEvent::Added { catalogue_key: key, entry });
+      }
+      None => events.push(CatalogueEvent::Added { catalogue_key: key, entry })
+    }
   }
 
-  debug!("Updated catalogue entries:\n{}", guard.keys().sorted().join("\n"))
+  debug!("Updated catalogue entries:\n{}", guard.keys().sorted().join("\n"));
+  if !events.is_empty() {
+    clear_content_type_lookups(&driver);
+  }
+  drop(guard);
+  publish_catalogue_events(&driver, events);
 }
 
-/// Register the core Pact framework entries in the global catalogue
+/// Register the core Pact framework entries in the catalogue of the current driver
 pub fn register_core_entries(entries: &Vec<CatalogueEntry>) {
   trace!("register_core_entries({:?})", entries);
 
-  let mut inner = CATALOGUE_REGISTER.lock().unwrap();
+  let driver = PluginDriver::current();
+  let mut inner = driver.inner.catalogue_register.lock().unwrap();
 
   let mut updated_keys = hashset!();
+  let mut events = vec![];
   for entry in entries {
     let key = format!("core/{}/{}", entry.entry_type, entry.key);
     if !inner.contains_key(&key) {
+      compile_content_type_patterns(&driver, &key, entry);
       inner.insert(key.clone(), entry.clone());
       updated_keys.insert(key.clone());
+      events.push(CatalogueEvent::Added { catalogue_key: key, entry: entry.clone() });
     }
   }
 
   if !updated_keys.is_empty() {
     debug!("Updated catalogue entries:\n{}", updated_keys.iter().sorted().join("\n"));
+    clear_content_type_lookups(&driver);
   }
+  drop(inner);
+  publish_catalogue_events(&driver, events);
 }
 
 /// Lookup an entry in the catalogue by the key, matched the same way [`resolve_capability`] does:
@@ -252,7 +326,8 @@ pub fn register_core_entries(entries: &Vec<CatalogueEntry>) {
 /// `HashMap` iteration order is randomised per process. Prefer [`resolve_capability`] wherever the
 /// expected entry type is known and a deterministic answer matters.
 pub fn lookup_entry(key: &str) -> Option<CatalogueEntry> {
-  let inner = CATALOGUE_REGISTER.lock().unwrap();
+  let driver = PluginDriver::current();
+  let inner = driver.inner.catalogue_register.lock().unwrap();
   inner.iter()
     .find(|(k, _)| names_catalogue_key(k, key))
     .map(|(_, v)| v.clone())
@@ -321,7 +396,8 @@ pub fn resolve_capability(entry_key: &str, expected_type: CatalogueEntryType) ->
 /// in a matcher/generator - use this directly.
 pub fn resolve_capability_entry(entry_key: &str, expected_type: CatalogueEntryType) -> anyhow::Result<CatalogueEntry> {
   let candidates: Vec<(String, CatalogueEntry)> = {
-    let inner = CATALOGUE_REGISTER.lock().unwrap();
+    let driver = PluginDriver::current();
+    let inner = driver.inner.catalogue_register.lock().unwrap();
     inner.iter()
       .filter(|(k, _)| names_catalogue_key(k, entry_key))
       .map(|(k, v)| (k.clone(), v.clone()))
@@ -358,23 +434,31 @@ pub fn remove_plugin_entries(name: &str) {
   trace!("remove_plugin_entries({})", name);
 
   let prefix = format!("plugin/{}/", name);
-  let keys: Vec<String> = {
-    let guard = CATALOGUE_REGISTER.lock().unwrap();
-    guard.keys()
+  let driver = PluginDriver::current();
+  let events = {
+    let mut guard = driver.inner.catalogue_register.lock().unwrap();
+    let keys: Vec<String> = guard.keys()
       .filter(|key| key.starts_with(&prefix))
+      .sorted()
       .cloned()
-      .collect()
+      .collect();
+    let events = keys.into_iter()
+      .filter_map(|key| guard.remove(&key).map(|entry| CatalogueEvent::Removed { catalogue_key: key, entry }))
+      .collect::<Vec<_>>();
+    if !events.is_empty() {
+      prune_content_type_patterns(&driver, &guard);
+      clear_content_type_lookups(&driver);
+    }
+    events
   };
 
-  let mut guard = CATALOGUE_REGISTER.lock().unwrap();
-  for key in keys {
-    guard.remove(&key);
-  }
-
   debug!("Removed all catalogue entries for plugin {}", name);
+  publish_catalogue_events(&driver, events);
 }
 
-/// Find a content matcher in the global catalogue for the provided content type
+/// Find a content matcher in the catalogue of the current driver for the provided content type.
+/// If more than one entry can handle the content type, the one with the highest precedence is
+/// used (see [`content_type_candidates`]).
 #[instrument(level = "trace", skip(content_type))]
 pub fn find_content_matcher<CT: Into<String>>(content_type: CT) -> Option<ContentMatcher> {
   let content_type_str = content_type.into();
@@ -386,21 +470,8 @@ pub fn find_content_matcher<CT: Into<String>>(content_type: CT) -> Option<Conten
       return None;
     }
   };
-  let guard = CATALOGUE_REGISTER.lock().unwrap();
-  trace!("Catalogue has {} entries", guard.len());
-  guard.values().find(|entry| {
-    trace!("Catalogue entry {:?}", entry);
-    if entry.entry_type == CatalogueEntryType::CONTENT_MATCHER {
-      trace!("Catalogue entry is a content matcher for {:?}", entry.values.get("content-types"));
-      if let Some(content_types) = entry.values.get("content-types") {
-        content_types.split(";").any(|ct| matches_pattern(ct.trim(), &content_type))
-      } else {
-        false
-      }
-    } else {
-      false
-    }
-  }).map(|entry| ContentMatcher { catalogue_entry: entry.clone() })
+  select_candidate(CatalogueEntryType::CONTENT_MATCHER, &content_type)
+    .map(|entry| ContentMatcher { catalogue_entry: entry })
 }
 
 /// Checks if a registered content-type pattern matches a content type. The pattern is
@@ -411,57 +482,484 @@ pub fn find_content_matcher<CT: Into<String>>(content_type: CT) -> Option<Conten
 /// regardless of which driver loaded it. Regex metacharacters in a content type (most
 /// commonly `+`, as in a `+json`/`+xml` structured syntax suffix) need to be escaped by the
 /// plugin author for a literal match.
-fn matches_pattern(pattern: &str, content_type: &ContentType) -> bool {
+///
+/// The patterns are compiled when the entries are registered (see [`compile_content_type_patterns`]),
+/// invalid patterns never match.
+fn matches_pattern(pattern: &str, base_type: &str, patterns: &HashMap<String, Option<Regex>>) -> bool {
+  match patterns.get(pattern) {
+    Some(Some(regex)) => regex.is_match(base_type),
+    Some(None) => false,
+    None => content_type_regex(pattern).map(|regex| regex.is_match(base_type)).unwrap_or(false)
+  }
+}
+
+fn content_type_regex(pattern: &str) -> Result<Regex, regex::Error> {
+  Regex::new(&format!("^(?:{})$", pattern))
+}
+
+/// Compiles the content type patterns of the entry, so each pattern is only compiled once.
+/// Invalid patterns are reported here and then ignored when looking up content types.
+fn compile_content_type_patterns(driver: &PluginDriver, catalogue_key: &str, entry: &CatalogueEntry) {
+  let Some(content_types) = entry.values.get("content-types") else {
+    return;
+  };
+  let mut patterns = driver.inner.content_type_patterns.lock().unwrap();
+  for pattern in content_types.split(';').map(|pattern| pattern.trim()) {
+    if patterns.get(pattern).is_some_and(Option::is_some) {
+      continue;
+    }
+    match content_type_regex(pattern) {
+      Ok(regex) => {
+        patterns.insert(pattern.to_string(), Some(regex));
+      }
+      Err(err) => {
+        warn!("Catalogue entry {} has an invalid content type pattern '{}', it will be ignored - {}",
+          catalogue_key, pattern, err);
+        patterns.insert(pattern.to_string(), None);
+      }
+    }
+  }
+}
+
+/// Called with the catalogue lock held when entries have been removed, so the compiled patterns
+/// only ever cover the entries still in the catalogue
+fn prune_content_type_patterns(driver: &PluginDriver, entries: &HashMap<String, CatalogueEntry>) {
+  let in_use = entries.values()
+    .filter_map(|entry| entry.values.get("content-types"))
+    .flat_map(|content_types| content_types.split(';').map(|pattern| pattern.trim()))
+    .collect::<HashSet<_>>();
+  driver.inner.content_type_patterns.lock().unwrap()
+    .retain(|pattern, _| in_use.contains(pattern.as_str()));
+}
+
+/// Called with the catalogue lock held when entries have been added or removed, so no lookups
+/// are cached from the previous entries
+fn clear_content_type_lookups(driver: &PluginDriver) {
+  driver.inner.content_type_lookups.lock().unwrap().clear();
+}
+
+/// Maximum number of content type lookups a driver caches (see [`content_type_candidates`])
+pub const DEFAULT_CONTENT_TYPE_LOOKUP_CACHE_SIZE: usize = 256;
+
+/// Least recently used cache of the catalogue entries matching a content type. The lookups are
+/// keyed by the content type of the body, which comes from the pact or the request, so without a
+/// bound it would grow with every distinct content type seen.
+pub(crate) struct ContentTypeLookupCache {
+  capacity: usize,
+  entries: HashMap<String, (Vec<ContentTypeCandidate>, u64)>,
+  recency: BTreeMap<u64, String>,
+  tick: u64
+}
+
+impl Default for ContentTypeLookupCache {
+  fn default() -> Self {
+    ContentTypeLookupCache::new(DEFAULT_CONTENT_TYPE_LOOKUP_CACHE_SIZE)
+  }
+}
+
+impl ContentTypeLookupCache {
+  pub(crate) fn new(capacity: usize) -> Self {
+    ContentTypeLookupCache {
+      capacity,
+      entries: HashMap::new(),
+      recency: BTreeMap::new(),
+      tick: 0
+    }
+  }
+
+  fn get(&mut self, key: &str) -> Option<Vec<ContentTypeCandidate>> {
+    self.tick += 1;
+    let (candidates, last_used) = self.entries.get_mut(key)?;
+    self.recency.remove(last_used);
+    self.recency.insert(self.tick, key.to_string());
+    *last_used = self.tick;
+    Some(candidates.clone())
+  }
+
+  fn insert(&mut self, key: String, candidates: Vec<ContentTypeCandidate>) {
+    if self.capacity == 0 {
+      return;
+    }
+    self.tick += 1;
+    if let Some((_, last_used)) = self.entries.insert(key.clone(), (candidates, self.tick)) {
+      self.recency.remove(&last_used);
+    }
+    self.recency.insert(self.tick, key);
+    while self.entries.len() > self.capacity {
+      match self.recency.pop_first() {
+        Some((_, oldest)) => {
+          self.entries.remove(&oldest);
+        }
+        None => break
+      }
+    }
+  }
+
+  #[cfg(test)]
+  fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  fn clear(&mut self) {
+    self.entries.clear();
+    self.recency.clear();
+  }
+}
+
+fn base_type(content_type: &ContentType) -> String {
   // Deliberately not `content_type.base_type()`: that replaces the subtype with the
   // structured syntax suffix (e.g. "application/jwt+json" -> "application/json"), which is
   // useful for deciding how to *parse* a body but wrong here - it would make two unrelated
   // "+json" content types register as the same catalogue entry. Just strip attributes
   // (e.g. `charset`), keeping the type/subtype+suffix as the plugin actually registered it.
-  let base_type = match &content_type.suffix {
+  match &content_type.suffix {
     Some(suffix) => format!("{}/{}+{}", content_type.main_type, content_type.sub_type, suffix),
     None => format!("{}/{}", content_type.main_type, content_type.sub_type)
-  };
-  match Regex::new(&format!("^(?:{})$", pattern)) {
-    Ok(regex) => regex.is_match(base_type.as_str()),
-    Err(err) => {
-      error!("Failed to parse '{}' as a regex - {}", pattern, err);
-      false
-    }
   }
 }
 
-/// Find a content generator in the global catalogue for the provided content type
+/// Find a content generator in the catalogue of the current driver for the provided content type.
+/// If more than one entry can handle the content type, the one with the highest precedence is
+/// used (see [`content_type_candidates`]).
 pub fn find_content_generator(content_type: &ContentType) -> Option<ContentGenerator> {
   debug!("Looking for a content generator for {}", content_type);
-  let guard = CATALOGUE_REGISTER.lock().unwrap();
-  guard.values().find(|entry| {
-    if entry.entry_type == CatalogueEntryType::CONTENT_GENERATOR {
-      if let Some(content_types) = entry.values.get("content-types") {
-        content_types.split(";").any(|ct| matches_pattern(ct.trim(), content_type))
-      } else {
-        false
+  select_candidate(CatalogueEntryType::CONTENT_GENERATOR, content_type)
+    .map(|entry| ContentGenerator { catalogue_entry: entry })
+}
+
+/// Set which provider handles a content type when more than one content matcher or generator is
+/// registered for it. The provider is either the name of a plugin or `core` for the core Pact
+/// framework entries. The content type is compared to the base type of the content (i.e.
+/// `application/json` for `application/json;charset=UTF-8`). Takes precedence over the
+/// `PACT_PLUGIN_CONTENT_TYPE_OVERRIDES` environment variable, which is a comma separated list of
+/// `content-type=provider` pairs.
+pub fn set_content_type_override(content_type: &str, provider: &str) {
+  let driver = PluginDriver::current();
+  let mut guard = driver.inner.content_type_overrides.write().unwrap();
+  guard.insert(content_type.trim().to_lowercase(), provider.trim().to_string());
+}
+
+/// Removes the override set with [`set_content_type_override`] for the content type
+pub fn remove_content_type_override(content_type: &str) {
+  let driver = PluginDriver::current();
+  let mut guard = driver.inner.content_type_overrides.write().unwrap();
+  guard.remove(&content_type.trim().to_lowercase());
+}
+
+fn content_type_override(base_type: &str) -> Option<String> {
+  content_type_overrides().remove(&base_type.to_lowercase())
+}
+
+/// The content type overrides in use by the current driver, keyed by the base content type. These
+/// are the overrides set with [`set_content_type_override`] and then any others from the
+/// `PACT_PLUGIN_CONTENT_TYPE_OVERRIDES` environment variable.
+pub fn content_type_overrides() -> BTreeMap<String, String> {
+  let mut overrides = BTreeMap::new();
+  if let Ok(value) = env::var("PACT_PLUGIN_CONTENT_TYPE_OVERRIDES") {
+    for (content_type, provider) in value.split(',').filter_map(|pair| pair.split_once('=')) {
+      overrides.entry(content_type.trim().to_lowercase())
+        .or_insert_with(|| provider.trim().to_string());
+    }
+  }
+  let driver = PluginDriver::current();
+  for (content_type, provider) in driver.inner.content_type_overrides.read().unwrap().iter() {
+    overrides.insert(content_type.clone(), provider.clone());
+  }
+  overrides
+}
+
+/// A catalogue entry that can handle a content type, along with what decides its precedence
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContentTypeCandidate {
+  /// Key the entry is registered under in the catalogue
+  pub catalogue_key: String,
+  /// The catalogue entry
+  pub entry: CatalogueEntry,
+  /// The content type pattern of the entry that matched
+  pub pattern: String,
+  /// If the entry is from the provider set as the override for the content type
+  pub overridden: bool,
+  /// Priority of the entry (the `priority` value of the entry, defaults to 0)
+  pub priority: i32,
+  /// If the pattern is a literal content type rather than a regex
+  pub exact: bool,
+  /// Number of literal characters in the pattern
+  pub literal_chars: usize
+}
+
+impl ContentTypeCandidate {
+  fn rank(&self) -> (bool, i32, bool, usize) {
+    (self.overridden, self.priority, self.exact, self.literal_chars)
+  }
+}
+
+/// Returns the catalogue entries of the given type that can handle the content type, in order of
+/// precedence. The entry used is the first one that:
+/// 1. is from the provider set as the override for the content type (see [`set_content_type_override`]),
+/// 2. has the highest `priority` value,
+/// 3. has the most specific matching pattern (a literal content type, then the pattern with the
+///    most literal characters).
+///
+/// Entries that are still equal are ambiguous, and a warning is logged when one is picked. They
+/// are ordered with plugin entries before core ones, and then by catalogue key, so the same entry
+/// is always used.
+pub fn content_type_candidates(entry_type: CatalogueEntryType, content_type: &ContentType) -> Vec<ContentTypeCandidate> {
+  let base_type = base_type(content_type);
+  let override_provider = content_type_override(&base_type);
+  matching_candidates(&PluginDriver::current(), entry_type, &base_type)
+    .into_iter()
+    .map(|candidate| ContentTypeCandidate {
+      overridden: override_provider.as_ref().map(|provider| is_provider(&candidate.entry, provider)).unwrap_or(false),
+      .. candidate
+    })
+    .sorted_by(|a, b| b.rank().cmp(&a.rank())
+      .then_with(|| is_core(&a.entry).cmp(&is_core(&b.entry)))
+      .then_with(|| a.catalogue_key.cmp(&b.catalogue_key)))
+    .collect()
+}
+
+/// The entries with a content type pattern matching the base type, before any override is
+/// applied. The result is cached until the catalogue changes, as this is called for every body.
+fn matching_candidates(driver: &PluginDriver, entry_type: CatalogueEntryType, base_type: &str) -> Vec<ContentTypeCandidate> {
+  let guard = driver.inner.catalogue_register.lock().unwrap();
+  let cache_key = format!("{}/{}", entry_type, base_type);
+  let mut lookups = driver.inner.content_type_lookups.lock().unwrap();
+  if let Some(candidates) = lookups.get(&cache_key) {
+    return candidates;
+  }
+
+  trace!("Catalogue has {} entries", guard.len());
+  let patterns = driver.inner.content_type_patterns.lock().unwrap();
+  let candidates = guard.iter()
+    .filter(|(_, entry)| entry.entry_type == entry_type)
+    .filter_map(|(key, entry)| {
+      let content_types = entry.values.get("content-types")?;
+      trace!("Catalogue entry {} is a {} for {:?}", key, entry_type, content_types);
+      content_types.split(';')
+        .map(|pattern| pattern.trim())
+        .filter(|pattern| matches_pattern(pattern, base_type, &patterns))
+        .map(|pattern| (pattern, pattern_specificity(pattern)))
+        .max_by_key(|(_, specificity)| *specificity)
+        .map(|(pattern, (exact, literal_chars))| ContentTypeCandidate {
+          catalogue_key: key.clone(),
+          entry: entry.clone(),
+          pattern: pattern.to_string(),
+          overridden: false,
+          priority: entry_priority(key, entry),
+          exact,
+          literal_chars
+        })
+    })
+    .collect::<Vec<_>>();
+  lookups.insert(cache_key, candidates.clone());
+  candidates
+}
+
+fn select_candidate(entry_type: CatalogueEntryType, content_type: &ContentType) -> Option<CatalogueEntry> {
+  let candidates = content_type_candidates(entry_type, content_type);
+  if let [first, second, ..] = candidates.as_slice() && first.rank() == second.rank() {
+    let keys = candidates.iter()
+      .filter(|candidate| candidate.rank() == first.rank())
+      .map(|candidate| candidate.catalogue_key.as_str())
+      .join(", ");
+    // Only report each ambiguity once, as this is called for every body
+    if PluginDriver::current().inner.reported_ambiguities.lock().unwrap().insert(format!("{}/{}", base_type(content_type), keys)) {
+      warn!("More than one {} can handle content type {} with the same precedence ({}), using {}. \
+        Set a priority on the entries or an override for the content type to choose one.",
+        entry_type, content_type, keys, first.catalogue_key);
+    }
+  }
+  candidates.into_iter().next().map(|candidate| candidate.entry)
+}
+
+fn is_core(entry: &CatalogueEntry) -> bool {
+  entry.provider_type == CatalogueEntryProviderType::CORE
+}
+
+fn is_provider(entry: &CatalogueEntry, provider: &str) -> bool {
+  match &entry.plugin {
+    Some(plugin) if entry.provider_type == CatalogueEntryProviderType::PLUGIN => plugin.name == provider,
+    _ => is_core(entry) && provider.eq_ignore_ascii_case("core")
+  }
+}
+
+fn entry_priority(key: &str, entry: &CatalogueEntry) -> i32 {
+  match entry.values.get("priority") {
+    Some(priority) => priority.trim().parse().unwrap_or_else(|_| {
+      warn!("Ignoring invalid priority '{}' of catalogue entry {}", priority, key);
+      0
+    }),
+    None => 0
+  }
+}
+
+/// How specific a content type pattern is: whether it is a literal content type (no regex
+/// metacharacters, other than escaped ones), and the number of literal characters in it
+fn pattern_specificity(pattern: &str) -> (bool, usize) {
+  let mut exact = true;
+  let mut literal_chars = 0;
+  let mut in_class = false;
+  let mut chars = pattern.chars();
+  while let Some(ch) = chars.next() {
+    if in_class {
+      in_class = ch != ']';
+      continue;
+    }
+    match ch {
+      '\\' => match chars.next() {
+        Some(escaped) if !escaped.is_ascii_alphanumeric() => literal_chars += 1,
+        _ => exact = false
+      },
+      '[' => {
+        in_class = true;
+        exact = false;
       }
-    } else {
-      false
+      '.' | '^' | '$' | '*' | '+' | '?' | '(' | ')' | '{' | '}' | '|' => exact = false,
+      _ => literal_chars += 1
     }
-  }).map(|entry| ContentGenerator { catalogue_entry: entry.clone() })
+  }
+  (exact, literal_chars)
 }
 
 /// Returns a copy of all catalogue entries
 pub fn all_entries() -> Vec<CatalogueEntry> {
-  let guard = CATALOGUE_REGISTER.lock().unwrap();
+  let driver = PluginDriver::current();
+  let guard = driver.inner.catalogue_register.lock().unwrap();
   guard.values().cloned().collect()
 }
 
 /// Returns catalogue entries provided by the core host framework (excludes plugin entries)
 pub fn core_entries() -> Vec<CatalogueEntry> {
-  let guard = CATALOGUE_REGISTER.lock().unwrap();
+  let driver = PluginDriver::current();
+  let guard = driver.inner.catalogue_register.lock().unwrap();
   guard.values()
     .filter(|entry| entry.provider_type == CatalogueEntryProviderType::CORE)
     .cloned()
     .collect()
 }
 
+/// Query for entries in the catalogue (see [`query_entries`]). Only the entries that match all
+/// the criteria that are set are returned, so the default query returns all the entries.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CatalogueQuery {
+  /// Type of entry
+  pub entry_type: Option<CatalogueEntryType>,
+  /// Provider of the entry
+  pub provider_type: Option<CatalogueEntryProviderType>,
+  /// Name of the plugin that provides the entry
+  pub plugin_name: Option<String>,
+  /// Version of the plugin that provides the entry
+  pub plugin_version: Option<String>,
+  /// Content type that the entry handles. Only content matchers and generators have content
+  /// types.
+  pub content_type: Option<String>
+}
+
+impl CatalogueQuery {
+  /// If the entry matches the criteria of this query, apart from the content type
+  pub fn matches(&self, entry: &CatalogueEntry) -> bool {
+    self.entry_type.map(|entry_type| entry.entry_type == entry_type).unwrap_or(true)
+      && self.provider_type.map(|provider_type| entry.provider_type == provider_type).unwrap_or(true)
+      && self.plugin_name.as_ref()
+        .map(|name| entry.plugin.as_ref().map(|plugin| &plugin.name == name).unwrap_or(false))
+        .unwrap_or(true)
+      && self.plugin_version.as_ref()
+        .map(|version| entry.plugin.as_ref().map(|plugin| &plugin.version == version).unwrap_or(false))
+        .unwrap_or(true)
+  }
+}
+
+/// Returns the entries in the catalogue of the current driver that match the query, ordered by
+/// catalogue key. When the query has a content type, the content matchers and generators that can
+/// handle it are returned instead, each in the order they would be used (see
+/// [`content_type_candidates`]). Fails if the content type is not valid.
+pub fn query_entries(query: &CatalogueQuery) -> anyhow::Result<Vec<CatalogueEntry>> {
+  let entries = match &query.content_type {
+    Some(content_type) => {
+      let content_type = ContentType::parse(content_type.as_str())
+        .map_err(|err| anyhow!("'{}' is not a valid content type - {}", content_type, err))?;
+      [CatalogueEntryType::CONTENT_MATCHER, CatalogueEntryType::CONTENT_GENERATOR].iter()
+        .flat_map(|entry_type| content_type_candidates(*entry_type, &content_type))
+        .map(|candidate| candidate.entry)
+        .collect()
+    }
+    None => all_entries().into_iter()
+      .sorted_by_cached_key(|entry| entry.catalogue_key())
+      .collect::<Vec<_>>()
+  };
+  Ok(entries.into_iter().filter(|entry| query.matches(entry)).collect())
+}
+
+/// Exports the catalogue of the current driver as JSON, for tooling to show what is registered.
+/// The entries are ordered by catalogue key, so the same catalogue always gives the same JSON:
+///
+/// ```json
+/// {
+///   "contentTypeOverrides": { "application/json": "core" },
+///   "entries": [
+///     {
+///       "catalogueKey": "plugin/protobuf/content-matcher/protobuf",
+///       "key": "protobuf",
+///       "plugin": { "name": "protobuf", "version": "0.5.4" },
+///       "provider": "plugin",
+///       "type": "content-matcher",
+///       "values": { "content-types": "application/protobuf;application/grpc" }
+///     }
+///   ]
+/// }
+/// ```
+pub fn catalogue_to_json() -> Value {
+  let entries = all_entries().iter()
+    .sorted_by_cached_key(|entry| entry.catalogue_key())
+    .map(entry_to_json)
+    .collect::<Vec<_>>();
+  json!({
+    "contentTypeOverrides": content_type_overrides(),
+    "entries": entries
+  })
+}
+
+/// Explains, as JSON, which content matchers and generators in the catalogue of the current
+/// driver can handle the content type, in the order they would be used. Each candidate has the
+/// values that decide its precedence (see [`content_type_candidates`]), and the one that would be
+/// used is marked as selected.
+pub fn content_type_resolution_to_json(content_type: &ContentType) -> Value {
+  let base_type = base_type(content_type);
+  let candidates = |entry_type| content_type_candidates(entry_type, content_type).iter()
+    .enumerate()
+    .map(|(index, candidate)| json!({
+      "catalogueKey": candidate.catalogue_key,
+      "exact": candidate.exact,
+      "literalChars": candidate.literal_chars,
+      "overridden": candidate.overridden,
+      "pattern": candidate.pattern,
+      "priority": candidate.priority,
+      "selected": index == 0
+    }))
+    .collect::<Vec<_>>();
+  json!({
+    "baseType": base_type,
+    "contentGenerators": candidates(CatalogueEntryType::CONTENT_GENERATOR),
+    "contentMatchers": candidates(CatalogueEntryType::CONTENT_MATCHER),
+    "contentType": content_type.to_string(),
+    "override": content_type_override(&base_type)
+  })
+}
+
+fn entry_to_json(entry: &CatalogueEntry) -> Value {
+  json!({
+    "catalogueKey": entry.catalogue_key(),
+    "key": entry.key,
+    "plugin": entry.plugin.as_ref().map(|plugin| json!({ "name": plugin.name, "version": plugin.version })),
+    "provider": match entry.provider_type {
+      CatalogueEntryProviderType::CORE => "core",
+      CatalogueEntryProviderType::PLUGIN => "plugin"
+    },
+    "type": entry.entry_type.to_string(),
+    "values": entry.values.iter().collect::<BTreeMap<_, _>>()
+  })
+}
+
 #[cfg(test)]
 mod tests {
   use expectest::prelude::*;
@@ -529,6 +1027,50 @@ mod tests {
     }));
   }
 
+  #[test]
+  fn catalogue_changes_are_sent_to_subscribers() {
+    let driver = PluginDriver::new(Default::default());
+    let _guard = driver.enter();
+    let mut receiver = subscribe_to_catalogue_changes();
+    let manifest = PactPluginManifest {
+      name: "catalogue-events".to_string(),
+      .. PactPluginManifest::default()
+    };
+    let proto_entry = ProtoCatalogueEntry {
+      r#type: catalogue_entry::EntryType::ContentMatcher as i32,
+      key: "events".to_string(),
+      values: hashmap!{ "content-types".to_string() => "application/x-events".to_string() }
+    };
+    let core_entry = CatalogueEntry {
+      entry_type: CatalogueEntryType::MATCHER,
+      provider_type: CatalogueEntryProviderType::CORE,
+      plugin: None,
+      key: "events".to_string(),
+      values: hashmap!{}
+    };
+
+    register_plugin_entries(&manifest, &vec![proto_entry.clone()]);
+    register_plugin_entries(&manifest, &vec![proto_entry.clone()]);
+    register_core_entries(&vec![core_entry.clone()]);
+    remove_plugin_entries("catalogue-events");
+
+    let plugin_entry = CatalogueEntry {
+      entry_type: CatalogueEntryType::CONTENT_MATCHER,
+      provider_type: CatalogueEntryProviderType::PLUGIN,
+      plugin: Some(manifest.clone()),
+      key: "events".to_string(),
+      values: proto_entry.values.clone()
+    };
+    let key = "plugin/catalogue-events/content-matcher/events".to_string();
+    expect!(receiver.try_recv().unwrap()).to(be_equal_to(CatalogueEvent::Added {
+      catalogue_key: key.clone(), entry: plugin_entry.clone() }));
+    expect!(receiver.try_recv().unwrap()).to(be_equal_to(CatalogueEvent::Added {
+      catalogue_key: "core/matcher/events".to_string(), entry: core_entry }));
+    expect!(receiver.try_recv().unwrap()).to(be_equal_to(CatalogueEvent::Removed {
+      catalogue_key: key, entry: plugin_entry }));
+    expect!(receiver.try_recv().is_err()).to(be_true());
+  }
+
   #[test]
   fn entry_type_proto_values_and_names_round_trip() {
     for entry_type in [
@@ -636,6 +1178,197 @@ mod tests {
     expect!(unrelated_type).to(be_none());
   }
 
+  fn query_test_driver() -> PluginDriver {
+    let driver = PluginDriver::new(Default::default());
+    let _guard = driver.enter();
+    let manifest = PactPluginManifest {
+      name: "query".to_string(),
+      version: "1.2.3".to_string(),
+      .. PactPluginManifest::default()
+    };
+    register_plugin_entries(&manifest, &vec![
+      ProtoCatalogueEntry {
+        r#type: catalogue_entry::EntryType::ContentMatcher as i32,
+        key: "protobuf".to_string(),
+        values: hashmap!{
+          "content-types".to_string() => "application/x-protobuf;application/grpc".to_string(),
+          "priority".to_string() => "10".to_string()
+        }
+      },
+      ProtoCatalogueEntry {
+        r#type: catalogue_entry::EntryType::Transport as i32,
+        key: "grpc".to_string(),
+        values: hashmap!{}
+      }
+    ]);
+    register_core_entries(&vec![
+      CatalogueEntry {
+        entry_type: CatalogueEntryType::CONTENT_MATCHER,
+        provider_type: CatalogueEntryProviderType::CORE,
+        plugin: None,
+        key: "binary".to_string(),
+        values: hashmap!{ "content-types".to_string() => "application/.*".to_string() }
+      }
+    ]);
+    driver
+  }
+
+  fn query_keys(query: CatalogueQuery) -> Vec<String> {
+    query_entries(&query).unwrap().iter().map(|entry| entry.catalogue_key()).collect()
+  }
+
+  #[test]
+  fn query_entries_filters_the_catalogue() {
+    let driver = query_test_driver();
+    let _guard = driver.enter();
+
+    expect!(query_keys(CatalogueQuery::default())).to(be_equal_to(vec![
+      "core/content-matcher/binary".to_string(),
+      "plugin/query/content-matcher/protobuf".to_string(),
+      "plugin/query/transport/grpc".to_string()
+    ]));
+    expect!(query_keys(CatalogueQuery {
+      entry_type: Some(CatalogueEntryType::TRANSPORT),
+      .. CatalogueQuery::default()
+    })).to(be_equal_to(vec!["plugin/query/transport/grpc".to_string()]));
+    expect!(query_keys(CatalogueQuery {
+      provider_type: Some(CatalogueEntryProviderType::CORE),
+      .. CatalogueQuery::default()
+    })).to(be_equal_to(vec!["core/content-matcher/binary".to_string()]));
+    expect!(query_keys(CatalogueQuery {
+      plugin_name: Some("query".to_string()),
+      plugin_version: Some("1.2.3".to_string()),
+      .. CatalogueQuery::default()
+    }).len()).to(be_equal_to(2));
+    expect!(query_keys(CatalogueQuery {
+      plugin_version: Some("1.0.0".to_string()),
+      .. CatalogueQuery::default()
+    })).to(be_equal_to(Vec::<String>::new()));
+    expect!(query_keys(CatalogueQuery {
+      content_type: Some("application/x-protobuf".to_string()),
+      .. CatalogueQuery::default()
+    })).to(be_equal_to(vec![
+      "plugin/query/content-matcher/protobuf".to_string(),
+      "core/content-matcher/binary".to_string()
+    ]));
+    expect!(query_entries(&CatalogueQuery {
+      content_type: Some("not a content type".to_string()),
+      .. CatalogueQuery::default()
+    })).to(be_err());
+  }
+
+  #[test]
+  fn catalogue_can_be_exported_as_json() {
+    let driver = query_test_driver();
+    let _guard = driver.enter();
+    set_content_type_override("application/grpc", "core");
+
+    let json = catalogue_to_json();
+    expect!(json["contentTypeOverrides"]["application/grpc"].clone()).to(be_equal_to(json!("core")));
+    expect!(json["entries"][1].clone()).to(be_equal_to(json!({
+      "catalogueKey": "plugin/query/content-matcher/protobuf",
+      "key": "protobuf",
+      "plugin": { "name": "query", "version": "1.2.3" },
+      "provider": "plugin",
+      "type": "content-matcher",
+      "values": { "content-types": "application/x-protobuf;application/grpc", "priority": "10" }
+    })));
+    expect!(serde_json::to_string(&json).unwrap()).to(be_equal_to(serde_json::to_string(&catalogue_to_json()).unwrap()));
+
+    let resolution = content_type_resolution_to_json(&ContentType::parse("application/grpc").unwrap());
+    expect!(resolution["override"].clone()).to(be_equal_to(json!("core")));
+    expect!(resolution["contentMatchers"].clone()).to(be_equal_to(json!([
+      {
+        "catalogueKey": "core/content-matcher/binary",
+        "exact": false,
+        "literalChars": 12,
+        "overridden": true,
+        "pattern": "application/.*",
+        "priority": 0,
+        "selected": true
+      },
+      {
+        "catalogueKey": "plugin/query/content-matcher/protobuf",
+        "exact": true,
+        "literalChars": 16,
+        "overridden": false,
+        "pattern": "application/grpc",
+        "priority": 10,
+        "selected": false
+      }
+    ])));
+    expect!(resolution["contentGenerators"].clone()).to(be_equal_to(json!([])));
+  }
+
+  #[test]
+  fn content_type_lookups_are_cached_until_the_catalogue_changes() {
+    let driver = PluginDriver::new(Default::default());
+    let _guard = driver.enter();
+    let entry = |key: &str, content_types: &str| CatalogueEntry {
+      entry_type: CatalogueEntryType::CONTENT_MATCHER,
+      provider_type: CatalogueEntryProviderType::CORE,
+      plugin: None,
+      key: key.to_string(),
+      values: hashmap!{ "content-types".to_string() => content_types.to_string() }
+    };
+    register_core_entries(&vec![entry("invalid", "application/x-cache(;application/x-cache")]);
+    expect!(driver.inner.content_type_patterns.lock().unwrap().get("application/x-cache(").map(Option::is_none))
+      .to(be_some().value(true));
+
+    let matcher = find_content_matcher("application/x-cache").unwrap();
+    expect!(matcher.catalogue_entry.key).to(be_equal_to("invalid"));
+    expect!(driver.inner.content_type_lookups.lock().unwrap().get("content-matcher/application/x-cache"))
+      .to(be_some());
+
+    register_core_entries(&vec![entry("valid", "application/x-cache")]);
+    expect!(driver.inner.content_type_lookups.lock().unwrap().len()).to(be_equal_to(0));
+    let keys = content_type_candidates(CatalogueEntryType::CONTENT_MATCHER, &ContentType::parse("application/x-cache").unwrap())
+      .iter()
+      .map(|candidate| candidate.catalogue_key.clone())
+      .collect::<Vec<_>>();
+    expect!(keys).to(be_equal_to(vec!["core/content-matcher/invalid".to_string(), "core/content-matcher/valid".to_string()]));
+  }
+
+  #[test]
+  fn content_type_lookup_cache_evicts_the_least_recently_used_lookup() {
+    let mut cache = ContentTypeLookupCache::new(2);
+    cache.insert("content-matcher/application/a".to_string(), vec![]);
+    cache.insert("content-matcher/application/b".to_string(), vec![]);
+    expect!(cache.get("content-matcher/application/a")).to(be_some());
+    cache.insert("content-matcher/application/c".to_string(), vec![]);
+
+    expect!(cache.len()).to(be_equal_to(2));
+    expect!(cache.get("content-matcher/application/a")).to(be_some());
+    expect!(cache.get("content-matcher/application/b")).to(be_none());
+    expect!(cache.get("content-matcher/application/c")).to(be_some());
+
+    let mut disabled = ContentTypeLookupCache::new(0);
+    disabled.insert("content-matcher/application/a".to_string(), vec![]);
+    expect!(disabled.len()).to(be_equal_to(0));
+  }
+
+  #[test]
+  fn removing_a_plugin_removes_its_content_type_patterns() {
+    let driver = PluginDriver::new(Default::default());
+    let _guard = driver.enter();
+    let manifest = |name: &str| PactPluginManifest { name: name.to_string(), .. PactPluginManifest::default() };
+    let proto_entry = |content_types: &str| ProtoCatalogueEntry {
+      r#type: catalogue_entry::EntryType::ContentMatcher as i32,
+      key: "patterns".to_string(),
+      values: hashmap!{ "content-types".to_string() => content_types.to_string() }
+    };
+    register_plugin_entries(&manifest("first"), &vec![proto_entry("application/x-first;application/x-shared")]);
+    register_plugin_entries(&manifest("second"), &vec![proto_entry("application/x-shared")]);
+
+    remove_plugin_entries("first");
+
+    let patterns = driver.inner.content_type_patterns.lock().unwrap().keys()
+      .cloned()
+      .sorted()
+      .collect::<Vec<_>>();
+    expect!(patterns).to(be_equal_to(vec!["application/x-shared".to_string()]));
+  }
+
   #[test]
   fn resolve_capability_resolves_an_unambiguous_core_entry() {
     let key = "resolve_capability_resolves_an_unambiguous_core_entry";
@@ -860,4 +1593,75 @@ mod tests {
     let err = result.expect_err("expected an error for a key matching more than one entry");
     expect!(err.to_string().contains("Ambiguous catalogue entry key")).to(be_true());
   }
+
+  #[test]
+  fn pattern_specificity_test() {
+    expect!(pattern_specificity("application/json")).to(be_equal_to((true, 16)));
+    expect!(pattern_specificity("application/jwt\\+json")).to(be_equal_to((true, 20)));
+    expect!(pattern_specificity("application/.*json")).to(be_equal_to((false, 16)));
+    expect!(pattern_specificity("application/[a-z]+")).to(be_equal_to((false, 12)));
+    expect!(pattern_specificity("application/\\w+")).to(be_equal_to((false, 12)));
+  }
+
+  fn content_matcher_entry(key: &str, values: HashMap<String, String>) -> ProtoCatalogueEntry {
+    ProtoCatalogueEntry {
+      r#type: catalogue_entry::EntryType::ContentMatcher as i32,
+      key: key.to_string(),
+      values
+    }
+  }
+
+  #[test]
+  fn content_type_candidates_are_ordered_by_precedence() {
+    let content_type = ContentType::parse("application/x-precedence-test").unwrap();
+    let wildcard_plugin = PactPluginManifest {
+      name: "content_type_candidates_wildcard".to_string(),
+      .. PactPluginManifest::default()
+    };
+    let exact_plugin = PactPluginManifest {
+      name: "content_type_candidates_exact".to_string(),
+      .. PactPluginManifest::default()
+    };
+    register_core_entries(&vec![CatalogueEntry {
+      entry_type: CatalogueEntryType::CONTENT_MATCHER,
+      provider_type: CatalogueEntryProviderType::CORE,
+      plugin: None,
+      key: "content_type_candidates_core".to_string(),
+      values: hashmap!{ "content-types".to_string() => "application/x-precedence-test".to_string() }
+    }]);
+    register_plugin_entries(&wildcard_plugin, &vec![content_matcher_entry("wildcard",
+      hashmap!{ "content-types".to_string() => "application/x-precedence-.*".to_string() })]);
+    register_plugin_entries(&exact_plugin, &vec![content_matcher_entry("exact",
+      hashmap!{ "content-types".to_string() => "application/json;application/x-precedence-test".to_string() })]);
+
+    let by_specificity = content_type_candidates(CatalogueEntryType::CONTENT_MATCHER, &content_type).iter()
+      .map(|candidate| candidate.catalogue_key.clone())
+      .collect_vec();
+    let selected = find_content_matcher("application/x-precedence-test;charset=UTF-8")
+      .map(|matcher| matcher.catalogue_entry.key);
+
+    set_content_type_override("application/x-precedence-test", "core");
+    let overridden = find_content_matcher("application/x-precedence-test")
+      .map(|matcher| matcher.catalogue_entry.key);
+    remove_content_type_override("application/x-precedence-test");
+
+    register_plugin_entries(&wildcard_plugin, &vec![content_matcher_entry("wildcard", hashmap!{
+      "content-types".to_string() => "application/x-precedence-.*".to_string(),
+      "priority".to_string() => "10".to_string()
+    })]);
+    let by_priority = find_content_matcher("application/x-precedence-test")
+      .map(|matcher| matcher.catalogue_entry.key);
+
+    remove_plugin_entries(&wildcard_plugin.name);
+    remove_plugin_entries(&exact_plugin.name);
+
+    expect!(by_specificity).to(be_equal_to(vec![
+      "plugin/content_type_candidates_exact/content-matcher/exact".to_string(),
+      "core/content-matcher/content_type_candidates_core".to_string(),
+      "plugin/content_type_candidates_wildcard/content-matcher/wildcard".to_string()
+    ]));
+    expect!(selected).to(be_some().value("exact"));
+    expect!(overridden).to(be_some().value("content_type_candidates_core"));
+    expect!(by_priority).to(be_some().value("wildcard"));
+  }
 }
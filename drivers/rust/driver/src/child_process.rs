@@ -45,6 +45,11 @@ pub(crate) fn plugin_log_dir() -> PathBuf {
   }
 }
 
+/// Path of the per-instance log file a plugin's diagnostic output is captured to.
+pub(crate) fn plugin_log_file_path(plugin_name: &str, instance_id: &str) -> PathBuf {
+  plugin_log_dir().join(format!("pact-plugin-{}-{}.log", plugin_name, instance_id))
+}
+
 /// Opens (creating/truncating) the per-instance log file a plugin's diagnostic output is
 /// captured to: `<pact-dir>/logs/pact-plugin-<name>-<instance_id>.log`. Used for a gRPC
 /// plugin's stderr stream, and for a Lua plugin's `print`/`logger` output (see `lua_plugin`) -
@@ -56,7 +61,7 @@ pub(crate) fn open_plugin_log_file(plugin_name: &str, instance_id: &str) -> Opti
     warn!("Could not create plugin log directory {:?}: {}", log_dir, err);
     return None;
   }
-  let log_path = log_dir.join(format!("pact-plugin-{}-{}.log", plugin_name, instance_id));
+  let log_path = plugin_log_file_path(plugin_name, instance_id);
   match File::create(&log_path) {
     Ok(f) => {
       debug!("Plugin output for instance {} captured to {:?}", instance_id, log_path);
@@ -69,10 +74,40 @@ pub(crate) fn open_plugin_log_file(plugin_name: &str, instance_id: &str) -> Opti
   }
 }
 
+/// Number of lines from the end of a plugin's log file to include when its startup fails
+const STARTUP_STDERR_LINES: usize = 10;
+
+/// What the plugin process did first: print its startup message, or exit.
+enum StartupEvent {
+  Started(ChildPluginProcess),
+  Failed(anyhow::Error),
+  Exited(String),
+}
+
+/// The last lines the plugin wrote to its standard error, read back from its log file, formatted
+/// to be appended to a startup error. Empty if there are none.
+fn stderr_tail(log_path: &PathBuf) -> String {
+  let lines = fs::read_to_string(log_path)
+    .map(|contents| {
+      let lines = contents.lines().collect::<Vec<_>>();
+      lines[lines.len().saturating_sub(STARTUP_STDERR_LINES)..].iter()
+        .map(|line| format!("  {}", line))
+        .collect::<Vec<_>>()
+    })
+    .unwrap_or_default();
+  if lines.is_empty() {
+    String::new()
+  } else {
+    format!("\nLast lines of the plugin's standard error (from {}):\n{}", log_path.display(),
+      lines.join("\n"))
+  }
+}
+
 impl ChildPluginProcess {
   /// Start the child process and try read the startup JSON message from its standard output.
   pub async fn new(mut child: Child, manifest: &PactPluginManifest, instance_id: String) -> anyhow::Result<Self> {
     let (tx, rx) = channel();
+    let exit_tx = tx.clone();
     let child_pid = child.id();
     let child_out = child.stdout.take()
       .ok_or_else(|| anyhow!("Could not get the child process standard output stream"))?;
@@ -97,7 +132,7 @@ impl ChildPluginProcess {
             if line.starts_with("{") {
               match serde_json::from_str::<RunningPluginInfo>(line) {
                 Ok(plugin_info) => {
-                  tx.send(Ok(ChildPluginProcess {
+                  tx.send(StartupEvent::Started(ChildPluginProcess {
                     child_pid: child_pid as usize,
                     plugin_info,
                     instance_id: stdout_instance_id.clone(),
@@ -105,7 +140,7 @@ impl ChildPluginProcess {
                 }
                 Err(err) => {
                   error!("Failed to read startup info from plugin - {}", err);
-                  tx.send(Err(anyhow!("Failed to read startup info from plugin - {}", err)))
+                  tx.send(StartupEvent::Failed(anyhow!("Failed to read startup info from plugin - {}", err)))
                     .unwrap_or_default()
                 }
               };
@@ -121,9 +156,11 @@ impl ChildPluginProcess {
       trace!("Thread to poll plugin STDOUT done");
     });
 
+    let log_path = plugin_log_file_path(&manifest.name, &instance_id);
     let log_file = open_plugin_log_file(&manifest.name, &instance_id);
     let plugin_name = manifest.name.clone();
-    let stderr_instance_id = instance_id;
+    let stderr_instance_id = instance_id.clone();
+    let (stderr_done_tx, stderr_done_rx) = channel();
     std::thread::spawn(move || {
       trace!("Starting thread to poll plugin STDERR");
       let mut log_file = log_file;
@@ -151,17 +188,47 @@ impl ChildPluginProcess {
         line.clear();
       }
       trace!("Thread to poll plugin STDERR done");
+      stderr_done_tx.send(()).unwrap_or_default();
+    });
+
+    // Wait on the process so the driver finds out when it exits (this also reaps it). The plugin
+    // manager ignores the exit if it shut the plugin down itself, or the driver has been dropped.
+    let watched_instance_id = instance_id;
+    let driver = crate::driver::PluginDriver::current().downgrade();
+    std::thread::spawn(move || {
+      trace!("Starting thread to wait on plugin process {}", child_pid);
+      match child.wait() {
+        Ok(status) => {
+          debug!("Plugin process {} exited ({})", child_pid, status);
+          // Only read if the plugin has not started yet
+          exit_tx.send(StartupEvent::Exited(status.to_string())).unwrap_or_default();
+          if let Some(driver) = crate::driver::PluginDriver::upgrade(&driver) {
+            let _guard = driver.enter();
+            crate::plugin_manager::plugin_process_exited(&watched_instance_id, &status.to_string());
+          }
+        }
+        Err(err) => warn!("Could not wait on plugin process {} - {}", child_pid, err)
+      }
     });
 
     trace!("Starting output polling tasks... DONE");
 
-    // TODO: This timeout needs to be configurable
     // TODO: Timeout is not working on Alpine, waits indefinitely if the plugin does not start properly
-    match rx.recv_timeout(Duration::from_secs(60)) {
-      Ok(value) => value,
+    let timeout = crate::plugin_manager::plugin_startup_timeout(manifest);
+    match rx.recv_timeout(timeout) {
+      Ok(StartupEvent::Started(child)) => Ok(child),
+      Ok(StartupEvent::Failed(err)) => Err(err),
+      Ok(StartupEvent::Exited(status)) => {
+        error!("Plugin process exited before it output the startup message ({})", status);
+        // Give the STDERR thread a moment to write out the last of the output
+        stderr_done_rx.recv_timeout(Duration::from_secs(1)).unwrap_or_default();
+        Err(anyhow!("Plugin process exited before it output the startup message ({}){}", status,
+          stderr_tail(&log_path)))
+      }
       Err(err) => {
         error!("Timeout waiting to get plugin startup info: {}", err);
-        Err(anyhow!("Plugin process did not output the correct startup message in 60 seconds: {}", err))
+        Err(anyhow!("Plugin process did not output the correct startup message in {:?}: {}{}", timeout, err,
+          stderr_tail(&log_path)))
       }
     }
   }
@@ -186,3 +253,64 @@ impl ChildPluginProcess {
     }
   }
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+  use std::process::{Command, Stdio};
+
+  use expectest::prelude::*;
+  use maplit::hashmap;
+  use serde_json::json;
+
+  use crate::plugin_models::PactPluginManifest;
+
+  use super::ChildPluginProcess;
+
+  fn spawn(script: &str) -> std::process::Child {
+    Command::new("sh")
+      .arg("-c")
+      .arg(script)
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()
+      .unwrap()
+  }
+
+  #[test_log::test(tokio::test)]
+  async fn startup_fails_early_with_the_exit_status_and_stderr_when_the_plugin_exits() {
+    let manifest = PactPluginManifest {
+      name: "startup-exit-test".to_string(),
+      ..PactPluginManifest::default()
+    };
+    let child = spawn("echo 'first line' >&2; echo 'could not load config' >&2; exit 3");
+
+    let err = ChildPluginProcess::new(child, &manifest, uuid::Uuid::new_v4().to_string())
+      .await
+      .unwrap_err()
+      .to_string();
+
+    expect!(err.starts_with("Plugin process exited before it output the startup message (exit status: 3)"))
+      .to(be_true());
+    expect!(err.contains("  first line\n  could not load config")).to(be_true());
+  }
+
+  #[test_log::test(tokio::test)]
+  async fn startup_times_out_using_the_timeout_from_the_manifest() {
+    let manifest = PactPluginManifest {
+      name: "startup-timeout-test".to_string(),
+      plugin_config: hashmap!{ "startupTimeout".to_string() => json!(1) },
+      ..PactPluginManifest::default()
+    };
+    let child = spawn("echo 'still starting' >&2; sleep 5");
+    let child_pid = child.id();
+
+    let err = ChildPluginProcess::new(child, &manifest, uuid::Uuid::new_v4().to_string())
+      .await
+      .unwrap_err()
+      .to_string();
+    let _ = Command::new("kill").arg(child_pid.to_string()).status();
+
+    expect!(err.starts_with("Plugin process did not output the correct startup message in 1s")).to(be_true());
+    expect!(err.contains("  still starting")).to(be_true());
+  }
+}
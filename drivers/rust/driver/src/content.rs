@@ -16,6 +16,7 @@ use tracing::{debug, error};
 
 use crate::catalogue_manager::{CatalogueEntry, CatalogueEntryProviderType};
 use crate::core_capabilities;
+use crate::diff::{DiffEntry, generate_diff};
 use crate::plugin_manager::lookup_plugin;
 use crate::plugin_models::{PactPluginManifest, PluginInteractionConfig};
 use crate::proto::{
@@ -28,7 +29,7 @@ use crate::proto::{
 };
 use crate::proto::body;
 use crate::proto::interaction_response::MarkupType;
-use crate::utils::{proto_struct_to_hashmap, proto_struct_to_json, proto_struct_to_map, to_proto_struct};
+use crate::utils::{optional_string, proto_struct_to_hashmap, proto_struct_to_json, proto_struct_to_map, to_proto_struct};
 
 /// Matcher for contents based on content type
 #[derive(Clone, Debug)]
@@ -45,7 +46,7 @@ impl ContentMatcher {
 }
 
 /// Mismatch result
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct ContentMismatch {
   /// Expected value in string format
   // TODO: change to bytes
@@ -60,7 +61,140 @@ pub struct ContentMismatch {
   /// Optional diff of the expected and actual values
   pub diff: Option<String>,
   /// The type of item that the mismatch is for
-  pub mismatch_type: Option<String>
+  pub mismatch_type: Option<String>,
+  /// Raw values, content type and structured diff of the mismatch, if known
+  pub details: Option<ContentMismatchDetails>
+}
+
+/// Details of a mismatch that are not in its display form
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ContentMismatchDetails {
+  /// Raw bytes of the expected value
+  pub expected_bytes: Option<Bytes>,
+  /// Raw bytes of the actual value
+  pub actual_bytes: Option<Bytes>,
+  /// Content type of the contents the mismatch is for
+  pub content_type: Option<ContentType>,
+  /// Structured diff of the expected and actual values
+  pub structured_diff: Vec<DiffEntry>
+}
+
+impl ContentMismatch {
+  /// Creates a mismatch from string expected and actual values
+  pub fn new<S: Into<String>>(expected: S, actual: S, mismatch: &str, path: &str) -> ContentMismatch {
+    ContentMismatch {
+      expected: expected.into(),
+      actual: actual.into(),
+      mismatch: mismatch.to_string(),
+      path: path.to_string(),
+      .. ContentMismatch::default()
+    }
+  }
+
+  /// Creates a mismatch from raw expected and actual bytes. The display forms are rendered from
+  /// the bytes.
+  pub fn from_bytes(
+    expected: Option<Bytes>,
+    actual: Option<Bytes>,
+    content_type: Option<ContentType>,
+    mismatch: &str,
+    path: &str
+  ) -> ContentMismatch {
+    ContentMismatch {
+      expected: expected.as_ref().map(|value| display_mismatch_value(value)).unwrap_or_default(),
+      actual: actual.as_ref().map(|value| display_mismatch_value(value)).unwrap_or_default(),
+      mismatch: mismatch.to_string(),
+      path: path.to_string(),
+      details: Some(ContentMismatchDetails {
+        expected_bytes: expected,
+        actual_bytes: actual,
+        content_type,
+        .. ContentMismatchDetails::default()
+      }),
+      .. ContentMismatch::default()
+    }
+  }
+
+  /// Converts a mismatch returned from a plugin. Empty diffs and mismatch types are treated as
+  /// not set. A diff is generated if the plugin did not supply one.
+  pub(crate) fn from_proto(
+    mismatch: &crate::proto::ContentMismatch,
+    content_type: Option<ContentType>
+  ) -> ContentMismatch {
+    ContentMismatch {
+      diff: optional_string(mismatch.diff.as_str()),
+      mismatch_type: optional_string(mismatch.mismatch_type.as_str()),
+      .. ContentMismatch::from_bytes(
+        mismatch.expected.clone().map(Bytes::from),
+        mismatch.actual.clone().map(Bytes::from),
+        content_type,
+        mismatch.mismatch.as_str(),
+        mismatch.path.as_str()
+      )
+    }.with_generated_diff()
+  }
+
+  /// Sets the structured diff of the mismatch
+  pub fn with_structured_diff(self, structured_diff: Vec<DiffEntry>) -> ContentMismatch {
+    let details = self.details.unwrap_or_default();
+    ContentMismatch { details: Some(ContentMismatchDetails { structured_diff, .. details }), .. self }
+  }
+
+  /// Fills in the diff (and structured diff, for JSON values) from the expected and actual
+  /// values if the mismatch does not have one. See [`generate_diff`].
+  pub fn with_generated_diff(self) -> ContentMismatch {
+    if self.diff.is_some() {
+      return self;
+    }
+    let (structured_diff, diff) = generate_diff(&self);
+    let mismatch = ContentMismatch { diff, .. self };
+    if structured_diff.is_empty() { mismatch } else { mismatch.with_structured_diff(structured_diff) }
+  }
+
+  /// Raw bytes of the expected value, if the mismatch was created from bytes
+  pub fn expected_bytes(&self) -> Option<&Bytes> {
+    self.details.as_ref().and_then(|details| details.expected_bytes.as_ref())
+  }
+
+  /// Raw bytes of the actual value, if the mismatch was created from bytes
+  pub fn actual_bytes(&self) -> Option<&Bytes> {
+    self.details.as_ref().and_then(|details| details.actual_bytes.as_ref())
+  }
+
+  /// Content type of the contents the mismatch is for, if known
+  pub fn content_type(&self) -> Option<&ContentType> {
+    self.details.as_ref().and_then(|details| details.content_type.as_ref())
+  }
+
+  /// Structured diff of the expected and actual values. Empty if there is none.
+  pub fn structured_diff(&self) -> &[DiffEntry] {
+    self.details.as_ref().map(|details| details.structured_diff.as_slice()).unwrap_or_default()
+  }
+
+  /// Raw bytes of the expected value. Falls back to the bytes of the display form if the mismatch
+  /// was created from a string.
+  pub fn expected_value(&self) -> Bytes {
+    self.expected_bytes().cloned().unwrap_or_else(|| Bytes::from(self.expected.clone()))
+  }
+
+  /// Raw bytes of the actual value. Falls back to the bytes of the display form if the mismatch
+  /// was created from a string.
+  pub fn actual_value(&self) -> Bytes {
+    self.actual_bytes().cloned().unwrap_or_else(|| Bytes::from(self.actual.clone()))
+  }
+}
+
+/// Renders a mismatch value for display. UTF-8 values are displayed as they are, otherwise the
+/// first 32 bytes are displayed in hexadecimal form, along with the length.
+pub fn display_mismatch_value(value: &[u8]) -> String {
+  match from_utf8(value) {
+    Ok(value) => value.to_string(),
+    Err(_) => {
+      let hex: String = value.iter().take(32).map(|b| format!("{:02X}", b)).collect();
+      let ellipsis = if value.len() > 32 { "..." } else { "" };
+      format!("{}{} ({} bytes)", hex, ellipsis, value.len())
+    }
+  }
 }
 
 /// Interaction contents setup by the plugin
@@ -389,18 +523,15 @@ impl ContentMatcher {
 
     if self.is_core() {
       return match core_capabilities::lookup_core_content_matcher(&self.catalogue_entry.key) {
-        Some(handler) => Self::process_compare_contents_response(handler.compare_contents(request).await),
+        Some(handler) => Self::process_compare_contents_response(
+          handler.compare_contents(request).await, expected.content_type()),
         None => {
           error!("No core content matcher registered for {:?}", self.catalogue_entry);
           Err(hashmap! {
             String::default() => vec![
               ContentMismatch {
-                expected: "".to_string(),
-                actual: "".to_string(),
                 mismatch: format!("No core content matcher registered for {:?}", self.catalogue_entry),
-                path: "".to_string(),
-                diff: None,
-                mismatch_type: None
+                .. ContentMismatch::default()
               }
             ]
           })
@@ -415,7 +546,8 @@ impl ContentMatcher {
         let chain_id = crate::call_chain::new_call_chain_id();
         let deadline_ms = crate::call_chain::default_deadline_ms();
         Self::process_compare_contents_response(
-          plugin.compare_contents_with_chain(request, &chain_id, deadline_ms).await
+          plugin.compare_contents_with_chain(request, &chain_id, deadline_ms).await,
+          expected.content_type()
         )
       }
       None => {
@@ -423,12 +555,8 @@ impl ContentMatcher {
         Err(hashmap! {
           String::default() => vec![
             ContentMismatch {
-              expected: "".to_string(),
-              actual: "".to_string(),
               mismatch: format!("Plugin for {:?} was not found in the plugin register", self.catalogue_entry),
-              path: "".to_string(),
-              diff: None,
-              mismatch_type: None
+              .. ContentMismatch::default()
             }
           ]
         })
@@ -439,55 +567,35 @@ impl ContentMatcher {
   /// Convert the result of a CompareContents call (from either a core handler or a plugin) into
   /// the mismatch map result shape shared by both callers of `match_contents`.
   fn process_compare_contents_response(
-    response: anyhow::Result<crate::proto::CompareContentsResponse>
+    response: anyhow::Result<crate::proto::CompareContentsResponse>,
+    content_type: Option<ContentType>
   ) -> Result<(), HashMap<String, Vec<ContentMismatch>>> {
     match response {
       Ok(response) => if let Some(mismatch) = response.type_mismatch {
         Err(hashmap!{
           String::default() => vec![
-            ContentMismatch {
-              expected: mismatch.expected.clone(),
-              actual: mismatch.actual.clone(),
-              mismatch: format!("Expected content type '{}' but got '{}'", mismatch.expected, mismatch.actual),
-              path: "".to_string(),
-              diff: None,
-              mismatch_type: None
-            }
+            ContentMismatch::new(
+              mismatch.expected.as_str(),
+              mismatch.actual.as_str(),
+              format!("Expected content type '{}' but got '{}'", mismatch.expected, mismatch.actual).as_str(),
+              ""
+            )
           ]
         })
       } else if !response.error.is_empty() {
         Err(hashmap! {
           String::default() => vec![
             ContentMismatch {
-              expected: Default::default(),
-              actual: Default::default(),
               mismatch: response.error.clone(),
-              path: "".to_string(),
-              diff: None,
-              mismatch_type: None
+              .. ContentMismatch::default()
             }
           ]
         })
       } else if !response.results.is_empty() {
         Err(response.results.iter().map(|(k, v)| {
-          (k.clone(), v.mismatches.iter().map(|mismatch| {
-            ContentMismatch {
-              expected: mismatch.expected.as_ref()
-                .map(|e| from_utf8(e).unwrap_or_default().to_string())
-                .unwrap_or_default(),
-              actual: mismatch.actual.as_ref()
-                .map(|a| from_utf8(a).unwrap_or_default().to_string())
-                .unwrap_or_default(),
-              mismatch: mismatch.mismatch.clone(),
-              path: mismatch.path.clone(),
-              diff: if mismatch.diff.is_empty() {
-                None
-              } else {
-                Some(mismatch.diff.clone())
-              },
-              mismatch_type: Some(mismatch.mismatch_type.clone())
-            }
-          }).collect())
+          (k.clone(), v.mismatches.iter()
+            .map(|mismatch| ContentMismatch::from_proto(mismatch, content_type.clone()))
+            .collect())
         }).collect())
       } else {
         Ok(())
@@ -497,12 +605,8 @@ impl ContentMatcher {
         Err(hashmap! {
           String::default() => vec![
             ContentMismatch {
-              expected: "".to_string(),
-              actual: "".to_string(),
               mismatch: format!("Call to compare contents handler failed = {}", err),
-              path: "".to_string(),
-              diff: None,
-              mismatch_type: None
+              .. ContentMismatch::default()
             }
           ]
         })
@@ -625,7 +729,7 @@ mod tests {
 
   use async_trait::async_trait;
   use bytes::Bytes;
-  use maplit::btreemap;
+  use maplit::{btreemap, hashmap};
   use pact_models::bodies::OptionalBody;
   use pact_models::content_types::{ContentType, ContentTypeHint};
   use pact_models::matchingrules::{Category, MatchingRuleCategory};
@@ -637,10 +741,11 @@ mod tests {
   use crate::core_capabilities::{self, CoreContentGenerator, CoreContentMatcher};
   use crate::proto::{
     Body, body, CompareContentsRequest, CompareContentsResponse, ConfigureInteractionResponse,
-    GenerateContentRequest, GenerateContentResponse, InteractionResponse
+    ContentMismatch as ProtoContentMismatch, ContentMismatches, GenerateContentRequest,
+    GenerateContentResponse, InteractionResponse
   };
 
-  use super::{ContentGenerator, ContentMatcher, InteractionContents};
+  use super::{ContentGenerator, ContentMatcher, ContentMismatch, InteractionContents, display_mismatch_value};
 
   // Issue https://github.com/YOU54F/pact-ruby-ffi/issues/6
   #[test_log::test]
@@ -776,6 +881,72 @@ mod tests {
     );
   }
 
+  struct BinaryMismatchCoreMatcher;
+
+  #[async_trait]
+  impl CoreContentMatcher for BinaryMismatchCoreMatcher {
+    async fn compare_contents(&self, _request: CompareContentsRequest) -> anyhow::Result<CompareContentsResponse> {
+      Ok(CompareContentsResponse {
+        results: hashmap!{
+          "$.value".to_string() => ContentMismatches {
+            mismatches: vec![
+              ProtoContentMismatch {
+                expected: Some(vec![0x08, 0x96, 0x01]),
+                actual: Some(b"text".to_vec()),
+                mismatch: "Values are different".to_string(),
+                path: "$.value".to_string(),
+                .. ProtoContentMismatch::default()
+              }
+            ]
+          }
+        },
+        .. CompareContentsResponse::default()
+      })
+    }
+  }
+
+  #[test_log::test(tokio::test)]
+  async fn match_contents_keeps_the_raw_bytes_of_binary_mismatches() {
+    let key = "match_contents_keeps_the_raw_bytes_of_binary_mismatches";
+    core_capabilities::register_core_content_matcher(key, Arc::new(BinaryMismatchCoreMatcher));
+
+    let content_type = ContentType::parse("application/protobuf").unwrap();
+    let result = core_content_matcher(key).match_contents(
+      &OptionalBody::Present(Bytes::from_static(&[0x08, 0x96, 0x01]), Some(content_type.clone()), None),
+      &OptionalBody::Present(Bytes::from("text"), Some(content_type.clone()), None),
+      &MatchingRuleCategory { name: Category::BODY, rules: Default::default() },
+      true,
+      None
+    ).await;
+
+    core_capabilities::deregister_core_content_matcher(key);
+
+    let mismatches = result.expect_err("expected a mismatch");
+    let mismatch = &mismatches["$.value"][0];
+    assert_eq!(mismatch.expected_bytes(), Some(&Bytes::from_static(&[0x08, 0x96, 0x01])));
+    assert_eq!(mismatch.expected, "089601 (3 bytes)");
+    assert_eq!(mismatch.actual_value(), Bytes::from("text"));
+    assert_eq!(mismatch.actual, "text");
+    assert_eq!(mismatch.content_type(), Some(&content_type));
+    assert_eq!(mismatch.mismatch_type, None);
+  }
+
+  #[test]
+  fn content_mismatch_from_strings_falls_back_to_the_string_bytes() {
+    let mismatch = ContentMismatch::new("100", "200", "Expected 100 but got 200", "$.a");
+    assert_eq!(mismatch.expected_bytes(), None);
+    assert_eq!(mismatch.expected_value(), Bytes::from("100"));
+    assert_eq!(mismatch.actual_value(), Bytes::from("200"));
+  }
+
+  #[test]
+  fn display_mismatch_value_test() {
+    assert_eq!(display_mismatch_value(b""), "");
+    assert_eq!(display_mismatch_value(b"hello"), "hello");
+    assert_eq!(display_mismatch_value(&[0xFF, 0x00]), "FF00 (2 bytes)");
+    assert_eq!(display_mismatch_value(&[0xFF; 40]), format!("{}... (40 bytes)", "FF".repeat(32)));
+  }
+
   struct FixedCoreGenerator;
 
   #[async_trait]
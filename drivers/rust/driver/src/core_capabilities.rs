@@ -9,14 +9,14 @@
 //! Registration should happen alongside [`crate::catalogue_manager::register_core_entries`] for
 //! the corresponding `CatalogueEntryProviderType::CORE` entry, so an entry and its handler never
 //! drift apart. Callers resolve a capability via the catalogue entry's `key` (unprefixed, e.g.
-//! `"xml"` for `core/content-matcher/xml`), not the full catalogue key.
+//! `"xml"` for `core/content-matcher/xml`), not the full catalogue key. Handlers are registered
+//! with the current driver (see [`crate::driver`]).
 
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 use async_trait::async_trait;
-use lazy_static::lazy_static;
 
+use crate::driver::PluginDriver;
 use crate::proto::{CompareContentsRequest, CompareContentsResponse, GenerateContentRequest, GenerateContentResponse};
 use crate::proto_v2::{GenerateFieldRequest, GenerateFieldResponse, MatchFieldRequest, MatchFieldResponse};
 
@@ -58,18 +58,11 @@ pub trait CoreContentGenerator: Send + Sync {
   async fn generate_content(&self, request: GenerateContentRequest) -> anyhow::Result<GenerateContentResponse>;
 }
 
-lazy_static! {
-  static ref CORE_CONTENT_MATCHERS: Mutex<HashMap<String, Arc<dyn CoreContentMatcher>>> = Mutex::new(HashMap::new());
-  static ref CORE_CONTENT_GENERATORS: Mutex<HashMap<String, Arc<dyn CoreContentGenerator>>> = Mutex::new(HashMap::new());
-  static ref CORE_FIELD_MATCHERS: Mutex<HashMap<String, Arc<dyn CoreFieldMatcher>>> = Mutex::new(HashMap::new());
-  static ref CORE_FIELD_GENERATORS: Mutex<HashMap<String, Arc<dyn CoreFieldGenerator>>> = Mutex::new(HashMap::new());
-}
-
 /// Register a handler for a host-provided content matcher capability, keyed by the catalogue
 /// entry key (e.g. `"xml"` for the `core/content-matcher/xml` entry). Replaces any handler
 /// previously registered under the same key.
 pub fn register_core_content_matcher(key: &str, handler: Arc<dyn CoreContentMatcher>) {
-  CORE_CONTENT_MATCHERS.lock()
+  PluginDriver::current().inner.core_content_matchers.lock()
     .expect("CORE_CONTENT_MATCHERS mutex poisoned")
     .insert(key.to_string(), handler);
 }
@@ -78,21 +71,21 @@ pub fn register_core_content_matcher(key: &str, handler: Arc<dyn CoreContentMatc
 /// entry key (e.g. `"xml"` for the `core/content-generator/xml` entry). Replaces any handler
 /// previously registered under the same key.
 pub fn register_core_content_generator(key: &str, handler: Arc<dyn CoreContentGenerator>) {
-  CORE_CONTENT_GENERATORS.lock()
+  PluginDriver::current().inner.core_content_generators.lock()
     .expect("CORE_CONTENT_GENERATORS mutex poisoned")
     .insert(key.to_string(), handler);
 }
 
 /// Look up a registered core content matcher handler by catalogue entry key.
 pub fn lookup_core_content_matcher(key: &str) -> Option<Arc<dyn CoreContentMatcher>> {
-  CORE_CONTENT_MATCHERS.lock()
+  PluginDriver::current().inner.core_content_matchers.lock()
     .expect("CORE_CONTENT_MATCHERS mutex poisoned")
     .get(key).cloned()
 }
 
 /// Look up a registered core content generator handler by catalogue entry key.
 pub fn lookup_core_content_generator(key: &str) -> Option<Arc<dyn CoreContentGenerator>> {
-  CORE_CONTENT_GENERATORS.lock()
+  PluginDriver::current().inner.core_content_generators.lock()
     .expect("CORE_CONTENT_GENERATORS mutex poisoned")
     .get(key).cloned()
 }
@@ -101,7 +94,7 @@ pub fn lookup_core_content_generator(key: &str) -> Option<Arc<dyn CoreContentGen
 /// (e.g. `"type"` for the `core/matcher/type` entry). Replaces any handler previously
 /// registered under the same key.
 pub fn register_core_field_matcher(key: &str, handler: Arc<dyn CoreFieldMatcher>) {
-  CORE_FIELD_MATCHERS.lock()
+  PluginDriver::current().inner.core_field_matchers.lock()
     .expect("CORE_FIELD_MATCHERS mutex poisoned")
     .insert(key.to_string(), handler);
 }
@@ -110,49 +103,49 @@ pub fn register_core_field_matcher(key: &str, handler: Arc<dyn CoreFieldMatcher>
 /// (e.g. `"date"` for the `core/generator/date` entry). Replaces any handler previously
 /// registered under the same key.
 pub fn register_core_field_generator(key: &str, handler: Arc<dyn CoreFieldGenerator>) {
-  CORE_FIELD_GENERATORS.lock()
+  PluginDriver::current().inner.core_field_generators.lock()
     .expect("CORE_FIELD_GENERATORS mutex poisoned")
     .insert(key.to_string(), handler);
 }
 
 /// Look up a registered core field matcher handler by catalogue entry key.
 pub fn lookup_core_field_matcher(key: &str) -> Option<Arc<dyn CoreFieldMatcher>> {
-  CORE_FIELD_MATCHERS.lock()
+  PluginDriver::current().inner.core_field_matchers.lock()
     .expect("CORE_FIELD_MATCHERS mutex poisoned")
     .get(key).cloned()
 }
 
 /// Look up a registered core field generator handler by catalogue entry key.
 pub fn lookup_core_field_generator(key: &str) -> Option<Arc<dyn CoreFieldGenerator>> {
-  CORE_FIELD_GENERATORS.lock()
+  PluginDriver::current().inner.core_field_generators.lock()
     .expect("CORE_FIELD_GENERATORS mutex poisoned")
     .get(key).cloned()
 }
 
 /// Remove a registered core field matcher handler. Mainly useful for tests.
 pub fn deregister_core_field_matcher(key: &str) {
-  CORE_FIELD_MATCHERS.lock()
+  PluginDriver::current().inner.core_field_matchers.lock()
     .expect("CORE_FIELD_MATCHERS mutex poisoned")
     .remove(key);
 }
 
 /// Remove a registered core field generator handler. Mainly useful for tests.
 pub fn deregister_core_field_generator(key: &str) {
-  CORE_FIELD_GENERATORS.lock()
+  PluginDriver::current().inner.core_field_generators.lock()
     .expect("CORE_FIELD_GENERATORS mutex poisoned")
     .remove(key);
 }
 
 /// Remove a registered core content matcher handler. Mainly useful for tests.
 pub fn deregister_core_content_matcher(key: &str) {
-  CORE_CONTENT_MATCHERS.lock()
+  PluginDriver::current().inner.core_content_matchers.lock()
     .expect("CORE_CONTENT_MATCHERS mutex poisoned")
     .remove(key);
 }
 
 /// Remove a registered core content generator handler. Mainly useful for tests.
 pub fn deregister_core_content_generator(key: &str) {
-  CORE_CONTENT_GENERATORS.lock()
+  PluginDriver::current().inner.core_content_generators.lock()
     .expect("CORE_CONTENT_GENERATORS mutex poisoned")
     .remove(key);
 }
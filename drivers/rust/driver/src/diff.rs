@@ -0,0 +1,353 @@
+//! Diffs of the expected and actual values of content mismatches.
+//!
+//! Plugins can return a diff as text, or with the V2 interface a structured diff (a list of the
+//! changes at each path). If a plugin does not return a diff, the driver generates one from the
+//! expected and actual values: a structured diff for JSON values, and a unified diff for
+//! multi-line text values.
+//! Structured diffs are always rendered as text the same way, so reports look the same whichever
+//! plugin produced the mismatch.
+
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::str::from_utf8;
+
+use pact_models::path_exp::DocPath;
+use serde_json::Value;
+
+use crate::content::ContentMismatch;
+use crate::proto_v2::DiffEntry as ProtoDiffEntry;
+use crate::proto_v2::diff_entry::ChangeType;
+
+/// Number of unchanged lines shown around the changes in a unified diff
+pub const DIFF_CONTEXT_LINES: usize = 3;
+
+/// Type of change in a structured diff
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DiffChangeType {
+  /// The value at the path is different
+  CHANGED,
+  /// The value at the path is in the actual value but not the expected one
+  ADDED,
+  /// The value at the path is in the expected value but not the actual one
+  REMOVED
+}
+
+/// A change in a structured diff of the expected and actual values
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffEntry {
+  /// Type of change
+  pub change_type: DiffChangeType,
+  /// Path to the value that changed, as a Pact matching rule expression. Paths are relative to
+  /// the values being compared.
+  pub path: String,
+  /// Expected value. Not set for added values.
+  pub expected: Option<Value>,
+  /// Actual value. Not set for removed values.
+  pub actual: Option<Value>
+}
+
+impl DiffEntry {
+  /// Converts a diff entry returned from a plugin. Values that are not valid JSON are taken as
+  /// strings.
+  pub fn from_proto(entry: &ProtoDiffEntry) -> DiffEntry {
+    let from_json = |json: &String| serde_json::from_str(json).unwrap_or_else(|_| Value::String(json.clone()));
+    DiffEntry {
+      change_type: match entry.change_type() {
+        ChangeType::Changed => DiffChangeType::CHANGED,
+        ChangeType::Added => DiffChangeType::ADDED,
+        ChangeType::Removed => DiffChangeType::REMOVED
+      },
+      path: entry.path.clone(),
+      expected: entry.expected.as_ref().map(from_json),
+      actual: entry.actual.as_ref().map(from_json)
+    }
+  }
+
+  /// Converts this diff entry into the form sent over the plugin interface
+  pub fn to_proto(&self) -> ProtoDiffEntry {
+    ProtoDiffEntry {
+      change_type: match self.change_type {
+        DiffChangeType::CHANGED => ChangeType::Changed,
+        DiffChangeType::ADDED => ChangeType::Added,
+        DiffChangeType::REMOVED => ChangeType::Removed
+      } as i32,
+      path: self.path.clone(),
+      expected: self.expected.as_ref().map(|value| value.to_string()),
+      actual: self.actual.as_ref().map(|value| value.to_string())
+    }
+  }
+}
+
+impl Display for DiffEntry {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let display = |value: &Option<Value>| value.as_ref()
+      .map(|value| value.to_string())
+      .unwrap_or_else(|| "null".to_string());
+    match self.change_type {
+      DiffChangeType::CHANGED => write!(f, "~ {}: {} -> {}", self.path, display(&self.expected), display(&self.actual)),
+      DiffChangeType::ADDED => write!(f, "+ {}: {}", self.path, display(&self.actual)),
+      DiffChangeType::REMOVED => write!(f, "- {}: {}", self.path, display(&self.expected))
+    }
+  }
+}
+
+/// Renders a structured diff as text, with one line per change.
+pub fn render_structured_diff(entries: &[DiffEntry]) -> String {
+  entries.iter().map(|entry| entry.to_string()).collect::<Vec<_>>().join("\n")
+}
+
+/// Generates a structured diff of two JSON values. Object keys are compared in sorted order, and
+/// array items are compared by index.
+pub fn json_diff(expected: &Value, actual: &Value) -> Vec<DiffEntry> {
+  let mut entries = vec![];
+  json_diff_at(&DocPath::root(), expected, actual, &mut entries);
+  entries
+}
+
+fn json_diff_at(path: &DocPath, expected: &Value, actual: &Value, entries: &mut Vec<DiffEntry>) {
+  match (expected, actual) {
+    (Value::Object(expected), Value::Object(actual)) => {
+      let keys: BTreeSet<&String> = expected.keys().chain(actual.keys()).collect();
+      for key in keys {
+        let item_path = path.join_field(key.as_str());
+        match (expected.get(key), actual.get(key)) {
+          (Some(expected), Some(actual)) => json_diff_at(&item_path, expected, actual, entries),
+          (Some(expected), None) => entries.push(removed(&item_path, expected)),
+          (None, Some(actual)) => entries.push(added(&item_path, actual)),
+          (None, None) => {}
+        }
+      }
+    }
+    (Value::Array(expected), Value::Array(actual)) => {
+      for index in 0..expected.len().max(actual.len()) {
+        let item_path = path.join_index(index);
+        match (expected.get(index), actual.get(index)) {
+          (Some(expected), Some(actual)) => json_diff_at(&item_path, expected, actual, entries),
+          (Some(expected), None) => entries.push(removed(&item_path, expected)),
+          (None, Some(actual)) => entries.push(added(&item_path, actual)),
+          (None, None) => {}
+        }
+      }
+    }
+    _ => if expected != actual {
+      entries.push(DiffEntry {
+        change_type: DiffChangeType::CHANGED,
+        path: path.to_string(),
+        expected: Some(expected.clone()),
+        actual: Some(actual.clone())
+      });
+    }
+  }
+}
+
+fn added(path: &DocPath, value: &Value) -> DiffEntry {
+  DiffEntry {
+    change_type: DiffChangeType::ADDED,
+    path: path.to_string(),
+    expected: None,
+    actual: Some(value.clone())
+  }
+}
+
+fn removed(path: &DocPath, value: &Value) -> DiffEntry {
+  DiffEntry {
+    change_type: DiffChangeType::REMOVED,
+    path: path.to_string(),
+    expected: Some(value.clone()),
+    actual: None
+  }
+}
+
+/// Generates a unified diff of two text values, with [`DIFF_CONTEXT_LINES`] lines of context
+/// around each change. Returns an empty string if the values are the same.
+pub fn unified_diff(expected: &str, actual: &str) -> String {
+  // Each line of the diff, with the (1-based) line numbers it has in the expected and actual values
+  let mut lines = vec![];
+  let (mut expected_line, mut actual_line) = (1, 1);
+  for result in diff::lines(expected, actual) {
+    match result {
+      diff::Result::Both(line, _) => {
+        lines.push((' ', line, expected_line, actual_line));
+        expected_line += 1;
+        actual_line += 1;
+      }
+      diff::Result::Left(line) => {
+        lines.push(('-', line, expected_line, actual_line));
+        expected_line += 1;
+      }
+      diff::Result::Right(line) => {
+        lines.push(('+', line, expected_line, actual_line));
+        actual_line += 1;
+      }
+    }
+  }
+
+  // Group the changes into hunks, merging changes whose context overlaps
+  let mut hunks: Vec<(usize, usize)> = vec![];
+  for (index, (op, ..)) in lines.iter().enumerate() {
+    if *op == ' ' {
+      continue;
+    }
+    let start = index.saturating_sub(DIFF_CONTEXT_LINES);
+    let end = (index + DIFF_CONTEXT_LINES + 1).min(lines.len());
+    match hunks.last_mut() {
+      Some(hunk) if start <= hunk.1 => hunk.1 = end,
+      _ => hunks.push((start, end))
+    }
+  }
+  if hunks.is_empty() {
+    return String::default();
+  }
+
+  let mut result = vec!["--- expected".to_string(), "+++ actual".to_string()];
+  for (start, end) in hunks {
+    let hunk = &lines[start..end];
+    let expected_count = hunk.iter().filter(|(op, ..)| *op != '+').count();
+    let actual_count = hunk.iter().filter(|(op, ..)| *op != '-').count();
+    let (_, _, expected_start, actual_start) = hunk[0];
+    result.push(format!("@@ -{} +{} @@",
+      hunk_range(expected_start, expected_count), hunk_range(actual_start, actual_count)));
+    result.extend(hunk.iter().map(|(op, line, ..)| format!("{}{}", op, line)));
+  }
+  result.join("\n")
+}
+
+fn hunk_range(start: usize, count: usize) -> String {
+  match count {
+    // An empty range refers to the line before it
+    0 => format!("{},0", start - 1),
+    1 => start.to_string(),
+    _ => format!("{},{}", start, count)
+  }
+}
+
+/// Generates a diff for a mismatch from its structured diff, or from its expected and actual
+/// values if it does not have one. JSON values (where the mismatch type or content type is JSON)
+/// get a structured diff, and multi-line text values get a unified diff. Single line values are
+/// not diffed, as the expected and actual values already show the difference.
+///
+/// Returns the structured diff and the diff as text.
+pub fn generate_diff(mismatch: &ContentMismatch) -> (Vec<DiffEntry>, Option<String>) {
+  let structured_diff = mismatch.structured_diff();
+  if !structured_diff.is_empty() {
+    return (structured_diff.to_vec(), Some(render_structured_diff(structured_diff)));
+  }
+
+  let (Some(expected), Some(actual)) = (mismatch.expected_bytes(), mismatch.actual_bytes()) else {
+    return (vec![], None);
+  };
+  if expected == actual {
+    return (vec![], None);
+  }
+
+  if is_json(mismatch)
+    && let (Ok(expected), Ok(actual)) = (serde_json::from_slice(expected), serde_json::from_slice(actual)) {
+    let entries = json_diff(&expected, &actual);
+    if !entries.is_empty() {
+      let diff = render_structured_diff(&entries);
+      return (entries, Some(diff));
+    }
+  }
+
+  if is_text(mismatch)
+    && let (Ok(expected), Ok(actual)) = (from_utf8(expected), from_utf8(actual))
+    && (expected.contains('\n') || actual.contains('\n')) {
+    return (vec![], Some(unified_diff(expected, actual)));
+  }
+
+  (vec![], None)
+}
+
+fn is_json(mismatch: &ContentMismatch) -> bool {
+  mismatch.mismatch_type.as_ref().is_some_and(|mismatch_type| mismatch_type.to_lowercase().contains("json"))
+    || mismatch.content_type().is_some_and(|content_type| content_type.is_json())
+}
+
+fn is_text(mismatch: &ContentMismatch) -> bool {
+  mismatch.content_type().map(|content_type| content_type.is_text()).unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+  use bytes::Bytes;
+  use expectest::prelude::*;
+  use pact_models::content_types::ContentType;
+  use serde_json::json;
+
+  use crate::content::ContentMismatch;
+
+  use super::*;
+
+  #[test]
+  fn json_diff_test() {
+    let expected = json!({ "a": 1, "b": [1, 2, 3], "c": { "d": "x", "e f": true } });
+    let actual = json!({ "a": 2, "b": [1, 2], "c": { "d": "x", "e f": false }, "g": null });
+    expect!(render_structured_diff(&json_diff(&expected, &actual))).to(be_equal_to(
+      "~ $.a: 1 -> 2\n- $.b[2]: 3\n~ $.c['e f']: true -> false\n+ $.g: null"));
+    expect!(json_diff(&expected, &expected)).to(be_equal_to(vec![]));
+    expect!(json_diff(&json!([1]), &json!({"a": 1}))).to(be_equal_to(vec![DiffEntry {
+      change_type: DiffChangeType::CHANGED,
+      path: "$".to_string(),
+      expected: Some(json!([1])),
+      actual: Some(json!({"a": 1}))
+    }]));
+  }
+
+  #[test]
+  fn unified_diff_test() {
+    expect!(unified_diff("a\nb\nc", "a\nb\nc")).to(be_equal_to(""));
+    expect!(unified_diff("a\nb\nc", "a\nB\nc")).to(be_equal_to(
+      "--- expected\n+++ actual\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c"));
+    expect!(unified_diff("1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12", "0\n1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11")).to(be_equal_to(
+      "--- expected\n+++ actual\n@@ -1,3 +1,4 @@\n+0\n 1\n 2\n 3\n@@ -9,4 +10,3 @@\n 9\n 10\n 11\n-12"));
+  }
+
+  #[test]
+  fn diff_entries_round_trip_through_the_plugin_interface() {
+    let entry = DiffEntry {
+      change_type: DiffChangeType::REMOVED,
+      path: "$.a".to_string(),
+      expected: Some(json!({ "b": [1.5, "2"] })),
+      actual: None
+    };
+    expect!(DiffEntry::from_proto(&entry.to_proto())).to(be_equal_to(entry));
+  }
+
+  fn mismatch(expected: &str, actual: &str, content_type: Option<&str>, mismatch_type: Option<&str>) -> ContentMismatch {
+    ContentMismatch {
+      mismatch_type: mismatch_type.map(|mismatch_type| mismatch_type.to_string()),
+      .. ContentMismatch::from_bytes(
+        Some(Bytes::copy_from_slice(expected.as_bytes())),
+        Some(Bytes::copy_from_slice(actual.as_bytes())),
+        content_type.map(|content_type| ContentType::parse(content_type).unwrap()),
+        "mismatch",
+        "$"
+      )
+    }
+  }
+
+  #[test]
+  fn generate_diff_test() {
+    let (entries, diff) = generate_diff(&mismatch("{\"a\":1}", "{\"a\":2}", None, Some("json")));
+    expect!(entries.len()).to(be_equal_to(1));
+    expect!(diff).to(be_some().value("~ $.a: 1 -> 2"));
+    expect!(generate_diff(&mismatch("{\"a\":1}", "{\"a\":2}", Some("application/json"), None)).1)
+      .to(be_some().value("~ $.a: 1 -> 2"));
+
+    expect!(generate_diff(&mismatch("a\nb", "a\nc", Some("text/csv"), Some("body"))))
+      .to(be_equal_to((vec![], Some("--- expected\n+++ actual\n@@ -1,2 +1,2 @@\n a\n-b\n+c".to_string()))));
+    expect!(generate_diff(&mismatch("a\nb", "a\nc", None, None)).1).to(be_some());
+    expect!(generate_diff(&mismatch("a\nb", "a\nc", Some("application/octet-stream"), None)).1).to(be_none());
+    expect!(generate_diff(&mismatch("a", "b", Some("text/plain"), None)).1).to(be_none());
+    expect!(generate_diff(&mismatch("a\nb", "a\nb", None, None)).1).to(be_none());
+    expect!(generate_diff(&ContentMismatch::new("a\nb", "a\nc", "mismatch", "$")).1).to(be_none());
+
+    let structured = mismatch("a\nb", "a\nc", None, None).with_structured_diff(vec![DiffEntry {
+      change_type: DiffChangeType::ADDED,
+      path: "$[1]".to_string(),
+      expected: None,
+      actual: Some(json!("b"))
+    }]);
+    expect!(generate_diff(&structured).1).to(be_some().value("+ $[1]: \"b\""));
+  }
+}
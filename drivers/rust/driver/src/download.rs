@@ -19,9 +19,14 @@ use tracing::{debug, info};
 use futures_util::StreamExt;
 
 use crate::plugin_models::PactPluginManifest;
+use crate::signatures::{SIGNATURE_EXTENSION, trusted_keys, verify_signature};
 use crate::utils::os_and_arch;
+use minisign_verify::PublicKey;
 use tar::Archive;
 
+/// File name of the plugin manifest
+const MANIFEST_FILE: &str = "pact-plugin.json";
+
 pub(crate) async fn fetch_json_from_url(source: &str, http_client: &Client) -> anyhow::Result<Value> {
   info!(%source, "Fetching root document for source");
   let response: Value = http_client.get(source)
@@ -58,6 +63,20 @@ pub async fn download_plugin_executable(
   base_url: &str,
   tag: &String,
   display_progress: bool
+) -> anyhow::Result<PathBuf> {
+  download_plugin_executable_from(manifest, plugin_dir, http_client, &release_url(base_url, tag),
+    display_progress).await
+}
+
+/// Downloads the plugin executable for the current OS and architecture from the given location,
+/// which is the URL of a directory holding the plugin files. This can be a `file://` URL, for
+/// instance for a plugin repository mirror (see [`crate::repository::mirrored_plugin_url`]).
+pub async fn download_plugin_executable_from(
+  manifest: &PactPluginManifest,
+  plugin_dir: &PathBuf,
+  http_client: &Client,
+  location: &str,
+  display_progress: bool
 ) -> anyhow::Result<PathBuf> {
   let (os, arch) = os_and_arch()?;
 
@@ -65,15 +84,16 @@ pub async fn download_plugin_executable(
   let ext = if os == "windows" { ".exe" } else { "" };
   let gz_file = format!("pact-{}-plugin-{}-{}{}.gz", manifest.name, os, arch, ext);
   let sha_file = format!("pact-{}-plugin-{}-{}{}.gz.sha256", manifest.name, os, arch, ext);
-  if github_file_exists(http_client, base_url, tag, gz_file.as_str()).await? {
+  if file_exists(http_client, location, gz_file.as_str()).await? {
     debug!(file = %gz_file, "Found a GZipped file");
-    let file = download_file_from_github(http_client, base_url, tag, gz_file.as_str(), plugin_dir, display_progress).await?;
+    let file = download_file(http_client, location, gz_file.as_str(), plugin_dir, display_progress).await?;
 
-    if github_file_exists(http_client, base_url, tag, sha_file.as_str()).await? {
-      let sha_file = download_file_from_github(http_client, base_url, tag, sha_file.as_str(), plugin_dir, display_progress).await?;
+    if file_exists(http_client, location, sha_file.as_str()).await? {
+      let sha_file = download_file(http_client, location, sha_file.as_str(), plugin_dir, display_progress).await?;
       check_sha(&file, &sha_file)?;
       fs::remove_file(sha_file)?;
     }
+    check_signature(http_client, location, gz_file.as_str(), &file, &trusted_keys()?).await?;
 
     let file = gunzip_file(&file, plugin_dir, manifest, ext)?;
     #[cfg(unix)]
@@ -89,50 +109,71 @@ pub async fn download_plugin_executable(
   // Check for an arch specific Zip file
   let zip_file = format!("pact-{}-plugin-{}-{}.zip", manifest.name, os, arch);
   let zip_sha_file = format!("pact-{}-plugin-{}-{}.zip.sha256", manifest.name, os, arch);
-  if github_file_exists(http_client, base_url, tag, zip_file.as_str()).await? {
-    return download_zip_file(plugin_dir, http_client, base_url, tag, zip_file, zip_sha_file, display_progress).await;
+  if file_exists(http_client, location, zip_file.as_str()).await? {
+    return install_zip_file(plugin_dir, http_client, location, zip_file, zip_sha_file, display_progress).await;
   }
 
   // Check for a Zip file
   let zip_file = format!("pact-{}-plugin.zip", manifest.name);
   let zip_sha_file = format!("pact-{}-plugin.zip.sha256", manifest.name);
-  if github_file_exists(http_client, base_url, tag, zip_file.as_str()).await? {
-    return download_zip_file(plugin_dir, http_client, base_url, tag, zip_file, zip_sha_file, display_progress).await;
+  if file_exists(http_client, location, zip_file.as_str()).await? {
+    return install_zip_file(plugin_dir, http_client, location, zip_file, zip_sha_file, display_progress).await;
   }
 
   // Check for a tar.gz file
   let tar_gz_file = format!("pact-{}-plugin.tar.gz", manifest.name);
   let tar_gz_sha_file = format!("pact-{}-plugin.tar.gz.sha256", manifest.name);
-  if github_file_exists(http_client, base_url, tag, tar_gz_file.as_str()).await? {
-    return download_tar_gz_file(plugin_dir, http_client, base_url, tag, tar_gz_file, tar_gz_sha_file, display_progress).await;
+  if file_exists(http_client, location, tar_gz_file.as_str()).await? {
+    return install_tar_gz_file(plugin_dir, http_client, location, tar_gz_file, tar_gz_sha_file, display_progress).await;
   }
 
   // Check for an arch specific tar.gz file
   let tar_gz_file = format!("pact-{}-plugin-{}-{}.tar.gz", manifest.name, os, arch);
   let tar_gz_sha_file = format!("pact-{}-plugin-{}-{}.tar.gz.sha256", manifest.name, os, arch);
-  if github_file_exists(http_client, base_url, tag, tar_gz_file.as_str()).await? {
-    return download_tar_gz_file(plugin_dir, http_client, base_url, tag, tar_gz_file, tar_gz_sha_file, display_progress).await;
+  if file_exists(http_client, location, tar_gz_file.as_str()).await? {
+    return install_tar_gz_file(plugin_dir, http_client, location, tar_gz_file, tar_gz_sha_file, display_progress).await;
   }
 
   // Check for an arch specific tgz file
   let tgz_file = format!("pact-{}-plugin-{}-{}.tgz", manifest.name, os, arch);
   let tgz_sha_file = format!("pact-{}-plugin-{}-{}.tgz.sha256", manifest.name, os, arch);
-  if github_file_exists(http_client, base_url, tag, tgz_file.as_str()).await? {
-    return download_tar_gz_file(plugin_dir, http_client, base_url, tag, tgz_file, tgz_sha_file, display_progress).await;
+  if file_exists(http_client, location, tgz_file.as_str()).await? {
+    return install_tar_gz_file(plugin_dir, http_client, location, tgz_file, tgz_sha_file, display_progress).await;
   }
 
   // Check for a tgz file
   let tgz_file = format!("pact-{}-plugin.tgz", manifest.name);
   let tgz_sha_file = format!("pact-{}-plugin.tgz.sha256", manifest.name);
-  if github_file_exists(http_client, base_url, tag, tgz_file.as_str()).await? {
-    return download_tar_gz_file(plugin_dir, http_client, base_url, tag, tgz_file, tgz_sha_file, display_progress).await;
+  if file_exists(http_client, location, tgz_file.as_str()).await? {
+    return install_tar_gz_file(plugin_dir, http_client, location, tgz_file, tgz_sha_file, display_progress).await;
   }
 
-  bail!("Did not find a matching file pattern on GitHub to install")
+  bail!("Did not find a matching file pattern at {} to install", location)
 }
 
-async fn github_file_exists(http_client: &Client, base_url: &str, tag: &String, filename: &str) -> anyhow::Result<bool> {
-  let url = format!("{}/download/{}/{}", base_url, tag, filename);
+/// URL of the directory holding the files of a GitHub release
+pub(crate) fn release_url(base_url: &str, tag: &String) -> String {
+  format!("{}/download/{}", base_url, tag)
+}
+
+/// If the location is a `file://` URL, the path of the file in that directory
+fn local_file(location: &str, filename: &str) -> anyhow::Result<Option<PathBuf>> {
+  if location.starts_with("file:") {
+    let dir = reqwest::Url::parse(location)?
+      .to_file_path()
+      .map_err(|_| anyhow!("'{}' is not a valid file URL", location))?;
+    Ok(Some(dir.join(filename)))
+  } else {
+    Ok(None)
+  }
+}
+
+async fn file_exists(http_client: &Client, location: &str, filename: &str) -> anyhow::Result<bool> {
+  if let Some(path) = local_file(location, filename)? {
+    debug!("Checking existence of file {}", path.display());
+    return Ok(path.is_file());
+  }
+  let url = format!("{}/{}", location, filename);
   debug!("Checking existence of file from {}", url);
   Ok(http_client.head(url)
     .send()
@@ -140,6 +181,74 @@ async fn github_file_exists(http_client: &Client, base_url: &str, tag: &String,
     .status().is_success())
 }
 
+/// Fetches the contents of a text file from the given location, which is the URL of the directory
+/// holding it. This can be a `file://` URL.
+pub async fn fetch_text_file(http_client: &Client, location: &str, filename: &str) -> anyhow::Result<String> {
+  if let Some(path) = local_file(location, filename)? {
+    debug!("Reading file {}", path.display());
+    return fs::read_to_string(&path).with_context(|| format!("Could not read {}", path.display()));
+  }
+  let url = format!("{}/{}", location, filename);
+  debug!("Downloading file from {}", url);
+  Ok(http_client.get(url)
+    .send()
+    .await?
+    .error_for_status()?
+    .text()
+    .await?)
+}
+
+/// Fetches the `pact-plugin.json` manifest of a plugin from the given location, which is the URL
+/// of the directory holding the plugin files (this can be a `file://` URL). The manifest decides
+/// what gets run for the plugin, so if trusted signing keys are configured (see
+/// [`crate::signatures`]) it must have a valid signature (`pact-plugin.json.minisig`), which is
+/// checked before the manifest is used.
+pub async fn fetch_plugin_manifest(http_client: &Client, location: &str) -> anyhow::Result<PactPluginManifest> {
+  fetch_signed_manifest(http_client, location, &trusted_keys()?).await
+}
+
+async fn fetch_signed_manifest(
+  http_client: &Client,
+  location: &str,
+  keys: &[PublicKey]
+) -> anyhow::Result<PactPluginManifest> {
+  let manifest_json = fetch_file(http_client, location, MANIFEST_FILE)
+    .await
+    .with_context(|| format!("Downloading manifest file from {}", location))?;
+
+  if !keys.is_empty() {
+    let signature_file = format!("{}.{}", MANIFEST_FILE, SIGNATURE_EXTENSION);
+    let result = match fetch_text_file(http_client, location, signature_file.as_str()).await {
+      Ok(signature) => verify_signature(MANIFEST_FILE, &manifest_json, signature.as_str(), keys),
+      Err(err) => Err(anyhow!("Could not get the signature file {} - {}", signature_file, err))
+    };
+    if let Err(err) = result {
+      bail!("Refusing to use the plugin manifest from {} as its signature could not be verified: {}", location, err);
+    }
+  }
+
+  serde_json::from_slice(&manifest_json)
+    .with_context(|| format!("Failed to parse JSON manifest file from {}", location))
+}
+
+/// Fetches the contents of a file from the given location, which is the URL of the directory
+/// holding it. This can be a `file://` URL.
+async fn fetch_file(http_client: &Client, location: &str, filename: &str) -> anyhow::Result<Vec<u8>> {
+  if let Some(path) = local_file(location, filename)? {
+    debug!("Reading file {}", path.display());
+    return fs::read(&path).with_context(|| format!("Could not read {}", path.display()));
+  }
+  let url = format!("{}/{}", location, filename);
+  debug!("Downloading file from {}", url);
+  Ok(http_client.get(url)
+    .send()
+    .await?
+    .error_for_status()?
+    .bytes()
+    .await?
+    .to_vec())
+}
+
 /// Downloads a plugin zip file from GitHub and installs it
 pub async fn download_zip_file(
   plugin_dir: &PathBuf,
@@ -149,15 +258,28 @@ pub async fn download_zip_file(
   zip_file: String,
   zip_sha_file: String,
   display_progress: bool
+) -> anyhow::Result<PathBuf> {
+  install_zip_file(plugin_dir, http_client, &release_url(base_url, tag), zip_file, zip_sha_file,
+    display_progress).await
+}
+
+async fn install_zip_file(
+  plugin_dir: &PathBuf,
+  http_client: &Client,
+  location: &str,
+  zip_file: String,
+  zip_sha_file: String,
+  display_progress: bool
 ) -> anyhow::Result<PathBuf> {
   debug!(file = %zip_file, "Found a Zip file");
-  let file = download_file_from_github(http_client, base_url, tag, zip_file.as_str(), plugin_dir, display_progress).await?;
+  let file = download_file(http_client, location, zip_file.as_str(), plugin_dir, display_progress).await?;
 
-  if github_file_exists(http_client, base_url, tag, zip_sha_file.as_str()).await? {
-    let sha_file = download_file_from_github(http_client, base_url, tag, zip_sha_file.as_str(), plugin_dir, display_progress).await?;
+  if file_exists(http_client, location, zip_sha_file.as_str()).await? {
+    let sha_file = download_file(http_client, location, zip_sha_file.as_str(), plugin_dir, display_progress).await?;
     check_sha(&file, &sha_file)?;
     fs::remove_file(sha_file)?;
   }
+  check_signature(http_client, location, zip_file.as_str(), &file, &trusted_keys()?).await?;
 
   unzip_file(&file, plugin_dir)
 }
@@ -171,15 +293,28 @@ pub async fn download_tar_gz_file(
   tar_gz_file: String,
   tar_gz_sha_file: String,
   display_progress: bool
+) -> anyhow::Result<PathBuf> {
+  install_tar_gz_file(plugin_dir, http_client, &release_url(base_url, tag), tar_gz_file, tar_gz_sha_file,
+    display_progress).await
+}
+
+async fn install_tar_gz_file(
+  plugin_dir: &PathBuf,
+  http_client: &Client,
+  location: &str,
+  tar_gz_file: String,
+  tar_gz_sha_file: String,
+  display_progress: bool
 ) -> anyhow::Result<PathBuf> {
   debug!(file = %tar_gz_file, "Found a tar gz file");
-  let file = download_file_from_github(http_client, base_url, tag, tar_gz_file.as_str(), plugin_dir, display_progress).await?;
+  let file = download_file(http_client, location, tar_gz_file.as_str(), plugin_dir, display_progress).await?;
 
-  if github_file_exists(http_client, base_url, tag, tar_gz_sha_file.as_str()).await? {
-    let sha_file = download_file_from_github(http_client, base_url, tag, tar_gz_sha_file.as_str(), plugin_dir, display_progress).await?;
+  if file_exists(http_client, location, tar_gz_sha_file.as_str()).await? {
+    let sha_file = download_file(http_client, location, tar_gz_sha_file.as_str(), plugin_dir, display_progress).await?;
     check_sha(&file, &sha_file)?;
     fs::remove_file(sha_file)?;
   }
+  check_signature(http_client, location, tar_gz_file.as_str(), &file, &trusted_keys()?).await?;
 
   extract_tar_gz(&file, plugin_dir)
 }
@@ -261,7 +396,33 @@ pub async fn download_file_from_github(
   plugin_dir: &PathBuf,
   display_progress: bool
 ) -> anyhow::Result<PathBuf> {
-  let url = format!("{}/download/{}/{}", base_url, tag, filename);
+  download_file(http_client, &release_url(base_url, tag), filename, plugin_dir, display_progress).await
+}
+
+/// Downloads a file from the given location into the plugin directory, showing console progress.
+/// A file from a `file://` location is copied.
+async fn download_file(
+  http_client: &Client,
+  location: &str,
+  filename: &str,
+  plugin_dir: &PathBuf,
+  display_progress: bool
+) -> anyhow::Result<PathBuf> {
+  let path = plugin_dir.join(filename);
+  if let Some(source) = local_file(location, filename)? {
+    debug!("Copying file from {}", source.display());
+    let copied = fs::copy(&source, &path)
+      .with_context(|| format!("Could not copy {} to {}", source.display(), path.display()))?;
+    if display_progress {
+      let pb = ProgressBar::new(copied);
+      pb.set_style(ProgressStyle::with_template("{msg}").unwrap());
+      pb.finish_with_message(format!("Copied {} to {}", source.display(), path.display()));
+    }
+    debug!(source = %source.display(), copied_bytes = copied, "File copied OK");
+    return Ok(path);
+  }
+
+  let url = format!("{}/{}", location, filename);
   debug!("Downloading file from {}", url);
 
   let res = http_client.get(url.as_str()).send().await?;
@@ -277,7 +438,6 @@ pub async fn download_file_from_github(
     pb.set_message(format!("Downloading {}", url));
   }
 
-  let path = plugin_dir.join(filename);
   let mut file = File::create(path.clone())?;
   let mut downloaded: u64 = 0;
   let mut stream = res.bytes_stream();
@@ -299,6 +459,36 @@ pub async fn download_file_from_github(
   Ok(path.clone())
 }
 
+/// If there are trusted signing keys, checks the downloaded file against its minisign signature
+/// (`<file>.minisig`) from the same location. The file is removed and an error returned if the
+/// signature is missing or not valid.
+async fn check_signature(
+  http_client: &Client,
+  location: &str,
+  filename: &str,
+  file: &PathBuf,
+  keys: &[PublicKey]
+) -> anyhow::Result<()> {
+  if keys.is_empty() {
+    return Ok(());
+  }
+
+  let signature_file = format!("{}.{}", filename, SIGNATURE_EXTENSION);
+  let result = match fetch_text_file(http_client, location, signature_file.as_str()).await {
+    Ok(signature) => {
+      let data = fs::read(file)?;
+      verify_signature(filename, &data, signature.as_str(), keys)
+    }
+    Err(err) => Err(anyhow!("Could not get the signature file {} - {}", signature_file, err))
+  };
+
+  if let Err(err) = result {
+    fs::remove_file(file)?;
+    bail!("Refusing to install {} as its signature could not be verified: {}", filename, err);
+  }
+  Ok(())
+}
+
 /// Validates a file against a SHA file
 pub fn check_sha(file: &PathBuf, sha_file: &PathBuf) -> anyhow::Result<()> {
   debug!(file = %file.display(), sha_file = %sha_file.display(), "Checking SHA of downloaded file");
@@ -333,3 +523,128 @@ pub fn check_sha(file: &PathBuf, sha_file: &PathBuf) -> anyhow::Result<()> {
       file.display(), sha, calculated))
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use std::fs::{self, File};
+  use std::io::Write;
+
+  use expectest::prelude::*;
+  use flate2::Compression;
+  use flate2::write::GzEncoder;
+  use reqwest::{Client, Url};
+  use sha2::{Digest, Sha256};
+
+  use crate::plugin_models::PactPluginManifest;
+  use crate::utils::os_and_arch;
+  use crate::signatures::tests::{PUBLIC_KEY, SIGNATURE};
+
+  use super::{check_signature, download_plugin_executable_from, fetch_signed_manifest, fetch_text_file};
+
+  const MANIFEST: &str = r#"{"manifestVersion":1,"pluginInterfaceVersion":1,"name":"test","version":"0.0.1","executableType":"exec","entryPoint":"test-plugin"}"#;
+  const MANIFEST_PUBLIC_KEY: &str = "RWR1mbkJ1i/DfAwB0Xib5+0MFOfS0fU1b1m4OEZgMYJlMZSI2Kb9hguK";
+  /// Signature of `MANIFEST` made with the secret key for `MANIFEST_PUBLIC_KEY`
+  const MANIFEST_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUR1mbkJ1i/DfNCRLOVAXwtc8Em+BkLou/7grZQ9XbwbFv0F+9ZqgW8+ce2DWamsGhBwxiWOT/c2KHUMecUbt4V5jiAKLF2aFwM=
+trusted comment: timestamp:1792300000\tfile:pact-plugin.json
+eoCbbiUTRZv5YdewKmRNgpqa78JVdgwKK0yufevdUkJfYe1qiNlKdCf55Pwll9uF4Zv8C6Ush1hOc/GU819+Dg==
+";
+
+  fn http_client() -> Client {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    Client::new()
+  }
+
+  #[tokio::test]
+  async fn fetch_text_file_reads_from_a_file_url() {
+    let mirror = tempdir::TempDir::new("plugin-mirror").unwrap();
+    fs::write(mirror.path().join("repository.index.sha256"), "1234").unwrap();
+    let url = Url::from_directory_path(mirror.path()).unwrap().to_string();
+
+    let contents = fetch_text_file(&http_client(), url.trim_end_matches('/'), "repository.index.sha256").await;
+    expect!(contents.unwrap()).to(be_equal_to("1234"));
+
+    let missing = fetch_text_file(&http_client(), url.trim_end_matches('/'), "repository.index").await;
+    expect!(missing).to(be_err());
+  }
+
+  #[tokio::test]
+  async fn download_plugin_executable_from_installs_from_a_file_url() {
+    let mirror = tempdir::TempDir::new("plugin-mirror").unwrap();
+    let plugin_dir = tempdir::TempDir::new("plugin-dir").unwrap();
+    let manifest = PactPluginManifest {
+      name: "test".to_string(),
+      version: "0.0.1".to_string(),
+      entry_point: "test-plugin".to_string(),
+      .. PactPluginManifest::default()
+    };
+
+    let (os, arch) = os_and_arch().unwrap();
+    let ext = if os == "windows" { ".exe" } else { "" };
+    let gz_file = format!("pact-test-plugin-{}-{}{}.gz", os, arch, ext);
+    let mut encoder = GzEncoder::new(File::create(mirror.path().join(&gz_file)).unwrap(), Compression::default());
+    encoder.write_all(b"plugin executable").unwrap();
+    encoder.finish().unwrap();
+    let sha = format!("{:x}", Sha256::digest(fs::read(mirror.path().join(&gz_file)).unwrap()));
+    fs::write(mirror.path().join(format!("{}.sha256", gz_file)), sha).unwrap();
+
+    let url = Url::from_directory_path(mirror.path()).unwrap().to_string();
+    let result = download_plugin_executable_from(&manifest, &plugin_dir.path().to_path_buf(),
+      &http_client(), url.trim_end_matches('/'), false).await;
+
+    let file = result.unwrap();
+    expect!(fs::read_to_string(&file).unwrap()).to(be_equal_to("plugin executable"));
+    expect!(plugin_dir.path().join(&gz_file).exists()).to(be_false());
+    expect!(mirror.path().join(&gz_file).exists()).to(be_true());
+  }
+
+  #[tokio::test]
+  async fn check_signature_refuses_files_without_a_valid_signature() {
+    let mirror = tempdir::TempDir::new("plugin-mirror").unwrap();
+    let plugin_dir = tempdir::TempDir::new("plugin-dir").unwrap();
+    let url = Url::from_directory_path(mirror.path()).unwrap().to_string();
+    let url = url.trim_end_matches('/');
+    let keys = [minisign_verify::PublicKey::from_base64(PUBLIC_KEY).unwrap()];
+    let file = plugin_dir.path().join("plugin.gz");
+
+    fs::write(&file, "plugin executable").unwrap();
+    expect!(check_signature(&http_client(), url, "plugin.gz", &file, &[]).await).to(be_ok());
+    let result = check_signature(&http_client(), url, "plugin.gz", &file, &keys).await;
+    expect!(result.unwrap_err().to_string()).to(be_equal_to(
+      "Refusing to install plugin.gz as its signature could not be verified: Could not get the signature file plugin.gz.minisig - Could not read ".to_string()
+        + mirror.path().join("plugin.gz.minisig").display().to_string().as_str()));
+    expect!(file.exists()).to(be_false());
+
+    fs::write(&file, "plugin executable").unwrap();
+    fs::write(mirror.path().join("plugin.gz.minisig"), SIGNATURE).unwrap();
+    expect!(check_signature(&http_client(), url, "plugin.gz", &file, &keys).await).to(be_ok());
+
+    fs::write(&file, "modified plugin executable").unwrap();
+    expect!(check_signature(&http_client(), url, "plugin.gz", &file, &keys).await).to(be_err());
+    expect!(file.exists()).to(be_false());
+  }
+
+  #[tokio::test]
+  async fn fetch_signed_manifest_refuses_a_manifest_without_a_valid_signature() {
+    let mirror = tempdir::TempDir::new("plugin-mirror").unwrap();
+    let url = Url::from_directory_path(mirror.path()).unwrap().to_string();
+    let url = url.trim_end_matches('/');
+    let keys = [minisign_verify::PublicKey::from_base64(MANIFEST_PUBLIC_KEY).unwrap()];
+    fs::write(mirror.path().join("pact-plugin.json"), MANIFEST).unwrap();
+
+    let manifest = fetch_signed_manifest(&http_client(), url, &[]).await.unwrap();
+    expect!(manifest.entry_point).to(be_equal_to("test-plugin"));
+
+    let result = fetch_signed_manifest(&http_client(), url, &keys).await;
+    expect!(result.unwrap_err().to_string().starts_with(format!(
+      "Refusing to use the plugin manifest from {} as its signature could not be verified: Could not get the signature file pact-plugin.json.minisig",
+      url).as_str())).to(be_true());
+
+    fs::write(mirror.path().join("pact-plugin.json.minisig"), MANIFEST_SIGNATURE).unwrap();
+    let manifest = fetch_signed_manifest(&http_client(), url, &keys).await.unwrap();
+    expect!(manifest.entry_point).to(be_equal_to("test-plugin"));
+
+    fs::write(mirror.path().join("pact-plugin.json"), MANIFEST.replace("test-plugin", "/bin/sh")).unwrap();
+    expect!(fetch_signed_manifest(&http_client(), url, &keys).await).to(be_err());
+  }
+}
@@ -0,0 +1,487 @@
+//! Instance-scoped plugin driver context.
+//!
+//! A [`PluginDriver`] owns the state that the driver functions work on: the plugin and plugin
+//! manifest registers, the catalogue, the core capability handlers, the call chains and the
+//! driver configuration (such as the plugin directory). The free functions in the other modules
+//! (e.g. [`crate::plugin_manager::load_plugin`] or [`crate::catalogue_manager::find_content_matcher`])
+//! work on the *current* driver, which is the process wide default driver unless another driver
+//! has been made current with [`PluginDriver::enter`] (for synchronous code) or
+//! [`PluginDriver::scope`] (for futures).
+//!
+//! This allows isolated drivers to run side by side in one process, for example test suites run
+//! in parallel with different plugin directories:
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! use pact_plugin_driver::driver::{PluginDriver, PluginDriverConfig};
+//! use pact_plugin_driver::plugin_models::PluginDependency;
+//!
+//! let driver = PluginDriver::new(PluginDriverConfig {
+//!   plugin_dir: Some("/tmp/test-plugins".into()),
+//!   .. PluginDriverConfig::default()
+//! });
+//! let plugin = driver.load_plugin(&PluginDependency {
+//!   name: "protobuf".to_string(),
+//!   version: None,
+//!   dependency_type: Default::default()
+//! }).await?;
+//! driver.shutdown_plugins();
+//! # Ok(())
+//! # }
+//! ```
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use pact_models::prelude::ContentType;
+use regex::Regex;
+use tokio::sync::broadcast;
+
+use crate::catalogue_manager::{
+  CATALOGUE_EVENT_CAPACITY, CatalogueEntry, CatalogueEvent, CatalogueQuery, ContentTypeLookupCache
+};
+use crate::content::{ContentGenerator, ContentMatcher};
+use crate::core_capabilities::{CoreContentGenerator, CoreContentMatcher, CoreFieldGenerator, CoreFieldMatcher};
+use crate::field::{
+  DEFAULT_FIELD_MATCH_CACHE_SIZE, FieldMatchCache, FieldMatchCacheStats, FieldRuntime, FieldRuntimeState
+};
+use crate::plugin_manager::{PluginRestartPolicy, RegisteredPlugin};
+use crate::plugin_models::{PactPlugin, PactPluginManifest, PluginDependency, PluginInstance};
+
+/// Configuration for a plugin driver. Any values not set fall back to the environment variables
+/// (i.e. `PACT_PLUGIN_DIR`) and then the defaults.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PluginDriverConfig {
+  /// Directory plugins are loaded from and installed into. Takes precedence over the
+  /// `PACT_PLUGIN_DIR` environment variable.
+  pub plugin_dir: Option<PathBuf>,
+  /// Restart policy for plugins that do not set one in their manifest
+  pub restart_policy: Option<PluginRestartPolicy>,
+  /// How long to wait for a plugin process to output its startup message, for plugins that do
+  /// not set one in their manifest
+  pub startup_timeout: Option<Duration>,
+  /// Lock file used to pin the plugin versions (see [`crate::lockfile`])
+  pub lock_file: Option<PathBuf>,
+  /// Maximum number of results cached for field-level matching rules that declare themselves
+  /// pure (see [`crate::field::FieldMatcher::is_pure`]). Defaults to
+  /// [`crate::field::DEFAULT_FIELD_MATCH_CACHE_SIZE`]; zero disables the cache.
+  pub field_match_cache_size: Option<usize>
+}
+
+/// The registers and configuration owned by a plugin driver
+pub(crate) struct DriverState {
+  pub(crate) id: u64,
+  pub(crate) config: RwLock<PluginDriverConfig>,
+  pub(crate) plugin_register: Mutex<HashMap<String, RegisteredPlugin>>,
+  pub(crate) plugin_manifest_register: Mutex<HashMap<String, PactPluginManifest>>,
+  pub(crate) catalogue_register: Mutex<HashMap<String, CatalogueEntry>>,
+  pub(crate) catalogue_events: broadcast::Sender<CatalogueEvent>,
+  pub(crate) content_type_patterns: Mutex<HashMap<String, Option<Regex>>>,
+  pub(crate) content_type_lookups: Mutex<ContentTypeLookupCache>,
+  pub(crate) content_type_overrides: RwLock<HashMap<String, String>>,
+  pub(crate) reported_ambiguities: Mutex<HashSet<String>>,
+  pub(crate) core_content_matchers: Mutex<HashMap<String, Arc<dyn CoreContentMatcher>>>,
+  pub(crate) core_content_generators: Mutex<HashMap<String, Arc<dyn CoreContentGenerator>>>,
+  pub(crate) core_field_matchers: Mutex<HashMap<String, Arc<dyn CoreFieldMatcher>>>,
+  pub(crate) core_field_generators: Mutex<HashMap<String, Arc<dyn CoreFieldGenerator>>>,
+  pub(crate) call_chains: Mutex<HashMap<String, Vec<String>>>,
+  pub(crate) field_match_cache: Mutex<FieldMatchCache>,
+  pub(crate) field_runtime: Mutex<FieldRuntimeState>
+}
+
+impl DriverState {
+  fn new(id: u64, config: PluginDriverConfig) -> Self {
+    let field_match_cache_size = config.field_match_cache_size.unwrap_or(DEFAULT_FIELD_MATCH_CACHE_SIZE);
+    DriverState {
+      id,
+      config: RwLock::new(config),
+      plugin_register: Default::default(),
+      plugin_manifest_register: Default::default(),
+      catalogue_register: Default::default(),
+      catalogue_events: broadcast::channel(CATALOGUE_EVENT_CAPACITY).0,
+      content_type_patterns: Default::default(),
+      content_type_lookups: Default::default(),
+      content_type_overrides: Default::default(),
+      reported_ambiguities: Default::default(),
+      core_content_matchers: Default::default(),
+      core_content_generators: Default::default(),
+      core_field_matchers: Default::default(),
+      core_field_generators: Default::default(),
+      call_chains: Default::default(),
+      field_match_cache: Mutex::new(FieldMatchCache::new(field_match_cache_size)),
+      field_runtime: Default::default()
+    }
+  }
+}
+
+impl Drop for DriverState {
+  fn drop(&mut self) {
+    DRIVERS.lock().unwrap().remove(&self.id);
+    if let Ok(field_runtime) = self.field_runtime.get_mut() {
+      field_runtime.shutdown();
+    }
+  }
+}
+
+/// Id of the default driver. Call chains started by other drivers are prefixed with the driver id,
+/// so callbacks from plugins can be routed to the driver that made the call.
+const DEFAULT_DRIVER_ID: u64 = 0;
+
+lazy_static! {
+  static ref DEFAULT_DRIVER: PluginDriver = PluginDriver::with_id(DEFAULT_DRIVER_ID, PluginDriverConfig::default());
+  static ref DRIVERS: Mutex<HashMap<u64, Weak<DriverState>>> = Mutex::new(HashMap::new());
+}
+
+static NEXT_DRIVER_ID: AtomicU64 = AtomicU64::new(DEFAULT_DRIVER_ID + 1);
+
+thread_local! {
+  static CURRENT_DRIVER: RefCell<Option<PluginDriver>> = const { RefCell::new(None) };
+}
+
+/// A plugin driver. Cloning it returns a handle to the same driver. The plugins loaded by the
+/// driver are not shut down when it is dropped, use [`PluginDriver::shutdown_plugins`] for that.
+#[derive(Clone)]
+pub struct PluginDriver {
+  pub(crate) inner: Arc<DriverState>
+}
+
+impl PluginDriver {
+  /// Create a new driver with its own registers and the given configuration
+  pub fn new(config: PluginDriverConfig) -> Self {
+    PluginDriver::with_id(NEXT_DRIVER_ID.fetch_add(1, Ordering::SeqCst), config)
+  }
+
+  fn with_id(id: u64, config: PluginDriverConfig) -> Self {
+    let inner = Arc::new(DriverState::new(id, config));
+    if id != DEFAULT_DRIVER_ID {
+      DRIVERS.lock().unwrap().insert(id, Arc::downgrade(&inner));
+    }
+    PluginDriver { inner }
+  }
+
+  /// The process wide default driver, used by the free functions when no other driver is current
+  pub fn default_driver() -> PluginDriver {
+    DEFAULT_DRIVER.clone()
+  }
+
+  /// The current driver: the driver made current with [`PluginDriver::enter`] or
+  /// [`PluginDriver::scope`], otherwise the default driver.
+  pub fn current() -> PluginDriver {
+    CURRENT_DRIVER.with(|current| current.borrow().clone())
+      .unwrap_or_else(PluginDriver::default_driver)
+  }
+
+  /// Returns the driver with the given id, if it is still alive
+  pub(crate) fn find(id: u64) -> Option<PluginDriver> {
+    if id == DEFAULT_DRIVER_ID {
+      Some(PluginDriver::default_driver())
+    } else {
+      DRIVERS.lock().unwrap().get(&id)
+        .and_then(Weak::upgrade)
+        .map(|inner| PluginDriver { inner })
+    }
+  }
+
+  /// Id of this driver, unique within the process
+  pub fn id(&self) -> u64 {
+    self.inner.id
+  }
+
+  /// If this is the default driver
+  pub fn is_default(&self) -> bool {
+    self.inner.id == DEFAULT_DRIVER_ID
+  }
+
+  /// A weak handle to this driver, for tasks that should not keep the driver alive
+  pub(crate) fn downgrade(&self) -> Weak<DriverState> {
+    Arc::downgrade(&self.inner)
+  }
+
+  /// Returns the driver for the weak handle, if it is still alive
+  pub(crate) fn upgrade(driver: &Weak<DriverState>) -> Option<PluginDriver> {
+    driver.upgrade().map(|inner| PluginDriver { inner })
+  }
+
+  /// Returns a copy of the configuration of this driver
+  pub fn config(&self) -> PluginDriverConfig {
+    self.inner.config.read().unwrap().clone()
+  }
+
+  /// Updates the configuration of this driver
+  pub fn update_config<F: FnOnce(&mut PluginDriverConfig)>(&self, f: F) {
+    f(&mut self.inner.config.write().unwrap());
+  }
+
+  /// Makes this the current driver for the calling thread until the returned guard is dropped.
+  /// This is for synchronous code: do not hold the guard across an `await`, use
+  /// [`PluginDriver::scope`] for futures.
+  pub fn enter(&self) -> PluginDriverGuard {
+    let previous = CURRENT_DRIVER.with(|current| current.replace(Some(self.clone())));
+    PluginDriverGuard { previous }
+  }
+
+  /// Wraps the future so that this is the current driver whenever it is polled
+  pub fn scope<F: Future>(&self, future: F) -> impl Future<Output = F::Output> + use<F> {
+    let driver = self.clone();
+    let mut future = Box::pin(future);
+    std::future::poll_fn(move |cx| {
+      let _guard = driver.enter();
+      future.as_mut().poll(cx)
+    })
+  }
+
+  /// Load the plugin with this driver. See [`crate::plugin_manager::load_plugin`].
+  pub async fn load_plugin(&self, plugin: &PluginDependency) -> anyhow::Result<PactPlugin> {
+    let plugin = plugin.clone();
+    self.scope(async move { crate::plugin_manager::load_plugin(&plugin).await }).await
+  }
+
+  /// Look up a plugin loaded by this driver. See [`crate::plugin_manager::lookup_plugin`].
+  pub fn lookup_plugin(&self, plugin: &PluginDependency) -> Option<Arc<dyn PluginInstance + Send + Sync>> {
+    let _guard = self.enter();
+    crate::plugin_manager::lookup_plugin(plugin)
+  }
+
+  /// Shut down all the plugins loaded by this driver. See [`crate::plugin_manager::shutdown_plugins`].
+  pub fn shutdown_plugins(&self) {
+    let _guard = self.enter();
+    crate::plugin_manager::shutdown_plugins();
+  }
+
+  /// The directory this driver loads plugins from and installs them into: the configured plugin
+  /// directory, otherwise `$PACT_PLUGIN_DIR` or `$HOME/.pact/plugins`.
+  pub fn plugin_dir(&self) -> anyhow::Result<PathBuf> {
+    let _guard = self.enter();
+    crate::plugin_manager::pact_plugin_dir()
+  }
+
+  /// Register the core Pact framework entries in the catalogue of this driver. See
+  /// [`crate::catalogue_manager::register_core_entries`].
+  pub fn register_core_entries(&self, entries: &Vec<CatalogueEntry>) {
+    let _guard = self.enter();
+    crate::catalogue_manager::register_core_entries(entries);
+  }
+
+  /// All the entries in the catalogue of this driver
+  pub fn catalogue_entries(&self) -> Vec<CatalogueEntry> {
+    let _guard = self.enter();
+    crate::catalogue_manager::all_entries()
+  }
+
+  /// The entries in the catalogue of this driver that match the query. See
+  /// [`crate::catalogue_manager::query_entries`].
+  pub fn query_catalogue(&self, query: &CatalogueQuery) -> anyhow::Result<Vec<CatalogueEntry>> {
+    let _guard = self.enter();
+    crate::catalogue_manager::query_entries(query)
+  }
+
+  /// Statistics for the field-level match result cache of this driver. See
+  /// [`crate::field::field_match_cache_stats`].
+  pub fn field_match_cache_stats(&self) -> FieldMatchCacheStats {
+    let _guard = self.enter();
+    crate::field::field_match_cache_stats()
+  }
+
+  /// Empty the field-level match result cache of this driver. See
+  /// [`crate::field::clear_field_match_cache`].
+  pub fn clear_field_match_cache(&self) {
+    let _guard = self.enter();
+    crate::field::clear_field_match_cache()
+  }
+
+  /// Set where the blocking field-level wrappers of this driver run a call. See
+  /// [`crate::field::configure_field_runtime`].
+  pub fn configure_field_runtime(&self, runtime: FieldRuntime) -> anyhow::Result<()> {
+    let _guard = self.enter();
+    crate::field::configure_field_runtime(runtime)
+  }
+
+  /// Subscribe to the changes made to the catalogue of this driver. See
+  /// [`crate::catalogue_manager::subscribe_to_catalogue_changes`].
+  pub fn subscribe_to_catalogue_changes(&self) -> broadcast::Receiver<CatalogueEvent> {
+    self.inner.catalogue_events.subscribe()
+  }
+
+  /// Find a content matcher for the content type in the catalogue of this driver. See
+  /// [`crate::catalogue_manager::find_content_matcher`].
+  pub fn find_content_matcher<CT: Into<String>>(&self, content_type: CT) -> Option<ContentMatcher> {
+    let _guard = self.enter();
+    crate::catalogue_manager::find_content_matcher(content_type)
+  }
+
+  /// Find a content generator for the content type in the catalogue of this driver. See
+  /// [`crate::catalogue_manager::find_content_generator`].
+  pub fn find_content_generator(&self, content_type: &ContentType) -> Option<ContentGenerator> {
+    let _guard = self.enter();
+    crate::catalogue_manager::find_content_generator(content_type)
+  }
+}
+
+impl Default for PluginDriver {
+  /// Returns the default driver
+  fn default() -> Self {
+    PluginDriver::default_driver()
+  }
+}
+
+impl Debug for PluginDriver {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("PluginDriver")
+      .field("id", &self.inner.id)
+      .field("config", &self.config())
+      .finish()
+  }
+}
+
+impl PartialEq for PluginDriver {
+  fn eq(&self, other: &Self) -> bool {
+    Arc::ptr_eq(&self.inner, &other.inner)
+  }
+}
+
+/// Guard returned by [`PluginDriver::enter`]. Restores the previously current driver when dropped.
+#[must_use]
+pub struct PluginDriverGuard {
+  previous: Option<PluginDriver>
+}
+
+impl Drop for PluginDriverGuard {
+  fn drop(&mut self) {
+    let previous = self.previous.take();
+    CURRENT_DRIVER.with(|current| *current.borrow_mut() = previous);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+  use std::path::Path;
+
+  use expectest::prelude::*;
+  use maplit::hashmap;
+  use pact_models::prelude::ContentType;
+  use tempdir::TempDir;
+
+  use crate::catalogue_manager::{CatalogueEntry, CatalogueEntryProviderType, CatalogueEntryType};
+  use crate::plugin_manager::load_plugin_manifest;
+  use crate::plugin_models::{PactPluginManifest, PluginDependency};
+
+  use super::{PluginDriver, PluginDriverConfig};
+
+  fn content_matcher_entry(key: &str, content_types: &str) -> CatalogueEntry {
+    CatalogueEntry {
+      entry_type: CatalogueEntryType::CONTENT_MATCHER,
+      provider_type: CatalogueEntryProviderType::CORE,
+      plugin: None,
+      key: key.to_string(),
+      values: hashmap!{ "content-types".to_string() => content_types.to_string() }
+    }
+  }
+
+  #[test]
+  fn current_driver_is_the_default_driver_outside_a_scope() {
+    expect!(PluginDriver::current()).to(be_equal_to(PluginDriver::default_driver()));
+    expect!(PluginDriver::current().is_default()).to(be_true());
+
+    let driver = PluginDriver::new(PluginDriverConfig::default());
+    {
+      let _guard = driver.enter();
+      expect!(PluginDriver::current()).to(be_equal_to(driver.clone()));
+      {
+        let other = PluginDriver::new(PluginDriverConfig::default());
+        let _guard = other.enter();
+        expect!(PluginDriver::current()).to(be_equal_to(other.clone()));
+      }
+      expect!(PluginDriver::current()).to(be_equal_to(driver.clone()));
+    }
+    expect!(PluginDriver::current()).to(be_equal_to(PluginDriver::default_driver()));
+  }
+
+  #[test_log::test(tokio::test(flavor = "multi_thread", worker_threads = 2))]
+  async fn scope_makes_the_driver_current_for_the_future() {
+    let driver = PluginDriver::new(PluginDriverConfig::default());
+    let scoped = driver.clone();
+    let current = tokio::spawn(driver.scope(async move {
+      tokio::task::yield_now().await;
+      PluginDriver::current()
+    })).await.unwrap();
+    expect!(current).to(be_equal_to(scoped));
+    expect!(PluginDriver::current().is_default()).to(be_true());
+  }
+
+  #[test]
+  fn drivers_have_their_own_catalogues() {
+    let driver_1 = PluginDriver::new(PluginDriverConfig::default());
+    let driver_2 = PluginDriver::new(PluginDriverConfig::default());
+    driver_1.register_core_entries(&vec![content_matcher_entry("driver-one", "application/x-driver-one")]);
+    driver_2.register_core_entries(&vec![content_matcher_entry("driver-two", "application/x-driver-two")]);
+
+    expect!(driver_1.find_content_matcher("application/x-driver-one")).to(be_some());
+    expect!(driver_2.find_content_matcher("application/x-driver-one")).to(be_none());
+    expect!(PluginDriver::default_driver().find_content_matcher("application/x-driver-one")).to(be_none());
+    let content_type = ContentType::parse("application/x-driver-two").unwrap();
+    expect!(driver_2.find_content_generator(&content_type)).to(be_none());
+    expect!(driver_2.catalogue_entries().iter().map(|entry| entry.key.clone()).collect::<Vec<_>>())
+      .to(be_equal_to(vec!["driver-two".to_string()]));
+  }
+
+  fn write_manifest(dir: &Path, name: &str, version: &str) {
+    let plugin_dir = dir.join(format!("{}-{}", name, version));
+    fs::create_dir_all(&plugin_dir).unwrap();
+    let manifest = PactPluginManifest {
+      name: name.to_string(),
+      version: version.to_string(),
+      .. PactPluginManifest::default()
+    };
+    fs::write(plugin_dir.join("pact-plugin.json"), serde_json::to_string(&manifest).unwrap()).unwrap();
+  }
+
+  #[test]
+  fn drivers_load_plugins_from_their_own_plugin_directories() {
+    let tmp_dir_1 = TempDir::new("driver-plugin-dir-1").unwrap();
+    let tmp_dir_2 = TempDir::new("driver-plugin-dir-2").unwrap();
+    write_manifest(tmp_dir_1.path(), "isolated-plugin", "1.0.0");
+    write_manifest(tmp_dir_2.path(), "isolated-plugin", "2.0.0");
+    let driver_1 = PluginDriver::new(PluginDriverConfig {
+      plugin_dir: Some(tmp_dir_1.path().to_path_buf()),
+      .. PluginDriverConfig::default()
+    });
+    let driver_2 = PluginDriver::new(PluginDriverConfig {
+      plugin_dir: Some(tmp_dir_2.path().to_path_buf()),
+      .. PluginDriverConfig::default()
+    });
+    let dependency = PluginDependency {
+      name: "isolated-plugin".to_string(),
+      version: None,
+      dependency_type: Default::default()
+    };
+
+    expect!(driver_1.plugin_dir().unwrap()).to(be_equal_to(tmp_dir_1.path().to_path_buf()));
+    let manifest_1 = {
+      let _guard = driver_1.enter();
+      load_plugin_manifest(&dependency).unwrap()
+    };
+    let manifest_2 = {
+      let _guard = driver_2.enter();
+      load_plugin_manifest(&dependency).unwrap()
+    };
+    expect!(manifest_1.version).to(be_equal_to("1.0.0"));
+    expect!(manifest_2.version).to(be_equal_to("2.0.0"));
+  }
+
+  #[test]
+  fn dropped_drivers_can_not_be_found() {
+    let driver = PluginDriver::new(PluginDriverConfig::default());
+    let id = driver.id();
+    expect!(PluginDriver::find(id)).to(be_some());
+    drop(driver);
+    expect!(PluginDriver::find(id)).to(be_none());
+  }
+}
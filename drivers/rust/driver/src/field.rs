@@ -5,33 +5,40 @@
 //! content - a field in a JSON body, a header, a message metadata value. See proposal 006
 //! (Field-level matchers and generators) for the design.
 //!
+//! Every operation is async, and an async host awaits it directly on its own runtime - see
+//! [`match_field_value`]. The `_blocking` wrappers are for hosts whose matching is synchronous;
+//! they run the call on the field runtime of the driver, which is configured with
+//! [`configure_field_runtime`].
+//!
 //! The proto types used here are the V2 interface ones. Field-level operations were introduced in
 //! V2 and have no V1 equivalent, so a V1 plugin cannot provide them.
 
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::collections::{BTreeMap, HashMap};
 
 use anyhow::anyhow;
 use bytes::Bytes;
-use lazy_static::lazy_static;
 use pact_models::matchingrules::MatchingRule;
 use pact_models::path_exp::DocPath;
 use pact_models::prelude::Generator;
+use prost::Message;
 use serde_json::Value;
-use tokio::runtime::Runtime;
+use tokio::runtime::{Handle, Runtime, RuntimeFlavor};
 use tracing::{debug, error};
 
 use crate::catalogue_manager::{CatalogueEntry, CatalogueEntryProviderType, CatalogueEntryType, resolve_capability_entry};
 use crate::content::ContentMismatch;
 use crate::core_capabilities;
+use crate::diff::DiffEntry;
 use crate::plugin_manager::lookup_plugin;
-use crate::plugin_models::{PactPluginManifest, PluginInteractionConfig};
+use crate::plugin_models::{FIELD_BATCH_CAPABILITY, PactPluginManifest, PluginDependency, PluginInteractionConfig};
 use crate::proto_v2::{
   FieldValue as ProtoFieldValue,
   GenerateFieldRequest,
   GenerateFieldResponse,
+  GenerateFieldsBatchRequest,
   MatchFieldRequest,
   MatchFieldResponse,
+  MatchFieldsBatchRequest,
   MatchingRule as ProtoMatchingRule,
   Generator as ProtoGenerator,
   PluginConfiguration as ProtoPluginConfiguration,
@@ -213,6 +220,15 @@ impl FieldMatcher {
       .unwrap_or("core".to_string())
   }
 
+  /// If this matching rule declares itself a pure function of its rule, expected and actual
+  /// values (a `pure` catalogue entry value of `true`), so the driver can cache its results. See
+  /// [`field_match_cache_stats`].
+  pub fn is_pure(&self) -> bool {
+    self.catalogue_entry.values.get("pure")
+      .map(|value| value.eq_ignore_ascii_case("true"))
+      .unwrap_or(false)
+  }
+
   /// Apply this matching rule to a single value.
   ///
   /// The context carries where the value lives and which part of the interaction it came from;
@@ -225,27 +241,79 @@ impl FieldMatcher {
     actual: &FieldValue,
     context: &FieldContext
   ) -> Result<(), Vec<ContentMismatch>> {
+    let request = self.request_for(rule, expected, actual, context);
+    let cache_key = self.cache_key(&request);
+    let response = match cache_key.as_ref().and_then(|key| cached_match_response(key, &request.path)) {
+      Some(response) => Ok(response),
+      None => self.dispatch_and_cache(cache_key, request).await
+    };
+
+    process_match_field_response(response, context)
+  }
+
+  /// Key the result of the request is cached under, if this rule is pure. Where the value sits
+  /// and the test context are left out, as neither can change the result of a pure rule.
+  fn cache_key(&self, request: &MatchFieldRequest) -> Option<FieldMatchCacheKey> {
+    if !self.is_pure() {
+      return None;
+    }
+    let version = self.catalogue_entry.plugin.as_ref()
+      .map(|plugin| plugin.version.clone())
+      .unwrap_or_default();
     let request = MatchFieldRequest {
-      key: self.catalogue_entry.key.clone(),
-      rule: Some(to_proto_matching_rule(rule)),
-      path: context.path.to_string(),
-      mismatch_type: context.category.clone(),
-      expected: Some(expected.to_proto()),
-      actual: Some(actual.to_proto()),
-      plugin_configuration: context.plugin_config.clone().map(to_proto_plugin_config),
-      test_context: Some(to_proto_struct(&with_test_run_id(&context.test_context)))
+      path: String::default(),
+      mismatch_type: String::default(),
+      test_context: None,
+      .. request.clone()
     };
+    Some((format!("{}/{}", self.catalogue_entry_key(), version), request.encode_to_vec()))
+  }
 
-    let response = if self.is_core() {
+  async fn dispatch_and_cache(
+    &self,
+    cache_key: Option<FieldMatchCacheKey>,
+    request: MatchFieldRequest
+  ) -> anyhow::Result<MatchFieldResponse> {
+    let Some(cache_key) = cache_key else {
+      return self.dispatch(request).await
+    };
+    let path = request.path.clone();
+    let mismatch_type = request.mismatch_type.clone();
+    let response = self.dispatch(request).await;
+    if let Ok(response) = &response {
+      cache_match_response(cache_key, &path, &mismatch_type, response);
+    }
+    response
+  }
+
+  async fn dispatch(&self, request: MatchFieldRequest) -> anyhow::Result<MatchFieldResponse> {
+    if self.is_core() {
       match core_capabilities::lookup_core_field_matcher(&self.catalogue_entry.key) {
         Some(handler) => handler.match_field(request).await,
         None => Err(anyhow!("No core field matcher registered for '{}'", self.catalogue_entry.key))
       }
     } else {
       self.call_plugin(request).await
-    };
+    }
+  }
 
-    process_match_field_response(response, context)
+  fn request_for(
+    &self,
+    rule: &MatchingRule,
+    expected: &FieldValue,
+    actual: &FieldValue,
+    context: &FieldContext
+  ) -> MatchFieldRequest {
+    MatchFieldRequest {
+      key: self.catalogue_entry.key.clone(),
+      rule: Some(to_proto_matching_rule(rule)),
+      path: context.path.to_string(),
+      mismatch_type: context.category.clone(),
+      expected: Some(expected.to_proto()),
+      actual: Some(actual.to_proto()),
+      plugin_configuration: context.plugin_config.clone().map(to_proto_plugin_config),
+      test_context: Some(to_proto_struct(&with_test_run_id(&context.test_context)))
+    }
   }
 
   async fn call_plugin(&self, request: MatchFieldRequest) -> anyhow::Result<MatchFieldResponse> {
@@ -321,16 +389,7 @@ impl FieldGenerator {
     mode: TestMode,
     context: &FieldContext
   ) -> anyhow::Result<FieldValue> {
-    let request = GenerateFieldRequest {
-      key: self.catalogue_entry.key.clone(),
-      generator: Some(to_proto_generator(generator)),
-      path: context.path.to_string(),
-      example_value: Some(example.to_proto()),
-      plugin_configuration: context.plugin_config.clone().map(to_proto_plugin_config),
-      test_context: Some(to_proto_struct(&with_test_run_id(&context.test_context))),
-      test_mode: mode.to_proto() as i32
-    };
-
+    let request = self.request_for(generator, example, mode, context);
     let response = if self.is_core() {
       let handler = core_capabilities::lookup_core_field_generator(&self.catalogue_entry.key)
         .ok_or_else(|| anyhow!("No core field generator registered for '{}'", self.catalogue_entry.key))?;
@@ -339,6 +398,28 @@ impl FieldGenerator {
       self.call_plugin(request).await?
     };
 
+    self.process_response(response)
+  }
+
+  fn request_for(
+    &self,
+    generator: &Generator,
+    example: &FieldValue,
+    mode: TestMode,
+    context: &FieldContext
+  ) -> GenerateFieldRequest {
+    GenerateFieldRequest {
+      key: self.catalogue_entry.key.clone(),
+      generator: Some(to_proto_generator(generator)),
+      path: context.path.to_string(),
+      example_value: Some(example.to_proto()),
+      plugin_configuration: context.plugin_config.clone().map(to_proto_plugin_config),
+      test_context: Some(to_proto_struct(&with_test_run_id(&context.test_context))),
+      test_mode: mode.to_proto() as i32
+    }
+  }
+
+  fn process_response(&self, response: GenerateFieldResponse) -> anyhow::Result<FieldValue> {
     if !response.error.is_empty() {
       return Err(anyhow!("Generator '{}' failed: {}", self.catalogue_entry.key, response.error));
     }
@@ -380,6 +461,412 @@ impl FieldGenerator {
   }
 }
 
+/// Find the field-level matching rule with the given name and apply it to a single value.
+///
+/// This is the entry point for an async host: it is awaited on the host's own runtime, with no
+/// thread blocked and no hop through the field runtime. A rule name that does not resolve is
+/// reported as a mismatch, the same as any other failure to apply the rule. See
+/// [`find_field_matcher`] and [`FieldMatcher::match_field`].
+pub async fn match_field_value(
+  name: &str,
+  rule: &MatchingRule,
+  expected: &FieldValue,
+  actual: &FieldValue,
+  context: &FieldContext
+) -> Result<(), Vec<ContentMismatch>> {
+  match find_field_matcher(name) {
+    Ok(matcher) => matcher.match_field(rule, expected, actual, context).await,
+    Err(err) => Err(vec![mismatch_for(err.to_string(), context)])
+  }
+}
+
+/// Find the field-level generator with the given name and generate a single value. See
+/// [`match_field_value`].
+pub async fn generate_field_value(
+  name: &str,
+  generator: &Generator,
+  example: &FieldValue,
+  mode: TestMode,
+  context: &FieldContext
+) -> anyhow::Result<FieldValue> {
+  find_field_generator(name)?
+    .generate_field(generator, example, mode, context)
+    .await
+}
+
+/// One value to match as part of a batch. See [`match_fields`].
+#[derive(Clone, Debug)]
+pub struct FieldMatchRequest {
+  /// Matching rule to apply
+  pub matcher: FieldMatcher,
+  /// Matching rule definition from the Pact file
+  pub rule: MatchingRule,
+  /// Expected value
+  pub expected: FieldValue,
+  /// Actual value
+  pub actual: FieldValue,
+  /// Where the value sits
+  pub context: FieldContext
+}
+
+/// One value to generate as part of a batch. See [`generate_fields`].
+#[derive(Clone, Debug)]
+pub struct FieldGenerateRequest {
+  /// Generator to apply
+  pub generator: FieldGenerator,
+  /// Generator definition from the Pact file
+  pub definition: Generator,
+  /// Example value from the Pact interaction
+  pub example: FieldValue,
+  /// Which side of the test is running
+  pub mode: TestMode,
+  /// Where the value sits
+  pub context: FieldContext
+}
+
+/// Apply a set of matching rules, one per value, with as few plugin calls as possible.
+///
+/// Requests are grouped by the plugin that provides the rule. A plugin that advertises the
+/// `field/batch` capability gets one `MatchFieldsBatch` call for its whole group; any other plugin,
+/// and every core handler, gets one call per value exactly as [`FieldMatcher::match_field`] would
+/// make. If a batch call fails, every value in that batch reports the failure as a mismatch.
+///
+/// Results are returned in the same order as the requests.
+pub async fn match_fields(requests: &[FieldMatchRequest]) -> Vec<Result<(), Vec<ContentMismatch>>> {
+  let mut results = vec![None; requests.len()];
+  let mut field_requests = Vec::with_capacity(requests.len());
+  let mut cache_keys = Vec::with_capacity(requests.len());
+  for (index, request) in requests.iter().enumerate() {
+    let field_request = request.matcher.request_for(&request.rule, &request.expected, &request.actual, &request.context);
+    let cache_key = request.matcher.cache_key(&field_request);
+    if let Some(response) = cache_key.as_ref().and_then(|key| cached_match_response(key, &field_request.path)) {
+      results[index] = Some(process_match_field_response(Ok(response), &request.context));
+    }
+    field_requests.push(field_request);
+    cache_keys.push(cache_key);
+  }
+
+  let uncached = requests.iter().enumerate()
+    .filter(|(index, _)| results[*index].is_none())
+    .map(|(index, request)| (index, &request.matcher.catalogue_entry));
+  for (dependency, indices) in plugin_groups(uncached) {
+    let Some(plugin) = lookup_plugin(&dependency) else { continue };
+    if indices.len() < 2 || !plugin.has_capability(FIELD_BATCH_CAPABILITY) {
+      continue;
+    }
+
+    let batch = MatchFieldsBatchRequest {
+      requests: indices.iter().map(|index| field_requests[*index].clone()).collect()
+    };
+    debug!("Sending MatchFieldsBatch request with {} fields to plugin {:?}", indices.len(), dependency.name);
+    let chain_id = crate::call_chain::new_call_chain_id();
+    let deadline_ms = crate::call_chain::default_deadline_ms();
+    let responses = plugin.match_fields_batch_with_chain(batch, &chain_id, deadline_ms).await
+      .and_then(|response| batch_responses(response.error, response.responses, indices.len()));
+    match responses {
+      Ok(responses) => for (index, response) in indices.iter().zip(responses) {
+        if let Some(cache_key) = cache_keys[*index].take() {
+          let field_request = &field_requests[*index];
+          cache_match_response(cache_key, &field_request.path, &field_request.mismatch_type, &response);
+        }
+        results[*index] = Some(process_match_field_response(Ok(response), &requests[*index].context));
+      },
+      Err(err) => {
+        error!("Batched field-level match call to plugin {:?} failed - {}", dependency.name, err);
+        for index in indices {
+          results[index] = Some(Err(vec![mismatch_for(err.to_string(), &requests[index].context)]));
+        }
+      }
+    }
+  }
+
+  let mut matched = Vec::with_capacity(requests.len());
+  for (((request, result), field_request), cache_key) in requests.iter().zip(results).zip(field_requests).zip(cache_keys) {
+    matched.push(match result {
+      Some(result) => result,
+      None => {
+        let response = request.matcher.dispatch_and_cache(cache_key, field_request).await;
+        process_match_field_response(response, &request.context)
+      }
+    });
+  }
+  matched
+}
+
+/// Apply a set of matching rules from a synchronous call path. Unlike calling
+/// [`FieldMatcher::match_field_blocking`] for each value, the whole set costs one hop onto the
+/// field runtime - see [`block_on_field_call`].
+pub fn match_fields_blocking(requests: &[FieldMatchRequest]) -> Vec<Result<(), Vec<ContentMismatch>>> {
+  let call_requests = requests.to_vec();
+  block_on_field_call(async move { match_fields(&call_requests).await })
+    .unwrap_or_else(|err| requests.iter()
+      .map(|request| Err(vec![mismatch_for(err.to_string(), &request.context)]))
+      .collect())
+}
+
+/// Generate a set of values with as few plugin calls as possible. See [`match_fields`] for how
+/// requests are grouped; if a batch call fails, every value in that batch gets the error.
+///
+/// Results are returned in the same order as the requests.
+pub async fn generate_fields(requests: &[FieldGenerateRequest]) -> Vec<anyhow::Result<FieldValue>> {
+  let mut results: Vec<Option<anyhow::Result<FieldValue>>> = requests.iter().map(|_| None).collect();
+
+  let entries = requests.iter().enumerate()
+    .map(|(index, request)| (index, &request.generator.catalogue_entry));
+  for (dependency, indices) in plugin_groups(entries) {
+    let Some(plugin) = lookup_plugin(&dependency) else { continue };
+    if indices.len() < 2 || !plugin.has_capability(FIELD_BATCH_CAPABILITY) {
+      continue;
+    }
+
+    let batch = GenerateFieldsBatchRequest {
+      requests: indices.iter()
+        .map(|index| {
+          let request = &requests[*index];
+          request.generator.request_for(&request.definition, &request.example, request.mode, &request.context)
+        })
+        .collect()
+    };
+    debug!("Sending GenerateFieldsBatch request with {} fields to plugin {:?}", indices.len(), dependency.name);
+    let chain_id = crate::call_chain::new_call_chain_id();
+    let deadline_ms = crate::call_chain::default_deadline_ms();
+    let responses = plugin.generate_fields_batch_with_chain(batch, &chain_id, deadline_ms).await
+      .and_then(|response| batch_responses(response.error, response.responses, indices.len()));
+    match responses {
+      Ok(responses) => for (index, response) in indices.iter().zip(responses) {
+        results[*index] = Some(requests[*index].generator.process_response(response));
+      },
+      Err(err) => {
+        error!("Batched field-level generate call to plugin {:?} failed - {}", dependency.name, err);
+        for index in indices {
+          results[index] = Some(Err(anyhow!("Generator '{}' failed: {}",
+            requests[index].generator.catalogue_entry.key, err)));
+        }
+      }
+    }
+  }
+
+  let mut generated = Vec::with_capacity(requests.len());
+  for (request, result) in requests.iter().zip(results) {
+    generated.push(match result {
+      Some(result) => result,
+      None => request.generator.generate_field(&request.definition, &request.example, request.mode, &request.context).await
+    });
+  }
+  generated
+}
+
+/// Generate a set of values from a synchronous call path. See [`match_fields_blocking`].
+pub fn generate_fields_blocking(requests: &[FieldGenerateRequest]) -> Vec<anyhow::Result<FieldValue>> {
+  let call_requests = requests.to_vec();
+  block_on_field_call(async move { generate_fields(&call_requests).await })
+    .unwrap_or_else(|err| requests.iter()
+      .map(|_| Err(anyhow!("{}", err)))
+      .collect())
+}
+
+/// Indices of the plugin-provided entries, grouped by plugin in order of first appearance. Core
+/// entries are left out.
+fn plugin_groups<'a>(
+  entries: impl Iterator<Item = (usize, &'a CatalogueEntry)>
+) -> Vec<(PluginDependency, Vec<usize>)> {
+  let mut groups: Vec<(PluginDependency, Vec<usize>)> = vec![];
+  for (index, entry) in entries {
+    if entry.provider_type == CatalogueEntryProviderType::CORE {
+      continue;
+    }
+    if let Some(manifest) = &entry.plugin {
+      let dependency = manifest.as_dependency();
+      match groups.iter_mut().find(|(group, _)| *group == dependency) {
+        Some((_, indices)) => indices.push(index),
+        None => groups.push((dependency, vec![index]))
+      }
+    }
+  }
+  groups
+}
+
+/// A batch response has to answer for every request in the batch, in order, or it can't be
+/// trusted for any of them.
+fn batch_responses<T>(error: String, responses: Vec<T>, expected: usize) -> anyhow::Result<Vec<T>> {
+  if !error.is_empty() {
+    Err(anyhow!(error))
+  } else if responses.len() != expected {
+    Err(anyhow!("Plugin returned {} responses for a batch of {} fields", responses.len(), expected))
+  } else {
+    Ok(responses)
+  }
+}
+
+/// Number of results kept for pure field-level matching rules, unless the driver is configured
+/// with another size (see [`crate::driver::PluginDriverConfig::field_match_cache_size`]).
+pub const DEFAULT_FIELD_MATCH_CACHE_SIZE: usize = 10_000;
+
+/// Key a cached result is stored under: the catalogue entry (with the plugin version) and the
+/// encoded request.
+pub(crate) type FieldMatchCacheKey = (String, Vec<u8>);
+
+/// Results of field-level matching rules that declare themselves pure, so the same rule applied
+/// to the same values again (which happens a lot during verification, where the same example
+/// values recur across interactions) does not cost another plugin call.
+///
+/// Each driver has its own cache, bounded to a fixed number of results. The least recently used
+/// result is evicted first. Only successful responses are cached: a failed call may well succeed
+/// the next time.
+#[derive(Debug)]
+pub(crate) struct FieldMatchCache {
+  capacity: usize,
+  entries: HashMap<FieldMatchCacheKey, (MatchFieldResponse, u64)>,
+  recency: BTreeMap<u64, FieldMatchCacheKey>,
+  tick: u64,
+  hits: u64,
+  misses: u64,
+  evictions: u64
+}
+
+/// Statistics for the field-level match result cache of a driver
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FieldMatchCacheStats {
+  /// Number of lookups that found a cached result
+  pub hits: u64,
+  /// Number of lookups that did not
+  pub misses: u64,
+  /// Number of results evicted to keep the cache within its capacity
+  pub evictions: u64,
+  /// Number of results currently cached
+  pub entries: usize,
+  /// Maximum number of results cached. Zero means caching is disabled.
+  pub capacity: usize
+}
+
+impl FieldMatchCache {
+  pub(crate) fn new(capacity: usize) -> Self {
+    FieldMatchCache {
+      capacity,
+      entries: HashMap::new(),
+      recency: BTreeMap::new(),
+      tick: 0,
+      hits: 0,
+      misses: 0,
+      evictions: 0
+    }
+  }
+
+  fn get(&mut self, key: &FieldMatchCacheKey) -> Option<MatchFieldResponse> {
+    if self.capacity == 0 {
+      return None;
+    }
+    self.tick += 1;
+    match self.entries.get_mut(key) {
+      Some((response, last_used)) => {
+        self.recency.remove(last_used);
+        self.recency.insert(self.tick, key.clone());
+        *last_used = self.tick;
+        self.hits += 1;
+        Some(response.clone())
+      }
+      None => {
+        self.misses += 1;
+        None
+      }
+    }
+  }
+
+  fn insert(&mut self, key: FieldMatchCacheKey, response: MatchFieldResponse) {
+    if self.capacity == 0 {
+      return;
+    }
+    self.tick += 1;
+    if let Some((_, last_used)) = self.entries.insert(key.clone(), (response, self.tick)) {
+      self.recency.remove(&last_used);
+    }
+    self.recency.insert(self.tick, key);
+    while self.entries.len() > self.capacity {
+      match self.recency.pop_first() {
+        Some((_, oldest)) => {
+          self.entries.remove(&oldest);
+          self.evictions += 1;
+        }
+        None => break
+      }
+    }
+  }
+
+  fn clear(&mut self) {
+    *self = FieldMatchCache::new(self.capacity);
+  }
+
+  fn stats(&self) -> FieldMatchCacheStats {
+    FieldMatchCacheStats {
+      hits: self.hits,
+      misses: self.misses,
+      evictions: self.evictions,
+      entries: self.entries.len(),
+      capacity: self.capacity
+    }
+  }
+}
+
+/// Statistics for the field-level match result cache of the current driver
+pub fn field_match_cache_stats() -> FieldMatchCacheStats {
+  crate::driver::PluginDriver::current().inner.field_match_cache.lock().unwrap().stats()
+}
+
+/// Empty the field-level match result cache of the current driver and reset its statistics
+pub fn clear_field_match_cache() {
+  crate::driver::PluginDriver::current().inner.field_match_cache.lock().unwrap().clear();
+}
+
+/// A cached response, with the mismatch paths put back under the path of this request (see
+/// [`cache_match_response`])
+fn cached_match_response(key: &FieldMatchCacheKey, path: &str) -> Option<MatchFieldResponse> {
+  let response = crate::driver::PluginDriver::current().inner.field_match_cache.lock().unwrap().get(key)?;
+  Some(MatchFieldResponse {
+    mismatches: response.mismatches.into_iter()
+      .map(|mismatch| crate::proto_v2::ContentMismatch {
+        path: format!("{}{}", path, mismatch.path),
+        .. mismatch
+      })
+      .collect(),
+    .. response
+  })
+}
+
+/// Cache a successful response. The mismatch paths are stored relative to the path of the request,
+/// and mismatches reported against the category of the request are stored without it, so a cache
+/// hit for a value somewhere else is reported against where that value sits. A response with a
+/// mismatch outside the value that was matched is specific to where that value sits, so is not
+/// cached.
+fn cache_match_response(key: FieldMatchCacheKey, path: &str, mismatch_type: &str, response: &MatchFieldResponse) {
+  if !response.error.is_empty() {
+    return;
+  }
+  let mismatches = response.mismatches.iter()
+    .map(|mismatch| relative_mismatch_path(&mismatch.path, path)
+      .map(|relative_path| crate::proto_v2::ContentMismatch {
+        path: relative_path.to_string(),
+        mismatch_type: if mismatch.mismatch_type == mismatch_type { String::default() } else { mismatch.mismatch_type.clone() },
+        .. mismatch.clone()
+      }))
+    .collect::<Option<Vec<_>>>();
+  let Some(mismatches) = mismatches else {
+    debug!("Not caching the response for {}, as it has mismatches for other paths", path);
+    return;
+  };
+  let response = MatchFieldResponse { error: String::default(), mismatches };
+  crate::driver::PluginDriver::current().inner.field_match_cache.lock().unwrap().insert(key, response);
+}
+
+/// The part of the mismatch path below the path of the request, if it is the same path or below
+/// it. A mismatch that does not place itself is for the value that was matched.
+fn relative_mismatch_path<'a>(mismatch_path: &'a str, path: &str) -> Option<&'a str> {
+  if mismatch_path.is_empty() {
+    return Some(mismatch_path);
+  }
+  mismatch_path.strip_prefix(path)
+    .filter(|relative| relative.is_empty() || relative.starts_with('.') || relative.starts_with('['))
+}
+
 /// Which side of the test a generator is running on, mirroring `GenerateContentRequest.TestMode`
 /// in the plugin interface.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -403,30 +890,101 @@ impl TestMode {
   }
 }
 
-lazy_static! {
-  /// Runtime the driver owns for field-level plugin calls made from a synchronous call path.
-  /// Built on first use and never dropped - dropping a Tokio runtime from inside an async context
-  /// panics, and by definition this one is reached from call paths that may be exactly that.
-  static ref FIELD_RUNTIME: Mutex<Option<Arc<Runtime>>> = Mutex::new(None);
+/// Number of worker threads of the runtime a driver owns for the blocking field-level wrappers,
+/// unless configured otherwise. See [`FieldRuntime`].
+pub const DEFAULT_FIELD_RUNTIME_WORKERS: usize = 1;
+
+/// Where the blocking field-level wrappers ([`FieldMatcher::match_field_blocking`] and friends)
+/// run a call. The calling thread always waits for the result; this decides which threads do the
+/// work while it does.
+#[derive(Clone, Debug)]
+pub enum FieldRuntime {
+  /// A runtime the driver owns, built on first use with the given number of worker threads. The
+  /// default is one worker thread, which every blocking call from every test shares - enough when
+  /// calls are waiting on plugins, but a host running tests in parallel against CPU-bound
+  /// handlers should give it more.
+  Dedicated {
+    /// Number of worker threads
+    worker_threads: usize
+  },
+  /// A runtime the host owns. It must be a multi-thread runtime, with enough worker threads that
+  /// one is free to run the call while the calling thread waits - calls made from its own worker
+  /// threads each hold one of them.
+  Host(Handle)
+}
+
+impl Default for FieldRuntime {
+  fn default() -> Self {
+    FieldRuntime::Dedicated { worker_threads: DEFAULT_FIELD_RUNTIME_WORKERS }
+  }
+}
+
+/// The field runtime of a driver, and the runtime built for it if it is a dedicated one
+#[derive(Debug, Default)]
+pub(crate) struct FieldRuntimeState {
+  config: FieldRuntime,
+  runtime: Option<Runtime>
 }
 
-fn field_runtime() -> anyhow::Result<Arc<Runtime>> {
-  let mut guard = FIELD_RUNTIME.lock()
-    .map_err(|err| anyhow!("FIELD_RUNTIME mutex poisoned - {}", err))?;
-  match guard.as_ref() {
-    Some(runtime) => Ok(runtime.clone()),
-    None => {
-      let runtime = Arc::new(tokio::runtime::Builder::new_multi_thread()
-        .worker_threads(1)
-        .enable_all()
-        .thread_name("pact-plugin-field")
-        .build()?);
-      *guard = Some(runtime.clone());
-      Ok(runtime)
+impl FieldRuntimeState {
+  fn configure(&mut self, config: FieldRuntime) -> anyhow::Result<()> {
+    match &config {
+      FieldRuntime::Dedicated { worker_threads: 0 } =>
+        return Err(anyhow!("The field runtime needs at least one worker thread")),
+      FieldRuntime::Host(handle) if handle.runtime_flavor() == RuntimeFlavor::CurrentThread =>
+        return Err(anyhow!("The field runtime can not be a current_thread runtime, as its only thread would be blocked waiting for the call")),
+      _ => {}
+    }
+    self.shutdown();
+    self.config = config;
+    Ok(())
+  }
+
+  fn handle(&mut self) -> anyhow::Result<Handle> {
+    match &self.config {
+      FieldRuntime::Host(handle) => Ok(handle.clone()),
+      FieldRuntime::Dedicated { worker_threads } => match &self.runtime {
+        Some(runtime) => Ok(runtime.handle().clone()),
+        None => {
+          let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(*worker_threads)
+            .enable_all()
+            .thread_name("pact-plugin-field")
+            .build()?;
+          let handle = runtime.handle().clone();
+          self.runtime = Some(runtime);
+          Ok(handle)
+        }
+      }
+    }
+  }
+
+  /// Shut down the dedicated runtime, if one was built. Dropping a Tokio runtime from inside an
+  /// async context panics, and a driver can be dropped from anywhere, so it is shut down in the
+  /// background instead. Any call still running on it fails.
+  pub(crate) fn shutdown(&mut self) {
+    if let Some(runtime) = self.runtime.take() {
+      runtime.shutdown_background();
     }
   }
 }
 
+/// Set where the blocking field-level wrappers of the current driver run a call. A dedicated
+/// runtime the driver already built for the previous configuration is shut down, failing any call
+/// still running on it, so this is best done once before any tests run. Returns an error for a
+/// dedicated runtime with no worker threads, or a host runtime that is a `current_thread` one.
+pub fn configure_field_runtime(runtime: FieldRuntime) -> anyhow::Result<()> {
+  crate::driver::PluginDriver::current().inner.field_runtime.lock()
+    .map_err(|err| anyhow!("Field runtime mutex poisoned - {}", err))?
+    .configure(runtime)
+}
+
+fn field_runtime_handle() -> anyhow::Result<Handle> {
+  crate::driver::PluginDriver::current().inner.field_runtime.lock()
+    .map_err(|err| anyhow!("Field runtime mutex poisoned - {}", err))?
+    .handle()
+}
+
 /// Run a field-level plugin call to completion from a synchronous call path.
 ///
 /// The obvious approaches do not work. `Handle::current().block_on(..)` panics ("Cannot start a
@@ -434,23 +992,24 @@ fn field_runtime() -> anyhow::Result<Arc<Runtime>> {
 /// calling thread is already driving tasks. `task::block_in_place` re-enters legitimately but
 /// panics on a `current_thread` runtime, and some Pact entry points use one.
 ///
-/// So the future runs on a runtime the driver owns, and the calling thread waits on a channel. It
-/// does block a host thread for the duration, which is inherent to bridging sync and async, but the
-/// plugin call itself never depends on the host's runtime making progress: the driver opens a fresh
-/// gRPC channel per call (see `GrpcPactPlugin::connect_channel`), so the connection driving that
-/// call belongs to this runtime too.
+/// So the future runs on the field runtime of the driver (see [`FieldRuntime`]), and the calling
+/// thread waits on a channel. It does block a host thread for the duration, which is inherent to
+/// bridging sync and async, but the plugin call itself never depends on the calling thread's
+/// runtime making progress: the gRPC channel the call goes over is driven by another runtime the
+/// driver owns (see `grpc_plugin::CHANNEL_RUNTIME`), not by whichever runtime first connected it.
+/// A host that can await the call should use the async operations instead.
 fn block_on_field_call<F, T>(future: F) -> anyhow::Result<T>
 where
   F: std::future::Future<Output = T> + Send + 'static,
   T: Send + 'static
 {
-  let runtime = field_runtime()?;
+  let handle = field_runtime_handle()?;
   let deadline_ms = crate::call_chain::default_deadline_ms();
   let (sender, receiver) = std::sync::mpsc::channel();
-  runtime.spawn(async move {
+  handle.spawn(crate::driver::PluginDriver::current().scope(async move {
     // A send error just means the caller already gave up waiting
     let _ = sender.send(future.await);
-  });
+  }));
   receiver.recv_timeout(crate::call_chain::remaining(deadline_ms))
     .map_err(|err| {
       error!("Timed out waiting for a field-level plugin call to complete - {}", err);
@@ -469,22 +1028,24 @@ fn process_match_field_response(
     } else if response.mismatches.is_empty() {
       Ok(())
     } else {
-      Err(response.mismatches.iter().map(|mismatch| ContentMismatch {
-        expected: mismatch.expected.as_ref()
-          .map(|bytes| String::from_utf8_lossy(bytes).to_string())
-          .unwrap_or_default(),
-        actual: mismatch.actual.as_ref()
-          .map(|bytes| String::from_utf8_lossy(bytes).to_string())
-          .unwrap_or_default(),
-        mismatch: mismatch.mismatch.clone(),
-        // A mismatch that does not place itself is reported against the value being matched
-        path: if mismatch.path.is_empty() { path.clone() } else { mismatch.path.clone() },
-        diff: if mismatch.diff.is_empty() { None } else { Some(mismatch.diff.clone()) },
-        mismatch_type: if mismatch.mismatch_type.is_empty() {
-          Some(context.category.clone())
-        } else {
-          Some(mismatch.mismatch_type.clone())
-        }
+      Err(response.mismatches.iter().map(|mismatch| {
+        ContentMismatch {
+          diff: if mismatch.diff.is_empty() { None } else { Some(mismatch.diff.clone()) },
+          mismatch_type: if mismatch.mismatch_type.is_empty() {
+            Some(context.category.clone())
+          } else {
+            Some(mismatch.mismatch_type.clone())
+          },
+          .. ContentMismatch::from_bytes(
+            mismatch.expected.clone().map(Bytes::from),
+            mismatch.actual.clone().map(Bytes::from),
+            None,
+            mismatch.mismatch.as_str(),
+            // A mismatch that does not place itself is reported against the value being matched
+            if mismatch.path.is_empty() { path.as_str() } else { mismatch.path.as_str() }
+          )
+        }.with_structured_diff(mismatch.structured_diff.iter().map(DiffEntry::from_proto).collect())
+          .with_generated_diff()
       }).collect())
     },
     Err(err) => {
@@ -496,12 +1057,10 @@ fn process_match_field_response(
 
 fn mismatch_for(message: String, context: &FieldContext) -> ContentMismatch {
   ContentMismatch {
-    expected: Default::default(),
-    actual: Default::default(),
     mismatch: message,
     path: context.path.to_string(),
-    diff: None,
-    mismatch_type: Some(context.category.clone())
+    mismatch_type: Some(context.category.clone()),
+    .. ContentMismatch::default()
   }
 }
 
@@ -546,12 +1105,15 @@ fn to_proto_plugin_config(config: PluginInteractionConfig) -> ProtoPluginConfigu
 
 #[cfg(test)]
 mod tests {
+  use std::sync::{Arc, Mutex};
+
   use async_trait::async_trait;
   use expectest::prelude::*;
   use maplit::hashmap;
   use pact_models::matchingrules::MatchingRule;
 
   use crate::catalogue_manager::{CatalogueEntryProviderType, register_core_entries};
+  use crate::child_process::{ChildPluginProcess, RunningPluginInfo};
   use crate::core_capabilities::{
     CoreFieldGenerator,
     CoreFieldMatcher,
@@ -560,6 +1122,10 @@ mod tests {
     register_core_field_generator,
     register_core_field_matcher
   };
+  use crate::driver::PluginDriver;
+  use crate::plugin_manager::RegisteredPlugin;
+  use crate::plugin_models::PactPlugin;
+  use crate::plugin_models::tests::MockPlugin;
   use crate::proto_v2::ContentMismatch as ProtoContentMismatch;
 
   use super::*;
@@ -953,4 +1519,444 @@ mod tests {
 
     expect!(result).to(be_ok());
   }
+
+  #[allow(deprecated)]
+  fn register_mock_plugin(name: &str, capabilities: Vec<String>) -> Arc<MockPlugin> {
+    let manifest = PactPluginManifest {
+      name: name.to_string(),
+      version: "0.0.1".to_string(),
+      .. PactPluginManifest::default()
+    };
+    let child = ChildPluginProcess {
+      child_pid: 0,
+      plugin_info: RunningPluginInfo { port: 0, server_key: String::new() },
+      instance_id: format!("{}-instance", name)
+    };
+    let plugin = PactPlugin::new(&manifest, child).unwrap();
+    let instance = Arc::new(MockPlugin { manifest, capabilities, .. MockPlugin::default() });
+    PluginDriver::current().inner.plugin_register.lock().unwrap()
+      .insert(format!("{}/0.0.1", name), RegisteredPlugin::new(instance.clone(), plugin));
+    instance
+  }
+
+  fn catalogue_entry_for(plugin: &MockPlugin, entry_type: CatalogueEntryType) -> CatalogueEntry {
+    CatalogueEntry {
+      entry_type,
+      provider_type: CatalogueEntryProviderType::PLUGIN,
+      plugin: Some(plugin.manifest.clone()),
+      key: "mock".to_string(),
+      values: hashmap!{}
+    }
+  }
+
+  fn match_request(matcher: &FieldMatcher, path: &str, expected: &str, actual: &str) -> FieldMatchRequest {
+    FieldMatchRequest {
+      matcher: matcher.clone(),
+      rule: a_rule(),
+      expected: FieldValue::Json(Value::String(expected.to_string())),
+      actual: FieldValue::Json(Value::String(actual.to_string())),
+      context: FieldContext::new(&DocPath::new(path).unwrap(), "body")
+    }
+  }
+
+  #[test_log::test(tokio::test)]
+  async fn match_fields_sends_one_batch_to_a_plugin_that_supports_it() {
+    let driver = PluginDriver::new(Default::default());
+    let _guard = driver.enter();
+    let plugin = register_mock_plugin("batching-plugin", vec![FIELD_BATCH_CAPABILITY.to_string()]);
+    let matcher = FieldMatcher { catalogue_entry: catalogue_entry_for(&plugin, CatalogueEntryType::MATCHER) };
+    let key = "match_fields_sends_one_batch_to_a_plugin_that_supports_it";
+    register_core_matcher_entry(key, CatalogueEntryType::MATCHER);
+    register_core_field_matcher(key, Arc::new(TestCoreMatcher {
+      mismatches: vec![],
+      error: String::default()
+    }));
+    let core_request = FieldMatchRequest {
+      matcher: find_field_matcher(key).unwrap(),
+      context: field_context(),
+      .. match_request(&matcher, "$.card.number", "4111111111111111", "4012888888881881")
+    };
+
+    let results = match_fields(&[
+      match_request(&matcher, "$.a", "1", "1"),
+      core_request,
+      match_request(&matcher, "$.b", "2", "3"),
+      match_request(&matcher, "$.c", "4", "4")
+    ]).await;
+
+    deregister_core_field_matcher(key);
+
+    expect!(plugin.field_calls.read().unwrap().clone())
+      .to(be_equal_to(vec!["match_fields_batch:3".to_string()]));
+    expect!(results.len()).to(be_equal_to(4));
+    expect!(results[0].clone()).to(be_ok());
+    expect!(results[1].clone()).to(be_ok());
+    let mismatches = results[2].clone().unwrap_err();
+    expect!(mismatches.iter().map(|m| m.path.clone()).collect::<Vec<_>>())
+      .to(be_equal_to(vec!["$.b".to_string()]));
+    expect!(results[3].clone()).to(be_ok());
+  }
+
+  #[test_log::test(tokio::test)]
+  async fn match_fields_falls_back_to_one_call_per_field_without_the_capability() {
+    let driver = PluginDriver::new(Default::default());
+    let _guard = driver.enter();
+    let plugin = register_mock_plugin("non-batching-plugin", vec![]);
+    let matcher = FieldMatcher { catalogue_entry: catalogue_entry_for(&plugin, CatalogueEntryType::MATCHER) };
+
+    let results = match_fields(&[
+      match_request(&matcher, "$.a", "1", "2"),
+      match_request(&matcher, "$.b", "3", "3")
+    ]).await;
+
+    expect!(plugin.field_calls.read().unwrap().clone()).to(be_equal_to(vec![
+      "match_field:$.a".to_string(),
+      "match_field:$.b".to_string()
+    ]));
+    expect!(results[0].clone()).to(be_err());
+    expect!(results[1].clone()).to(be_ok());
+  }
+
+  #[test]
+  fn generate_fields_blocking_batches_in_a_single_call() {
+    let driver = PluginDriver::new(Default::default());
+    let _guard = driver.enter();
+    let plugin = register_mock_plugin("batching-generator-plugin", vec![FIELD_BATCH_CAPABILITY.to_string()]);
+    let generator = FieldGenerator { catalogue_entry: catalogue_entry_for(&plugin, CatalogueEntryType::GENERATOR) };
+    let requests = ["$.a", "$.b", "$.c"].iter()
+      .map(|path| FieldGenerateRequest {
+        generator: generator.clone(),
+        definition: Generator::Uuid(None),
+        example: FieldValue::Json(Value::Null),
+        mode: TestMode::Consumer,
+        context: FieldContext::new(&DocPath::new(*path).unwrap(), "body")
+      })
+      .collect::<Vec<_>>();
+
+    let values = generate_fields_blocking(&requests).into_iter()
+      .map(|result| result.unwrap())
+      .collect::<Vec<_>>();
+
+    expect!(plugin.field_calls.read().unwrap().clone())
+      .to(be_equal_to(vec!["generate_fields_batch:3".to_string()]));
+    expect!(values).to(be_equal_to(vec![
+      FieldValue::Json(Value::String("generated $.a".to_string())),
+      FieldValue::Json(Value::String("generated $.b".to_string())),
+      FieldValue::Json(Value::String("generated $.c".to_string()))
+    ]));
+  }
+
+  #[test]
+  fn the_match_cache_evicts_the_least_recently_used_result() {
+    let key = |name: &str| (name.to_string(), vec![]);
+    let mut cache = FieldMatchCache::new(2);
+    cache.insert(key("one"), MatchFieldResponse::default());
+    cache.insert(key("two"), MatchFieldResponse::default());
+    expect!(cache.get(&key("one"))).to(be_some());
+    cache.insert(key("three"), MatchFieldResponse::default());
+
+    expect!(cache.get(&key("two"))).to(be_none());
+    expect!(cache.get(&key("one"))).to(be_some());
+    expect!(cache.get(&key("three"))).to(be_some());
+    expect!(cache.stats()).to(be_equal_to(FieldMatchCacheStats {
+      hits: 3,
+      misses: 1,
+      evictions: 1,
+      entries: 2,
+      capacity: 2
+    }));
+
+    let mut disabled = FieldMatchCache::new(0);
+    disabled.insert(key("one"), MatchFieldResponse::default());
+    expect!(disabled.get(&key("one"))).to(be_none());
+    expect!(disabled.stats()).to(be_equal_to(FieldMatchCacheStats::default()));
+  }
+
+  /// Counts the calls it gets, and reports a mismatch against the requested path when the values
+  /// differ
+  #[derive(Debug, Default)]
+  struct CountingMatcher {
+    calls: std::sync::atomic::AtomicUsize
+  }
+
+  #[async_trait]
+  impl CoreFieldMatcher for CountingMatcher {
+    async fn match_field(&self, request: MatchFieldRequest) -> anyhow::Result<MatchFieldResponse> {
+      self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+      let mismatches = if request.expected == request.actual {
+        vec![]
+      } else {
+        vec![ProtoContentMismatch {
+          mismatch: "Values differ".to_string(),
+          path: request.path.clone(),
+          mismatch_type: request.mismatch_type.clone(),
+          .. ProtoContentMismatch::default()
+        }]
+      };
+      Ok(MatchFieldResponse { error: String::default(), mismatches })
+    }
+  }
+
+  fn register_counting_matcher(key: &str, pure: bool) -> Arc<CountingMatcher> {
+    let values = if pure { hashmap!{ "pure".to_string() => "true".to_string() } } else { hashmap!{} };
+    register_core_entries(&vec![CatalogueEntry {
+      entry_type: CatalogueEntryType::MATCHER,
+      provider_type: CatalogueEntryProviderType::CORE,
+      plugin: None,
+      key: key.to_string(),
+      values
+    }]);
+    let matcher = Arc::new(CountingMatcher::default());
+    register_core_field_matcher(key, matcher.clone());
+    matcher
+  }
+
+  #[test_log::test(tokio::test)]
+  async fn a_pure_matchers_results_are_cached() {
+    let driver = PluginDriver::new(Default::default());
+    let _guard = driver.enter();
+    let handler = register_counting_matcher("pure-matcher", true);
+    let matcher = find_field_matcher("pure-matcher").unwrap();
+    expect!(matcher.is_pure()).to(be_true());
+
+    let first = match_request(&matcher, "$.a", "1", "2");
+    let second = FieldMatchRequest {
+      context: FieldContext::new(&DocPath::new_unwrap("$.b"), "header"),
+      .. match_request(&matcher, "$.a", "1", "2")
+    };
+    let first_result = matcher.match_field(&first.rule, &first.expected, &first.actual, &first.context).await;
+    let second_result = matcher.match_field(&second.rule, &second.expected, &second.actual, &second.context).await;
+
+    expect!(handler.calls.load(std::sync::atomic::Ordering::SeqCst)).to(be_equal_to(1));
+    // The cached mismatch is reported against where the second value sits, not the first
+    let first_mismatch = first_result.unwrap_err()[0].clone();
+    let second_mismatch = second_result.unwrap_err()[0].clone();
+    expect!(first_mismatch.path).to(be_equal_to("$.a"));
+    expect!(second_mismatch.path).to(be_equal_to("$.b"));
+    expect!(second_mismatch.mismatch_type).to(be_some().value("header"));
+    expect!(driver.field_match_cache_stats()).to(be_equal_to(FieldMatchCacheStats {
+      hits: 1,
+      misses: 1,
+      evictions: 0,
+      entries: 1,
+      capacity: DEFAULT_FIELD_MATCH_CACHE_SIZE
+    }));
+
+    driver.clear_field_match_cache();
+    expect!(driver.field_match_cache_stats().entries).to(be_equal_to(0));
+  }
+
+  /// Reports mismatches below the requested path, and optionally one somewhere else
+  #[derive(Debug, Default)]
+  struct NestedMismatchMatcher {
+    calls: std::sync::atomic::AtomicUsize,
+    elsewhere: bool
+  }
+
+  #[async_trait]
+  impl CoreFieldMatcher for NestedMismatchMatcher {
+    async fn match_field(&self, request: MatchFieldRequest) -> anyhow::Result<MatchFieldResponse> {
+      self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+      let mismatch = |path: String| ProtoContentMismatch {
+        mismatch: "Values differ".to_string(),
+        path,
+        .. ProtoContentMismatch::default()
+      };
+      let mut mismatches = vec![
+        mismatch(String::default()),
+        mismatch(format!("{}.number", request.path)),
+        mismatch(format!("{}[0]", request.path))
+      ];
+      if self.elsewhere {
+        mismatches.push(mismatch("$.elsewhere".to_string()));
+      }
+      Ok(MatchFieldResponse { error: String::default(), mismatches })
+    }
+  }
+
+  fn register_nested_mismatch_matcher(key: &str, elsewhere: bool) -> (Arc<NestedMismatchMatcher>, FieldMatcher) {
+    register_core_entries(&vec![CatalogueEntry {
+      entry_type: CatalogueEntryType::MATCHER,
+      provider_type: CatalogueEntryProviderType::CORE,
+      plugin: None,
+      key: key.to_string(),
+      values: hashmap!{ "pure".to_string() => "true".to_string() }
+    }]);
+    let handler = Arc::new(NestedMismatchMatcher { elsewhere, .. NestedMismatchMatcher::default() });
+    register_core_field_matcher(key, handler.clone());
+    (handler, find_field_matcher(key).unwrap())
+  }
+
+  async fn mismatch_paths(matcher: &FieldMatcher, path: &str) -> Vec<String> {
+    let request = match_request(matcher, path, "1", "2");
+    matcher.match_field(&request.rule, &request.expected, &request.actual, &request.context).await
+      .unwrap_err()
+      .iter()
+      .map(|mismatch| mismatch.path.clone())
+      .collect()
+  }
+
+  #[test_log::test(tokio::test)]
+  async fn cached_mismatches_are_reported_below_where_the_value_sits() {
+    let driver = PluginDriver::new(Default::default());
+    let _guard = driver.enter();
+    let (handler, matcher) = register_nested_mismatch_matcher("nested-matcher", false);
+
+    let first = mismatch_paths(&matcher, "$.card").await;
+    let second = mismatch_paths(&matcher, "$.cards[1].card").await;
+
+    expect!(handler.calls.load(std::sync::atomic::Ordering::SeqCst)).to(be_equal_to(1));
+    expect!(first).to(be_equal_to(vec![
+      "$.card".to_string(),
+      "$.card.number".to_string(),
+      "$.card[0]".to_string()
+    ]));
+    expect!(second).to(be_equal_to(vec![
+      "$.cards[1].card".to_string(),
+      "$.cards[1].card.number".to_string(),
+      "$.cards[1].card[0]".to_string()
+    ]));
+  }
+
+  #[test_log::test(tokio::test)]
+  async fn a_response_with_mismatches_outside_the_value_is_not_cached() {
+    let driver = PluginDriver::new(Default::default());
+    let _guard = driver.enter();
+    let (handler, matcher) = register_nested_mismatch_matcher("elsewhere-matcher", true);
+
+    let _ = mismatch_paths(&matcher, "$.card").await;
+    let second = mismatch_paths(&matcher, "$.cards[1].card").await;
+
+    expect!(handler.calls.load(std::sync::atomic::Ordering::SeqCst)).to(be_equal_to(2));
+    expect!(second.last().cloned()).to(be_some().value("$.elsewhere".to_string()));
+    expect!(driver.field_match_cache_stats().entries).to(be_equal_to(0));
+  }
+
+  #[test_log::test(tokio::test)]
+  async fn a_matcher_that_does_not_declare_itself_pure_is_not_cached() {
+    let driver = PluginDriver::new(Default::default());
+    let _guard = driver.enter();
+    let handler = register_counting_matcher("impure-matcher", false);
+    let matcher = find_field_matcher("impure-matcher").unwrap();
+    let request = match_request(&matcher, "$.a", "1", "1");
+
+    for _ in 0..2 {
+      let result = matcher.match_field(&request.rule, &request.expected, &request.actual, &request.context).await;
+      expect!(result).to(be_ok());
+    }
+
+    expect!(handler.calls.load(std::sync::atomic::Ordering::SeqCst)).to(be_equal_to(2));
+    expect!(driver.field_match_cache_stats()).to(be_equal_to(FieldMatchCacheStats {
+      capacity: DEFAULT_FIELD_MATCH_CACHE_SIZE,
+      .. FieldMatchCacheStats::default()
+    }));
+  }
+
+  #[test_log::test(tokio::test)]
+  async fn match_fields_only_sends_uncached_values_to_the_plugin() {
+    let driver = PluginDriver::new(Default::default());
+    let _guard = driver.enter();
+    let plugin = register_mock_plugin("pure-batching-plugin", vec![FIELD_BATCH_CAPABILITY.to_string()]);
+    let matcher = FieldMatcher {
+      catalogue_entry: CatalogueEntry {
+        values: hashmap!{ "pure".to_string() => "true".to_string() },
+        .. catalogue_entry_for(&plugin, CatalogueEntryType::MATCHER)
+      }
+    };
+
+    let _ = match_fields(&[
+      match_request(&matcher, "$.a", "1", "1"),
+      match_request(&matcher, "$.b", "2", "3")
+    ]).await;
+    let results = match_fields(&[
+      match_request(&matcher, "$.c", "1", "1"),
+      match_request(&matcher, "$.d", "2", "3"),
+      match_request(&matcher, "$.e", "5", "5")
+    ]).await;
+
+    expect!(plugin.field_calls.read().unwrap().clone()).to(be_equal_to(vec![
+      "match_fields_batch:2".to_string(),
+      "match_field:$.e".to_string()
+    ]));
+    expect!(results[0].clone()).to(be_ok());
+    expect!(results[1].clone().unwrap_err()[0].path.clone()).to(be_equal_to("$.d"));
+    expect!(results[2].clone()).to(be_ok());
+    expect!(driver.field_match_cache_stats().hits).to(be_equal_to(2));
+  }
+
+  /// Records the name of the thread each call runs on
+  #[derive(Debug, Default)]
+  struct ThreadRecordingMatcher {
+    threads: Mutex<Vec<Option<String>>>
+  }
+
+  #[async_trait]
+  impl CoreFieldMatcher for ThreadRecordingMatcher {
+    async fn match_field(&self, _request: MatchFieldRequest) -> anyhow::Result<MatchFieldResponse> {
+      self.threads.lock().unwrap().push(std::thread::current().name().map(|name| name.to_string()));
+      Ok(MatchFieldResponse::default())
+    }
+  }
+
+  fn register_thread_recording_matcher(key: &str) -> Arc<ThreadRecordingMatcher> {
+    register_core_matcher_entry(key, CatalogueEntryType::MATCHER);
+    let matcher = Arc::new(ThreadRecordingMatcher::default());
+    register_core_field_matcher(key, matcher.clone());
+    matcher
+  }
+
+  #[test_log::test(tokio::test)]
+  async fn the_async_entry_point_runs_on_the_callers_runtime() {
+    let driver = PluginDriver::new(Default::default());
+    let _guard = driver.enter();
+    let handler = register_thread_recording_matcher("thread-recording-matcher");
+    let context = FieldContext::new(&DocPath::new_unwrap("$.a"), "body");
+    let value = FieldValue::Json(Value::Null);
+
+    let result = match_field_value("thread-recording-matcher", &a_rule(), &value, &value, &context).await;
+    let unknown = match_field_value("no-such-matcher", &a_rule(), &value, &value, &context).await;
+
+    expect!(result).to(be_ok());
+    expect!(unknown.unwrap_err()[0].path.clone()).to(be_equal_to("$.a"));
+    // A current_thread test runtime: the call ran right here, not on the field runtime
+    let threads = handler.threads.lock().unwrap().clone();
+    expect!(threads).to(be_equal_to(vec![std::thread::current().name().map(|name| name.to_string())]));
+  }
+
+  #[test]
+  fn the_blocking_wrappers_run_on_the_configured_field_runtime() {
+    let driver = PluginDriver::new(Default::default());
+    let _guard = driver.enter();
+    let handler = register_thread_recording_matcher("field-runtime-matcher");
+    let matcher = find_field_matcher("field-runtime-matcher").unwrap();
+    let value = FieldValue::Json(Value::Null);
+    let host_runtime = tokio::runtime::Builder::new_multi_thread()
+      .worker_threads(2)
+      .thread_name("host-runtime")
+      .enable_all()
+      .build()
+      .unwrap();
+
+    expect!(matcher.match_field_blocking(&a_rule(), &value, &value, &field_context())).to(be_ok());
+    driver.configure_field_runtime(FieldRuntime::Host(host_runtime.handle().clone())).unwrap();
+    expect!(matcher.match_field_blocking(&a_rule(), &value, &value, &field_context())).to(be_ok());
+    driver.configure_field_runtime(FieldRuntime::Dedicated { worker_threads: 4 }).unwrap();
+    expect!(matcher.match_field_blocking(&a_rule(), &value, &value, &field_context())).to(be_ok());
+
+    let threads = handler.threads.lock().unwrap().clone();
+    expect!(threads).to(be_equal_to(vec![
+      Some("pact-plugin-field".to_string()),
+      Some("host-runtime".to_string()),
+      Some("pact-plugin-field".to_string())
+    ]));
+  }
+
+  #[test]
+  fn a_field_runtime_that_could_not_run_a_call_is_rejected() {
+    let driver = PluginDriver::new(Default::default());
+    let current_thread = tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+    expect!(driver.configure_field_runtime(FieldRuntime::Dedicated { worker_threads: 0 })).to(be_err());
+    expect!(driver.configure_field_runtime(FieldRuntime::Host(current_thread.handle().clone()))).to(be_err());
+  }
 }
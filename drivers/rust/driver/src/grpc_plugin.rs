@@ -1,20 +1,25 @@
 //! gRPC plugin wrapper and process management
 
+use std::env;
+use std::error::Error;
 use std::process::Command;
 use std::process::Stdio;
+use std::sync::{Arc, Mutex};
 
 use anyhow::anyhow;
 use async_trait::async_trait;
+use lazy_static::lazy_static;
 use log::max_level;
 use os_info::Type;
 use prost::Message;
 use std::path::PathBuf;
 use sysinfo::{Pid, System};
+use tokio::runtime::Runtime;
 use tonic::codegen::InterceptedService;
 use tonic::metadata::{Ascii, MetadataValue};
 use tonic::service::Interceptor;
 use tonic::transport::Channel;
-use tonic::{Request, Status};
+use tonic::{Code, Request, Status};
 use tracing::{debug, warn};
 use uuid::Uuid;
 
@@ -325,6 +330,72 @@ impl PluginClient {
     }
   }
 
+  async fn match_fields_batch(
+    &mut self,
+    request: proto_v2::MatchFieldsBatchRequest,
+  ) -> Result<proto_v2::MatchFieldsBatchResponse, Status> {
+    match self {
+      PluginClient::V1(_) => Err(Status::unimplemented(
+        "Field-level matching rules require the V2 plugin interface, and this plugin uses V1"
+      )),
+      PluginClient::V2(client) => client
+        .match_fields_batch(Request::new(request))
+        .await
+        .map(|response| response.into_inner()),
+    }
+  }
+
+  async fn match_fields_batch_with_metadata(
+    &mut self,
+    request: proto_v2::MatchFieldsBatchRequest,
+    chain_id: &str,
+    deadline_ms: u64,
+  ) -> Result<proto_v2::MatchFieldsBatchResponse, Status> {
+    match self {
+      PluginClient::V1(_) => Err(Status::unimplemented(
+        "Field-level matching rules require the V2 plugin interface, and this plugin uses V1"
+      )),
+      PluginClient::V2(client) => {
+        let mut req = Request::new(request);
+        insert_chain_metadata(&mut req, chain_id, deadline_ms)?;
+        client.match_fields_batch(req).await.map(|response| response.into_inner())
+      }
+    }
+  }
+
+  async fn generate_fields_batch(
+    &mut self,
+    request: proto_v2::GenerateFieldsBatchRequest,
+  ) -> Result<proto_v2::GenerateFieldsBatchResponse, Status> {
+    match self {
+      PluginClient::V1(_) => Err(Status::unimplemented(
+        "Field-level generators require the V2 plugin interface, and this plugin uses V1"
+      )),
+      PluginClient::V2(client) => client
+        .generate_fields_batch(Request::new(request))
+        .await
+        .map(|response| response.into_inner()),
+    }
+  }
+
+  async fn generate_fields_batch_with_metadata(
+    &mut self,
+    request: proto_v2::GenerateFieldsBatchRequest,
+    chain_id: &str,
+    deadline_ms: u64,
+  ) -> Result<proto_v2::GenerateFieldsBatchResponse, Status> {
+    match self {
+      PluginClient::V1(_) => Err(Status::unimplemented(
+        "Field-level generators require the V2 plugin interface, and this plugin uses V1"
+      )),
+      PluginClient::V2(client) => {
+        let mut req = Request::new(request);
+        insert_chain_metadata(&mut req, chain_id, deadline_ms)?;
+        client.generate_fields_batch(req).await.map(|response| response.into_inner())
+      }
+    }
+  }
+
   async fn start_mock_server_v2(
     &mut self,
     request: proto_v2::StartMockServerRequest,
@@ -474,21 +545,117 @@ impl Interceptor for PactPluginInterceptor {
   }
 }
 
-/// Wrapper around `PactPlugin` that provides gRPC connectivity
+lazy_static! {
+  /// Runtime the driver owns for the background tasks of its plugin gRPC channels (the request
+  /// buffer and the HTTP/2 connection). A channel is shared by every call to its plugin, so these
+  /// tasks must not belong to whichever runtime happened to make the first call: that runtime may
+  /// be a test's, dropped when the test ends, or a `current_thread` runtime whose only thread is
+  /// blocked waiting on a field-level call (see `field::block_on_field_call`). Built on first use
+  /// and never dropped, as dropping a Tokio runtime from inside an async context panics and this
+  /// one is reached from exactly such call paths. Restarting a crashed plugin runs
+  /// here too (see `plugin_manager::plugin_process_exited`), as that has no runtime of its own.
+  static ref CHANNEL_RUNTIME: Mutex<Option<Arc<Runtime>>> = Mutex::new(None);
+}
+
+pub(crate) fn channel_runtime() -> anyhow::Result<Arc<Runtime>> {
+  let mut guard = CHANNEL_RUNTIME.lock()
+    .map_err(|err| anyhow!("CHANNEL_RUNTIME mutex poisoned - {}", err))?;
+  match guard.as_ref() {
+    Some(runtime) => Ok(runtime.clone()),
+    None => {
+      let runtime = Arc::new(tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(channel_runtime_threads())
+        .enable_all()
+        .thread_name("pact-plugin-grpc")
+        .build()?);
+      *guard = Some(runtime.clone());
+      Ok(runtime)
+    }
+  }
+}
+
+/// Number of worker threads for [`CHANNEL_RUNTIME`]: the `PACT_PLUGIN_GRPC_THREADS` environment
+/// variable if it is set, otherwise the available parallelism of the machine. Every call to every
+/// gRPC plugin goes through these threads, so one thread would serialise all plugin traffic.
+fn channel_runtime_threads() -> usize {
+  if let Ok(value) = env::var("PACT_PLUGIN_GRPC_THREADS") {
+    match value.trim().parse::<usize>() {
+      Ok(threads) if threads > 0 => return threads,
+      _ => warn!("Ignoring invalid PACT_PLUGIN_GRPC_THREADS '{}', it must be a number greater than zero", value)
+    }
+  }
+  std::thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1)
+}
+
+/// Spawns a channel's background tasks onto [`CHANNEL_RUNTIME`].
+#[derive(Clone)]
+struct ChannelExecutor(Arc<Runtime>);
+
+impl<F> hyper::rt::Executor<F> for ChannelExecutor
+where
+  F: Future<Output = ()> + Send + 'static
+{
+  fn execute(&self, future: F) {
+    self.0.spawn(future);
+  }
+}
+
+/// An open channel to a plugin process, and the port it was opened to.
+#[derive(Debug, Clone)]
+struct PluginChannel {
+  port: u16,
+  channel: Channel,
+}
+
+/// Wrapper around `PactPlugin` that provides gRPC connectivity.
+///
+/// One channel is opened on the first call and shared by all later calls (and clones of this
+/// value), rather than connecting for every call. HTTP/2 multiplexes concurrent calls over the one
+/// connection, so there is no pool. If the plugin can not be reached the channel is dropped, and
+/// the next call connects again - as it does if the plugin's port has changed since, which means
+/// the plugin process was restarted.
 #[derive(Debug, Clone)]
 pub struct GrpcPactPlugin {
   pub plugin: PactPlugin,
+  channel: Arc<tokio::sync::Mutex<Option<PluginChannel>>>,
 }
 
 impl GrpcPactPlugin {
   pub fn new(plugin: PactPlugin) -> Self {
-    GrpcPactPlugin { plugin }
+    GrpcPactPlugin {
+      plugin,
+      channel: Arc::new(tokio::sync::Mutex::new(None)),
+    }
   }
 
+  /// Returns the shared channel to the plugin, connecting it first if there is none yet or the
+  /// plugin is now listening on a different port. Callers racing to connect wait on the lock, so
+  /// only one connection is made.
   #[allow(deprecated)]
-  async fn connect_channel(&self) -> anyhow::Result<Channel> {
+  async fn channel(&self) -> anyhow::Result<Channel> {
     let port = self.plugin.child.port();
+    let mut guard = self.channel.lock().await;
+    if let Some(plugin_channel) = guard.as_ref() {
+      if plugin_channel.port == port {
+        return Ok(plugin_channel.channel.clone());
+      }
+      debug!("Plugin {} is now on port {} (was {}), reconnecting", self.plugin.manifest.name,
+        port, plugin_channel.port);
+    }
+    let channel = self.connect_channel(port).await?;
+    *guard = Some(PluginChannel { port, channel: channel.clone() });
+    Ok(channel)
+  }
+
+  /// Drops the shared channel, so the next call connects again.
+  pub(crate) async fn reset_channel(&self) {
+    *self.channel.lock().await = None;
+  }
+
+  async fn connect_channel(&self, port: u16) -> anyhow::Result<Channel> {
+    let executor = ChannelExecutor(channel_runtime()?);
     match Channel::from_shared(format!("http://[::1]:{}", port))?
+      .executor(executor.clone())
       .connect()
       .await
     {
@@ -496,6 +663,7 @@ impl GrpcPactPlugin {
       Err(err) => {
         debug!("IP6 connection failed, will try IP4 address - {err}");
         Channel::from_shared(format!("http://127.0.0.1:{}", port))?
+          .executor(executor)
           .connect()
           .await
           .map_err(|err| anyhow!(err))
@@ -505,7 +673,7 @@ impl GrpcPactPlugin {
 
   #[allow(deprecated)]
   async fn get_plugin_client(&self) -> anyhow::Result<PluginClient> {
-    let channel = self.connect_channel().await?;
+    let channel = self.channel().await?;
     let interceptor =
       PactPluginInterceptor::new(self.plugin.child.plugin_info.server_key.as_str())?;
     match self.plugin.interface_version {
@@ -519,6 +687,36 @@ impl GrpcPactPlugin {
       ))),
     }
   }
+
+  /// Makes a call with a client on the shared channel, dropping the channel if the call failed
+  /// because the plugin could not be reached.
+  async fn with_client<T, F, Fut>(&self, call: F) -> anyhow::Result<T>
+  where
+    F: FnOnce(PluginClient) -> Fut,
+    Fut: Future<Output = Result<T, Status>>
+  {
+    let client = self.get_plugin_client().await?;
+    match call(client).await {
+      Ok(response) => Ok(response),
+      Err(status) => {
+        if is_connection_failure(&status) {
+          debug!("Call to plugin {} failed to connect, will reconnect on the next call - {}",
+            self.plugin.manifest.name, status);
+          self.reset_channel().await;
+        }
+        Err(status.into())
+      }
+    }
+  }
+}
+
+/// If a failed call's status means the plugin could not be reached, rather than it returning an
+/// error. tonic reports connection errors as `Unavailable`, or as a status carrying the transport
+/// error as its source.
+fn is_connection_failure(status: &Status) -> bool {
+  status.code() == Code::Unavailable || status.source()
+    .map(|source| source.is::<tonic::transport::Error>())
+    .unwrap_or(false)
 }
 
 #[async_trait]
@@ -527,8 +725,9 @@ impl PactPluginRpc for GrpcPactPlugin {
     &mut self,
     request: PluginInitRequest,
   ) -> anyhow::Result<PluginInitResponse> {
-    let mut client = self.get_plugin_client().await?;
-    client.init_plugin(request).await.map_err(anyhow::Error::from)
+    self.with_client(|mut client| async move {
+      client.init_plugin(request).await
+    }).await
   }
 }
 
@@ -555,8 +754,9 @@ impl PluginInstance for GrpcPactPlugin {
     &self,
     request: CompareContentsRequest,
   ) -> anyhow::Result<CompareContentsResponse> {
-    let mut client = self.get_plugin_client().await?;
-    client.compare_contents(request).await.map_err(anyhow::Error::from)
+    self.with_client(|mut client| async move {
+      client.compare_contents(request).await
+    }).await
   }
 
   async fn compare_contents_with_chain(
@@ -565,27 +765,27 @@ impl PluginInstance for GrpcPactPlugin {
     chain_id: &str,
     deadline_ms: u64,
   ) -> anyhow::Result<CompareContentsResponse> {
-    let mut client = self.get_plugin_client().await?;
-    client
-      .compare_contents_with_metadata(request, chain_id, deadline_ms)
-      .await
-      .map_err(anyhow::Error::from)
+    self.with_client(|mut client| async move {
+      client.compare_contents_with_metadata(request, chain_id, deadline_ms).await
+    }).await
   }
 
   async fn configure_interaction(
     &self,
     request: ConfigureInteractionRequest,
   ) -> anyhow::Result<ConfigureInteractionResponse> {
-    let mut client = self.get_plugin_client().await?;
-    client.configure_interaction(request).await.map_err(anyhow::Error::from)
+    self.with_client(|mut client| async move {
+      client.configure_interaction(request).await
+    }).await
   }
 
   async fn generate_content(
     &self,
     request: GenerateContentRequest,
   ) -> anyhow::Result<GenerateContentResponse> {
-    let mut client = self.get_plugin_client().await?;
-    client.generate_content(request).await.map_err(anyhow::Error::from)
+    self.with_client(|mut client| async move {
+      client.generate_content(request).await
+    }).await
   }
 
   async fn generate_content_with_chain(
@@ -594,19 +794,18 @@ impl PluginInstance for GrpcPactPlugin {
     chain_id: &str,
     deadline_ms: u64,
   ) -> anyhow::Result<GenerateContentResponse> {
-    let mut client = self.get_plugin_client().await?;
-    client
-      .generate_content_with_metadata(request, chain_id, deadline_ms)
-      .await
-      .map_err(anyhow::Error::from)
+    self.with_client(|mut client| async move {
+      client.generate_content_with_metadata(request, chain_id, deadline_ms).await
+    }).await
   }
 
   async fn match_field(
     &self,
     request: proto_v2::MatchFieldRequest,
   ) -> anyhow::Result<proto_v2::MatchFieldResponse> {
-    let mut client = self.get_plugin_client().await?;
-    client.match_field(request).await.map_err(anyhow::Error::from)
+    self.with_client(|mut client| async move {
+      client.match_field(request).await
+    }).await
   }
 
   async fn match_field_with_chain(
@@ -615,19 +814,18 @@ impl PluginInstance for GrpcPactPlugin {
     chain_id: &str,
     deadline_ms: u64,
   ) -> anyhow::Result<proto_v2::MatchFieldResponse> {
-    let mut client = self.get_plugin_client().await?;
-    client
-      .match_field_with_metadata(request, chain_id, deadline_ms)
-      .await
-      .map_err(anyhow::Error::from)
+    self.with_client(|mut client| async move {
+      client.match_field_with_metadata(request, chain_id, deadline_ms).await
+    }).await
   }
 
   async fn generate_field(
     &self,
     request: proto_v2::GenerateFieldRequest,
   ) -> anyhow::Result<proto_v2::GenerateFieldResponse> {
-    let mut client = self.get_plugin_client().await?;
-    client.generate_field(request).await.map_err(anyhow::Error::from)
+    self.with_client(|mut client| async move {
+      client.generate_field(request).await
+    }).await
   }
 
   async fn generate_field_with_chain(
@@ -636,96 +834,135 @@ impl PluginInstance for GrpcPactPlugin {
     chain_id: &str,
     deadline_ms: u64,
   ) -> anyhow::Result<proto_v2::GenerateFieldResponse> {
-    let mut client = self.get_plugin_client().await?;
-    client
-      .generate_field_with_metadata(request, chain_id, deadline_ms)
-      .await
-      .map_err(anyhow::Error::from)
+    self.with_client(|mut client| async move {
+      client.generate_field_with_metadata(request, chain_id, deadline_ms).await
+    }).await
+  }
+
+  async fn match_fields_batch(
+    &self,
+    request: proto_v2::MatchFieldsBatchRequest,
+  ) -> anyhow::Result<proto_v2::MatchFieldsBatchResponse> {
+    self.with_client(|mut client| async move {
+      client.match_fields_batch(request).await
+    }).await
+  }
+
+  async fn match_fields_batch_with_chain(
+    &self,
+    request: proto_v2::MatchFieldsBatchRequest,
+    chain_id: &str,
+    deadline_ms: u64,
+  ) -> anyhow::Result<proto_v2::MatchFieldsBatchResponse> {
+    self.with_client(|mut client| async move {
+      client.match_fields_batch_with_metadata(request, chain_id, deadline_ms).await
+    }).await
+  }
+
+  async fn generate_fields_batch(
+    &self,
+    request: proto_v2::GenerateFieldsBatchRequest,
+  ) -> anyhow::Result<proto_v2::GenerateFieldsBatchResponse> {
+    self.with_client(|mut client| async move {
+      client.generate_fields_batch(request).await
+    }).await
+  }
+
+  async fn generate_fields_batch_with_chain(
+    &self,
+    request: proto_v2::GenerateFieldsBatchRequest,
+    chain_id: &str,
+    deadline_ms: u64,
+  ) -> anyhow::Result<proto_v2::GenerateFieldsBatchResponse> {
+    self.with_client(|mut client| async move {
+      client.generate_fields_batch_with_metadata(request, chain_id, deadline_ms).await
+    }).await
   }
 
   async fn start_mock_server(
     &self,
     request: StartMockServerRequest,
   ) -> anyhow::Result<StartMockServerResponse> {
-    let mut client = self.get_plugin_client().await?;
-    client.start_mock_server(request).await.map_err(anyhow::Error::from)
+    self.with_client(|mut client| async move {
+      client.start_mock_server(request).await
+    }).await
   }
 
   async fn start_mock_server_v2(
     &self,
     request: proto_v2::StartMockServerRequest,
   ) -> anyhow::Result<StartMockServerResponse> {
-    let mut client = self.get_plugin_client().await?;
-    client.start_mock_server_v2(request).await.map_err(anyhow::Error::from)
+    self.with_client(|mut client| async move {
+      client.start_mock_server_v2(request).await
+    }).await
   }
 
   async fn shutdown_mock_server(
     &self,
     request: ShutdownMockServerRequest,
   ) -> anyhow::Result<ShutdownMockServerResponse> {
-    let mut client = self.get_plugin_client().await?;
-    client.shutdown_mock_server(request).await.map_err(anyhow::Error::from)
+    self.with_client(|mut client| async move {
+      client.shutdown_mock_server(request).await
+    }).await
   }
 
   async fn get_mock_server_results(
     &self,
     request: MockServerRequest,
   ) -> anyhow::Result<MockServerResults> {
-    let mut client = self.get_plugin_client().await?;
-    client.get_mock_server_results(request).await.map_err(anyhow::Error::from)
+    self.with_client(|mut client| async move {
+      client.get_mock_server_results(request).await
+    }).await
   }
 
   async fn prepare_interaction_for_verification(
     &self,
     request: VerificationPreparationRequest,
   ) -> anyhow::Result<VerificationPreparationResponse> {
-    let mut client = self.get_plugin_client().await?;
-    client
-      .prepare_interaction_for_verification(request)
-      .await
-      .map_err(anyhow::Error::from)
+    self.with_client(|mut client| async move {
+      client.prepare_interaction_for_verification(request).await
+    }).await
   }
 
   async fn prepare_interaction_for_verification_v2(
     &self,
     request: proto_v2::VerificationPreparationRequest,
   ) -> anyhow::Result<VerificationPreparationResponse> {
-    let mut client = self.get_plugin_client().await?;
-    client
-      .prepare_interaction_for_verification_v2(request)
-      .await
-      .map_err(anyhow::Error::from)
+    self.with_client(|mut client| async move {
+      client.prepare_interaction_for_verification_v2(request).await
+    }).await
   }
 
   async fn verify_interaction(
     &self,
     request: VerifyInteractionRequest,
   ) -> anyhow::Result<VerifyInteractionResponse> {
-    let mut client = self.get_plugin_client().await?;
-    client.verify_interaction(request).await.map_err(anyhow::Error::from)
+    self.with_client(|mut client| async move {
+      client.verify_interaction(request).await
+    }).await
   }
 
   async fn verify_interaction_v2(
     &self,
     request: proto_v2::VerifyInteractionRequest,
   ) -> anyhow::Result<VerifyInteractionResponse> {
-    let mut client = self.get_plugin_client().await?;
-    client.verify_interaction_v2(request).await.map_err(anyhow::Error::from)
+    self.with_client(|mut client| async move {
+      client.verify_interaction_v2(request).await
+    }).await
   }
 
   async fn update_catalogue(&self, request: Catalogue) -> anyhow::Result<()> {
-    let mut client = self.get_plugin_client().await?;
-    client.update_catalogue(request).await.map_err(anyhow::Error::from)
+    self.with_client(|mut client| async move {
+      client.update_catalogue(request).await
+    }).await
   }
 }
 
-/// Start a plugin process and return a PactPlugin with the child process attached
-pub(crate) async fn start_plugin_process(manifest: &PactPluginManifest) -> anyhow::Result<PactPlugin> {
-  debug!("Starting plugin with manifest {:?}", manifest);
-
+/// Path of the executable to run for the plugin on this OS
+pub(crate) fn entry_point_path(manifest: &PactPluginManifest) -> PathBuf {
   let os_info = os_info::get();
   debug!("Detected OS: {}", os_info);
-  let mut path = if let Some(entry_point) = manifest.entry_points.get(&os_info.to_string()) {
+  let path = if let Some(entry_point) = manifest.entry_points.get(&os_info.to_string()) {
     PathBuf::from(entry_point)
   } else if os_info.os_type() == Type::Windows && manifest.entry_points.contains_key("windows") {
     PathBuf::from(manifest.entry_points.get("windows").unwrap())
@@ -733,8 +970,17 @@ pub(crate) async fn start_plugin_process(manifest: &PactPluginManifest) -> anyho
     PathBuf::from(&manifest.entry_point)
   };
   if !path.is_absolute() || !path.exists() {
-    path = PathBuf::from(manifest.plugin_dir.clone()).join(path);
+    PathBuf::from(manifest.plugin_dir.clone()).join(path)
+  } else {
+    path
   }
+}
+
+/// Start a plugin process and return a PactPlugin with the child process attached
+pub(crate) async fn start_plugin_process(manifest: &PactPluginManifest) -> anyhow::Result<PactPlugin> {
+  debug!("Starting plugin with manifest {:?}", manifest);
+
+  let path = entry_point_path(manifest);
   debug!("Starting plugin using {:?}", &path);
 
   let host_port = match crate::plugin_host::ensure_plugin_host_running().await {
@@ -808,12 +1054,21 @@ pub(crate) async fn start_plugin_process(manifest: &PactPluginManifest) -> anyho
 
 #[cfg(test)]
 pub(crate) mod tests {
-  use tonic::Status;
-
-  use crate::plugin_models::PluginInitResponse;
+  use std::sync::Arc;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  use expectest::prelude::*;
+  use futures_util::stream;
+  use tokio::net::TcpListener;
+  use tokio::sync::oneshot;
+  use tonic::{Request, Response, Status};
+
+  use crate::child_process::{ChildPluginProcess, RunningPluginInfo};
+  use crate::plugin_models::{PactPlugin, PactPluginManifest, PactPluginRpc, PluginInitRequest, PluginInitResponse};
+  use crate::proto::pact_plugin_server::{PactPlugin as PactPluginService, PactPluginServer};
   use crate::proto::*;
 
-  use super::PluginClient;
+  use super::{GrpcPactPlugin, PluginClient};
 
   #[test]
   fn converts_between_v1_and_v2_messages() {
@@ -874,4 +1129,181 @@ pub(crate) mod tests {
     );
     assert_eq!(converted_response.plugin_capabilities, vec!["plugin/verification"]);
   }
+
+  #[test]
+  fn is_connection_failure_test() {
+    use tonic::Code;
+    use super::is_connection_failure;
+
+    assert!(is_connection_failure(&Status::unavailable("connection refused")));
+    assert!(!is_connection_failure(&Status::internal("plugin failed")));
+    assert!(!is_connection_failure(&Status::new(Code::InvalidArgument, "bad request")));
+  }
+
+  /// A plugin gRPC server that only answers `init_plugin`, counting the connections made to it
+  struct TestPluginServer {
+    port: u16,
+    connections: Arc<AtomicUsize>,
+    shutdown: Option<oneshot::Sender<()>>,
+    server: tokio::task::JoinHandle<()>
+  }
+
+  impl TestPluginServer {
+    async fn start(port: u16) -> TestPluginServer {
+      let listener = TcpListener::bind(("127.0.0.1", port)).await.unwrap();
+      let port = listener.local_addr().unwrap().port();
+      let connections = Arc::new(AtomicUsize::new(0));
+      let accepted = connections.clone();
+      let incoming = stream::unfold(listener, move |listener| {
+        let accepted = accepted.clone();
+        async move {
+          let result = listener.accept().await.map(|(stream, _)| stream);
+          accepted.fetch_add(1, Ordering::SeqCst);
+          Some((result, listener))
+        }
+      });
+      let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+      let server = tokio::spawn(async move {
+        tonic::transport::Server::builder()
+          .add_service(PactPluginServer::new(TestPlugin))
+          .serve_with_incoming_shutdown(incoming, async { let _ = shutdown_rx.await; })
+          .await
+          .unwrap();
+      });
+      TestPluginServer { port, connections, shutdown: Some(shutdown), server }
+    }
+
+    fn connections(&self) -> usize {
+      self.connections.load(Ordering::SeqCst)
+    }
+
+    async fn stop(mut self) {
+      if let Some(shutdown) = self.shutdown.take() {
+        let _ = shutdown.send(());
+      }
+      let _ = self.server.await;
+    }
+  }
+
+  struct TestPlugin;
+
+  #[tonic::async_trait]
+  impl PactPluginService for TestPlugin {
+    async fn init_plugin(&self, _request: Request<InitPluginRequest>) -> Result<Response<InitPluginResponse>, Status> {
+      Ok(Response::new(InitPluginResponse::default()))
+    }
+
+    async fn update_catalogue(&self, _request: Request<Catalogue>) -> Result<Response<()>, Status> {
+      Err(Status::unimplemented("update_catalogue"))
+    }
+
+    async fn compare_contents(&self, _request: Request<CompareContentsRequest>) -> Result<Response<CompareContentsResponse>, Status> {
+      Err(Status::unimplemented("compare_contents"))
+    }
+
+    async fn configure_interaction(&self, _request: Request<ConfigureInteractionRequest>) -> Result<Response<ConfigureInteractionResponse>, Status> {
+      Err(Status::unimplemented("configure_interaction"))
+    }
+
+    async fn generate_content(&self, _request: Request<GenerateContentRequest>) -> Result<Response<GenerateContentResponse>, Status> {
+      Err(Status::unimplemented("generate_content"))
+    }
+
+    async fn start_mock_server(&self, _request: Request<StartMockServerRequest>) -> Result<Response<StartMockServerResponse>, Status> {
+      Err(Status::unimplemented("start_mock_server"))
+    }
+
+    async fn shutdown_mock_server(&self, _request: Request<ShutdownMockServerRequest>) -> Result<Response<ShutdownMockServerResponse>, Status> {
+      Err(Status::unimplemented("shutdown_mock_server"))
+    }
+
+    async fn get_mock_server_results(&self, _request: Request<MockServerRequest>) -> Result<Response<MockServerResults>, Status> {
+      Err(Status::unimplemented("get_mock_server_results"))
+    }
+
+    async fn prepare_interaction_for_verification(&self, _request: Request<VerificationPreparationRequest>) -> Result<Response<VerificationPreparationResponse>, Status> {
+      Err(Status::unimplemented("prepare_interaction_for_verification"))
+    }
+
+    async fn verify_interaction(&self, _request: Request<VerifyInteractionRequest>) -> Result<Response<VerifyInteractionResponse>, Status> {
+      Err(Status::unimplemented("verify_interaction"))
+    }
+  }
+
+  fn child_process_on(port: u16) -> ChildPluginProcess {
+    ChildPluginProcess {
+      child_pid: 0,
+      plugin_info: RunningPluginInfo { port, server_key: "test-key".to_string() },
+      instance_id: "grpc-test-instance".to_string()
+    }
+  }
+
+  fn grpc_plugin_on(port: u16) -> GrpcPactPlugin {
+    let manifest = PactPluginManifest {
+      name: "grpc-test".to_string(),
+      version: "0.0.1".to_string(),
+      .. PactPluginManifest::default()
+    };
+    GrpcPactPlugin::new(PactPlugin::new(&manifest, child_process_on(port)).unwrap())
+  }
+
+  fn init_request() -> PluginInitRequest {
+    PluginInitRequest {
+      implementation: "plugin-driver-rust".to_string(),
+      version: "0.0.0".to_string(),
+      host_capabilities: vec![],
+      plugin_instance_id: "grpc-test-instance".to_string()
+    }
+  }
+
+  #[test_log::test(tokio::test)]
+  async fn calls_share_one_channel() {
+    let server = TestPluginServer::start(0).await;
+    let mut plugin = grpc_plugin_on(server.port);
+    let mut clone = plugin.clone();
+
+    expect!(plugin.init_plugin(init_request()).await).to(be_ok());
+    expect!(plugin.init_plugin(init_request()).await).to(be_ok());
+    expect!(clone.init_plugin(init_request()).await).to(be_ok());
+
+    expect!(server.connections()).to(be_equal_to(1));
+    server.stop().await;
+  }
+
+  #[test_log::test(tokio::test)]
+  #[allow(deprecated)]
+  async fn reconnects_when_the_plugin_port_changes() {
+    let first = TestPluginServer::start(0).await;
+    let second = TestPluginServer::start(0).await;
+    let mut plugin = grpc_plugin_on(first.port);
+    expect!(plugin.init_plugin(init_request()).await).to(be_ok());
+
+    // What restarting the plugin process does
+    plugin.plugin.child = Arc::new(child_process_on(second.port));
+    expect!(plugin.init_plugin(init_request()).await).to(be_ok());
+    expect!(plugin.init_plugin(init_request()).await).to(be_ok());
+
+    expect!(first.connections()).to(be_equal_to(1));
+    expect!(second.connections()).to(be_equal_to(1));
+    first.stop().await;
+    second.stop().await;
+  }
+
+  #[test_log::test(tokio::test)]
+  async fn drops_the_channel_when_the_plugin_can_not_be_reached() {
+    let server = TestPluginServer::start(0).await;
+    let port = server.port;
+    let mut plugin = grpc_plugin_on(port);
+    expect!(plugin.init_plugin(init_request()).await).to(be_ok());
+    server.stop().await;
+
+    expect!(plugin.init_plugin(init_request()).await).to(be_err());
+    expect!(plugin.channel.lock().await.is_none()).to(be_true());
+
+    // The plugin is back on the same port, and the next call connects to it again
+    let server = TestPluginServer::start(port).await;
+    expect!(plugin.init_plugin(init_request()).await).to(be_ok());
+    expect!(server.connections()).to(be_equal_to(1));
+    server.stop().await;
+  }
 }
@@ -0,0 +1,115 @@
+//! Dispatch for the host callbacks an in-process plugin runtime exposes to its plugins.
+//!
+//! A Lua script calls `host_compare_contents` and friends as global functions, a Python script as
+//! functions of its module, and a WASM module imports them as host functions, but all resolve the entry key and dispatch the call the same
+//! way - to a handler the host framework registered (see [`crate::core_capabilities`]) or to
+//! another running plugin. The gRPC `PluginHost` service ([`crate::plugin_host`]) is the
+//! out-of-process equivalent, and uses the same resolver. See proposal 007 (Driver-plugin
+//! callback model).
+
+use anyhow::anyhow;
+
+use crate::call_chain;
+use crate::catalogue_manager::{CatalogueEntryType, ResolvedCapability, resolve_capability};
+use crate::plugin_manager::lookup_plugin;
+use crate::proto::{CompareContentsRequest, CompareContentsResponse, GenerateContentRequest, GenerateContentResponse};
+use crate::proto_v2;
+
+/// Resolve `entry_key` to a content matcher capability and dispatch to it - a host-registered
+/// [`crate::core_capabilities::CoreContentMatcher`] called in-process, or another running plugin
+/// called under `chain` (the call chain ID and deadline of the call the callback was made from),
+/// or a freshly-started call chain if the runtime does not track one (see [`crate::call_chain`]).
+/// This matches the same resolver [`crate::plugin_host`] uses for the gRPC callback path.
+pub(crate) async fn call_host_compare_contents(
+  entry_key: &str,
+  request: CompareContentsRequest,
+  chain: Option<(&str, u64)>
+) -> anyhow::Result<CompareContentsResponse> {
+  match resolve_capability(entry_key, CatalogueEntryType::CONTENT_MATCHER)? {
+    ResolvedCapability::Core(core_key) => {
+      let handler = crate::core_capabilities::lookup_core_content_matcher(&core_key)
+        .ok_or_else(|| anyhow!("No core content matcher registered for '{}'", core_key))?;
+      handler.compare_contents(request).await
+    }
+    ResolvedCapability::Plugin(manifest) => {
+      let plugin = lookup_plugin(&manifest.as_dependency())
+        .ok_or_else(|| anyhow!("Plugin '{}' for entry '{}' is not currently running", manifest.name, entry_key))?;
+      let (chain_id, deadline_ms) = continue_call_chain(chain);
+      plugin.compare_contents_with_chain(request, &chain_id, deadline_ms).await
+    }
+  }
+}
+
+/// Resolve `entry_key` to a content generator capability and dispatch to it. See
+/// [`call_host_compare_contents`].
+pub(crate) async fn call_host_generate_content(
+  entry_key: &str,
+  request: GenerateContentRequest,
+  chain: Option<(&str, u64)>
+) -> anyhow::Result<GenerateContentResponse> {
+  match resolve_capability(entry_key, CatalogueEntryType::CONTENT_GENERATOR)? {
+    ResolvedCapability::Core(core_key) => {
+      let handler = crate::core_capabilities::lookup_core_content_generator(&core_key)
+        .ok_or_else(|| anyhow!("No core content generator registered for '{}'", core_key))?;
+      handler.generate_content(request).await
+    }
+    ResolvedCapability::Plugin(manifest) => {
+      let plugin = lookup_plugin(&manifest.as_dependency())
+        .ok_or_else(|| anyhow!("Plugin '{}' for entry '{}' is not currently running", manifest.name, entry_key))?;
+      let (chain_id, deadline_ms) = continue_call_chain(chain);
+      plugin.generate_content_with_chain(request, &chain_id, deadline_ms).await
+    }
+  }
+}
+
+/// Resolve `entry_key` to a field-level matching rule and dispatch to it. See
+/// [`call_host_compare_contents`].
+pub(crate) async fn call_host_match_field(
+  entry_key: &str,
+  request: proto_v2::MatchFieldRequest,
+  chain: Option<(&str, u64)>
+) -> anyhow::Result<proto_v2::MatchFieldResponse> {
+  match resolve_capability(entry_key, CatalogueEntryType::MATCHER)? {
+    ResolvedCapability::Core(core_key) => {
+      let handler = crate::core_capabilities::lookup_core_field_matcher(&core_key)
+        .ok_or_else(|| anyhow!("No core field matcher registered for '{}'", core_key))?;
+      handler.match_field(request).await
+    }
+    ResolvedCapability::Plugin(manifest) => {
+      let plugin = lookup_plugin(&manifest.as_dependency())
+        .ok_or_else(|| anyhow!("Plugin '{}' for entry '{}' is not currently running", manifest.name, entry_key))?;
+      let (chain_id, deadline_ms) = continue_call_chain(chain);
+      plugin.match_field_with_chain(request, &chain_id, deadline_ms).await
+    }
+  }
+}
+
+/// Resolve `entry_key` to a field-level generator and dispatch to it. See
+/// [`call_host_compare_contents`].
+pub(crate) async fn call_host_generate_field(
+  entry_key: &str,
+  request: proto_v2::GenerateFieldRequest,
+  chain: Option<(&str, u64)>
+) -> anyhow::Result<proto_v2::GenerateFieldResponse> {
+  match resolve_capability(entry_key, CatalogueEntryType::GENERATOR)? {
+    ResolvedCapability::Core(core_key) => {
+      let handler = crate::core_capabilities::lookup_core_field_generator(&core_key)
+        .ok_or_else(|| anyhow!("No core field generator registered for '{}'", core_key))?;
+      handler.generate_field(request).await
+    }
+    ResolvedCapability::Plugin(manifest) => {
+      let plugin = lookup_plugin(&manifest.as_dependency())
+        .ok_or_else(|| anyhow!("Plugin '{}' for entry '{}' is not currently running", manifest.name, entry_key))?;
+      let (chain_id, deadline_ms) = continue_call_chain(chain);
+      plugin.generate_field_with_chain(request, &chain_id, deadline_ms).await
+    }
+  }
+}
+
+/// The call chain a callback to another plugin is made under: the one the callback came from, or
+/// a new one.
+fn continue_call_chain(chain: Option<(&str, u64)>) -> (String, u64) {
+  chain
+    .map(|(chain_id, deadline_ms)| (chain_id.to_string(), deadline_ms))
+    .unwrap_or_else(|| (call_chain::new_call_chain_id(), call_chain::default_deadline_ms()))
+}
@@ -19,23 +19,43 @@ pub mod catalogue_manager;
 mod child_process;
 pub mod core_capabilities;
 pub mod grpc_plugin;
+#[cfg(any(feature = "lua", feature = "wasm", feature = "python"))]
+pub(crate) mod host_callbacks;
 pub(crate) mod plugin_host;
 pub mod content;
+pub mod diff;
 pub mod download;
+pub mod driver;
 pub mod field;
 #[cfg(feature = "lua")]
+pub(crate) mod lua_crypto;
+#[cfg(feature = "lua")]
+pub(crate) mod lua_data;
+#[cfg(feature = "lua")]
 pub mod lua_plugin;
+#[cfg(feature = "lua")]
+pub(crate) mod lua_pool;
+#[cfg(feature = "lua")]
+pub(crate) mod lua_sandbox;
+pub mod lockfile;
 mod metrics;
 pub mod mock_server;
 pub mod plugin_log_sink;
+#[cfg(any(feature = "lua", feature = "python"))]
+pub(crate) mod plugin_conversions;
 pub mod plugin_manager;
 pub mod plugin_models;
 pub mod proto;
+#[cfg(feature = "python")]
+pub mod python_plugin;
 // Public because the V2-only message types are part of the driver's public API: the field-level
 // operations (proposal 006) have no V1 equivalent, so the embedding Pact framework needs these
 // types to implement `core_capabilities::CoreFieldMatcher`/`CoreFieldGenerator`.
 pub mod proto_v2;
 pub mod repository;
+pub mod signatures;
 pub mod test_context;
 pub mod utils;
 pub mod verification;
+#[cfg(feature = "wasm")]
+pub mod wasm_plugin;
@@ -0,0 +1,310 @@
+//! Lock file recording the exact plugin versions used by a test suite (`pact-plugins.lock`).
+//!
+//! Installed plugins are normally resolved to the highest installed version compatible with the
+//! dependency, so two runs can load different plugin versions. When a lock file is in use (see
+//! [`plugin_lock_file`]), each dependency resolved is recorded in it with the exact version and the
+//! SHA256 digest of the plugin's entry point, and later loads of the dependency are pinned to that
+//! version and refused if the installed files do not match.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::driver::PluginDriver;
+use crate::grpc_plugin::entry_point_path;
+use crate::plugin_manager::load_plugin_manifest;
+use crate::plugin_models::{PactPluginManifest, PluginDependency};
+use crate::repository::calculate_sha;
+use crate::utils::os_and_arch;
+
+/// Default name of the lock file
+pub const LOCK_FILE_NAME: &str = "pact-plugins.lock";
+
+const LOCK_FILE_HEADER: &str = "# Plugin versions locked by the Pact plugin driver. Update this file with `pact-plugin lock`.\n\n";
+
+/// Set the lock file the current driver uses to record and pin the versions of the plugins
+/// loaded. The file is created if it does not exist. Takes precedence over the
+/// `PACT_PLUGIN_LOCK_FILE` environment variable.
+pub fn set_plugin_lock_file<P: AsRef<Path>>(path: P) {
+  let path = path.as_ref().to_path_buf();
+  PluginDriver::current().update_config(|config| config.lock_file = Some(path));
+}
+
+/// The lock file in use. Taken from the first of the lock file configured for the current driver
+/// (see [`set_plugin_lock_file`]) and the `PACT_PLUGIN_LOCK_FILE` environment variable that is
+/// set, otherwise `pact-plugins.lock` in the current directory if it exists.
+pub fn plugin_lock_file() -> Option<PathBuf> {
+  if let Some(path) = PluginDriver::current().config().lock_file {
+    return Some(path);
+  }
+  if let Some(path) = env::var_os("PACT_PLUGIN_LOCK_FILE").filter(|path| !path.is_empty()) {
+    return Some(PathBuf::from(path));
+  }
+  let path = PathBuf::from(LOCK_FILE_NAME);
+  if path.is_file() {
+    Some(path)
+  } else {
+    None
+  }
+}
+
+/// Contents of a plugin lock file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct PluginLockFile {
+  /// File format version of the lock file
+  pub format_version: usize,
+
+  /// Locked plugins
+  #[serde(default, rename = "plugin")]
+  pub plugins: Vec<LockedPlugin>
+}
+
+/// Exact version of a plugin resolved for a plugin dependency
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LockedPlugin {
+  /// Name of the plugin
+  pub name: String,
+
+  /// Version of the dependency the plugin was resolved for. None is any version.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub requirement: Option<String>,
+
+  /// Version of the plugin that was resolved
+  pub version: String,
+
+  /// SHA256 digest of the plugin entry point, keyed by the OS and architecture (i.e.
+  /// `linux-x86_64`), as plugins can have different executables for each
+  #[serde(default)]
+  pub sha256: BTreeMap<String, String>
+}
+
+impl LockedPlugin {
+  /// Dependency for the exact version of the plugin that was locked
+  pub fn as_dependency(&self) -> PluginDependency {
+    PluginDependency {
+      name: self.name.clone(),
+      version: Some(self.version.clone()),
+      dependency_type: Default::default()
+    }
+  }
+
+  /// The dependency this plugin version was resolved for
+  pub fn requirement_dependency(&self) -> PluginDependency {
+    PluginDependency {
+      name: self.name.clone(),
+      version: self.requirement.clone(),
+      dependency_type: Default::default()
+    }
+  }
+
+  fn is_for(&self, dependency: &PluginDependency) -> bool {
+    self.name == dependency.name && self.requirement == dependency.version
+  }
+}
+
+impl PluginLockFile {
+  /// Loads the lock file. A lock file that does not exist yet is empty.
+  pub fn load(path: &Path) -> anyhow::Result<PluginLockFile> {
+    if !path.exists() {
+      return Ok(PluginLockFile::default());
+    }
+    let contents = fs::read_to_string(path)
+      .with_context(|| format!("Could not read the plugin lock file {}", path.display()))?;
+    toml::from_str(contents.as_str())
+      .with_context(|| format!("Could not parse the plugin lock file {}", path.display()))
+  }
+
+  /// Writes the lock file
+  pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+    let contents = toml::to_string(self)?;
+    fs::write(path, format!("{}{}", LOCK_FILE_HEADER, contents))
+      .with_context(|| format!("Could not write the plugin lock file {}", path.display()))
+  }
+
+  /// Find the plugin version locked for the dependency
+  pub fn find(&self, dependency: &PluginDependency) -> Option<&LockedPlugin> {
+    self.plugins.iter().find(|plugin| plugin.is_for(dependency))
+  }
+
+  /// Records the plugin resolved for the dependency. If the dependency is already locked, checks
+  /// that the plugin is the same version with the same entry point SHA256 digest (adding the digest
+  /// if it was locked on a different OS or architecture). Returns true if the lock file was changed.
+  pub fn lock(&mut self, dependency: &PluginDependency, manifest: &PactPluginManifest) -> anyhow::Result<bool> {
+    let platform = platform()?;
+    let sha = entry_point_sha(manifest)?;
+    match self.plugins.iter_mut().find(|plugin| plugin.is_for(dependency)) {
+      Some(locked) => {
+        if locked.version != manifest.version {
+          bail!("Plugin {} resolved to version {} for {}, but version {} is locked", manifest.name,
+            manifest.version, dependency, locked.version);
+        }
+        match locked.sha256.get(&platform) {
+          Some(locked_sha) if *locked_sha != sha => bail!(
+            "The installed plugin {}:{} does not match the locked plugin: the SHA256 digest of its entry point is {}, but {} is locked. Reinstall the plugin, or update the lock file with `pact-plugin lock`",
+            manifest.name, manifest.version, sha, locked_sha),
+          Some(_) => Ok(false),
+          None => {
+            locked.sha256.insert(platform, sha);
+            Ok(true)
+          }
+        }
+      }
+      None => {
+        self.plugins.push(LockedPlugin {
+          name: manifest.name.clone(),
+          requirement: dependency.version.clone(),
+          version: manifest.version.clone(),
+          sha256: BTreeMap::from([(platform, sha)])
+        });
+        self.sort();
+        Ok(true)
+      }
+    }
+  }
+
+  /// Resolves each locked dependency, along with any additional dependencies, again against the
+  /// installed plugins, locking them to the highest compatible version that is installed.
+  pub fn update(&mut self, additional: &[PluginDependency]) -> anyhow::Result<()> {
+    let platform = platform()?;
+    let dependencies = self.plugins.iter()
+      .map(|plugin| plugin.requirement_dependency())
+      .chain(additional.iter().cloned())
+      .unique()
+      .collect_vec();
+    let mut plugins = vec![];
+    for dependency in dependencies {
+      let manifest = load_plugin_manifest(&dependency)?;
+      let mut sha256 = self.find(&dependency)
+        .filter(|locked| locked.version == manifest.version)
+        .map(|locked| locked.sha256.clone())
+        .unwrap_or_default();
+      sha256.insert(platform.clone(), entry_point_sha(&manifest)?);
+      info!(%dependency, version = %manifest.version, "Locked plugin version");
+      plugins.push(LockedPlugin {
+        name: manifest.name.clone(),
+        requirement: dependency.version.clone(),
+        version: manifest.version.clone(),
+        sha256
+      });
+    }
+    self.plugins = plugins;
+    self.sort();
+    Ok(())
+  }
+
+  fn sort(&mut self) {
+    self.plugins.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.requirement.cmp(&b.requirement)));
+  }
+}
+
+/// Records the plugin resolved for the dependency in the lock file, or checks it against the
+/// locked plugin (see [`PluginLockFile::lock`])
+pub(crate) fn lock_plugin(
+  path: &Path,
+  dependency: &PluginDependency,
+  manifest: &PactPluginManifest
+) -> anyhow::Result<()> {
+  let mut lock_file = PluginLockFile::load(path)?;
+  if lock_file.lock(dependency, manifest)? {
+    debug!(path = %path.display(), %dependency, "Updating the plugin lock file");
+    lock_file.write(path)?;
+  }
+  Ok(())
+}
+
+fn entry_point_sha(manifest: &PactPluginManifest) -> anyhow::Result<String> {
+  let path = entry_point_path(manifest);
+  calculate_sha(&path)
+    .with_context(|| format!("Could not calculate the SHA256 digest of {}", path.display()))
+}
+
+fn platform() -> anyhow::Result<String> {
+  let (os, arch) = os_and_arch()?;
+  Ok(format!("{}-{}", os, arch))
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use expectest::prelude::*;
+
+  use crate::plugin_models::{PactPluginManifest, PluginDependency};
+
+  use super::{PluginLockFile, lock_plugin, platform};
+
+  fn installed_plugin(dir: &tempdir::TempDir, version: &str, contents: &str) -> PactPluginManifest {
+    let plugin_dir = dir.path().join(format!("test-{}", version));
+    fs::create_dir_all(&plugin_dir).unwrap();
+    fs::write(plugin_dir.join("plugin"), contents).unwrap();
+    PactPluginManifest {
+      name: "test".to_string(),
+      version: version.to_string(),
+      entry_point: "plugin".to_string(),
+      plugin_dir: plugin_dir.to_string_lossy().to_string(),
+      .. PactPluginManifest::default()
+    }
+  }
+
+  fn dependency(version: Option<&str>) -> PluginDependency {
+    PluginDependency {
+      name: "test".to_string(),
+      version: version.map(|v| v.to_string()),
+      dependency_type: Default::default()
+    }
+  }
+
+  #[test]
+  fn lock_records_the_resolved_plugin_version() {
+    let dir = tempdir::TempDir::new("plugin-lock").unwrap();
+    let manifest = installed_plugin(&dir, "1.2.3", "plugin executable");
+    let mut lock_file = PluginLockFile::default();
+
+    expect!(lock_file.lock(&dependency(Some("1.2")), &manifest).unwrap()).to(be_true());
+    expect!(lock_file.lock(&dependency(Some("1.2")), &manifest).unwrap()).to(be_false());
+
+    let locked = lock_file.find(&dependency(Some("1.2"))).unwrap();
+    expect!(locked.version.as_str()).to(be_equal_to("1.2.3"));
+    expect!(locked.as_dependency()).to(be_equal_to(dependency(Some("1.2.3"))));
+    expect!(locked.sha256.get(&platform().unwrap()).unwrap().as_str()).to(be_equal_to(
+      "a89d2e37a81403ecf8a7fdb3f0b293f841769526b949a60218dd8c5cdb3c1ba1"));
+    expect!(lock_file.find(&dependency(None))).to(be_none());
+  }
+
+  #[test]
+  fn lock_refuses_a_plugin_that_does_not_match_the_locked_one() {
+    let dir = tempdir::TempDir::new("plugin-lock").unwrap();
+    let mut lock_file = PluginLockFile::default();
+    lock_file.lock(&dependency(None), &installed_plugin(&dir, "1.2.3", "plugin executable")).unwrap();
+
+    let result = lock_file.lock(&dependency(None), &installed_plugin(&dir, "1.2.4", "plugin executable"));
+    expect!(result.unwrap_err().to_string()).to(be_equal_to(
+      "Plugin test resolved to version 1.2.4 for test:*, but version 1.2.3 is locked"));
+
+    let result = lock_file.lock(&dependency(None), &installed_plugin(&dir, "1.2.3", "modified executable"));
+    expect!(result.unwrap_err().to_string()).to(be_equal_to(
+      "The installed plugin test:1.2.3 does not match the locked plugin: the SHA256 digest of its entry point is d94be6a2200cb9f1e37a0cf15cd082938cfc117792ab39bc0c9e579afa46d704, but a89d2e37a81403ecf8a7fdb3f0b293f841769526b949a60218dd8c5cdb3c1ba1 is locked. Reinstall the plugin, or update the lock file with `pact-plugin lock`"));
+  }
+
+  #[test]
+  fn lock_file_round_trips_through_the_file() {
+    let dir = tempdir::TempDir::new("plugin-lock").unwrap();
+    let path = dir.path().join("pact-plugins.lock");
+    let manifest = installed_plugin(&dir, "1.2.3", "plugin executable");
+
+    expect!(PluginLockFile::load(&path).unwrap()).to(be_equal_to(PluginLockFile::default()));
+    lock_plugin(&path, &dependency(Some("1.2")), &manifest).unwrap();
+    lock_plugin(&path, &dependency(None), &manifest).unwrap();
+
+    let lock_file = PluginLockFile::load(&path).unwrap();
+    expect!(lock_file.plugins.len()).to(be_equal_to(2));
+    expect!(lock_file.plugins[0].requirement.clone()).to(be_none());
+    expect!(lock_file.plugins[1].requirement.clone()).to(be_some().value("1.2"));
+  }
+}
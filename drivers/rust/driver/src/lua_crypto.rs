@@ -0,0 +1,363 @@
+//! The `pact.crypto` and `pact.encoding` libraries for Lua plugins.
+//!
+//! Lua has no cryptography or binary encoding in its standard library, and pure-Lua
+//! implementations are slow, so the driver provides the common primitives a plugin needs for
+//! signed payloads, webhooks and tokens. Every function takes and returns binary data as Lua
+//! strings (a Lua string is an arbitrary byte array), so hashes, MACs and signatures come back
+//! as raw bytes to encode with `pact.encoding` as the format needs.
+//!
+//! `pact.crypto`:
+//!
+//! - `sha1(data)`, `sha256(data)`, `sha384(data)`, `sha512(data)` - digest of the data.
+//! - `hmac(hash, key, data)` - HMAC of the data, where `hash` is one of `sha1`, `sha256`,
+//!   `sha384` or `sha512`.
+//! - `sign(algorithm, private_key_pem, data)` - signature over the data, where `algorithm` is one
+//!   of the JWS names `RS256`, `RS384`, `RS512` (RSA PKCS#1 v1.5), `ES256` (ECDSA P-256, as the 64
+//!   byte `r || s` JWS uses) or `EdDSA` (Ed25519). The key is PKCS#8 PEM, or PKCS#1 for RSA and
+//!   SEC1 for ES256.
+//! - `verify(algorithm, public_key_pem, data, signature)` - whether the signature over the data is
+//!   valid. The key is SPKI PEM, or PKCS#1 for RSA. An ES256 signature can also be DER.
+//! - `public_key(algorithm, private_key_pem)` - the SPKI PEM public key of a private key.
+//! - `random_bytes(count)` - that many bytes from the operating system's secure random source, up
+//!   to [`MAX_RANDOM_BYTES`].
+//! - `uuid()` - a random (version 4) UUID string.
+//!
+//! `pact.encoding`:
+//!
+//! - `base64_encode(data)` / `base64_decode(text)` - standard, padded base64.
+//! - `base64url_encode(data)` / `base64url_decode(text)` - URL-safe base64 without padding, as
+//!   JWTs use.
+//! - `hex_encode(data)` / `hex_decode(text)` - lowercase hex.
+//!
+//! The decode functions accept input with or without padding. A function given an unknown
+//! algorithm, an unreadable key or undecodable input raises a Lua error.
+
+use anyhow::anyhow;
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use mlua::{Lua, LuaString, Table};
+use rand::RngCore;
+use rsa::pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey};
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePublicKey, LineEnding};
+use rsa::signature::{SignatureEncoding, Signer, Verifier};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+/// Most bytes a single `random_bytes` call can return (1 MiB)
+pub(crate) const MAX_RANDOM_BYTES: usize = 1024 * 1024;
+
+/// Register `pact.crypto` and `pact.encoding` in the given `pact` table
+pub(crate) fn register_crypto_library(lua: &Lua, pact: &Table) -> anyhow::Result<()> {
+  let crypto = lua.create_table()?;
+  crypto.set("sha1", lua.create_function(|lua, data: LuaString| lua.create_string(Sha1::digest(data.as_bytes())))?)?;
+  crypto.set("sha256", lua.create_function(|lua, data: LuaString| lua.create_string(Sha256::digest(data.as_bytes())))?)?;
+  crypto.set("sha384", lua.create_function(|lua, data: LuaString| lua.create_string(Sha384::digest(data.as_bytes())))?)?;
+  crypto.set("sha512", lua.create_function(|lua, data: LuaString| lua.create_string(Sha512::digest(data.as_bytes())))?)?;
+  crypto.set("hmac", lua.create_function(|lua, (hash, key, data): (String, LuaString, LuaString)| {
+    let mac = hmac(&hash, &key.as_bytes(), &data.as_bytes()).map_err(mlua::Error::external)?;
+    lua.create_string(mac)
+  })?)?;
+  crypto.set("sign", lua.create_function(|lua, (algorithm, key, data): (String, String, LuaString)| {
+    let signature = sign(&algorithm, &key, &data.as_bytes()).map_err(mlua::Error::external)?;
+    lua.create_string(signature)
+  })?)?;
+  crypto.set("verify", lua.create_function(|_, (algorithm, key, data, signature): (String, String, LuaString, LuaString)| {
+    verify(&algorithm, &key, &data.as_bytes(), &signature.as_bytes()).map_err(mlua::Error::external)
+  })?)?;
+  crypto.set("public_key", lua.create_function(|_, (algorithm, key): (String, String)| {
+    public_key(&algorithm, &key).map_err(mlua::Error::external)
+  })?)?;
+  crypto.set("random_bytes", lua.create_function(|lua, count: usize| {
+    if count > MAX_RANDOM_BYTES {
+      return Err(mlua::Error::external(anyhow!(
+        "random_bytes can return at most {} bytes, {} were asked for", MAX_RANDOM_BYTES, count)));
+    }
+    let mut bytes = vec![0; count];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    lua.create_string(bytes)
+  })?)?;
+  crypto.set("uuid", lua.create_function(|_, ()| Ok(uuid::Uuid::new_v4().to_string()))?)?;
+  pact.set("crypto", crypto)?;
+
+  let encoding = lua.create_table()?;
+  encoding.set("base64_encode", lua.create_function(|_, data: LuaString| Ok(STANDARD.encode(data.as_bytes())))?)?;
+  encoding.set("base64_decode", lua.create_function(|lua, text: LuaString| {
+    let text = text.as_bytes();
+    let bytes = STANDARD.decode(&text).or_else(|_| STANDARD_NO_PAD.decode(&text))
+      .map_err(|err| mlua::Error::external(anyhow!("Failed to base64 decode value - {}", err)))?;
+    lua.create_string(bytes)
+  })?)?;
+  encoding.set("base64url_encode", lua.create_function(|_, data: LuaString| Ok(URL_SAFE_NO_PAD.encode(data.as_bytes())))?)?;
+  encoding.set("base64url_decode", lua.create_function(|lua, text: LuaString| {
+    let text = text.as_bytes();
+    let bytes = URL_SAFE_NO_PAD.decode(&text).or_else(|_| URL_SAFE.decode(&text))
+      .map_err(|err| mlua::Error::external(anyhow!("Failed to base64url decode value - {}", err)))?;
+    lua.create_string(bytes)
+  })?)?;
+  encoding.set("hex_encode", lua.create_function(|_, data: LuaString| Ok(hex::encode(data.as_bytes())))?)?;
+  encoding.set("hex_decode", lua.create_function(|lua, text: LuaString| {
+    let bytes = hex::decode(text.as_bytes())
+      .map_err(|err| mlua::Error::external(anyhow!("Failed to hex decode value - {}", err)))?;
+    lua.create_string(bytes)
+  })?)?;
+  pact.set("encoding", encoding)?;
+
+  Ok(())
+}
+
+fn hmac(hash: &str, key: &[u8], data: &[u8]) -> anyhow::Result<Vec<u8>> {
+  fn mac<M: Mac + hmac::digest::KeyInit>(key: &[u8], data: &[u8]) -> Vec<u8> {
+    // HMAC takes a key of any length, so this can't fail
+    let mut mac = <M as hmac::digest::KeyInit>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+  }
+
+  match hash {
+    "sha1" => Ok(mac::<Hmac<Sha1>>(key, data)),
+    "sha256" => Ok(mac::<Hmac<Sha256>>(key, data)),
+    "sha384" => Ok(mac::<Hmac<Sha384>>(key, data)),
+    "sha512" => Ok(mac::<Hmac<Sha512>>(key, data)),
+    _ => Err(anyhow!("Unsupported HMAC hash '{}' - expected one of sha1, sha256, sha384 or sha512", hash))
+  }
+}
+
+fn unsupported_algorithm(algorithm: &str) -> anyhow::Error {
+  anyhow!("Unsupported signature algorithm '{}' - expected one of RS256, RS384, RS512, ES256 or EdDSA", algorithm)
+}
+
+fn rsa_private_key(pem: &str) -> anyhow::Result<RsaPrivateKey> {
+  RsaPrivateKey::from_pkcs8_pem(pem)
+    .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+    .map_err(|err| anyhow!("Could not read the RSA private key - {}", err))
+}
+
+fn rsa_public_key(pem: &str) -> anyhow::Result<RsaPublicKey> {
+  RsaPublicKey::from_public_key_pem(pem)
+    .or_else(|_| RsaPublicKey::from_pkcs1_pem(pem))
+    .map_err(|err| anyhow!("Could not read the RSA public key - {}", err))
+}
+
+fn ec_private_key(pem: &str) -> anyhow::Result<p256::ecdsa::SigningKey> {
+  p256::SecretKey::from_pkcs8_pem(pem)
+    .or_else(|_| p256::SecretKey::from_sec1_pem(pem))
+    .map(p256::ecdsa::SigningKey::from)
+    .map_err(|err| anyhow!("Could not read the P-256 private key - {}", err))
+}
+
+fn ec_public_key(pem: &str) -> anyhow::Result<p256::ecdsa::VerifyingKey> {
+  p256::ecdsa::VerifyingKey::from_public_key_pem(pem)
+    .map_err(|err| anyhow!("Could not read the P-256 public key - {}", err))
+}
+
+fn ed25519_private_key(pem: &str) -> anyhow::Result<ed25519_dalek::SigningKey> {
+  ed25519_dalek::SigningKey::from_pkcs8_pem(pem)
+    .map_err(|err| anyhow!("Could not read the Ed25519 private key - {}", err))
+}
+
+fn ed25519_public_key(pem: &str) -> anyhow::Result<ed25519_dalek::VerifyingKey> {
+  ed25519_dalek::VerifyingKey::from_public_key_pem(pem)
+    .map_err(|err| anyhow!("Could not read the Ed25519 public key - {}", err))
+}
+
+fn rsa_sign<D>(key: RsaPrivateKey, data: &[u8]) -> anyhow::Result<Vec<u8>>
+  where D: Digest + rsa::pkcs8::AssociatedOid {
+  let signature = rsa::pkcs1v15::SigningKey::<D>::new(key).try_sign(data)?;
+  Ok(signature.to_vec())
+}
+
+fn rsa_verify<D>(key: RsaPublicKey, data: &[u8], signature: &[u8]) -> bool
+  where D: Digest + rsa::pkcs8::AssociatedOid {
+  rsa::pkcs1v15::Signature::try_from(signature)
+    .map(|signature| rsa::pkcs1v15::VerifyingKey::<D>::new(key).verify(data, &signature).is_ok())
+    .unwrap_or(false)
+}
+
+fn sign(algorithm: &str, key: &str, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+  match algorithm {
+    "RS256" => rsa_sign::<Sha256>(rsa_private_key(key)?, data),
+    "RS384" => rsa_sign::<Sha384>(rsa_private_key(key)?, data),
+    "RS512" => rsa_sign::<Sha512>(rsa_private_key(key)?, data),
+    "ES256" => {
+      let signature: p256::ecdsa::Signature = ec_private_key(key)?.try_sign(data)?;
+      Ok(signature.to_bytes().to_vec())
+    }
+    "EdDSA" => Ok(ed25519_private_key(key)?.try_sign(data)?.to_bytes().to_vec()),
+    _ => Err(unsupported_algorithm(algorithm))
+  }
+}
+
+/// A signature that isn't even the right shape for the algorithm is invalid rather than an error
+fn verify(algorithm: &str, key: &str, data: &[u8], signature: &[u8]) -> anyhow::Result<bool> {
+  match algorithm {
+    "RS256" => Ok(rsa_verify::<Sha256>(rsa_public_key(key)?, data, signature)),
+    "RS384" => Ok(rsa_verify::<Sha384>(rsa_public_key(key)?, data, signature)),
+    "RS512" => Ok(rsa_verify::<Sha512>(rsa_public_key(key)?, data, signature)),
+    "ES256" => {
+      let key = ec_public_key(key)?;
+      let signature = p256::ecdsa::Signature::from_slice(signature)
+        .or_else(|_| p256::ecdsa::Signature::from_der(signature));
+      Ok(signature.map(|signature| key.verify(data, &signature).is_ok()).unwrap_or(false))
+    }
+    "EdDSA" => {
+      let key = ed25519_public_key(key)?;
+      let signature = ed25519_dalek::Signature::from_slice(signature);
+      Ok(signature.map(|signature| key.verify(data, &signature).is_ok()).unwrap_or(false))
+    }
+    _ => Err(unsupported_algorithm(algorithm))
+  }
+}
+
+fn public_key(algorithm: &str, key: &str) -> anyhow::Result<String> {
+  let pem = match algorithm {
+    "RS256" | "RS384" | "RS512" => RsaPublicKey::from(rsa_private_key(key)?).to_public_key_pem(LineEnding::LF)?,
+    "ES256" => ec_private_key(key)?.verifying_key().to_public_key_pem(LineEnding::LF)?,
+    "EdDSA" => ed25519_private_key(key)?.verifying_key().to_public_key_pem(LineEnding::LF)?,
+    _ => return Err(unsupported_algorithm(algorithm))
+  };
+  Ok(pem)
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use rsa::pkcs8::EncodePrivateKey;
+
+  use super::*;
+
+  const RSA_PRIVATE_KEY: &str = include_str!("../tests/fixtures/jwt-test-key.pem");
+
+  fn lua() -> Lua {
+    let lua = Lua::new();
+    let pact = lua.create_table().unwrap();
+    register_crypto_library(&lua, &pact).unwrap();
+    lua.globals().set("pact", pact).unwrap();
+    lua
+  }
+
+  fn eval(lua: &Lua, script: &str) -> mlua::Result<String> {
+    lua.load(script).eval()
+  }
+
+  #[test]
+  fn digests_and_hmacs_match_the_standard_test_vectors() {
+    let lua = lua();
+    let hex = |script: &str| eval(&lua, &format!("return pact.encoding.hex_encode({})", script)).unwrap();
+
+    expect!(hex("pact.crypto.sha1('abc')")).to(be_equal_to("a9993e364706816aba3e25717850c26c9cd0d89d"));
+    expect!(hex("pact.crypto.sha256('abc')"))
+      .to(be_equal_to("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"));
+    expect!(hex("pact.crypto.sha512('abc')")).to(be_equal_to(
+      "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"));
+    // RFC 4231, test case 2
+    expect!(hex("pact.crypto.hmac('sha256', 'Jefe', 'what do ya want for nothing?')"))
+      .to(be_equal_to("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"));
+
+    let err = eval(&lua, "return pact.crypto.hmac('md5', 'key', 'data')").unwrap_err();
+    expect!(err.to_string().contains("Unsupported HMAC hash 'md5'")).to(be_true());
+  }
+
+  #[test]
+  fn encodings_round_trip_binary_data() {
+    let lua = lua();
+
+    expect!(eval(&lua, "return pact.encoding.base64_encode('\\0\\255?>')").unwrap()).to(be_equal_to("AP8/Pg=="));
+    expect!(eval(&lua, "return pact.encoding.base64url_encode('\\0\\255?>')").unwrap()).to(be_equal_to("AP8_Pg"));
+    expect!(eval(&lua, "return pact.encoding.hex_encode('\\0\\255?>')").unwrap()).to(be_equal_to("00ff3f3e"));
+    let round_trips: bool = lua.load(r#"
+      local data = '\0\255?>'
+      return pact.encoding.base64_decode('AP8/Pg==') == data
+        and pact.encoding.base64_decode('AP8/Pg') == data
+        and pact.encoding.base64url_decode('AP8_Pg') == data
+        and pact.encoding.base64url_decode('AP8_Pg==') == data
+        and pact.encoding.hex_decode('00FF3f3e') == data
+    "#).eval().unwrap();
+    expect!(round_trips).to(be_true());
+
+    let err = eval(&lua, "return pact.encoding.hex_decode('xyz')").unwrap_err();
+    expect!(err.to_string().contains("Failed to hex decode value")).to(be_true());
+  }
+
+  #[test]
+  fn signatures_round_trip_for_every_algorithm() {
+    let ec_key = p256::SecretKey::from_slice(&[1; 32]).unwrap().to_pkcs8_pem(LineEnding::LF).unwrap();
+    let ed25519_key = ed25519_dalek::SigningKey::from_bytes(&[2; 32]).to_pkcs8_pem(LineEnding::LF).unwrap();
+    let keys = [
+      ("RS256", RSA_PRIVATE_KEY.to_string()),
+      ("RS384", RSA_PRIVATE_KEY.to_string()),
+      ("RS512", RSA_PRIVATE_KEY.to_string()),
+      ("ES256", ec_key.to_string()),
+      ("EdDSA", ed25519_key.to_string())
+    ];
+
+    let lua = lua();
+    for (algorithm, private_key) in keys {
+      lua.globals().set("algorithm", algorithm).unwrap();
+      lua.globals().set("private_key", private_key).unwrap();
+      let (valid, tampered): (bool, bool) = lua.load(r#"
+        local public_key = pact.crypto.public_key(algorithm, private_key)
+        local signature = pact.crypto.sign(algorithm, private_key, 'payload')
+        return pact.crypto.verify(algorithm, public_key, 'payload', signature),
+          pact.crypto.verify(algorithm, public_key, 'payload!', signature)
+      "#).eval().unwrap();
+      expect!(valid).to(be_true());
+      expect!(tampered).to(be_false());
+    }
+  }
+
+  #[test]
+  fn es256_signatures_are_the_fixed_size_jws_form_and_der_is_also_accepted() {
+    let signing_key = p256::ecdsa::SigningKey::from(p256::SecretKey::from_slice(&[1; 32]).unwrap());
+    let public_key = signing_key.verifying_key().to_public_key_pem(LineEnding::LF).unwrap();
+    let private_key = signing_key.to_pkcs8_pem(LineEnding::LF).unwrap();
+
+    let signature = sign("ES256", &private_key, b"payload").unwrap();
+    expect!(signature.len()).to(be_equal_to(64));
+    let der = p256::ecdsa::Signature::from_slice(&signature).unwrap().to_der();
+    expect!(verify("ES256", &public_key, b"payload", der.as_bytes()).unwrap()).to(be_true());
+  }
+
+  #[test]
+  fn rsa_signatures_match_the_existing_rs512_host_function() {
+    // `rsa_sign` in `lua_plugin` predates this library and signs the same way
+    let signature = sign("RS512", RSA_PRIVATE_KEY, b"header.payload").unwrap();
+    let public_key = RsaPublicKey::from(rsa_private_key(RSA_PRIVATE_KEY).unwrap());
+    let digest = Sha512::digest(b"header.payload");
+    expect!(public_key.verify(rsa::Pkcs1v15Sign::new::<Sha512>(), &digest, &signature)).to(be_ok());
+  }
+
+  #[test]
+  fn unknown_algorithms_and_unreadable_keys_are_errors() {
+    expect!(sign("HS256", RSA_PRIVATE_KEY, b"data").unwrap_err().to_string())
+      .to(be_equal_to("Unsupported signature algorithm 'HS256' - expected one of RS256, RS384, RS512, ES256 or EdDSA"));
+    expect!(sign("ES256", RSA_PRIVATE_KEY, b"data").unwrap_err().to_string()
+      .starts_with("Could not read the P-256 private key")).to(be_true());
+    expect!(verify("EdDSA", "not a key", b"data", b"sig")).to(be_err());
+  }
+
+  #[test]
+  fn random_bytes_and_uuids() {
+    let lua = lua();
+    let (length, different): (usize, bool) = lua.load(
+      "local a, b = pact.crypto.random_bytes(16), pact.crypto.random_bytes(16); return #a, a ~= b"
+    ).eval().unwrap();
+    expect!(length).to(be_equal_to(16));
+    expect!(different).to(be_true());
+
+    let uuid = eval(&lua, "return pact.crypto.uuid()").unwrap();
+    expect!(uuid::Uuid::parse_str(&uuid).map(|uuid| uuid.get_version_num())).to(be_ok().value(4));
+  }
+
+  #[test]
+  fn random_bytes_is_limited_to_a_mebibyte() {
+    let lua = lua();
+    let length: usize = lua.load(format!("return #pact.crypto.random_bytes({})", MAX_RANDOM_BYTES)).eval().unwrap();
+    expect!(length).to(be_equal_to(MAX_RANDOM_BYTES));
+
+    let err = eval(&lua, &format!("return pact.crypto.random_bytes({})", MAX_RANDOM_BYTES + 1)).unwrap_err();
+    expect!(err.to_string().contains("random_bytes can return at most 1048576 bytes")).to(be_true());
+    expect!(eval(&lua, "return pact.crypto.random_bytes(math.maxinteger)")).to(be_err());
+  }
+}
@@ -0,0 +1,266 @@
+//! The `pact.json`, `pact.xml` and `pact.cbor` libraries for Lua plugins.
+//!
+//! These parse and write structured data with the same Rust implementations the driver and Pact
+//! use themselves, so a plugin doesn't need to vendor a pure-Lua parser, and reads a body the same
+//! way the rest of Pact does.
+//!
+//! - `pact.json.decode(text)` - the JSON value as Lua values. JSON `null` becomes `pact.json.null`
+//!   (not `nil`, which would leave a hole in an array), and arrays are marked so they encode back
+//!   as arrays even when empty.
+//! - `pact.json.encode(value, pretty)` - the value as JSON text, indented if `pretty` is true. A
+//!   table with only the keys `1..n` (or one that came from `decode` as an array) is an array,
+//!   any other a JSON object.
+//! - `pact.xml.parse(text)` - the root element of the XML document, as a table with `name` (the
+//!   local name), `namespace` (the namespace URI, if it has one), `attributes` (local name to
+//!   value) and `children` (an array of child element tables and text strings, in document order).
+//!   Comments, processing instructions and whitespace-only text are left out. Only available with
+//!   the `xml` feature.
+//! - `pact.cbor.decode(data)` - the CBOR value as Lua values, as for JSON. Byte strings become Lua
+//!   strings, and tags are dropped in favour of the value they tag.
+//! - `pact.cbor.encode(value)` - the value as CBOR bytes. A Lua string that isn't valid UTF-8 is
+//!   written as a byte string, any other as a text string.
+//!
+//! Input that can't be parsed, and values that can't be written (a function, say), raise a Lua
+//! error.
+
+use anyhow::anyhow;
+use mlua::{Lua, LuaSerdeExt, LuaString, Table, Value};
+
+/// Register `pact.json`, `pact.xml` and `pact.cbor` in the given `pact` table
+pub(crate) fn register_data_library(lua: &Lua, pact: &Table) -> anyhow::Result<()> {
+  let json = lua.create_table()?;
+  json.set("null", lua.null())?;
+  json.set("decode", lua.create_function(|lua, text: LuaString| {
+    let value: serde_json::Value = serde_json::from_slice(&text.as_bytes())
+      .map_err(|err| mlua::Error::external(anyhow!("Failed to parse JSON - {}", err)))?;
+    lua.to_value(&value)
+  })?)?;
+  json.set("encode", lua.create_function(|lua, (value, pretty): (Value, Option<bool>)| {
+    let value: serde_json::Value = lua.from_value(value)
+      .map_err(|err| mlua::Error::external(anyhow!("Failed to write value as JSON - {}", err)))?;
+    let text = if pretty.unwrap_or_default() {
+      serde_json::to_string_pretty(&value)
+    } else {
+      serde_json::to_string(&value)
+    };
+    text.map_err(mlua::Error::external)
+  })?)?;
+  pact.set("json", json)?;
+
+  #[cfg(feature = "xml")]
+  {
+    let xml = lua.create_table()?;
+    xml.set("parse", lua.create_function(|lua, text: LuaString| {
+      let package = pact_models::xml_utils::parse_bytes(&text.as_bytes()).map_err(mlua::Error::external)?;
+      let document = package.as_document();
+      let root = document.root().children().into_iter()
+        .find_map(|child| child.element())
+        .ok_or_else(|| mlua::Error::external(anyhow!("XML document has no root element")))?;
+      xml_element_to_lua(lua, root)
+    })?)?;
+    pact.set("xml", xml)?;
+  }
+
+  let cbor = lua.create_table()?;
+  cbor.set("decode", lua.create_function(|lua, data: LuaString| {
+    let value: ciborium::Value = ciborium::de::from_reader(data.as_bytes().as_ref())
+      .map_err(|err| mlua::Error::external(anyhow!("Failed to parse CBOR - {}", err)))?;
+    cbor_to_lua(lua, value)
+  })?)?;
+  cbor.set("encode", lua.create_function(|lua, value: Value| {
+    let value: ciborium::Value = lua.from_value(value)
+      .map_err(|err| mlua::Error::external(anyhow!("Failed to write value as CBOR - {}", err)))?;
+    let mut bytes = vec![];
+    ciborium::ser::into_writer(&value, &mut bytes)
+      .map_err(|err| mlua::Error::external(anyhow!("Failed to write value as CBOR - {}", err)))?;
+    lua.create_string(bytes)
+  })?)?;
+  pact.set("cbor", cbor)?;
+
+  Ok(())
+}
+
+#[cfg(feature = "xml")]
+fn xml_element_to_lua(lua: &Lua, element: sxd_document::dom::Element) -> mlua::Result<Table> {
+  use sxd_document::dom::ChildOfElement;
+
+  let table = lua.create_table()?;
+  table.set("name", element.name().local_part())?;
+  table.set("namespace", element.name().namespace_uri())?;
+
+  let attributes = lua.create_table()?;
+  for attribute in element.attributes() {
+    attributes.set(attribute.name().local_part(), attribute.value())?;
+  }
+  table.set("attributes", attributes)?;
+
+  let children = lua.create_table()?;
+  for child in element.children() {
+    match child {
+      ChildOfElement::Element(child) => children.push(xml_element_to_lua(lua, child)?)?,
+      ChildOfElement::Text(text) if !text.text().trim().is_empty() => children.push(text.text())?,
+      _ => {}
+    }
+  }
+  table.set("children", children)?;
+
+  Ok(table)
+}
+
+fn cbor_to_lua(lua: &Lua, value: ciborium::Value) -> mlua::Result<Value> {
+  match value {
+    ciborium::Value::Integer(integer) => {
+      let integer = i128::from(integer);
+      // CBOR integers go to +/-2^64, further than a Lua integer can
+      Ok(i64::try_from(integer).map(Value::Integer).unwrap_or(Value::Number(integer as f64)))
+    }
+    ciborium::Value::Bytes(bytes) => lua.create_string(bytes).map(Value::String),
+    ciborium::Value::Float(float) => Ok(Value::Number(float)),
+    ciborium::Value::Text(text) => lua.create_string(text).map(Value::String),
+    ciborium::Value::Bool(bool) => Ok(Value::Boolean(bool)),
+    ciborium::Value::Null => Ok(lua.null()),
+    ciborium::Value::Tag(_, value) => cbor_to_lua(lua, *value),
+    ciborium::Value::Array(items) => {
+      let table = lua.create_table_with_capacity(items.len(), 0)?;
+      for item in items {
+        table.push(cbor_to_lua(lua, item)?)?;
+      }
+      table.set_metatable(Some(lua.array_metatable()))?;
+      Ok(Value::Table(table))
+    }
+    ciborium::Value::Map(entries) => {
+      let table = lua.create_table_with_capacity(0, entries.len())?;
+      for (key, value) in entries {
+        table.raw_set(cbor_to_lua(lua, key)?, cbor_to_lua(lua, value)?)?;
+      }
+      Ok(Value::Table(table))
+    }
+    _ => Err(mlua::Error::external(anyhow!("Unsupported CBOR value {:?}", value)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use super::*;
+
+  fn lua() -> Lua {
+    let lua = Lua::new();
+    let pact = lua.create_table().unwrap();
+    register_data_library(&lua, &pact).unwrap();
+    lua.globals().set("pact", pact).unwrap();
+    lua
+  }
+
+  #[test]
+  fn json_decodes_to_lua_values_and_encodes_back() {
+    let lua = lua();
+    let (name, count, price, missing, empty_length, encoded): (String, i64, f64, bool, usize, String) = lua.load(r#"
+      local value = pact.json.decode('{"name":"widget","count":3,"price":9.5,"tags":[],"owner":null}')
+      return value.name, value.count, value.price, value.owner == pact.json.null, #value.tags,
+        pact.json.encode(value)
+    "#).eval().unwrap();
+
+    expect!(name).to(be_equal_to("widget"));
+    expect!(count).to(be_equal_to(3));
+    expect!(price).to(be_equal_to(9.5));
+    expect!(missing).to(be_true());
+    expect!(empty_length).to(be_equal_to(0));
+    // The empty array stays an array, and the null a null, on the way back out
+    expect!(encoded).to(be_equal_to(r#"{"count":3,"name":"widget","owner":null,"price":9.5,"tags":[]}"#));
+  }
+
+  #[test]
+  fn json_encodes_sequences_as_arrays_and_can_pretty_print() {
+    let lua = lua();
+    let compact: String = lua.load("return pact.json.encode({ 1, 'two', { three = true } })").eval().unwrap();
+    expect!(compact).to(be_equal_to(r#"[1,"two",{"three":true}]"#));
+    let pretty: String = lua.load("return pact.json.encode({ a = 1 }, true)").eval().unwrap();
+    expect!(pretty).to(be_equal_to("{\n  \"a\": 1\n}"));
+  }
+
+  #[test]
+  fn json_errors_are_raised() {
+    let lua = lua();
+    let err = lua.load("return pact.json.decode('{not json')").eval::<Value>().unwrap_err();
+    expect!(err.to_string().contains("Failed to parse JSON")).to(be_true());
+    let err = lua.load("return pact.json.encode({ f = print })").eval::<String>().unwrap_err();
+    expect!(err.to_string().contains("Failed to write value as JSON")).to(be_true());
+  }
+
+  #[cfg(feature = "xml")]
+  #[test]
+  fn xml_parses_to_element_tables() {
+    let lua = lua();
+    let (name, namespace, id, first_child, text, child_count): (String, String, String, String, String, usize) = lua.load(r#"
+      local root = pact.xml.parse([[<?xml version="1.0"?>
+        <order xmlns="urn:orders" id="42">
+          <!-- a comment -->
+          <item sku="A1">Widget</item>
+          <item sku="B2">Gadget</item>
+        </order>]])
+      return root.name, root.namespace, root.attributes.id, root.children[1].attributes.sku,
+        root.children[2].children[1], #root.children
+    "#).eval().unwrap();
+
+    expect!(name).to(be_equal_to("order"));
+    expect!(namespace).to(be_equal_to("urn:orders"));
+    expect!(id).to(be_equal_to("42"));
+    expect!(first_child).to(be_equal_to("A1"));
+    expect!(text).to(be_equal_to("Gadget"));
+    expect!(child_count).to(be_equal_to(2));
+
+    let err = lua.load("return pact.xml.parse('<open>')").eval::<Value>().unwrap_err();
+    expect!(err.to_string().contains("Failed to parse bytes as XML")).to(be_true());
+  }
+
+  #[test]
+  fn cbor_round_trips_and_keeps_byte_strings_as_bytes() {
+    let lua = lua();
+    let encoded: LuaString = lua.load(
+      "return pact.cbor.encode({ id = 7, ratio = 0.5, blob = '\\0\\255', items = { 'a', 'b' } })"
+    ).eval().unwrap();
+    let value: ciborium::Value = ciborium::de::from_reader(encoded.as_bytes().as_ref()).unwrap();
+    let entries = value.as_map().unwrap();
+    let get = |key: &str| entries.iter()
+      .find(|(k, _)| k.as_text() == Some(key))
+      .map(|(_, v)| v.clone())
+      .unwrap();
+    expect!(get("id")).to(be_equal_to(ciborium::Value::Integer(7.into())));
+    expect!(get("ratio")).to(be_equal_to(ciborium::Value::Float(0.5)));
+    expect!(get("blob")).to(be_equal_to(ciborium::Value::Bytes(vec![0, 255])));
+    expect!(get("items")).to(be_equal_to(ciborium::Value::Array(vec![
+      ciborium::Value::Text("a".to_string()), ciborium::Value::Text("b".to_string())
+    ])));
+
+    lua.globals().set("encoded", encoded).unwrap();
+    let (id, blob, second): (i64, LuaString, String) = lua.load(r#"
+      local value = pact.cbor.decode(encoded)
+      return value.id, value.blob, value.items[2]
+    "#).eval().unwrap();
+    expect!(id).to(be_equal_to(7));
+    expect!(blob.as_bytes().to_vec()).to(be_equal_to(vec![0, 255]));
+    expect!(second).to(be_equal_to("b"));
+  }
+
+  #[test]
+  fn cbor_decodes_tagged_values_and_large_integers() {
+    let mut bytes = vec![];
+    ciborium::ser::into_writer(&ciborium::Value::Array(vec![
+      ciborium::Value::Tag(0, Box::new(ciborium::Value::Text("2024-01-01T00:00:00Z".to_string()))),
+      ciborium::Value::Integer(u64::MAX.into()),
+      ciborium::Value::Null
+    ]), &mut bytes).unwrap();
+
+    let lua = lua();
+    lua.globals().set("encoded", lua.create_string(bytes).unwrap()).unwrap();
+    let (date, large, null): (String, f64, bool) = lua.load(r#"
+      local value = pact.cbor.decode(encoded)
+      return value[1], value[2], value[3] == pact.json.null
+    "#).eval().unwrap();
+    expect!(date).to(be_equal_to("2024-01-01T00:00:00Z"));
+    expect!(large).to(be_equal_to(u64::MAX as f64));
+    expect!(null).to(be_true());
+  }
+}
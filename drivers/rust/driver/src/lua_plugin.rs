@@ -4,8 +4,9 @@
 //! process (`executableType: "lua"` in `pact-plugin.json`), instead of a separate child
 //! process speaking gRPC. The plugin script must define these global functions:
 //!
-//! - `init(implementation, version) -> table` - returns an array of catalogue entries,
-//!   each shaped as `{ entryType = "CONTENT_MATCHER", key = "...", values = { ... } }`.
+//! - `init(implementation, version, host_capabilities) -> table` - returns an array of catalogue
+//!   entries, each shaped as `{ entryType = "CONTENT_MATCHER", key = "...", values = { ... } }`,
+//!   or `{ catalogue = { ... }, capabilities = { ... } }` to also declare the plugin's capabilities.
 //! - `configure_interaction(content_type, config) -> table` - see [`PluginInstance::configure_interaction`].
 //! - `match_contents(request) -> table` - see [`PluginInstance::compare_contents`].
 //! - `generate_content(contents, generators, test_mode)` (optional) - see [`PluginInstance::generate_content`].
@@ -44,6 +45,10 @@
 //! (the two entry points the driver invokes via `call_async`); calling them from another entry
 //! point currently fails, since mlua can only resolve an async host function from within a Lua
 //! call chain that was itself started asynchronously.
+//!
+//! Scripts also get general-purpose hashing, HMAC, signatures and binary encodings as
+//! `pact.crypto` and `pact.encoding` - see [`crate::lua_crypto`] - and JSON, XML and CBOR
+//! parsing as `pact.json`, `pact.xml` and `pact.cbor` - see [`crate::lua_data`].
 
 use std::collections::HashMap;
 use std::fs::File;
@@ -59,26 +64,39 @@ use rsa::{Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
 use sha2::{Digest, Sha512};
 use tracing::{debug, warn};
 
-use crate::call_chain;
-use crate::catalogue_manager::{CatalogueEntryType, ResolvedCapability, resolve_capability};
-use crate::plugin_manager::lookup_plugin;
+use crate::catalogue_manager::CatalogueEntryType;
+use crate::host_callbacks::{
+  call_host_compare_contents, call_host_generate_content, call_host_generate_field, call_host_match_field,
+};
+use crate::lua_crypto::register_crypto_library;
+use crate::lua_data::register_data_library;
+use crate::lua_pool::{LuaVm, LuaVmPool, pool_size_from_manifest};
+use crate::lua_sandbox::{LuaSandbox, restrict_package};
 use crate::plugin_models::{
-  PactPluginManifest, PactPluginRpc, PluginInitRequest, PluginInitResponse, PluginInstance,
+  FIELD_BATCH_CAPABILITY, PactPluginManifest, PactPluginRpc, PluginInitRequest, PluginInitResponse,
+  PluginInstance,
+};
+use crate::plugin_conversions::{
+  as_json_map, content_type_hint_to_str, str_to_content_type_hint, str_to_test_mode, test_mode_to_str,
+  v1_content_mismatch_to_v2, v1_plugin_configuration_to_v2, v2_content_mismatch_to_v1, v2_interaction_data_to_v1,
+  v2_plugin_configuration_to_v1,
 };
 use crate::proto::*;
 use crate::proto_v2;
 use crate::utils::{proto_struct_to_json, proto_value_to_json, to_proto_struct, to_proto_value};
 
-/// A running Lua plugin instance. Each instance owns its own embedded Lua VM.
+/// A running Lua plugin instance. Each instance owns its own embedded Lua VMs - one unless the
+/// manifest asks for a pool of them (see [`crate::lua_pool`]).
 ///
-/// The mutex is `tokio::sync::Mutex`, not `std::sync::Mutex`: the two host functions plugins can
-/// call to reach a host-provided or another plugin's capability (`host_compare_contents`,
-/// `host_generate_content` - see [`register_host_functions`] and proposal 007) need to hold the
-/// lock across an `.await` while they dispatch to an async [`crate::core_capabilities`] handler
-/// or forward to another plugin, which a `std::sync::MutexGuard` cannot do (it isn't `Send`,
-/// and `PluginInstance`'s `#[async_trait]` methods require a `Send` future).
+/// Calls wait for a free VM asynchronously, rather than blocking on a `std::sync::Mutex`: the
+/// host functions plugins can call to reach a host-provided or another plugin's capability
+/// (`host_compare_contents`, `host_generate_content` - see [`register_host_functions`] and
+/// proposal 007) need to hold the VM across an `.await` while they dispatch to an async
+/// [`crate::core_capabilities`] handler or forward to another plugin, which a
+/// `std::sync::MutexGuard` cannot do (it isn't `Send`, and `PluginInstance`'s `#[async_trait]`
+/// methods require a `Send` future).
 pub struct LuaPactPlugin {
-  runtime: Arc<tokio::sync::Mutex<Lua>>,
+  vms: LuaVmPool,
   manifest: PactPluginManifest,
   instance_id: String,
   plugin_capabilities: Vec<String>,
@@ -90,34 +108,68 @@ impl std::fmt::Debug for LuaPactPlugin {
       .field("manifest", &self.manifest)
       .field("instance_id", &self.instance_id)
       .field("plugin_capabilities", &self.plugin_capabilities)
+      .field("vm_pool_size", &self.vms.size())
       .finish()
   }
 }
 
-/// Start a Lua plugin: resolve the entry point script, create a Lua VM, register the host
-/// functions the plugin can call, and load (execute) the script.
+/// Start a Lua plugin: resolve the entry point script, then for each VM the plugin has, create a
+/// Lua VM, register the host functions the plugin can call, and load (execute) the script.
 pub(crate) fn start_lua_plugin(
   manifest: &PactPluginManifest,
   instance_id: String,
 ) -> anyhow::Result<LuaPactPlugin> {
   let script_path = resolve_entry_point(manifest)?;
-  debug!("Loading Lua plugin {} from {:?}", manifest.name, script_path);
+  let sandbox = LuaSandbox::from_manifest(manifest)?;
+  let pool_size = pool_size_from_manifest(manifest)?;
+  debug!("Loading Lua plugin {} from {:?} into {} VM(s)", manifest.name, script_path, pool_size);
+  if let Some(sandbox) = &sandbox {
+    debug!("Lua plugin {} runs sandboxed - {:?}", manifest.name, sandbox);
+  }
 
   let log = Arc::new(LuaPluginLog::open(&manifest.name, &instance_id));
-  let lua = Lua::new();
-  set_package_path(&lua, manifest)?;
-  add_luarocks_path(&lua, manifest)?;
-  register_host_functions(&lua, &manifest.name, &log)?;
-  load_script(&lua, &script_path)?;
+  let vms = (0..pool_size)
+    .map(|_| start_lua_vm(manifest, sandbox.as_ref(), &script_path, &log))
+    .collect::<anyhow::Result<Vec<_>>>()?;
 
   Ok(LuaPactPlugin {
-    runtime: Arc::new(tokio::sync::Mutex::new(lua)),
+    vms: LuaVmPool::new(vms),
     manifest: manifest.clone(),
     instance_id,
     plugin_capabilities: vec![],
   })
 }
 
+fn start_lua_vm(
+  manifest: &PactPluginManifest,
+  sandbox: Option<&LuaSandbox>,
+  script_path: &Path,
+  log: &Arc<LuaPluginLog>,
+) -> anyhow::Result<LuaVm> {
+  let (lua, budget) = match sandbox {
+    Some(sandbox) => {
+      let (lua, budget) = sandbox.create_vm()?;
+      (lua, Some(budget))
+    }
+    None => (Lua::new(), None)
+  };
+  if sandbox.is_some() {
+    let directories = luarocks_lua_dir(manifest).into_iter()
+      .chain(std::iter::once(PathBuf::from(&manifest.plugin_dir)))
+      .collect();
+    restrict_package(&lua, directories)?;
+  } else {
+    set_package_path(&lua, manifest)?;
+    add_luarocks_path(&lua, manifest)?;
+  }
+  register_host_functions(&lua, &manifest.name, log)?;
+  if let Some(budget) = &budget {
+    budget.reset();
+  }
+  load_script(&lua, script_path)?;
+  Ok(LuaVm { lua, budget })
+}
+
 impl LuaPactPlugin {
   /// Set the capabilities negotiated for this plugin instance (called once, after the init
   /// handshake, before the instance is shared behind an `Arc`).
@@ -171,7 +223,10 @@ fn resolve_entry_point(manifest: &PactPluginManifest) -> anyhow::Result<PathBuf>
 /// driver's `LuaPactPlugin.kt`, which always uses `manifest.pluginDir` for the same purpose.
 fn set_package_path(lua: &Lua, manifest: &PactPluginManifest) -> anyhow::Result<()> {
   let plugin_dir = PathBuf::from(&manifest.plugin_dir);
-  let package: Table = lua.globals().get("package")?;
+  // The plugin may have been given a VM without the package library, in which case it can't `require`
+  let Some(package) = lua.globals().get::<Option<Table>>("package")? else {
+    return Ok(());
+  };
   let existing: String = package.get("path").unwrap_or_default();
   let new_path = format!(
     "{}/?.lua;{}/?/init.lua;{}",
@@ -185,6 +240,13 @@ fn set_package_path(lua: &Lua, manifest: &PactPluginManifest) -> anyhow::Result<
 /// segment LuaRocks uses in its per-version tree layout (e.g. `share/lua/5.4/`).
 const LUAROCKS_LUA_VERSION: &str = "5.4";
 
+/// Version of the embedded Lua, as reported by its `_VERSION` global (e.g. `5.4`). Lua only
+/// reports its major and minor version.
+pub(crate) fn lua_version() -> anyhow::Result<String> {
+  let version: String = Lua::new().globals().get("_VERSION")?;
+  Ok(version.trim_start_matches("Lua ").to_string())
+}
+
 /// Makes pure-Lua packages installed via `luarocks` available to `require`, so a plugin can
 /// depend on rocks instead of vendoring every third-party library it uses.
 ///
@@ -195,13 +257,29 @@ const LUAROCKS_LUA_VERSION: &str = "5.4";
 /// Only the `share/lua` (pure Lua) path is added - packages with compiled C extensions
 /// (under `lib/lua`) are not supported.
 fn add_luarocks_path(lua: &Lua, manifest: &PactPluginManifest) -> anyhow::Result<()> {
+  let Some(lua_dir) = luarocks_lua_dir(manifest) else {
+    return Ok(());
+  };
+  let Some(package) = lua.globals().get::<Option<Table>>("package")? else {
+    return Ok(());
+  };
+  let existing: String = package.get("path").unwrap_or_default();
+  let new_path = format!(
+    "{}/?.lua;{}/?/init.lua;{}",
+    lua_dir.to_string_lossy(), lua_dir.to_string_lossy(), existing
+  );
+  package.set("path", new_path)?;
+  debug!("Added LuaRocks path {:?} for plugin {}", lua_dir, manifest.name);
+  Ok(())
+}
+
+/// The directory pure-Lua LuaRocks packages are installed in for the plugin (see
+/// [`add_luarocks_path`]), if it exists
+fn luarocks_lua_dir(manifest: &PactPluginManifest) -> Option<PathBuf> {
   let configured = manifest.plugin_config.get("luaRocksDir").and_then(|v| v.as_str());
   let rocks_dir = match configured {
     Some(dir) => PathBuf::from(dir),
-    None => match home::home_dir() {
-      Some(home) => home.join(".luarocks"),
-      None => return Ok(()),
-    },
+    None => home::home_dir()?.join(".luarocks"),
   };
 
   let lua_dir = rocks_dir.join("share").join("lua").join(LUAROCKS_LUA_VERSION);
@@ -212,18 +290,9 @@ fn add_luarocks_path(lua: &Lua, manifest: &PactPluginManifest) -> anyhow::Result
         rocks_dir.display(), LUAROCKS_LUA_VERSION
       );
     }
-    return Ok(());
+    return None;
   }
-
-  let package: Table = lua.globals().get("package")?;
-  let existing: String = package.get("path").unwrap_or_default();
-  let new_path = format!(
-    "{}/?.lua;{}/?/init.lua;{}",
-    lua_dir.to_string_lossy(), lua_dir.to_string_lossy(), existing
-  );
-  package.set("path", new_path)?;
-  debug!("Added LuaRocks path {:?} for plugin {}", lua_dir, manifest.name);
-  Ok(())
+  Some(lua_dir)
 }
 
 fn load_script(lua: &Lua, script_path: &Path) -> anyhow::Result<()> {
@@ -231,13 +300,16 @@ fn load_script(lua: &Lua, script_path: &Path) -> anyhow::Result<()> {
   lua
     .load(script)
     .set_name(script_path.to_string_lossy().to_string())
+    .set_mode(mlua::chunk::ChunkMode::Text)
     .exec()
     .map_err(|err| anyhow!("Failed to load Lua plugin script {:?} - {}", script_path, err))?;
   Ok(())
 }
 
-/// Registers the host (Rust) functions that a Lua plugin script can call: a logger, and the
-/// RSA/base64 primitives needed by the JWT plugin (Lua has no crypto standard library).
+/// Registers the host (Rust) functions that a Lua plugin script can call: a logger, the
+/// RSA/base64 primitives needed by the JWT plugin (Lua has no crypto standard library), the
+/// callbacks into other capabilities, and the `pact` libraries (see [`crate::lua_crypto`] and
+/// [`crate::lua_data`]).
 fn register_host_functions(lua: &Lua, plugin_name: &str, log: &Arc<LuaPluginLog>) -> anyhow::Result<()> {
   let globals = lua.globals();
 
@@ -351,7 +423,7 @@ fn register_host_functions(lua: &Lua, plugin_name: &str, log: &Arc<LuaPluginLog>
     "host_compare_contents",
     lua.create_async_function(move |lua, (entry_key, request): (String, Table)| async move {
       let request = lua_to_compare_request(&lua, request).map_err(mlua::Error::external)?;
-      let response = call_host_compare_contents(&entry_key, request).await.map_err(mlua::Error::external)?;
+      let response = call_host_compare_contents(&entry_key, request, None).await.map_err(mlua::Error::external)?;
       compare_response_to_lua(&lua, &response)
     })?,
   )?;
@@ -360,7 +432,7 @@ fn register_host_functions(lua: &Lua, plugin_name: &str, log: &Arc<LuaPluginLog>
     "host_generate_content",
     lua.create_async_function(move |lua, (entry_key, contents, generators, test_mode): (String, Value, Option<Table>, Option<String>)| async move {
       let request = lua_to_generate_request(&lua, contents, generators, test_mode).map_err(mlua::Error::external)?;
-      let response = call_host_generate_content(&entry_key, request).await.map_err(mlua::Error::external)?;
+      let response = call_host_generate_content(&entry_key, request, None).await.map_err(mlua::Error::external)?;
       body_to_lua(&lua, &response.contents)
     })?,
   )?;
@@ -372,7 +444,7 @@ fn register_host_functions(lua: &Lua, plugin_name: &str, log: &Arc<LuaPluginLog>
     "host_match_field",
     lua.create_async_function(move |lua, (entry_key, request): (String, Table)| async move {
       let request = lua_to_match_field_request(&lua, request).map_err(mlua::Error::external)?;
-      let response = call_host_match_field(&entry_key, request).await.map_err(mlua::Error::external)?;
+      let response = call_host_match_field(&entry_key, request, None).await.map_err(mlua::Error::external)?;
       match_field_response_to_lua(&lua, &response)
     })?,
   )?;
@@ -381,103 +453,18 @@ fn register_host_functions(lua: &Lua, plugin_name: &str, log: &Arc<LuaPluginLog>
     "host_generate_field",
     lua.create_async_function(move |lua, (entry_key, request): (String, Table)| async move {
       let request = lua_to_generate_field_request(&lua, request).map_err(mlua::Error::external)?;
-      let response = call_host_generate_field(&entry_key, request).await.map_err(mlua::Error::external)?;
+      let response = call_host_generate_field(&entry_key, request, None).await.map_err(mlua::Error::external)?;
       generate_field_response_to_lua(&lua, &response)
     })?,
   )?;
 
-  Ok(())
-}
-
-/// Resolve `entry_key` to a content matcher capability and dispatch to it - a host-registered
-/// [`crate::core_capabilities::CoreContentMatcher`] called in-process, or another running plugin
-/// called via a freshly-started call chain (see [`crate::call_chain`]), matching the same
-/// resolver [`crate::plugin_host`] uses for the gRPC callback path. Backs the `host_compare_contents`
-/// Lua host function.
-async fn call_host_compare_contents(
-  entry_key: &str,
-  request: CompareContentsRequest
-) -> anyhow::Result<CompareContentsResponse> {
-  match resolve_capability(entry_key, CatalogueEntryType::CONTENT_MATCHER)? {
-    ResolvedCapability::Core(core_key) => {
-      let handler = crate::core_capabilities::lookup_core_content_matcher(&core_key)
-        .ok_or_else(|| anyhow!("No core content matcher registered for '{}'", core_key))?;
-      handler.compare_contents(request).await
-    }
-    ResolvedCapability::Plugin(manifest) => {
-      let plugin = lookup_plugin(&manifest.as_dependency())
-        .ok_or_else(|| anyhow!("Plugin '{}' for entry '{}' is not currently running", manifest.name, entry_key))?;
-      let chain_id = call_chain::new_call_chain_id();
-      let deadline_ms = call_chain::default_deadline_ms();
-      plugin.compare_contents_with_chain(request, &chain_id, deadline_ms).await
-    }
-  }
-}
-
-/// Resolve `entry_key` to a content generator capability and dispatch to it. See
-/// [`call_host_compare_contents`]; backs the `host_generate_content` Lua host function.
-async fn call_host_generate_content(
-  entry_key: &str,
-  request: GenerateContentRequest
-) -> anyhow::Result<GenerateContentResponse> {
-  match resolve_capability(entry_key, CatalogueEntryType::CONTENT_GENERATOR)? {
-    ResolvedCapability::Core(core_key) => {
-      let handler = crate::core_capabilities::lookup_core_content_generator(&core_key)
-        .ok_or_else(|| anyhow!("No core content generator registered for '{}'", core_key))?;
-      handler.generate_content(request).await
-    }
-    ResolvedCapability::Plugin(manifest) => {
-      let plugin = lookup_plugin(&manifest.as_dependency())
-        .ok_or_else(|| anyhow!("Plugin '{}' for entry '{}' is not currently running", manifest.name, entry_key))?;
-      let chain_id = call_chain::new_call_chain_id();
-      let deadline_ms = call_chain::default_deadline_ms();
-      plugin.generate_content_with_chain(request, &chain_id, deadline_ms).await
-    }
-  }
-}
+  // General-purpose libraries under one `pact` global, rather than a global per function
+  let pact = lua.create_table()?;
+  register_crypto_library(lua, &pact)?;
+  register_data_library(lua, &pact)?;
+  globals.set("pact", pact)?;
 
-/// Resolve `entry_key` to a field-level matching rule and dispatch to it. See
-/// [`call_host_compare_contents`]; backs the `host_match_field` Lua host function.
-async fn call_host_match_field(
-  entry_key: &str,
-  request: proto_v2::MatchFieldRequest
-) -> anyhow::Result<proto_v2::MatchFieldResponse> {
-  match resolve_capability(entry_key, CatalogueEntryType::MATCHER)? {
-    ResolvedCapability::Core(core_key) => {
-      let handler = crate::core_capabilities::lookup_core_field_matcher(&core_key)
-        .ok_or_else(|| anyhow!("No core field matcher registered for '{}'", core_key))?;
-      handler.match_field(request).await
-    }
-    ResolvedCapability::Plugin(manifest) => {
-      let plugin = lookup_plugin(&manifest.as_dependency())
-        .ok_or_else(|| anyhow!("Plugin '{}' for entry '{}' is not currently running", manifest.name, entry_key))?;
-      let chain_id = call_chain::new_call_chain_id();
-      let deadline_ms = call_chain::default_deadline_ms();
-      plugin.match_field_with_chain(request, &chain_id, deadline_ms).await
-    }
-  }
-}
-
-/// Resolve `entry_key` to a field-level generator and dispatch to it. See
-/// [`call_host_compare_contents`]; backs the `host_generate_field` Lua host function.
-async fn call_host_generate_field(
-  entry_key: &str,
-  request: proto_v2::GenerateFieldRequest
-) -> anyhow::Result<proto_v2::GenerateFieldResponse> {
-  match resolve_capability(entry_key, CatalogueEntryType::GENERATOR)? {
-    ResolvedCapability::Core(core_key) => {
-      let handler = crate::core_capabilities::lookup_core_field_generator(&core_key)
-        .ok_or_else(|| anyhow!("No core field generator registered for '{}'", core_key))?;
-      handler.generate_field(request).await
-    }
-    ResolvedCapability::Plugin(manifest) => {
-      let plugin = lookup_plugin(&manifest.as_dependency())
-        .ok_or_else(|| anyhow!("Plugin '{}' for entry '{}' is not currently running", manifest.name, entry_key))?;
-      let chain_id = call_chain::new_call_chain_id();
-      let deadline_ms = call_chain::default_deadline_ms();
-      plugin.generate_field_with_chain(request, &chain_id, deadline_ms).await
-    }
-  }
+  Ok(())
 }
 
 /// Decode base64 (URL-safe), trying the padded then the un-padded alphabet.
@@ -489,19 +476,43 @@ fn decode_base64_lenient(data: &str) -> anyhow::Result<Vec<u8>> {
     .map_err(|err| anyhow!("Failed to base64 decode value - {}", err))
 }
 
+/// What a script's `init` function returned: the catalogue entries it registers, and the
+/// optional capabilities it declares (proposal 005).
+#[derive(Clone, Debug, Default, PartialEq)]
+struct LuaInitResult {
+  catalogue: Vec<CatalogueEntry>,
+  capabilities: Vec<String>,
+}
+
+/// Calls the script's `init(implementation, version, host_capabilities)` global. It can return
+/// either just the array of catalogue entries, or `{ catalogue = {...}, capabilities = {...} }`
+/// to also declare capabilities.
 fn call_init(
   lua: &Lua,
   implementation: &str,
   version: &str,
-) -> anyhow::Result<Vec<CatalogueEntry>> {
+  host_capabilities: &[String],
+) -> anyhow::Result<LuaInitResult> {
   let init_fn: Function = lua
     .globals()
     .get("init")
     .map_err(|_| anyhow!("Lua plugin does not define a global 'init' function"))?;
+  let host_capabilities = lua.create_sequence_from(host_capabilities.iter().cloned())?;
   let result: Table = init_fn
-    .call((implementation.to_string(), version.to_string()))
+    .call((implementation.to_string(), version.to_string(), host_capabilities))
     .map_err(|err| anyhow!("Lua init() function failed - {}", err))?;
-  lua_table_to_catalogue_entries(result)
+  match result.get::<Option<Table>>("catalogue")? {
+    Some(catalogue) => Ok(LuaInitResult {
+      catalogue: lua_table_to_catalogue_entries(catalogue)?,
+      capabilities: result.get::<Option<Vec<String>>>("capabilities")
+        .map_err(|err| anyhow!("Lua init() 'capabilities' must be an array of strings - {}", err))?
+        .unwrap_or_default(),
+    }),
+    None => Ok(LuaInitResult {
+      catalogue: lua_table_to_catalogue_entries(result)?,
+      capabilities: vec![],
+    })
+  }
 }
 
 fn lua_table_to_catalogue_entries(table: Table) -> anyhow::Result<Vec<CatalogueEntry>> {
@@ -524,22 +535,6 @@ fn lua_table_to_catalogue_entries(table: Table) -> anyhow::Result<Vec<CatalogueE
 
 // ---- Body <-> Lua ----
 
-fn content_type_hint_to_str(hint: i32) -> &'static str {
-  match body::ContentTypeHint::try_from(hint).unwrap_or(body::ContentTypeHint::Default) {
-    body::ContentTypeHint::Default => "DEFAULT",
-    body::ContentTypeHint::Text => "TEXT",
-    body::ContentTypeHint::Binary => "BINARY",
-  }
-}
-
-fn str_to_content_type_hint(hint: &str) -> i32 {
-  match hint {
-    "TEXT" => body::ContentTypeHint::Text as i32,
-    "BINARY" => body::ContentTypeHint::Binary as i32,
-    _ => body::ContentTypeHint::Default as i32,
-  }
-}
-
 fn body_to_lua(lua: &Lua, body: &Option<Body>) -> mlua::Result<Value> {
   match body {
     None => Ok(Value::Nil),
@@ -661,13 +656,6 @@ fn lua_to_plugin_configuration(lua: &Lua, value: Option<Value>) -> anyhow::Resul
   }
 }
 
-fn as_json_map(value: serde_json::Value) -> HashMap<String, serde_json::Value> {
-  match value {
-    serde_json::Value::Object(map) => map.into_iter().collect(),
-    _ => HashMap::new(),
-  }
-}
-
 // ---- CompareContents <-> Lua ----
 
 fn compare_request_to_lua(lua: &Lua, request: &CompareContentsRequest) -> mlua::Result<Table> {
@@ -741,16 +729,17 @@ fn lua_to_compare_response(table: Table) -> anyhow::Result<CompareContentsRespon
   })
 }
 
-/// Stringifies a scalar Lua value (used for the `expected`/`actual` fields of a mismatch
-/// table), rather than requiring exactly a Lua string - a claim/header value being compared
-/// could just as easily be a number or boolean.
-fn lua_scalar_to_string(value: Value) -> anyhow::Result<Option<String>> {
+/// Converts a scalar Lua value (used for the `expected`/`actual` fields of a mismatch table) to
+/// bytes. Lua strings are passed through as they are, as they can hold binary data, and any other
+/// scalar is stringified, as a claim/header value being compared could just as easily be a number
+/// or boolean.
+fn lua_scalar_to_bytes(value: Value) -> anyhow::Result<Option<Vec<u8>>> {
   match value {
     Value::Nil => Ok(None),
-    Value::Boolean(b) => Ok(Some(b.to_string())),
-    Value::Integer(i) => Ok(Some(i.to_string())),
-    Value::Number(n) => Ok(Some(n.to_string())),
-    Value::String(s) => Ok(Some(s.to_str()?.to_string())),
+    Value::Boolean(b) => Ok(Some(b.to_string().into_bytes())),
+    Value::Integer(i) => Ok(Some(i.to_string().into_bytes())),
+    Value::Number(n) => Ok(Some(n.to_string().into_bytes())),
+    Value::String(s) => Ok(Some(s.as_bytes().to_vec())),
     other => Err(anyhow!("Expected a scalar mismatch value from Lua, got {}", other.type_name())),
   }
 }
@@ -770,15 +759,15 @@ fn lua_value_to_content_mismatches(path: &str, value: Value) -> anyhow::Result<V
       let mismatch_field: Option<String> = table.get("mismatch")?;
       if let Some(mismatch) = mismatch_field {
         // expected/actual can reasonably be non-string Lua values (e.g. a numeric or boolean
-        // claim value), so stringify whatever's there rather than requiring exactly a string.
-        let expected = lua_scalar_to_string(table.get("expected")?)?;
-        let actual = lua_scalar_to_string(table.get("actual")?)?;
+        // claim value), or binary Lua strings, so take the bytes of whatever's there.
+        let expected = lua_scalar_to_bytes(table.get("expected")?)?;
+        let actual = lua_scalar_to_bytes(table.get("actual")?)?;
         let path_override: Option<String> = table.get("path")?;
         let diff: Option<String> = table.get("diff")?;
         let mismatch_type: Option<String> = table.get("mismatch_type")?;
         Ok(vec![ContentMismatch {
-          expected: expected.map(|s| s.into_bytes()),
-          actual: actual.map(|s| s.into_bytes()),
+          expected,
+          actual,
           mismatch,
           path: path_override.unwrap_or_else(|| path.to_string()),
           diff: diff.unwrap_or_default(),
@@ -882,29 +871,6 @@ fn lua_to_generate_request(
   })
 }
 
-/// The test mode name a Lua script sees. The V1 and V2 `GenerateContentRequest.TestMode` enums
-/// have the same values, so one pair of helpers covers content generation on either interface as
-/// well as field-level generation (which is V2-only).
-fn test_mode_to_str(test_mode: i32) -> &'static str {
-  match generate_content_request::TestMode::try_from(test_mode)
-    .unwrap_or(generate_content_request::TestMode::Unknown)
-  {
-    generate_content_request::TestMode::Consumer => "Consumer",
-    generate_content_request::TestMode::Provider => "Provider",
-    generate_content_request::TestMode::Unknown => "Unknown",
-  }
-}
-
-/// Reverse of [`test_mode_to_str`]. Anything unrecognised (including a missing value) is
-/// `Unknown`, rather than an error - the mode is context for the plugin, not a contract.
-fn str_to_test_mode(test_mode: Option<&str>) -> i32 {
-  match test_mode {
-    Some("Consumer") => generate_content_request::TestMode::Consumer as i32,
-    Some("Provider") => generate_content_request::TestMode::Provider as i32,
-    _ => generate_content_request::TestMode::Unknown as i32,
-  }
-}
-
 // ---- MatchField / GenerateField <-> Lua ----
 
 /// Converts a single field value to a plain Lua value, following the convention message metadata
@@ -987,54 +953,6 @@ fn lua_to_typed_values(
   Ok((r#type, values))
 }
 
-/// V2's `PluginConfiguration` carries the same two `Struct` fields as the V1 message the rest of
-/// this module converts, so the field-level requests reuse those conversions rather than
-/// duplicating them. The conversion is needed at all only because field-level operations exist
-/// solely on the V2 interface (proposal 006).
-fn v2_plugin_configuration_to_v1(
-  config: &Option<proto_v2::PluginConfiguration>
-) -> Option<PluginConfiguration> {
-  config.as_ref().map(|config| PluginConfiguration {
-    interaction_configuration: config.interaction_configuration.clone(),
-    pact_configuration: config.pact_configuration.clone(),
-  })
-}
-
-/// Reverse of [`v2_plugin_configuration_to_v1`].
-fn v1_plugin_configuration_to_v2(
-  config: Option<PluginConfiguration>
-) -> Option<proto_v2::PluginConfiguration> {
-  config.map(|config| proto_v2::PluginConfiguration {
-    interaction_configuration: config.interaction_configuration,
-    pact_configuration: config.pact_configuration,
-  })
-}
-
-/// See [`v2_plugin_configuration_to_v1`] - `ContentMismatch` is likewise identical between the two
-/// interfaces, so the existing mismatch conversions are reused for the V2-only field messages.
-fn v1_content_mismatch_to_v2(mismatch: ContentMismatch) -> proto_v2::ContentMismatch {
-  proto_v2::ContentMismatch {
-    expected: mismatch.expected,
-    actual: mismatch.actual,
-    mismatch: mismatch.mismatch,
-    path: mismatch.path,
-    diff: mismatch.diff,
-    mismatch_type: mismatch.mismatch_type,
-  }
-}
-
-/// Reverse of [`v1_content_mismatch_to_v2`].
-fn v2_content_mismatch_to_v1(mismatch: &proto_v2::ContentMismatch) -> ContentMismatch {
-  ContentMismatch {
-    expected: mismatch.expected.clone(),
-    actual: mismatch.actual.clone(),
-    mismatch: mismatch.mismatch.clone(),
-    path: mismatch.path.clone(),
-    diff: mismatch.diff.clone(),
-    mismatch_type: mismatch.mismatch_type.clone(),
-  }
-}
-
 /// Builds the request table a plugin's own `match_field(request)` function receives.
 fn match_field_request_to_lua(lua: &Lua, request: &proto_v2::MatchFieldRequest) -> mlua::Result<Table> {
   let table = lua.create_table()?;
@@ -1269,19 +1187,6 @@ fn interaction_contents_to_lua(lua: &Lua, contents: &proto_v2::InteractionConten
   Ok(table)
 }
 
-/// V1 `InteractionData` and V2 `InteractionData` are structurally identical (same wire format);
-/// converting via an encode/decode round trip lets the rest of this module deal with a single
-/// (V1) type, matching the approach `plugin_manager.rs` uses in the other direction (see
-/// `to_proto_v2_interaction_data`). Returns an error rather than panicking if the round trip
-/// ever fails (it shouldn't, given the identical wire format, but this data originates from a
-/// caller-supplied gRPC request, so a decode failure should be a recoverable error, not a
-/// crash).
-fn v2_interaction_data_to_v1(data: &proto_v2::InteractionData) -> anyhow::Result<InteractionData> {
-  use prost::Message;
-  InteractionData::decode(data.encode_to_vec().as_slice())
-    .map_err(|err| anyhow!("Failed to convert V2 InteractionData to V1 - {}", err))
-}
-
 /// Converts request/response metadata to a Lua table. Each value is either a plain Lua value
 /// (JSON-like, for a non-binary `MetadataValue`) or a `{ binary = <lua string> }` wrapper table
 /// (for a binary `MetadataValue`), so a Lua script can tell the two apart.
@@ -1441,14 +1346,14 @@ fn lua_to_verification_result_item(value: Value) -> anyhow::Result<VerificationR
     Value::Table(table) => {
       let mismatch: Option<String> = table.get("mismatch")?;
       let path: Option<String> = table.get("path")?;
-      let expected = lua_scalar_to_string(table.get("expected")?)?;
-      let actual = lua_scalar_to_string(table.get("actual")?)?;
+      let expected = lua_scalar_to_bytes(table.get("expected")?)?;
+      let actual = lua_scalar_to_bytes(table.get("actual")?)?;
       let diff: Option<String> = table.get("diff")?;
       let mismatch_type: Option<String> = table.get("mismatch_type")?;
       Ok(VerificationResultItem {
         result: Some(verification_result_item::Result::Mismatch(ContentMismatch {
-          expected: expected.map(|s| s.into_bytes()),
-          actual: actual.map(|s| s.into_bytes()),
+          expected,
+          actual,
           mismatch: mismatch.unwrap_or_default(),
           path: path.unwrap_or_default(),
           diff: diff.unwrap_or_default(),
@@ -1500,14 +1405,85 @@ fn lua_to_verify_interaction_response(lua: &Lua, table: Table) -> anyhow::Result
   })
 }
 
+/// Calls the script's `match_field(request)` global on a VM the caller has acquired.
+async fn call_match_field(
+  lua: &Lua,
+  request: &proto_v2::MatchFieldRequest,
+) -> anyhow::Result<proto_v2::MatchFieldResponse> {
+  let match_fn: Function = lua
+    .globals()
+    .get("match_field")
+    .map_err(|_| anyhow!("Lua plugin does not define a global 'match_field' function"))?;
+  let request_table = match_field_request_to_lua(lua, request)?;
+  let result: Table = match_fn
+    .call_async(request_table)
+    .await
+    .map_err(|err| anyhow!("Lua match_field() function failed - {}", err))?;
+  lua_to_match_field_response(result, &request.path)
+}
+
+/// Calls the script's `generate_field(request)` global on a VM the caller has acquired.
+async fn call_generate_field(
+  lua: &Lua,
+  request: &proto_v2::GenerateFieldRequest,
+) -> anyhow::Result<proto_v2::GenerateFieldResponse> {
+  let generate_fn: Function = lua
+    .globals()
+    .get("generate_field")
+    .map_err(|_| anyhow!("Lua plugin does not define a global 'generate_field' function"))?;
+  let request_table = generate_field_request_to_lua(lua, request)?;
+  let result: Table = generate_fn
+    .call_async(request_table)
+    .await
+    .map_err(|err| anyhow!("Lua generate_field() function failed - {}", err))?;
+  lua_to_generate_field_response(lua, result)
+}
+
+fn call_update_catalogue(lua: &Lua, request: &Catalogue) -> anyhow::Result<()> {
+  let update_fn: Option<Function> = lua.globals().get("update_catalogue")?;
+  if let Some(update_fn) = update_fn {
+    let table = lua.create_table()?;
+    for entry in &request.catalogue {
+      let entry_table = lua.create_table()?;
+      // An entry type this driver doesn't understand is skipped rather than passed to the
+      // script as some other type it isn't - see `register_plugin_entries`.
+      let Some(entry_type) = CatalogueEntryType::from_proto_value(entry.r#type) else {
+        warn!("Not passing catalogue entry '{}' to the plugin: {} is not a catalogue entry type this driver understands",
+          entry.key, entry.r#type);
+        continue;
+      };
+      entry_table.set("entryType", entry_type.as_proto_name())?;
+      entry_table.set("key", entry.key.clone())?;
+      entry_table.set("values", entry.values.clone())?;
+      table.push(entry_table)?;
+    }
+    update_fn
+      .call::<()>(table)
+      .map_err(|err| anyhow!("Lua update_catalogue() function failed - {}", err))?;
+  }
+  Ok(())
+}
+
 #[async_trait]
 impl PactPluginRpc for LuaPactPlugin {
   async fn init_plugin(&mut self, request: PluginInitRequest) -> anyhow::Result<PluginInitResponse> {
-    let lua = self.runtime.lock().await;
-    let catalogue = call_init(&lua, &request.implementation, &request.version)?;
+    // Every VM is initialised, so each has whatever state the script sets up in `init`; they
+    // are all loaded from the same script, so the first one's result stands for them all
+    let vms = self.vms.acquire_all().await;
+    let result = call_init(&vms, &request.implementation, &request.version, &request.host_capabilities)?;
+    for lua in vms.iter().skip(1) {
+      call_init(lua, &request.implementation, &request.version, &request.host_capabilities)?;
+    }
+
+    // Batching costs a Lua plugin nothing (the driver just loops over the script's own
+    // `match_field`/`generate_field` on one VM), so it is always advertised.
+    let mut plugin_capabilities = result.capabilities;
+    if !plugin_capabilities.iter().any(|capability| capability == FIELD_BATCH_CAPABILITY) {
+      plugin_capabilities.push(FIELD_BATCH_CAPABILITY.to_string());
+    }
     Ok(PluginInitResponse {
-      catalogue,
-      plugin_capabilities: vec![],
+      catalogue: result.catalogue,
+      plugin_capabilities,
     })
   }
 }
@@ -1530,7 +1506,7 @@ impl PluginInstance for LuaPactPlugin {
     &self,
     request: CompareContentsRequest,
   ) -> anyhow::Result<CompareContentsResponse> {
-    let lua = self.runtime.lock().await;
+    let lua = self.vms.acquire().await;
     let match_fn: Function = lua
       .globals()
       .get("match_contents")
@@ -1547,7 +1523,7 @@ impl PluginInstance for LuaPactPlugin {
     &self,
     request: ConfigureInteractionRequest,
   ) -> anyhow::Result<ConfigureInteractionResponse> {
-    let lua = self.runtime.lock().await;
+    let lua = self.vms.acquire().await;
     let configure_fn: Function = lua
       .globals()
       .get("configure_interaction")
@@ -1566,7 +1542,7 @@ impl PluginInstance for LuaPactPlugin {
     &self,
     request: GenerateContentRequest,
   ) -> anyhow::Result<GenerateContentResponse> {
-    let lua = self.runtime.lock().await;
+    let lua = self.vms.acquire().await;
     let generate_fn: Option<Function> = lua.globals().get("generate_content")?;
     match generate_fn {
       None => Ok(GenerateContentResponse {
@@ -1607,18 +1583,8 @@ impl PluginInstance for LuaPactPlugin {
     &self,
     request: proto_v2::MatchFieldRequest,
   ) -> anyhow::Result<proto_v2::MatchFieldResponse> {
-    let lua = self.runtime.lock().await;
-    let match_fn: Function = lua
-      .globals()
-      .get("match_field")
-      .map_err(|_| anyhow!("Lua plugin does not define a global 'match_field' function"))?;
-    let path = request.path.clone();
-    let request_table = match_field_request_to_lua(&lua, &request)?;
-    let result: Table = match_fn
-      .call_async(request_table)
-      .await
-      .map_err(|err| anyhow!("Lua match_field() function failed - {}", err))?;
-    lua_to_match_field_response(result, &path)
+    let lua = self.vms.acquire().await;
+    call_match_field(&lua, &request).await
   }
 
   /// See [`LuaPactPlugin::match_field`].
@@ -1626,24 +1592,49 @@ impl PluginInstance for LuaPactPlugin {
     &self,
     request: proto_v2::GenerateFieldRequest,
   ) -> anyhow::Result<proto_v2::GenerateFieldResponse> {
-    let lua = self.runtime.lock().await;
-    let generate_fn: Function = lua
-      .globals()
-      .get("generate_field")
-      .map_err(|_| anyhow!("Lua plugin does not define a global 'generate_field' function"))?;
-    let request_table = generate_field_request_to_lua(&lua, &request)?;
-    let result: Table = generate_fn
-      .call_async(request_table)
-      .await
-      .map_err(|err| anyhow!("Lua generate_field() function failed - {}", err))?;
-    lua_to_generate_field_response(&lua, result)
+    let lua = self.vms.acquire().await;
+    call_generate_field(&lua, &request).await
+  }
+
+  /// Runs each request through the script's own `match_field` on one VM,
+  /// acquired once for the whole batch. A failure for one field is reported in that field's response
+  /// rather than failing its neighbours.
+  async fn match_fields_batch(
+    &self,
+    request: proto_v2::MatchFieldsBatchRequest,
+  ) -> anyhow::Result<proto_v2::MatchFieldsBatchResponse> {
+    let lua = self.vms.acquire().await;
+    let mut responses = Vec::with_capacity(request.requests.len());
+    for field_request in &request.requests {
+      let response = call_match_field(&lua, field_request).await
+        .unwrap_or_else(|err| proto_v2::MatchFieldResponse { error: err.to_string(), mismatches: vec![] });
+      responses.push(response);
+    }
+    Ok(proto_v2::MatchFieldsBatchResponse { error: String::new(), responses })
+  }
+
+  /// See [`LuaPactPlugin::match_fields_batch`].
+  async fn generate_fields_batch(
+    &self,
+    request: proto_v2::GenerateFieldsBatchRequest,
+  ) -> anyhow::Result<proto_v2::GenerateFieldsBatchResponse> {
+    let lua = self.vms.acquire().await;
+    let mut responses = Vec::with_capacity(request.requests.len());
+    for field_request in &request.requests {
+      let response = call_generate_field(&lua, field_request).await
+        .unwrap_or_else(|err| proto_v2::GenerateFieldResponse { error: err.to_string(), value: None });
+      responses.push(response);
+    }
+    Ok(proto_v2::GenerateFieldsBatchResponse { error: String::new(), responses })
   }
 
+  /// A script keeps its running mock servers in its globals, so this and the other mock server
+  /// calls all run on the primary VM (see [`LuaVmPool::acquire_primary`]).
   async fn start_mock_server(
     &self,
     request: StartMockServerRequest,
   ) -> anyhow::Result<StartMockServerResponse> {
-    let lua = self.runtime.lock().await;
+    let lua = self.vms.acquire_primary().await;
     let start_fn: Function = lua
       .globals()
       .get("start_mock_server")
@@ -1664,7 +1655,7 @@ impl PluginInstance for LuaPactPlugin {
     &self,
     request: proto_v2::StartMockServerRequest,
   ) -> anyhow::Result<StartMockServerResponse> {
-    let lua = self.runtime.lock().await;
+    let lua = self.vms.acquire_primary().await;
     let start_fn: Function = lua
       .globals()
       .get("start_mock_server")
@@ -1689,7 +1680,7 @@ impl PluginInstance for LuaPactPlugin {
     &self,
     request: ShutdownMockServerRequest,
   ) -> anyhow::Result<ShutdownMockServerResponse> {
-    let lua = self.runtime.lock().await;
+    let lua = self.vms.acquire_primary().await;
     let shutdown_fn: Function = lua
       .globals()
       .get("shutdown_mock_server")
@@ -1708,7 +1699,7 @@ impl PluginInstance for LuaPactPlugin {
     &self,
     request: MockServerRequest,
   ) -> anyhow::Result<MockServerResults> {
-    let lua = self.runtime.lock().await;
+    let lua = self.vms.acquire_primary().await;
     let results_fn: Function = lua
       .globals()
       .get("get_mock_server_results")
@@ -1719,11 +1710,13 @@ impl PluginInstance for LuaPactPlugin {
     lua_to_mock_server_results(result)
   }
 
+  /// Like the mock server calls, preparing and verifying an interaction run on the primary VM so
+  /// the verification sees what the preparation left behind.
   async fn prepare_interaction_for_verification(
     &self,
     request: VerificationPreparationRequest,
   ) -> anyhow::Result<VerificationPreparationResponse> {
-    let lua = self.runtime.lock().await;
+    let lua = self.vms.acquire_primary().await;
     let prepare_fn: Function = lua.globals().get("prepare_interaction_for_verification").map_err(|_| {
       anyhow!("Lua plugin does not define a global 'prepare_interaction_for_verification' function")
     })?;
@@ -1741,7 +1734,7 @@ impl PluginInstance for LuaPactPlugin {
     &self,
     request: proto_v2::VerificationPreparationRequest,
   ) -> anyhow::Result<VerificationPreparationResponse> {
-    let lua = self.runtime.lock().await;
+    let lua = self.vms.acquire_primary().await;
     let prepare_fn: Function = lua.globals().get("prepare_interaction_for_verification").map_err(|_| {
       anyhow!("Lua plugin does not define a global 'prepare_interaction_for_verification' function")
     })?;
@@ -1761,7 +1754,7 @@ impl PluginInstance for LuaPactPlugin {
     &self,
     request: VerifyInteractionRequest,
   ) -> anyhow::Result<VerifyInteractionResponse> {
-    let lua = self.runtime.lock().await;
+    let lua = self.vms.acquire_primary().await;
     let verify_fn: Function = lua
       .globals()
       .get("verify_interaction")
@@ -1781,7 +1774,7 @@ impl PluginInstance for LuaPactPlugin {
     &self,
     request: proto_v2::VerifyInteractionRequest,
   ) -> anyhow::Result<VerifyInteractionResponse> {
-    let lua = self.runtime.lock().await;
+    let lua = self.vms.acquire_primary().await;
     let verify_fn: Function = lua
       .globals()
       .get("verify_interaction")
@@ -1803,27 +1796,10 @@ impl PluginInstance for LuaPactPlugin {
   }
 
   async fn update_catalogue(&self, request: Catalogue) -> anyhow::Result<()> {
-    let lua = self.runtime.lock().await;
-    let update_fn: Option<Function> = lua.globals().get("update_catalogue")?;
-    if let Some(update_fn) = update_fn {
-      let table = lua.create_table()?;
-      for entry in &request.catalogue {
-        let entry_table = lua.create_table()?;
-        // An entry type this driver doesn't understand is skipped rather than passed to the
-        // script as some other type it isn't - see `register_plugin_entries`.
-        let Some(entry_type) = CatalogueEntryType::from_proto_value(entry.r#type) else {
-          warn!("Not passing catalogue entry '{}' to the plugin: {} is not a catalogue entry type this driver understands",
-            entry.key, entry.r#type);
-          continue;
-        };
-        entry_table.set("entryType", entry_type.as_proto_name())?;
-        entry_table.set("key", entry.key.clone())?;
-        entry_table.set("values", entry.values.clone())?;
-        table.push(entry_table)?;
-      }
-      update_fn
-        .call::<()>(table)
-        .map_err(|err| anyhow!("Lua update_catalogue() function failed - {}", err))?;
+    // Every VM gets the update, so a call sees the same catalogue whichever VM it runs on
+    let vms = self.vms.acquire_all().await;
+    for lua in vms.iter() {
+      call_update_catalogue(lua, &request)?;
     }
     Ok(())
   }
@@ -1865,8 +1841,8 @@ mod tests {
 
   const PRIVATE_KEY: &str = include_str!("../tests/fixtures/jwt-test-key.pem");
 
-  #[test]
-  fn loads_pure_lua_packages_from_a_configured_luarocks_directory() {
+  #[tokio::test]
+  async fn loads_pure_lua_packages_from_a_configured_luarocks_directory() {
     let rocks_root = tempdir::TempDir::new("luarocks-test").unwrap();
     let lua_dir = rocks_root.path().join("share").join("lua").join(LUAROCKS_LUA_VERSION);
     std::fs::create_dir_all(&lua_dir).unwrap();
@@ -1905,13 +1881,13 @@ mod tests {
     };
 
     let plugin = start_lua_plugin(&manifest, "test-instance".to_string()).unwrap();
-    let lua = plugin.runtime.blocking_lock();
+    let lua = plugin.vms.acquire().await;
     let result: String = lua.globals().get("GREETER_RESULT").unwrap();
     assert_eq!(result, "hello from luarocks");
   }
 
-  #[test]
-  fn loads_a_vendored_directory_style_module_from_the_plugin_directory() {
+  #[tokio::test]
+  async fn loads_a_vendored_directory_style_module_from_the_plugin_directory() {
     let plugin_dir = tempdir::TempDir::new("lua-plugin-test").unwrap();
     let module_dir = plugin_dir.path().join("greeter");
     std::fs::create_dir_all(&module_dir).unwrap();
@@ -1942,13 +1918,13 @@ mod tests {
     };
 
     let plugin = start_lua_plugin(&manifest, "test-instance".to_string()).unwrap();
-    let lua = plugin.runtime.blocking_lock();
+    let lua = plugin.vms.acquire().await;
     let result: String = lua.globals().get("GREETER_RESULT").unwrap();
     assert_eq!(result, "hello from a vendored module");
   }
 
-  #[test]
-  fn loads_a_vendored_module_when_the_entry_point_is_in_a_subdirectory() {
+  #[tokio::test]
+  async fn loads_a_vendored_module_when_the_entry_point_is_in_a_subdirectory() {
     // package.path must be rooted at the plugin directory, not the entry point script's own
     // directory, so a vendored module sitting next to a nested entry point still resolves -
     // matching the JVM driver, which always uses `manifest.pluginDir`.
@@ -1982,7 +1958,7 @@ mod tests {
     };
 
     let plugin = start_lua_plugin(&manifest, "test-instance".to_string()).unwrap();
-    let lua = plugin.runtime.blocking_lock();
+    let lua = plugin.vms.acquire().await;
     let result: String = lua.globals().get("GREETER_RESULT").unwrap();
     assert_eq!(result, "hello from the plugin root");
   }
@@ -2058,8 +2034,8 @@ mod tests {
   async fn loads_the_jwt_plugin_and_runs_the_init_function() {
     let manifest = jwt_manifest();
     let plugin = start_lua_plugin(&manifest, "test-instance".to_string()).unwrap();
-    let lua = plugin.runtime.lock().await;
-    let entries = call_init(&lua, "test", "0.0.0").unwrap();
+    let lua = plugin.vms.acquire().await;
+    let entries = call_init(&lua, "test", "0.0.0", &[]).unwrap().catalogue;
     assert_eq!(entries.len(), 2);
     assert_eq!(entries[0].key, "jwt");
     assert_eq!(entries[0].r#type, catalogue_entry::EntryType::ContentMatcher as i32);
@@ -2086,8 +2062,8 @@ mod tests {
 
     let manifest = lua_manifest(plugin_dir.path(), "field-entries-test");
     let plugin = start_lua_plugin(&manifest, "test-instance".to_string()).unwrap();
-    let lua = plugin.runtime.lock().await;
-    let entries = call_init(&lua, "test", "0.0.0").unwrap();
+    let lua = plugin.vms.acquire().await;
+    let entries = call_init(&lua, "test", "0.0.0", &[]).unwrap().catalogue;
 
     assert_eq!(entries.len(), 2);
     assert_eq!(entries[0].key, "creditcard");
@@ -2110,12 +2086,53 @@ mod tests {
 
     let manifest = lua_manifest(plugin_dir.path(), "bad-entry-type-test");
     let plugin = start_lua_plugin(&manifest, "test-instance".to_string()).unwrap();
-    let lua = plugin.runtime.lock().await;
+    let lua = plugin.vms.acquire().await;
 
-    let error = call_init(&lua, "test", "0.0.0").unwrap_err().to_string();
+    let error = call_init(&lua, "test", "0.0.0", &[]).unwrap_err().to_string();
     assert!(error.contains("NOT_AN_ENTRY_TYPE"), "unexpected error: {}", error);
   }
 
+  #[tokio::test]
+  async fn init_can_declare_capabilities_based_on_the_host_capabilities() {
+    let plugin_dir = tempdir::TempDir::new("lua-plugin-test").unwrap();
+    std::fs::write(
+      plugin_dir.path().join("entry.lua"),
+      r#"
+        function init(implementation, version, host_capabilities)
+          local capabilities = {}
+          for _, capability in ipairs(host_capabilities) do
+            if capability == "interaction/message" then
+              table.insert(capabilities, capability)
+            end
+          end
+          return {
+            catalogue = { { entryType = "TRANSPORT", key = "capabilities-test" } },
+            capabilities = capabilities
+          }
+        end
+      "#,
+    ).unwrap();
+
+    let manifest = lua_manifest(plugin_dir.path(), "capabilities-test");
+    let mut plugin = start_lua_plugin(&manifest, "test-instance".to_string()).unwrap();
+    let response = plugin.init_plugin(PluginInitRequest {
+      implementation: "test".to_string(),
+      version: "0.0.0".to_string(),
+      host_capabilities: vec!["interaction/request-response".to_string(), "interaction/message".to_string()],
+      plugin_instance_id: "test-instance".to_string()
+    }).await.unwrap();
+
+    assert_eq!(response.catalogue.len(), 1);
+    assert_eq!(response.catalogue[0].r#type, CatalogueEntryType::TRANSPORT.to_proto_value());
+    assert_eq!(response.plugin_capabilities, vec!["interaction/message", FIELD_BATCH_CAPABILITY]);
+
+    plugin.set_plugin_capabilities(response.plugin_capabilities);
+    let interaction_type = pact_models::v4::V4InteractionType::Asynchronous_Messages;
+    assert!(crate::plugin_models::check_interaction_type_capability(&plugin, interaction_type).is_ok());
+    let interaction_type = pact_models::v4::V4InteractionType::Synchronous_HTTP;
+    assert!(crate::plugin_models::check_interaction_type_capability(&plugin, interaction_type).is_err());
+  }
+
   #[tokio::test]
   async fn configure_interaction_then_match_contents_round_trip() {
     let manifest = jwt_manifest();
@@ -2443,7 +2460,8 @@ mod tests {
           return {
             mismatches = {
               ["claims:exp"] = { expected = 123, actual = 456, mismatch = "exp differs", path = "claims:exp" },
-              ["claims:verified"] = { expected = true, actual = false, mismatch = "verified differs", path = "claims:verified" }
+              ["claims:verified"] = { expected = true, actual = false, mismatch = "verified differs", path = "claims:verified" },
+              ["claims:sig"] = { expected = "\x08\x96\x01", actual = "\xff", mismatch = "sig differs", path = "claims:sig" }
             }
           }
         end
@@ -2481,6 +2499,10 @@ mod tests {
     let verified_mismatch = &response.results["claims:verified"].mismatches[0];
     assert_eq!(verified_mismatch.expected.as_deref(), Some("true".as_bytes()));
     assert_eq!(verified_mismatch.actual.as_deref(), Some("false".as_bytes()));
+
+    let sig_mismatch = &response.results["claims:sig"].mismatches[0];
+    assert_eq!(sig_mismatch.expected.as_deref(), Some([0x08, 0x96, 0x01].as_slice()));
+    assert_eq!(sig_mismatch.actual.as_deref(), Some([0xFF].as_slice()));
   }
 
   fn lua_manifest(plugin_dir: &std::path::Path, name: &str) -> PactPluginManifest {
@@ -2769,7 +2791,7 @@ mod tests {
     let response = plugin.start_mock_server_v2(request).await.unwrap();
     assert!(matches!(response.response.unwrap(), start_mock_server_response::Response::Details(_)));
 
-    let lua = plugin.runtime.lock().await;
+    let lua = plugin.vms.acquire().await;
     let captured: Table = lua.globals().get("START_MOCK_SERVER_REQUEST").unwrap();
     let interactions: Table = captured.get("interactions").unwrap();
     let first: Table = interactions.get(1).unwrap();
@@ -2842,7 +2864,7 @@ mod tests {
       verification_preparation_response::Response::InteractionData(_)
     ));
 
-    let lua = plugin.runtime.lock().await;
+    let lua = plugin.vms.acquire().await;
     let captured: Table = lua.globals().get("PREPARE_REQUEST").unwrap();
     let interaction_contents: Table = captured.get("interaction_contents").unwrap();
     assert_eq!(interaction_contents.get::<String>("provider").unwrap(), "test-provider");
@@ -2891,7 +2913,7 @@ mod tests {
       other => panic!("expected a verification result, got {:?}", other),
     }
 
-    let lua = plugin.runtime.lock().await;
+    let lua = plugin.vms.acquire().await;
     let captured: Table = lua.globals().get("VERIFY_REQUEST").unwrap();
     let interaction_data: Table = captured.get("interaction_data").unwrap();
     let metadata: Table = interaction_data.get("metadata").unwrap();
@@ -2942,7 +2964,7 @@ mod tests {
       verify_interaction_response::Response::Result(_)
     ));
 
-    let lua = plugin.runtime.lock().await;
+    let lua = plugin.vms.acquire().await;
     let captured: Table = lua.globals().get("VERIFY_REQUEST").unwrap();
     let interaction_data: Table = captured.get("interaction_data").unwrap();
     let body: Table = interaction_data.get("body").unwrap();
@@ -3069,8 +3091,8 @@ mod tests {
   async fn creditcard_plugin_registers_a_matcher_and_a_generator_under_the_same_key() {
     let manifest = creditcard_manifest();
     let plugin = start_lua_plugin(&manifest, "test-instance".to_string()).unwrap();
-    let lua = plugin.runtime.lock().await;
-    let entries = call_init(&lua, "test", "0.0.0").unwrap();
+    let lua = plugin.vms.acquire().await;
+    let entries = call_init(&lua, "test", "0.0.0", &[]).unwrap().catalogue;
 
     assert_eq!(entries.len(), 2);
     assert_eq!(entries[0].key, "creditcard");
@@ -3203,6 +3225,47 @@ mod tests {
     (plugin_dir, plugin)
   }
 
+  #[tokio::test]
+  async fn match_fields_batch_runs_each_request_through_match_field() {
+    let (_dir, plugin) = start_field_plugin(
+      "match-fields-batch-test",
+      r#"
+        function match_field(request)
+          if request.actual == "boom" then
+            error("could not check " .. request.path)
+          end
+          if request.expected ~= request.actual then
+            return { mismatches = { "Expected " .. request.expected .. " but got " .. request.actual } }
+          end
+          return {}
+        end
+      "#,
+    );
+
+    let response = plugin
+      .match_fields_batch(proto_v2::MatchFieldsBatchRequest {
+        requests: vec![
+          creditcard_match_request(None, "4111111111111111", "4111111111111111"),
+          creditcard_match_request(None, "4111111111111111", "boom"),
+          creditcard_match_request(None, "4111111111111111", "4012888888881881"),
+        ]
+      })
+      .await
+      .unwrap();
+
+    // A failure for one field stays with that field rather than failing the whole batch
+    assert_eq!(response.error, "");
+    assert_eq!(response.responses.len(), 3);
+    assert!(response.responses[0].error.is_empty() && response.responses[0].mismatches.is_empty());
+    assert!(response.responses[1].error.contains("could not check $.card.number"),
+      "unexpected error: {}", response.responses[1].error);
+    assert_eq!(response.responses[2].mismatches.len(), 1);
+    assert_eq!(
+      response.responses[2].mismatches[0].mismatch,
+      "Expected 4111111111111111 but got 4012888888881881"
+    );
+  }
+
   #[tokio::test]
   async fn each_field_value_type_survives_the_round_trip_through_lua() {
     use proto_v2::field_value::Value as FieldValue;
@@ -3307,6 +3370,178 @@ mod tests {
     );
   }
 
+  #[tokio::test]
+  async fn a_sandboxed_plugin_requires_vendored_modules_but_nothing_outside_the_plugin_directory() {
+    let root = tempdir::TempDir::new("lua-plugin-test").unwrap();
+    let plugin_dir = root.path().join("plugin");
+    std::fs::create_dir_all(&plugin_dir).unwrap();
+    std::fs::write(root.path().join("outside.lua"), "return 'outside'").unwrap();
+    std::fs::write(
+      plugin_dir.join("greeter.lua"),
+      r#"return { hello = function() return "hello from a vendored module" end }"#,
+    ).unwrap();
+    std::fs::write(
+      plugin_dir.join("entry.lua"),
+      r#"
+        GREETER_RESULT = require("greeter").hello()
+        OUTSIDE_LOADED = pcall(require, "outside")
+        OUTSIDE_ON_PATH = pcall(function() package.path = package.path .. ";../?.lua" end)
+      "#,
+    ).unwrap();
+    let mut manifest = lua_manifest(&plugin_dir, "sandboxed-require-test");
+    manifest.plugin_config.insert("sandbox".to_string(), serde_json::json!(true));
+
+    let plugin = start_lua_plugin(&manifest, "test-instance".to_string()).unwrap();
+    let lua = plugin.vms.acquire().await;
+    let result: String = lua.globals().get("GREETER_RESULT").unwrap();
+    let outside_loaded: bool = lua.globals().get("OUTSIDE_LOADED").unwrap();
+    let outside_on_path: bool = lua.globals().get("OUTSIDE_ON_PATH").unwrap();
+    assert_eq!(result, "hello from a vendored module");
+    assert!(!outside_loaded);
+    assert!(!outside_on_path);
+  }
+
+  #[tokio::test]
+  async fn a_sandboxed_plugin_that_runs_away_fails_the_call_and_recovers_for_the_next() {
+    let plugin_dir = tempdir::TempDir::new("lua-plugin-test").unwrap();
+    std::fs::write(plugin_dir.path().join("entry.lua"), r#"
+      function match_field(request)
+        if request.actual == "spin" then
+          while true do end
+        end
+        if io ~= nil then
+          return { mismatches = { "io should not be loaded" } }
+        end
+        return {}
+      end
+    "#).unwrap();
+    let mut manifest = lua_manifest(plugin_dir.path(), "sandboxed-field-test");
+    manifest.plugin_config.insert("sandbox".to_string(), serde_json::json!({ "instructionLimit": 100000 }));
+    let plugin = start_lua_plugin(&manifest, "test-instance".to_string()).unwrap();
+
+    let error = plugin.match_field(creditcard_match_request(None, "4111111111111111", "spin")).await
+      .expect_err("expected the runaway call to be aborted");
+    assert!(error.to_string().contains("instruction limit"), "unexpected error: {}", error);
+
+    // The budget is per call, so the next call starts afresh
+    let response = plugin
+      .match_field(creditcard_match_request(None, "4111111111111111", "4111111111111111"))
+      .await
+      .unwrap();
+    assert!(response.error.is_empty() && response.mismatches.is_empty(), "unexpected response: {:?}", response);
+  }
+
+  #[tokio::test]
+  async fn a_plugin_script_can_use_the_pact_crypto_library() {
+    let (_dir, plugin) = start_field_plugin(
+      "pact-crypto-test",
+      r#"
+        function match_field(request)
+          local digest = pact.encoding.hex_encode(pact.crypto.sha256(request.actual))
+          if digest ~= request.expected then
+            return { mismatches = { "Expected a SHA-256 of " .. request.expected .. " but got " .. digest } }
+          end
+          return {}
+        end
+      "#,
+    );
+
+    let response = plugin
+      .match_field(creditcard_match_request(None, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad", "abc"))
+      .await
+      .unwrap();
+    assert!(response.error.is_empty() && response.mismatches.is_empty(), "unexpected response: {:?}", response);
+  }
+
+  #[tokio::test]
+  async fn a_pooled_plugin_initialises_every_vm_and_runs_calls_on_a_free_one() {
+    let plugin_dir = tempdir::TempDir::new("lua-plugin-test").unwrap();
+    std::fs::write(plugin_dir.path().join("entry.lua"), r#"
+      function init(implementation, version)
+        initialised_by = implementation
+        return {}
+      end
+
+      function match_field(request)
+        if initialised_by == nil then
+          return { mismatches = { "init was not called on this VM" } }
+        end
+        return {}
+      end
+    "#).unwrap();
+    let mut manifest = lua_manifest(plugin_dir.path(), "pooled-field-test");
+    manifest.plugin_config.insert("vmConcurrency".to_string(), serde_json::json!("parallel"));
+    manifest.plugin_config.insert("vmPoolSize".to_string(), serde_json::json!(3));
+    let mut plugin = start_lua_plugin(&manifest, "test-instance".to_string()).unwrap();
+
+    plugin.init_plugin(PluginInitRequest {
+      implementation: "pool-test".to_string(),
+      version: "0.0.0".to_string(),
+      host_capabilities: vec![],
+      plugin_instance_id: "test-instance".to_string()
+    }).await.unwrap();
+    let vms = plugin.vms.acquire_all().await;
+    for lua in vms.iter() {
+      assert_eq!(lua.globals().get::<String>("initialised_by").unwrap(), "pool-test");
+    }
+    drop(vms);
+
+    // With two VMs busy, a call still gets the third rather than waiting
+    let _busy = (plugin.vms.acquire().await, plugin.vms.acquire().await);
+    let response = tokio::time::timeout(
+      std::time::Duration::from_secs(1),
+      plugin.match_field(creditcard_match_request(None, "4111111111111111", "4111111111111111"))
+    ).await
+      .expect("expected the call to run on the free VM")
+      .unwrap();
+    assert!(response.error.is_empty() && response.mismatches.is_empty(), "unexpected response: {:?}", response);
+  }
+
+  #[tokio::test]
+  async fn a_pooled_plugin_runs_the_mock_server_calls_on_one_vm() {
+    let plugin_dir = tempdir::TempDir::new("lua-plugin-test").unwrap();
+    std::fs::write(plugin_dir.path().join("entry.lua"), r#"
+      MOCK_SERVERS = {}
+
+      function start_mock_server(request)
+        MOCK_SERVERS["mock-server-1"] = true
+        return { details = { key = "mock-server-1", port = 12345, address = "127.0.0.1:12345" } }
+      end
+
+      function get_mock_server_results(server_key)
+        if MOCK_SERVERS[server_key] == nil then
+          return { ok = false, results = { { path = "", error = "no mock server " .. server_key } } }
+        end
+        return { ok = true, results = {} }
+      end
+    "#).unwrap();
+    let mut manifest = lua_manifest(plugin_dir.path(), "pooled-mock-server-test");
+    manifest.plugin_config.insert("vmConcurrency".to_string(), serde_json::json!("parallel"));
+    manifest.plugin_config.insert("vmPoolSize".to_string(), serde_json::json!(3));
+    let plugin = start_lua_plugin(&manifest, "test-instance".to_string()).unwrap();
+
+    plugin.start_mock_server(StartMockServerRequest {
+      host_interface: "127.0.0.1".to_string(),
+      port: 8080,
+      .. StartMockServerRequest::default()
+    }).await.unwrap();
+    let results = futures_util::future::join_all((0..6).map(|_| plugin.get_mock_server_results(MockServerRequest {
+      server_key: "mock-server-1".to_string()
+    }))).await;
+
+    for result in results {
+      assert!(result.unwrap().ok, "expected the results call to see the running mock server");
+    }
+    let vms = plugin.vms.acquire_all().await;
+    let with_server = vms.iter()
+      .filter(|lua| lua.globals().get::<Table>("MOCK_SERVERS").unwrap().contains_key("mock-server-1").unwrap())
+      .count();
+    assert_eq!(with_server, 1);
+    drop(vms);
+    let primary = plugin.vms.acquire_primary().await;
+    assert!(primary.globals().get::<Table>("MOCK_SERVERS").unwrap().contains_key("mock-server-1").unwrap());
+  }
+
   fn core_field_matcher_entry(key: &str) -> crate::catalogue_manager::CatalogueEntry {
     crate::catalogue_manager::CatalogueEntry {
       entry_type: crate::catalogue_manager::CatalogueEntryType::MATCHER,
@@ -3344,6 +3579,7 @@ mod tests {
           path: request.path,
           diff: String::new(),
           mismatch_type: request.mismatch_type,
+          structured_diff: vec![],
         }]
       })
     }
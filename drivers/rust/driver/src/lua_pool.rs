@@ -0,0 +1,319 @@
+//! Pool of Lua VMs for a Lua plugin.
+//!
+//! A Lua VM can only run one call at a time, so a plugin with a single VM serialises every call
+//! into it across all the tests running concurrently. A plugin whose script does not depend on
+//! state kept between calls can say so with a `vmConcurrency` key in the manifest's
+//! `pluginConfig`, and set the number of VMs with `vmPoolSize`:
+//!
+//! ```json
+//! "pluginConfig": {
+//!   "vmConcurrency": "parallel",
+//!   "vmPoolSize": 4
+//! }
+//! ```
+//!
+//! - `vmConcurrency` - `shared` (the default) if the script keeps state between calls, so all calls
+//!   share one VM, or `parallel` if calls can run on any VM.
+//! - `vmPoolSize` - the number of VMs of a `parallel` plugin. Defaults to the number of CPUs. A
+//!   `shared` plugin can only have one.
+//!
+//! The driver then loads the entry point into that many VMs and runs each call on whichever one
+//! is free. `init` and `update_catalogue` are called on every VM, so state they set up is seen by
+//! all calls. The calls that start, query and shut down a mock server, and that prepare and verify
+//! an interaction, always run on the same VM (the first one, see [`LuaVmPool::acquire_primary`]),
+//! so a script that keeps its mock servers in a global sees them from each of these calls.
+
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::anyhow;
+use mlua::Lua;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::lua_sandbox::LuaCallBudget;
+use crate::plugin_models::PactPluginManifest;
+
+/// Number of VMs a Lua plugin has unless its manifest says otherwise
+pub(crate) const DEFAULT_LUA_VM_POOL_SIZE: usize = 1;
+
+/// If calls into a Lua plugin can be spread over several VMs, from the `vmConcurrency` key of the
+/// manifest's `pluginConfig`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum LuaVmConcurrency {
+  /// The script keeps state between calls, so every call runs on the same VM
+  Shared,
+  /// The script does not depend on state kept between calls, so a call can run on any VM
+  Parallel
+}
+
+impl LuaVmConcurrency {
+  /// The setting from the manifest, defaulting to [`LuaVmConcurrency::Shared`]
+  pub(crate) fn from_manifest(manifest: &PactPluginManifest) -> anyhow::Result<LuaVmConcurrency> {
+    match manifest.plugin_config.get("vmConcurrency") {
+      None | Some(serde_json::Value::Null) => Ok(LuaVmConcurrency::Shared),
+      Some(value) => match value.as_str() {
+        Some("shared") => Ok(LuaVmConcurrency::Shared),
+        Some("parallel") => Ok(LuaVmConcurrency::Parallel),
+        _ => Err(anyhow!("Lua plugin 'vmConcurrency' must be either 'shared' or 'parallel', got {}", value))
+      }
+    }
+  }
+}
+
+/// The number of VMs from the `vmConcurrency` and `vmPoolSize` keys of the manifest's
+/// `pluginConfig`
+pub(crate) fn pool_size_from_manifest(manifest: &PactPluginManifest) -> anyhow::Result<usize> {
+  let concurrency = LuaVmConcurrency::from_manifest(manifest)?;
+  let size = match manifest.plugin_config.get("vmPoolSize") {
+    None | Some(serde_json::Value::Null) => match concurrency {
+      LuaVmConcurrency::Shared => DEFAULT_LUA_VM_POOL_SIZE,
+      LuaVmConcurrency::Parallel => std::thread::available_parallelism()
+        .map(|threads| threads.get())
+        .unwrap_or(DEFAULT_LUA_VM_POOL_SIZE)
+    },
+    Some(size) => match size.as_u64() {
+      Some(size) if size > 0 => size as usize,
+      _ => return Err(anyhow!("Lua plugin 'vmPoolSize' must be a number of VMs greater than zero, got {}", size))
+    }
+  };
+  if size > 1 && concurrency == LuaVmConcurrency::Shared {
+    return Err(anyhow!(
+      "Lua plugin 'vmPoolSize' is {}, but a plugin can only have more than one VM if 'vmConcurrency' is 'parallel'",
+      size
+    ));
+  }
+  Ok(size)
+}
+
+/// One of a plugin's VMs, with the limits on each call into it if the plugin runs sandboxed
+pub(crate) struct LuaVm {
+  pub(crate) lua: Lua,
+  pub(crate) budget: Option<Arc<LuaCallBudget>>
+}
+
+impl LuaVm {
+  fn start_call(&self) {
+    if let Some(budget) = &self.budget {
+      budget.reset();
+    }
+  }
+}
+
+/// The VMs of a Lua plugin, each loaded from the same entry point. The first VM is the primary
+/// one, which the calls that depend on state kept between calls always run on.
+pub(crate) struct LuaVmPool {
+  primary: tokio::sync::Mutex<LuaVm>,
+  idle: Mutex<Vec<LuaVm>>,
+  available: Semaphore,
+  size: usize
+}
+
+impl LuaVmPool {
+  /// Panics if there are no VMs
+  pub(crate) fn new(vms: Vec<LuaVm>) -> Self {
+    let size = vms.len();
+    let mut vms = vms.into_iter();
+    let primary = vms.next().expect("a Lua plugin needs at least one VM");
+    LuaVmPool {
+      primary: tokio::sync::Mutex::new(primary),
+      idle: Mutex::new(vms.collect()),
+      available: Semaphore::new(size - 1),
+      size
+    }
+  }
+
+  /// Number of VMs in the pool
+  pub(crate) fn size(&self) -> usize {
+    self.size
+  }
+
+  /// Wait for a free VM for a call, starting the call's sandbox budget if it has one
+  pub(crate) async fn acquire(&self) -> LuaVmGuard<'_> {
+    if self.size == 1 {
+      return self.acquire_primary().await;
+    }
+    // Both are cancel safe, so whichever VM is not taken stays free
+    tokio::select! {
+      primary = self.primary.lock() => {
+        primary.start_call();
+        LuaVmGuard { pool: self, primary: Some(primary), vms: vec![], _permit: None }
+      }
+      permit = self.available.acquire() => {
+        // The semaphore is never closed, so acquiring can't fail
+        let permit = permit.expect("Lua VM pool semaphore was closed");
+        let vm = self.take_idle(1).pop().expect("a permit was granted with no idle Lua VM");
+        vm.start_call();
+        LuaVmGuard { pool: self, primary: None, vms: vec![vm], _permit: Some(permit) }
+      }
+    }
+  }
+
+  /// Wait for the primary VM, for a call that has to see the state earlier calls left behind
+  /// (a running mock server, or an interaction prepared for verification)
+  pub(crate) async fn acquire_primary(&self) -> LuaVmGuard<'_> {
+    let primary = self.primary.lock().await;
+    primary.start_call();
+    LuaVmGuard { pool: self, primary: Some(primary), vms: vec![], _permit: None }
+  }
+
+  /// Wait until every VM is free, for a call that has to be made on each of them
+  pub(crate) async fn acquire_all(&self) -> LuaVmGuard<'_> {
+    let primary = self.primary.lock().await;
+    let permit = self.available.acquire_many(self.size as u32 - 1).await
+      .expect("Lua VM pool semaphore was closed");
+    let vms = self.take_idle(self.size - 1);
+    primary.start_call();
+    vms.iter().for_each(LuaVm::start_call);
+    LuaVmGuard { pool: self, primary: Some(primary), vms, _permit: Some(permit) }
+  }
+
+  fn take_idle(&self, count: usize) -> Vec<LuaVm> {
+    let mut idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+    let remaining = idle.len() - count;
+    idle.split_off(remaining)
+  }
+}
+
+/// VMs taken from a [`LuaVmPool`], returned to it when dropped. Derefs to the first (for
+/// [`LuaVmPool::acquire`] and [`LuaVmPool::acquire_primary`], the only) VM.
+pub(crate) struct LuaVmGuard<'a> {
+  pool: &'a LuaVmPool,
+  primary: Option<tokio::sync::MutexGuard<'a, LuaVm>>,
+  vms: Vec<LuaVm>,
+  _permit: Option<SemaphorePermit<'a>>
+}
+
+impl LuaVmGuard<'_> {
+  /// Every VM held by this guard
+  pub(crate) fn iter(&self) -> impl Iterator<Item = &Lua> {
+    self.primary.iter().map(|vm| &vm.lua).chain(self.vms.iter().map(|vm| &vm.lua))
+  }
+}
+
+impl Deref for LuaVmGuard<'_> {
+  type Target = Lua;
+
+  fn deref(&self) -> &Lua {
+    match &self.primary {
+      Some(primary) => &primary.lua,
+      None => &self.vms[0].lua
+    }
+  }
+}
+
+impl Drop for LuaVmGuard<'_> {
+  fn drop(&mut self) {
+    // The VMs go back before the permit is released (fields drop after this), so whoever the
+    // permit goes to next finds them idle
+    let mut idle = self.pool.idle.lock().unwrap_or_else(PoisonError::into_inner);
+    idle.append(&mut self.vms);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use expectest::prelude::*;
+  use maplit::hashmap;
+  use serde_json::json;
+
+  use super::*;
+
+  fn manifest(concurrency: Option<&str>, pool_size: Option<serde_json::Value>) -> PactPluginManifest {
+    let mut plugin_config = hashmap!{};
+    if let Some(concurrency) = concurrency {
+      plugin_config.insert("vmConcurrency".to_string(), json!(concurrency));
+    }
+    if let Some(size) = pool_size {
+      plugin_config.insert("vmPoolSize".to_string(), size);
+    }
+    PactPluginManifest {
+      name: "pool-test".to_string(),
+      plugin_config,
+      .. PactPluginManifest::default()
+    }
+  }
+
+  fn pool(size: usize) -> LuaVmPool {
+    LuaVmPool::new((0..size).map(|index| {
+      let lua = Lua::new();
+      lua.globals().set("vm_index", index).unwrap();
+      LuaVm { lua, budget: None }
+    }).collect())
+  }
+
+  #[test]
+  fn pool_size_from_manifest_test() {
+    let cpus = std::thread::available_parallelism().unwrap().get();
+    expect!(pool_size_from_manifest(&manifest(None, None)).unwrap()).to(be_equal_to(1));
+    expect!(pool_size_from_manifest(&manifest(Some("shared"), Some(json!(1)))).unwrap()).to(be_equal_to(1));
+    expect!(pool_size_from_manifest(&manifest(Some("parallel"), None)).unwrap()).to(be_equal_to(cpus));
+    expect!(pool_size_from_manifest(&manifest(Some("parallel"), Some(json!(4)))).unwrap()).to(be_equal_to(4));
+    expect!(pool_size_from_manifest(&manifest(Some("parallel"), Some(json!(0))))).to(be_err());
+    expect!(pool_size_from_manifest(&manifest(Some("parallel"), Some(json!("4"))))).to(be_err());
+    expect!(pool_size_from_manifest(&manifest(None, Some(json!(4))))).to(be_err());
+    expect!(pool_size_from_manifest(&manifest(Some("shared"), Some(json!(4))))).to(be_err());
+    expect!(pool_size_from_manifest(&manifest(Some("sometimes"), None))).to(be_err());
+  }
+
+  #[tokio::test]
+  async fn concurrent_calls_get_different_vms() {
+    let pool = pool(2);
+
+    let first = pool.acquire().await;
+    let second = pool.acquire().await;
+    let first_index: usize = first.globals().get("vm_index").unwrap();
+    let second_index: usize = second.globals().get("vm_index").unwrap();
+    expect!(first_index).to_not(be_equal_to(second_index));
+
+    // With both VMs busy, a third call has to wait for one to come back
+    expect!(tokio::time::timeout(Duration::from_millis(50), pool.acquire()).await.is_err()).to(be_true());
+    drop(first);
+    let third = tokio::time::timeout(Duration::from_millis(50), pool.acquire()).await.unwrap();
+    let third_index: usize = third.globals().get("vm_index").unwrap();
+    expect!(third_index).to(be_equal_to(first_index));
+  }
+
+  #[tokio::test]
+  async fn acquire_all_waits_for_every_vm() {
+    let pool = pool(3);
+
+    let busy = pool.acquire().await;
+    expect!(tokio::time::timeout(Duration::from_millis(50), pool.acquire_all()).await.is_err()).to(be_true());
+    drop(busy);
+
+    let all = pool.acquire_all().await;
+    let mut indexes = all.iter().map(|lua| lua.globals().get::<usize>("vm_index").unwrap()).collect::<Vec<_>>();
+    indexes.sort();
+    expect!(indexes).to(be_equal_to(vec![0, 1, 2]));
+    drop(all);
+
+    expect!(pool.idle.lock().unwrap().len()).to(be_equal_to(2));
+    expect!(pool.primary.try_lock().is_ok()).to(be_true());
+  }
+
+  #[tokio::test]
+  async fn acquire_primary_always_gets_the_first_vm() {
+    let pool = pool(3);
+
+    let primary = pool.acquire_primary().await;
+    expect!(primary.globals().get::<usize>("vm_index").unwrap()).to(be_equal_to(0));
+
+    // The other calls get the other VMs while the primary one is busy
+    let others = (pool.acquire().await, pool.acquire().await);
+    let mut indexes = vec![
+      others.0.globals().get::<usize>("vm_index").unwrap(),
+      others.1.globals().get::<usize>("vm_index").unwrap()
+    ];
+    indexes.sort();
+    expect!(indexes).to(be_equal_to(vec![1, 2]));
+    drop(others);
+
+    // A second call for the primary VM waits for it, even with the other VMs free
+    expect!(tokio::time::timeout(Duration::from_millis(50), pool.acquire_primary()).await.is_err()).to(be_true());
+    drop(primary);
+    let primary = tokio::time::timeout(Duration::from_millis(50), pool.acquire_primary()).await.unwrap();
+    expect!(primary.globals().get::<usize>("vm_index").unwrap()).to(be_equal_to(0));
+  }
+}
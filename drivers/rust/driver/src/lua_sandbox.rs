@@ -0,0 +1,497 @@
+//! Sandbox for Lua plugins.
+//!
+//! A Lua plugin runs embedded in the driver's own process, so by default nothing stops its script
+//! reading files, spawning processes or spinning forever inside the test process. A plugin can opt
+//! in to running sandboxed with a `sandbox` key in the manifest's `pluginConfig`, either `true`
+//! for the defaults or an object:
+//!
+//! ```json
+//! "pluginConfig": {
+//!   "sandbox": {
+//!     "libraries": ["string", "table", "math"],
+//!     "memoryLimit": 33554432,
+//!     "instructionLimit": 10000000,
+//!     "timeoutMs": 2000
+//!   }
+//! }
+//! ```
+//!
+//! - `libraries` - the standard libraries the script can use, from `coroutine`, `io`, `math`, `os`,
+//!   `package`, `string`, `table` and `utf8`. Defaults to [`DEFAULT_SANDBOX_LIBRARIES`]. Without
+//!   `os`, the script still gets the clock functions (`os.time`, `os.clock`, `os.date` and
+//!   `os.difftime`), and `require('os')` returns the same restricted table. The base library is
+//!   always loaded, minus `dofile` and `loadfile`, and `load` only accepts source text.
+//! - `memoryLimit` - the most memory, in bytes, the VM can allocate. Defaults to
+//!   [`DEFAULT_SANDBOX_MEMORY_LIMIT`].
+//! - `instructionLimit` - the most Lua VM instructions a single call into the plugin can execute.
+//!   Not limited by default.
+//! - `timeoutMs` - the longest a single call into the plugin can run Lua code for. Defaults to the
+//!   call deadline (see [`crate::call_chain::default_deadline_ms`]).
+//!
+//! A call that goes over a limit is aborted with a Lua error, which the driver reports as the call
+//! failing.
+//!
+//! With `package` allowed, `require` only loads Lua modules from the plugin directory and the
+//! LuaRocks directory (see [`restrict_package`]). The system `package.path`, C modules and
+//! `package.loadlib`, `package.searchpath` and `package.searchers` are not available, and the
+//! `package` table is read-only.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use itertools::Itertools;
+use mlua::chunk::ChunkMode;
+use mlua::{Function, HookTriggers, Lua, LuaOptions, MultiValue, StdLib, Table, Variadic, VmState};
+use serde_json::Value;
+
+use crate::plugin_models::PactPluginManifest;
+
+/// Standard libraries a sandboxed plugin can use unless its manifest says otherwise
+pub(crate) const DEFAULT_SANDBOX_LIBRARIES: [&str; 6] = ["coroutine", "math", "package", "string", "table", "utf8"];
+
+/// Most memory, in bytes, a sandboxed plugin's VM can allocate unless its manifest says otherwise
+pub(crate) const DEFAULT_SANDBOX_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// How many instructions run between checks of a call's limits
+const HOOK_INSTRUCTION_INTERVAL: u32 = 1000;
+
+/// Standard libraries a sandboxed plugin can be allowed
+const SANDBOX_LIBRARIES: [&str; 8] = ["coroutine", "io", "math", "os", "package", "string", "table", "utf8"];
+
+/// `os` functions a sandboxed script keeps when the `os` library is not allowed in full
+const SANDBOX_OS_FUNCTIONS: [&str; 4] = ["clock", "date", "difftime", "time"];
+
+/// Sandbox settings for a Lua plugin, read from its manifest
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct LuaSandbox {
+  /// Standard libraries the script can use
+  pub(crate) libraries: Vec<String>,
+  /// Most memory, in bytes, the VM can allocate
+  pub(crate) memory_limit: usize,
+  /// Most instructions a single call can execute
+  pub(crate) instruction_limit: Option<u64>,
+  /// Longest a single call can run Lua code for
+  pub(crate) timeout: Duration
+}
+
+impl Default for LuaSandbox {
+  fn default() -> Self {
+    LuaSandbox {
+      libraries: DEFAULT_SANDBOX_LIBRARIES.iter().map(|library| library.to_string()).collect(),
+      memory_limit: DEFAULT_SANDBOX_MEMORY_LIMIT,
+      instruction_limit: None,
+      timeout: Duration::from_millis(crate::call_chain::default_deadline_ms())
+    }
+  }
+}
+
+impl LuaSandbox {
+  /// The sandbox settings from the `sandbox` key of the manifest's `pluginConfig`, or `None` if
+  /// the plugin does not run sandboxed.
+  pub(crate) fn from_manifest(manifest: &PactPluginManifest) -> anyhow::Result<Option<LuaSandbox>> {
+    match manifest.plugin_config.get("sandbox") {
+      None | Some(Value::Null) | Some(Value::Bool(false)) => Ok(None),
+      Some(Value::Bool(true)) => Ok(Some(LuaSandbox::default())),
+      Some(Value::Object(config)) => {
+        let mut sandbox = LuaSandbox::default();
+        if let Some(libraries) = config.get("libraries") {
+          sandbox.libraries = libraries.as_array()
+            .ok_or_else(|| anyhow!("Lua sandbox 'libraries' must be an array of library names"))?
+            .iter()
+            .map(|library| match library.as_str() {
+              Some(name) if std_lib(name).is_some() => Ok(name.to_string()),
+              _ => Err(anyhow!("'{}' is not a Lua standard library that can be sandboxed", library))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        }
+        if let Some(limit) = config.get("memoryLimit") {
+          sandbox.memory_limit = limit.as_u64()
+            .ok_or_else(|| anyhow!("Lua sandbox 'memoryLimit' must be a number of bytes"))? as usize;
+        }
+        if let Some(limit) = config.get("instructionLimit") {
+          sandbox.instruction_limit = Some(limit.as_u64()
+            .ok_or_else(|| anyhow!("Lua sandbox 'instructionLimit' must be a number of instructions"))?);
+        }
+        if let Some(timeout) = config.get("timeoutMs") {
+          sandbox.timeout = Duration::from_millis(timeout.as_u64()
+            .ok_or_else(|| anyhow!("Lua sandbox 'timeoutMs' must be a number of milliseconds"))?);
+        }
+        Ok(Some(sandbox))
+      }
+      Some(other) => Err(anyhow!("Lua sandbox configuration must be true, false or an object, got {}", other))
+    }
+  }
+
+  /// Create a VM restricted to the allowed libraries and memory, with a hook that aborts a call
+  /// once it goes over the instruction or time limit. The limits apply from the last
+  /// [`LuaCallBudget::reset`].
+  pub(crate) fn create_vm(&self) -> anyhow::Result<(Lua, Arc<LuaCallBudget>)> {
+    // `os` is always loaded so the clock functions can be kept if the rest of it is not allowed
+    let libraries = self.libraries.iter()
+      .filter_map(|library| std_lib(library))
+      .fold(StdLib::OS, |libraries, library| libraries | library);
+    let lua = Lua::new_with(libraries, LuaOptions::default())?;
+
+    let globals = lua.globals();
+    globals.set("dofile", mlua::Nil)?;
+    globals.set("loadfile", mlua::Nil)?;
+    // Precompiled chunks are not verified by Lua, and crafted bytecode can break out of the VM
+    let load: Function = globals.get("load")?;
+    globals.set("load", lua.create_function(
+      move |lua, (chunk, name, _mode, env): (mlua::Value, mlua::Value, mlua::Value, Variadic<mlua::Value>)| {
+        // `env` is passed on only if given, as an explicit nil leaves the chunk without globals
+        let args = [chunk, name, mlua::Value::String(lua.create_string("t")?)].into_iter()
+          .chain(env.into_iter().take(1));
+        load.call::<MultiValue>(args.collect::<MultiValue>())
+      }
+    )?)?;
+
+    // The standard libraries are also in the table `require` returns them from, so a library that
+    // is not allowed has to be removed from there as well as from the globals
+    let loaded: Table = lua.named_registry_value("_LOADED")?;
+    for library in SANDBOX_LIBRARIES {
+      if !self.libraries.iter().any(|allowed| allowed == library) {
+        loaded.set(library, mlua::Nil)?;
+      }
+    }
+    if !self.libraries.iter().any(|library| library == "os") {
+      let os: Table = globals.get("os")?;
+      let restricted = lua.create_table()?;
+      for function in SANDBOX_OS_FUNCTIONS {
+        restricted.set(function, os.get::<mlua::Value>(function)?)?;
+      }
+      loaded.set("os", restricted.clone())?;
+      globals.set("os", restricted)?;
+    }
+
+    lua.set_memory_limit(self.memory_limit)?;
+
+    let budget = Arc::new(LuaCallBudget::new(self.instruction_limit, self.timeout));
+    let hook_budget = budget.clone();
+    lua.set_global_hook(
+      HookTriggers::new().every_nth_instruction(HOOK_INSTRUCTION_INTERVAL),
+      move |_, _| hook_budget.check().map(|_| VmState::Continue)
+    )?;
+    Ok((lua, budget))
+  }
+}
+
+/// Replace the `package` library of a sandboxed VM with a read-only one whose `require` only loads
+/// Lua modules from the given directories, in order. Does nothing if the VM does not have the
+/// `package` library.
+pub(crate) fn restrict_package(lua: &Lua, directories: Vec<PathBuf>) -> anyhow::Result<()> {
+  let Some(package) = lua.globals().get::<Option<Table>>("package")? else {
+    return Ok(());
+  };
+  let loaded: Table = package.get("loaded")?;
+
+  let restricted = lua.create_table()?;
+  restricted.set("config", package.get::<mlua::Value>("config")?)?;
+  restricted.set("path", directories.iter()
+    .map(|directory| format!("{0}/?.lua;{0}/?/init.lua", directory.to_string_lossy()))
+    .join(";"))?;
+  restricted.set("cpath", "")?;
+  restricted.set("loaded", loaded.clone())?;
+  restricted.set("preload", lua.create_table()?)?;
+
+  let metatable = lua.create_table()?;
+  metatable.set("__index", restricted)?;
+  metatable.set("__newindex", lua.create_function(|_, _: mlua::MultiValue| -> mlua::Result<()> {
+    Err(mlua::Error::RuntimeError("package can not be changed by a sandboxed Lua plugin".to_string()))
+  })?)?;
+  metatable.set("__metatable", false)?;
+  let read_only = lua.create_table()?;
+  read_only.set_metatable(Some(metatable))?;
+
+  let require_loaded = loaded.clone();
+  let require = lua.create_function(move |lua, name: String| {
+    sandboxed_require(lua, &name, &directories, &require_loaded)
+  })?;
+  loaded.set("package", read_only.clone())?;
+  lua.globals().set("package", read_only)?;
+  lua.globals().set("require", require)?;
+  Ok(())
+}
+
+/// `require` for a sandboxed VM. Module names map to files the same way as the standard `require`
+/// (`a.b` is `a/b.lua` or `a/b/init.lua`), but only files inside the directories are loaded.
+fn sandboxed_require(
+  lua: &Lua,
+  name: &str,
+  directories: &[PathBuf],
+  loaded: &Table
+) -> mlua::Result<(mlua::Value, mlua::Value)> {
+  let module = loaded.get::<mlua::Value>(name)?;
+  if !module.is_nil() {
+    return Ok((module, mlua::Nil));
+  }
+
+  let relative = name.replace('.', "/");
+  let file = directories.iter()
+    .flat_map(|directory| [
+      (directory, format!("{}/{}.lua", directory.to_string_lossy(), relative)),
+      (directory, format!("{}/{}/init.lua", directory.to_string_lossy(), relative))
+    ])
+    .find_map(|(directory, file)| module_file(directory, Path::new(&file)))
+    .ok_or_else(|| mlua::Error::RuntimeError(format!(
+      "module '{}' not found in the plugin or LuaRocks directories", name)))?;
+
+  let source = std::fs::read_to_string(&file).map_err(mlua::Error::external)?;
+  let file_name = file.to_string_lossy().to_string();
+  let module: mlua::Value = lua.load(source)
+    .set_name(file_name.as_str())
+    .set_mode(ChunkMode::Text)
+    .call((name, file_name.as_str()))?;
+  let module = if module.is_nil() { mlua::Value::Boolean(true) } else { module };
+  loaded.set(name, module.clone())?;
+  Ok((module, mlua::Value::String(lua.create_string(&file_name)?)))
+}
+
+/// The file, if it exists and resolves to somewhere inside the directory
+fn module_file(directory: &Path, file: &Path) -> Option<PathBuf> {
+  let directory = directory.canonicalize().ok()?;
+  file.canonicalize().ok()
+    .filter(|file| file.is_file() && file.starts_with(&directory))
+}
+
+fn std_lib(name: &str) -> Option<StdLib> {
+  match name {
+    "coroutine" => Some(StdLib::COROUTINE),
+    "io" => Some(StdLib::IO),
+    "math" => Some(StdLib::MATH),
+    "os" => Some(StdLib::OS),
+    "package" => Some(StdLib::PACKAGE),
+    "string" => Some(StdLib::STRING),
+    "table" => Some(StdLib::TABLE),
+    "utf8" => Some(StdLib::UTF8),
+    _ => None
+  }
+}
+
+/// What a single call into a sandboxed plugin has used of its limits. Reset before each call.
+#[derive(Debug)]
+pub(crate) struct LuaCallBudget {
+  instruction_limit: Option<u64>,
+  timeout: Duration,
+  instructions: AtomicU64,
+  started: Mutex<Instant>
+}
+
+impl LuaCallBudget {
+  fn new(instruction_limit: Option<u64>, timeout: Duration) -> Self {
+    LuaCallBudget {
+      instruction_limit,
+      timeout,
+      instructions: AtomicU64::new(0),
+      started: Mutex::new(Instant::now())
+    }
+  }
+
+  /// Start the budget of a new call
+  pub(crate) fn reset(&self) {
+    self.instructions.store(0, Ordering::SeqCst);
+    *self.started.lock().unwrap() = Instant::now();
+  }
+
+  /// Called from the instruction hook. Errors once the call has gone over a limit, which aborts
+  /// the running Lua code.
+  fn check(&self) -> mlua::Result<()> {
+    let instructions = self.instructions.fetch_add(HOOK_INSTRUCTION_INTERVAL as u64, Ordering::SeqCst)
+      + HOOK_INSTRUCTION_INTERVAL as u64;
+    if let Some(limit) = self.instruction_limit && instructions > limit {
+      return Err(mlua::Error::RuntimeError(format!(
+        "Lua plugin call aborted after exceeding the sandbox instruction limit of {}", limit)));
+    }
+    if self.started.lock().unwrap().elapsed() > self.timeout {
+      return Err(mlua::Error::RuntimeError(format!(
+        "Lua plugin call aborted after exceeding the sandbox time limit of {}ms", self.timeout.as_millis())));
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use maplit::hashmap;
+  use serde_json::json;
+
+  use super::*;
+
+  fn manifest(sandbox: Value) -> PactPluginManifest {
+    PactPluginManifest {
+      name: "sandbox-test".to_string(),
+      plugin_config: hashmap!{ "sandbox".to_string() => sandbox },
+      .. PactPluginManifest::default()
+    }
+  }
+
+  #[test]
+  fn sandbox_configuration_from_the_manifest() {
+    expect!(LuaSandbox::from_manifest(&PactPluginManifest::default()).unwrap()).to(be_none());
+    expect!(LuaSandbox::from_manifest(&manifest(json!(false))).unwrap()).to(be_none());
+    expect!(LuaSandbox::from_manifest(&manifest(json!(true))).unwrap()).to(be_some().value(LuaSandbox::default()));
+    expect!(LuaSandbox::from_manifest(&manifest(json!({
+      "libraries": ["string", "os"],
+      "memoryLimit": 1024,
+      "instructionLimit": 5000,
+      "timeoutMs": 100
+    }))).unwrap()).to(be_some().value(LuaSandbox {
+      libraries: vec!["string".to_string(), "os".to_string()],
+      memory_limit: 1024,
+      instruction_limit: Some(5000),
+      timeout: Duration::from_millis(100)
+    }));
+
+    expect!(LuaSandbox::from_manifest(&manifest(json!({ "libraries": ["debug"] })))).to(be_err());
+    expect!(LuaSandbox::from_manifest(&manifest(json!({ "memoryLimit": "lots" })))).to(be_err());
+    expect!(LuaSandbox::from_manifest(&manifest(json!("yes")))).to(be_err());
+  }
+
+  #[test]
+  fn a_sandboxed_vm_only_has_the_allowed_libraries() {
+    let (lua, _) = LuaSandbox::default().create_vm().unwrap();
+
+    let (io, execute, time, dofile, upper): (bool, bool, bool, bool, String) = lua.load(r#"
+      return io ~= nil, os.execute ~= nil, type(os.time()) == "number", dofile ~= nil, string.upper("ok")
+    "#).eval().unwrap();
+
+    expect!(io).to(be_false());
+    expect!(execute).to(be_false());
+    expect!(time).to(be_true());
+    expect!(dofile).to(be_false());
+    expect!(upper).to(be_equal_to("OK"));
+  }
+
+  #[test]
+  fn a_runaway_call_is_aborted() {
+    let sandbox = LuaSandbox {
+      instruction_limit: Some(100_000),
+      .. LuaSandbox::default()
+    };
+    let (lua, budget) = sandbox.create_vm().unwrap();
+
+    budget.reset();
+    let err = lua.load("while true do end").exec().unwrap_err();
+    expect!(err.to_string().contains("sandbox instruction limit of 100000")).to(be_true());
+
+    // The next call gets a budget of its own
+    budget.reset();
+    expect!(lua.load("local x = 0 for i = 1, 1000 do x = x + i end").exec()).to(be_ok());
+  }
+
+  #[test]
+  fn a_call_that_runs_too_long_is_aborted() {
+    let sandbox = LuaSandbox {
+      timeout: Duration::from_millis(50),
+      .. LuaSandbox::default()
+    };
+    let (lua, budget) = sandbox.create_vm().unwrap();
+
+    budget.reset();
+    let err = lua.load("while true do end").exec().unwrap_err();
+    expect!(err.to_string().contains("sandbox time limit of 50ms")).to(be_true());
+  }
+
+  #[test]
+  fn a_call_that_allocates_too_much_is_aborted() {
+    let sandbox = LuaSandbox {
+      memory_limit: 4 * 1024 * 1024,
+      .. LuaSandbox::default()
+    };
+    let (lua, budget) = sandbox.create_vm().unwrap();
+
+    budget.reset();
+    let result = lua.load("local t = {} for i = 1, 10000000 do t[i] = string.rep('x', 100) .. i end").exec();
+    expect!(matches!(result, Err(mlua::Error::MemoryError(_)))).to(be_true());
+  }
+
+  #[test]
+  fn a_sandboxed_vm_only_requires_modules_from_the_plugin_directories() {
+    let root = tempdir::TempDir::new("lua-sandbox-test").unwrap();
+    let plugin_dir = root.path().join("plugin");
+    std::fs::create_dir_all(plugin_dir.join("lib")).unwrap();
+    std::fs::write(plugin_dir.join("lib").join("helper.lua"), "return { name = ... }").unwrap();
+    std::fs::write(root.path().join("outside.lua"), "return 'outside'").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(root.path().join("outside.lua"), plugin_dir.join("linked.lua")).unwrap();
+    let (lua, _) = LuaSandbox::default().create_vm().unwrap();
+    lua.globals().get::<Table>("package").unwrap()
+      .set("path", format!("{}/?.lua", root.path().to_string_lossy())).unwrap();
+
+    restrict_package(&lua, vec![plugin_dir]).unwrap();
+
+    let name: String = lua.load("return require('lib.helper').name").eval().unwrap();
+    expect!(name).to(be_equal_to("lib.helper"));
+    let same: bool = lua.load("return require('lib.helper') == require('lib.helper')").eval().unwrap();
+    expect!(same).to(be_true());
+    for module in ["outside", "..outside", "../outside", "linked"] {
+      let result = lua.load(format!("return require('{}')", module)).eval::<String>();
+      expect!(result.map_err(|err| err.to_string().contains("not found in the plugin or LuaRocks directories")))
+        .to(be_err().value(true));
+    }
+  }
+
+  #[test]
+  fn a_sandboxed_vm_can_not_change_or_load_code_through_package() {
+    let (lua, _) = LuaSandbox::default().create_vm().unwrap();
+    restrict_package(&lua, vec![PathBuf::from("/plugin")]).unwrap();
+
+    let (path, cpath, loadlib, searchpath, searchers, same): (String, String, bool, bool, bool, bool) = lua.load(r#"
+      return package.path, package.cpath, package.loadlib ~= nil, package.searchpath ~= nil,
+        package.searchers ~= nil, package.loaded.package == package
+    "#).eval().unwrap();
+    expect!(path).to(be_equal_to("/plugin/?.lua;/plugin/?/init.lua"));
+    expect!(cpath).to(be_equal_to(""));
+    expect!(loadlib).to(be_false());
+    expect!(searchpath).to(be_false());
+    expect!(searchers).to(be_false());
+    expect!(same).to(be_true());
+    expect!(lua.load("package.path = '/?.lua'").exec()).to(be_err());
+    expect!(lua.load("return getmetatable(package)").eval::<bool>()).to(be_ok().value(false));
+  }
+
+  #[test]
+  fn a_sandboxed_vm_can_not_get_the_full_os_library_through_require() {
+    let (lua, _) = LuaSandbox::default().create_vm().unwrap();
+    restrict_package(&lua, vec![PathBuf::from("/plugin")]).unwrap();
+
+    let (required, loaded, time): (bool, bool, bool) = lua.load(r#"
+      return require('os').execute == nil, package.loaded.os.execute == nil, require('os').time ~= nil
+    "#).eval().unwrap();
+    expect!(required).to(be_true());
+    expect!(loaded).to(be_true());
+    expect!(time).to(be_true());
+    expect!(lua.load("return require('io')").exec()).to(be_err());
+  }
+
+  #[test]
+  fn a_sandboxed_vm_only_loads_source_chunks() {
+    let (lua, _) = LuaSandbox::default().create_vm().unwrap();
+
+    let (binary, error, text): (bool, String, i64) = lua.load(r#"
+      local chunk, err = load(string.dump(function() return 1 end))
+      return chunk == nil, err, load("return 2")()
+    "#).eval().unwrap();
+    expect!(binary).to(be_true());
+    expect!(error.contains("binary chunk")).to(be_true());
+    expect!(text).to(be_equal_to(2));
+    let no_globals: bool = lua.load("return select(2, pcall(load('return string', 'chunk', 't', {}))) == nil")
+      .eval().unwrap();
+    expect!(no_globals).to(be_true());
+  }
+
+  #[test]
+  fn a_vm_without_the_package_library_is_left_without_require() {
+    let sandbox = LuaSandbox { libraries: vec!["string".to_string()], .. LuaSandbox::default() };
+    let (lua, _) = sandbox.create_vm().unwrap();
+
+    restrict_package(&lua, vec![PathBuf::from("/plugin")]).unwrap();
+
+    let require: bool = lua.load("return require ~= nil or package ~= nil").eval().unwrap();
+    expect!(require).to(be_false());
+  }
+}
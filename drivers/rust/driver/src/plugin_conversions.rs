@@ -0,0 +1,123 @@
+//! Conversions shared by the in-process script plugin runtimes ([`crate::lua_plugin`] and
+//! [`crate::python_plugin`]).
+//!
+//! Both runtimes hand a script the same request and response shapes, so the parts of that mapping
+//! that don't depend on the scripting language (enum names, and the V1/V2 message conversions the
+//! V2-only field operations need) live here rather than in each runtime.
+
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+
+use crate::proto::*;
+use crate::proto_v2;
+
+/// The content type hint name a script sees
+pub(crate) fn content_type_hint_to_str(hint: i32) -> &'static str {
+  match body::ContentTypeHint::try_from(hint).unwrap_or(body::ContentTypeHint::Default) {
+    body::ContentTypeHint::Default => "DEFAULT",
+    body::ContentTypeHint::Text => "TEXT",
+    body::ContentTypeHint::Binary => "BINARY",
+  }
+}
+
+/// Reverse of [`content_type_hint_to_str`]. Anything unrecognised is `DEFAULT`.
+pub(crate) fn str_to_content_type_hint(hint: &str) -> i32 {
+  match hint {
+    "TEXT" => body::ContentTypeHint::Text as i32,
+    "BINARY" => body::ContentTypeHint::Binary as i32,
+    _ => body::ContentTypeHint::Default as i32,
+  }
+}
+
+/// The test mode name a script sees. The V1 and V2 `GenerateContentRequest.TestMode` enums have
+/// the same values, so one pair of helpers covers content generation on either interface as well
+/// as field-level generation (which is V2-only).
+pub(crate) fn test_mode_to_str(test_mode: i32) -> &'static str {
+  match generate_content_request::TestMode::try_from(test_mode)
+    .unwrap_or(generate_content_request::TestMode::Unknown)
+  {
+    generate_content_request::TestMode::Consumer => "Consumer",
+    generate_content_request::TestMode::Provider => "Provider",
+    generate_content_request::TestMode::Unknown => "Unknown",
+  }
+}
+
+/// Reverse of [`test_mode_to_str`]. Anything unrecognised (including a missing value) is
+/// `Unknown`, rather than an error - the mode is context for the plugin, not a contract.
+pub(crate) fn str_to_test_mode(test_mode: Option<&str>) -> i32 {
+  match test_mode {
+    Some("Consumer") => generate_content_request::TestMode::Consumer as i32,
+    Some("Provider") => generate_content_request::TestMode::Provider as i32,
+    _ => generate_content_request::TestMode::Unknown as i32,
+  }
+}
+
+/// The entries of a JSON object, or an empty map for any other JSON value
+pub(crate) fn as_json_map(value: serde_json::Value) -> HashMap<String, serde_json::Value> {
+  match value {
+    serde_json::Value::Object(map) => map.into_iter().collect(),
+    _ => HashMap::new(),
+  }
+}
+
+/// V2's `PluginConfiguration` carries the same two `Struct` fields as the V1 message, so the
+/// field-level requests reuse the V1 conversions rather than duplicating them. The conversion is
+/// needed at all only because field-level operations exist solely on the V2 interface
+/// (proposal 006).
+pub(crate) fn v2_plugin_configuration_to_v1(
+  config: &Option<proto_v2::PluginConfiguration>
+) -> Option<PluginConfiguration> {
+  config.as_ref().map(|config| PluginConfiguration {
+    interaction_configuration: config.interaction_configuration.clone(),
+    pact_configuration: config.pact_configuration.clone(),
+  })
+}
+
+/// Reverse of [`v2_plugin_configuration_to_v1`].
+pub(crate) fn v1_plugin_configuration_to_v2(
+  config: Option<PluginConfiguration>
+) -> Option<proto_v2::PluginConfiguration> {
+  config.map(|config| proto_v2::PluginConfiguration {
+    interaction_configuration: config.interaction_configuration,
+    pact_configuration: config.pact_configuration,
+  })
+}
+
+/// See [`v2_plugin_configuration_to_v1`] - `ContentMismatch` is likewise identical between the two
+/// interfaces apart from the V2-only structured diff, so the existing mismatch conversions are
+/// reused for the V2-only field messages.
+pub(crate) fn v1_content_mismatch_to_v2(mismatch: ContentMismatch) -> proto_v2::ContentMismatch {
+  proto_v2::ContentMismatch {
+    expected: mismatch.expected,
+    actual: mismatch.actual,
+    mismatch: mismatch.mismatch,
+    path: mismatch.path,
+    diff: mismatch.diff,
+    mismatch_type: mismatch.mismatch_type,
+    structured_diff: vec![],
+  }
+}
+
+/// Reverse of [`v1_content_mismatch_to_v2`].
+pub(crate) fn v2_content_mismatch_to_v1(mismatch: &proto_v2::ContentMismatch) -> ContentMismatch {
+  ContentMismatch {
+    expected: mismatch.expected.clone(),
+    actual: mismatch.actual.clone(),
+    mismatch: mismatch.mismatch.clone(),
+    path: mismatch.path.clone(),
+    diff: mismatch.diff.clone(),
+    mismatch_type: mismatch.mismatch_type.clone(),
+  }
+}
+
+/// V1 and V2 `InteractionData` have the same wire format, so a V2 value is converted by an
+/// encode/decode round trip and the runtimes deal only with the V1 type, matching the approach
+/// `plugin_manager.rs` uses in the other direction (see `to_proto_v2_interaction_data`). The data
+/// comes from a caller-supplied request, so a decode failure is returned as an error rather than
+/// a panic.
+pub(crate) fn v2_interaction_data_to_v1(data: &proto_v2::InteractionData) -> anyhow::Result<InteractionData> {
+  use prost::Message;
+  InteractionData::decode(data.encode_to_vec().as_slice())
+    .map_err(|err| anyhow!("Failed to convert V2 InteractionData to V1 - {}", err))
+}
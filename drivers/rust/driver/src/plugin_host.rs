@@ -47,35 +47,37 @@ impl plugin_host_server::PluginHost for PluginHostService {
   ) -> Result<Response<CompareContentsResponse>, Status> {
     let (metadata, _, msg) = request.into_parts();
     let (chain_id, deadline_ms) = call_chain_context(&metadata);
-    let entry_key = msg.entry_key;
-    let inner_request = msg.request
-      .ok_or_else(|| Status::invalid_argument("HostCompareContentsRequest.request is required"))?;
-
-    if call_chain::is_expired(deadline_ms) {
-      return Err(Status::deadline_exceeded(format!(
-        "Call chain {} deadline has already passed", chain_id
-      )));
-    }
-    let _guard = call_chain::push_call(&chain_id, &entry_key).map_err(Status::already_exists)?;
-
-    let v1_request = PluginClient::convert_message(inner_request)?;
-    match resolve_capability(&entry_key, CatalogueEntryType::CONTENT_MATCHER)
-      .map_err(|err| Status::not_found(err.to_string()))? {
-      ResolvedCapability::Core(core_key) => {
-        let handler = crate::core_capabilities::lookup_core_content_matcher(&core_key)
-          .ok_or_else(|| Status::not_found(format!("No core content matcher registered for '{}'", core_key)))?;
-        let response = handler.compare_contents(v1_request).await
-          .map_err(|err| Status::internal(format!("Core content matcher for '{}' failed: {}", core_key, err)))?;
-        Ok(Response::new(PluginClient::convert_message(response)?))
+    call_chain::driver_for_call_chain(&chain_id).scope(async move {
+      let entry_key = msg.entry_key;
+      let inner_request = msg.request
+        .ok_or_else(|| Status::invalid_argument("HostCompareContentsRequest.request is required"))?;
+
+      if call_chain::is_expired(deadline_ms) {
+        return Err(Status::deadline_exceeded(format!(
+          "Call chain {} deadline has already passed", chain_id
+        )));
       }
-      ResolvedCapability::Plugin(manifest) => {
-        let plugin = crate::plugin_manager::lookup_plugin(&manifest.as_dependency())
-          .ok_or_else(|| Status::not_found(format!("Plugin '{}' for entry '{}' is not currently running", manifest.name, entry_key)))?;
-        let response = plugin.compare_contents_with_chain(v1_request, &chain_id, deadline_ms).await
-          .map_err(|err| Status::internal(format!("Call to plugin '{}' failed: {}", manifest.name, err)))?;
-        Ok(Response::new(PluginClient::convert_message(response)?))
+      let _guard = call_chain::push_call(&chain_id, &entry_key).map_err(Status::already_exists)?;
+
+      let v1_request = PluginClient::convert_message(inner_request)?;
+      match resolve_capability(&entry_key, CatalogueEntryType::CONTENT_MATCHER)
+        .map_err(|err| Status::not_found(err.to_string()))? {
+        ResolvedCapability::Core(core_key) => {
+          let handler = crate::core_capabilities::lookup_core_content_matcher(&core_key)
+            .ok_or_else(|| Status::not_found(format!("No core content matcher registered for '{}'", core_key)))?;
+          let response = handler.compare_contents(v1_request).await
+            .map_err(|err| Status::internal(format!("Core content matcher for '{}' failed: {}", core_key, err)))?;
+          Ok(Response::new(PluginClient::convert_message(response)?))
+        }
+        ResolvedCapability::Plugin(manifest) => {
+          let plugin = crate::plugin_manager::lookup_plugin(&manifest.as_dependency())
+            .ok_or_else(|| Status::not_found(format!("Plugin '{}' for entry '{}' is not currently running", manifest.name, entry_key)))?;
+          let response = plugin.compare_contents_with_chain(v1_request, &chain_id, deadline_ms).await
+            .map_err(|err| Status::internal(format!("Call to plugin '{}' failed: {}", manifest.name, err)))?;
+          Ok(Response::new(PluginClient::convert_message(response)?))
+        }
       }
-    }
+    }).await
   }
 
   async fn generate_content(
@@ -84,35 +86,37 @@ impl plugin_host_server::PluginHost for PluginHostService {
   ) -> Result<Response<GenerateContentResponse>, Status> {
     let (metadata, _, msg) = request.into_parts();
     let (chain_id, deadline_ms) = call_chain_context(&metadata);
-    let entry_key = msg.entry_key;
-    let inner_request = msg.request
-      .ok_or_else(|| Status::invalid_argument("HostGenerateContentRequest.request is required"))?;
-
-    if call_chain::is_expired(deadline_ms) {
-      return Err(Status::deadline_exceeded(format!(
-        "Call chain {} deadline has already passed", chain_id
-      )));
-    }
-    let _guard = call_chain::push_call(&chain_id, &entry_key).map_err(Status::already_exists)?;
-
-    let v1_request = PluginClient::convert_message(inner_request)?;
-    match resolve_capability(&entry_key, CatalogueEntryType::CONTENT_GENERATOR)
-      .map_err(|err| Status::not_found(err.to_string()))? {
-      ResolvedCapability::Core(core_key) => {
-        let handler = crate::core_capabilities::lookup_core_content_generator(&core_key)
-          .ok_or_else(|| Status::not_found(format!("No core content generator registered for '{}'", core_key)))?;
-        let response = handler.generate_content(v1_request).await
-          .map_err(|err| Status::internal(format!("Core content generator for '{}' failed: {}", core_key, err)))?;
-        Ok(Response::new(PluginClient::convert_message(response)?))
+    call_chain::driver_for_call_chain(&chain_id).scope(async move {
+      let entry_key = msg.entry_key;
+      let inner_request = msg.request
+        .ok_or_else(|| Status::invalid_argument("HostGenerateContentRequest.request is required"))?;
+
+      if call_chain::is_expired(deadline_ms) {
+        return Err(Status::deadline_exceeded(format!(
+          "Call chain {} deadline has already passed", chain_id
+        )));
       }
-      ResolvedCapability::Plugin(manifest) => {
-        let plugin = crate::plugin_manager::lookup_plugin(&manifest.as_dependency())
-          .ok_or_else(|| Status::not_found(format!("Plugin '{}' for entry '{}' is not currently running", manifest.name, entry_key)))?;
-        let response = plugin.generate_content_with_chain(v1_request, &chain_id, deadline_ms).await
-          .map_err(|err| Status::internal(format!("Call to plugin '{}' failed: {}", manifest.name, err)))?;
-        Ok(Response::new(PluginClient::convert_message(response)?))
+      let _guard = call_chain::push_call(&chain_id, &entry_key).map_err(Status::already_exists)?;
+
+      let v1_request = PluginClient::convert_message(inner_request)?;
+      match resolve_capability(&entry_key, CatalogueEntryType::CONTENT_GENERATOR)
+        .map_err(|err| Status::not_found(err.to_string()))? {
+        ResolvedCapability::Core(core_key) => {
+          let handler = crate::core_capabilities::lookup_core_content_generator(&core_key)
+            .ok_or_else(|| Status::not_found(format!("No core content generator registered for '{}'", core_key)))?;
+          let response = handler.generate_content(v1_request).await
+            .map_err(|err| Status::internal(format!("Core content generator for '{}' failed: {}", core_key, err)))?;
+          Ok(Response::new(PluginClient::convert_message(response)?))
+        }
+        ResolvedCapability::Plugin(manifest) => {
+          let plugin = crate::plugin_manager::lookup_plugin(&manifest.as_dependency())
+            .ok_or_else(|| Status::not_found(format!("Plugin '{}' for entry '{}' is not currently running", manifest.name, entry_key)))?;
+          let response = plugin.generate_content_with_chain(v1_request, &chain_id, deadline_ms).await
+            .map_err(|err| Status::internal(format!("Call to plugin '{}' failed: {}", manifest.name, err)))?;
+          Ok(Response::new(PluginClient::convert_message(response)?))
+        }
       }
-    }
+    }).await
   }
 
   async fn match_field(
@@ -121,34 +125,36 @@ impl plugin_host_server::PluginHost for PluginHostService {
   ) -> Result<Response<MatchFieldResponse>, Status> {
     let (metadata, _, msg) = request.into_parts();
     let (chain_id, deadline_ms) = call_chain_context(&metadata);
-    let entry_key = msg.entry_key;
-    let inner_request = msg.request
-      .ok_or_else(|| Status::invalid_argument("HostMatchFieldRequest.request is required"))?;
-
-    if call_chain::is_expired(deadline_ms) {
-      return Err(Status::deadline_exceeded(format!(
-        "Call chain {} deadline has already passed", chain_id
-      )));
-    }
-    let _guard = call_chain::push_call(&chain_id, &entry_key).map_err(Status::already_exists)?;
-
-    match resolve_capability(&entry_key, CatalogueEntryType::MATCHER)
-      .map_err(|err| Status::not_found(err.to_string()))? {
-      ResolvedCapability::Core(core_key) => {
-        let handler = crate::core_capabilities::lookup_core_field_matcher(&core_key)
-          .ok_or_else(|| Status::not_found(format!("No core field matcher registered for '{}'", core_key)))?;
-        let response = handler.match_field(inner_request).await
-          .map_err(|err| Status::internal(format!("Core field matcher for '{}' failed: {}", core_key, err)))?;
-        Ok(Response::new(response))
+    call_chain::driver_for_call_chain(&chain_id).scope(async move {
+      let entry_key = msg.entry_key;
+      let inner_request = msg.request
+        .ok_or_else(|| Status::invalid_argument("HostMatchFieldRequest.request is required"))?;
+
+      if call_chain::is_expired(deadline_ms) {
+        return Err(Status::deadline_exceeded(format!(
+          "Call chain {} deadline has already passed", chain_id
+        )));
       }
-      ResolvedCapability::Plugin(manifest) => {
-        let plugin = crate::plugin_manager::lookup_plugin(&manifest.as_dependency())
-          .ok_or_else(|| Status::not_found(format!("Plugin '{}' for entry '{}' is not currently running", manifest.name, entry_key)))?;
-        let response = plugin.match_field_with_chain(inner_request, &chain_id, deadline_ms).await
-          .map_err(|err| Status::internal(format!("Call to plugin '{}' failed: {}", manifest.name, err)))?;
-        Ok(Response::new(response))
+      let _guard = call_chain::push_call(&chain_id, &entry_key).map_err(Status::already_exists)?;
+
+      match resolve_capability(&entry_key, CatalogueEntryType::MATCHER)
+        .map_err(|err| Status::not_found(err.to_string()))? {
+        ResolvedCapability::Core(core_key) => {
+          let handler = crate::core_capabilities::lookup_core_field_matcher(&core_key)
+            .ok_or_else(|| Status::not_found(format!("No core field matcher registered for '{}'", core_key)))?;
+          let response = handler.match_field(inner_request).await
+            .map_err(|err| Status::internal(format!("Core field matcher for '{}' failed: {}", core_key, err)))?;
+          Ok(Response::new(response))
+        }
+        ResolvedCapability::Plugin(manifest) => {
+          let plugin = crate::plugin_manager::lookup_plugin(&manifest.as_dependency())
+            .ok_or_else(|| Status::not_found(format!("Plugin '{}' for entry '{}' is not currently running", manifest.name, entry_key)))?;
+          let response = plugin.match_field_with_chain(inner_request, &chain_id, deadline_ms).await
+            .map_err(|err| Status::internal(format!("Call to plugin '{}' failed: {}", manifest.name, err)))?;
+          Ok(Response::new(response))
+        }
       }
-    }
+    }).await
   }
 
   async fn generate_field(
@@ -157,34 +163,36 @@ impl plugin_host_server::PluginHost for PluginHostService {
   ) -> Result<Response<GenerateFieldResponse>, Status> {
     let (metadata, _, msg) = request.into_parts();
     let (chain_id, deadline_ms) = call_chain_context(&metadata);
-    let entry_key = msg.entry_key;
-    let inner_request = msg.request
-      .ok_or_else(|| Status::invalid_argument("HostGenerateFieldRequest.request is required"))?;
-
-    if call_chain::is_expired(deadline_ms) {
-      return Err(Status::deadline_exceeded(format!(
-        "Call chain {} deadline has already passed", chain_id
-      )));
-    }
-    let _guard = call_chain::push_call(&chain_id, &entry_key).map_err(Status::already_exists)?;
-
-    match resolve_capability(&entry_key, CatalogueEntryType::GENERATOR)
-      .map_err(|err| Status::not_found(err.to_string()))? {
-      ResolvedCapability::Core(core_key) => {
-        let handler = crate::core_capabilities::lookup_core_field_generator(&core_key)
-          .ok_or_else(|| Status::not_found(format!("No core field generator registered for '{}'", core_key)))?;
-        let response = handler.generate_field(inner_request).await
-          .map_err(|err| Status::internal(format!("Core field generator for '{}' failed: {}", core_key, err)))?;
-        Ok(Response::new(response))
+    call_chain::driver_for_call_chain(&chain_id).scope(async move {
+      let entry_key = msg.entry_key;
+      let inner_request = msg.request
+        .ok_or_else(|| Status::invalid_argument("HostGenerateFieldRequest.request is required"))?;
+
+      if call_chain::is_expired(deadline_ms) {
+        return Err(Status::deadline_exceeded(format!(
+          "Call chain {} deadline has already passed", chain_id
+        )));
       }
-      ResolvedCapability::Plugin(manifest) => {
-        let plugin = crate::plugin_manager::lookup_plugin(&manifest.as_dependency())
-          .ok_or_else(|| Status::not_found(format!("Plugin '{}' for entry '{}' is not currently running", manifest.name, entry_key)))?;
-        let response = plugin.generate_field_with_chain(inner_request, &chain_id, deadline_ms).await
-          .map_err(|err| Status::internal(format!("Call to plugin '{}' failed: {}", manifest.name, err)))?;
-        Ok(Response::new(response))
+      let _guard = call_chain::push_call(&chain_id, &entry_key).map_err(Status::already_exists)?;
+
+      match resolve_capability(&entry_key, CatalogueEntryType::GENERATOR)
+        .map_err(|err| Status::not_found(err.to_string()))? {
+        ResolvedCapability::Core(core_key) => {
+          let handler = crate::core_capabilities::lookup_core_field_generator(&core_key)
+            .ok_or_else(|| Status::not_found(format!("No core field generator registered for '{}'", core_key)))?;
+          let response = handler.generate_field(inner_request).await
+            .map_err(|err| Status::internal(format!("Core field generator for '{}' failed: {}", core_key, err)))?;
+          Ok(Response::new(response))
+        }
+        ResolvedCapability::Plugin(manifest) => {
+          let plugin = crate::plugin_manager::lookup_plugin(&manifest.as_dependency())
+            .ok_or_else(|| Status::not_found(format!("Plugin '{}' for entry '{}' is not currently running", manifest.name, entry_key)))?;
+          let response = plugin.generate_field_with_chain(inner_request, &chain_id, deadline_ms).await
+            .map_err(|err| Status::internal(format!("Call to plugin '{}' failed: {}", manifest.name, err)))?;
+          Ok(Response::new(response))
+        }
       }
-    }
+    }).await
   }
 }
 
@@ -13,6 +13,9 @@ pub enum PluginLogSource {
   Stderr,
   /// Structured record received via the PluginHost Log RPC
   LogRpc,
+  /// Record the driver made about the plugin's lifecycle, such as its process exiting or being
+  /// restarted
+  Driver,
 }
 
 /// Structured log entry produced by a running plugin
@@ -41,7 +44,8 @@ pub struct PluginLogEntry {
 /// Register a custom implementation with [`set_plugin_log_sink`] to intercept plugin log
 /// output. The built-in `DefaultPluginLogSink` is a no-op for [`PluginLogSource::Stderr`]
 /// entries (those are already written to the per-instance log file) and forwards
-/// [`PluginLogSource::LogRpc`] entries into the `tracing` subscriber.
+/// [`PluginLogSource::LogRpc`] and [`PluginLogSource::Driver`] entries into the `tracing`
+/// subscriber.
 pub trait PluginLogSink: Send + Sync {
   fn log(&self, entry: &PluginLogEntry);
 }
@@ -56,7 +60,7 @@ fn is_transport_target(target: &str) -> bool {
 
 impl PluginLogSink for DefaultPluginLogSink {
   fn log(&self, entry: &PluginLogEntry) {
-    if entry.source != PluginLogSource::LogRpc {
+    if entry.source == PluginLogSource::Stderr {
       return;
     }
     if entry.level.to_uppercase() == "TRACE" {
@@ -3,18 +3,21 @@ use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::fs::File;
+use std::future::Future;
 use std::io::{BufReader, Write};
 use std::path::PathBuf;
+use std::pin::Pin;
 use std::str::FromStr;
-use std::str::from_utf8;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread;
+use std::time::Duration;
 
 use anyhow::{Context, anyhow, bail};
 use bytes::Bytes;
-use itertools::Either;
+use chrono::Utc;
+use itertools::{Either, Itertools};
 use lazy_static::lazy_static;
 use maplit::hashmap;
 use pact_models::PactSpecification;
@@ -26,43 +29,118 @@ use pact_models::v4::interaction::V4Interaction;
 use reqwest::Client;
 use semver::Version;
 use serde_json::Value;
-use tracing::{debug, info, trace, warn};
+use tracing::{debug, error, info, trace, warn};
 
 use crate::catalogue_manager::{
   CatalogueEntry, all_entries, core_entries, register_plugin_entries, remove_plugin_entries,
 };
 use crate::content::ContentMismatch;
-use crate::download::{download_json_from_github, download_plugin_executable, fetch_json_from_url};
+use crate::driver::PluginDriver;
+use crate::download::{
+  download_plugin_executable_from, fetch_json_from_url, fetch_plugin_manifest, release_url,
+};
 use crate::grpc_plugin::{GrpcPactPlugin, start_plugin_process};
+use crate::lockfile::{LockedPlugin, PluginLockFile, lock_plugin, plugin_lock_file};
 use crate::metrics::send_metrics;
 use crate::mock_server::{MockServerConfig, MockServerDetails, MockServerResults};
+use crate::plugin_log_sink::{PluginLogEntry, PluginLogSource, emit_plugin_log};
 use crate::plugin_models::{
-  PactPlugin, PactPluginManifest, PactPluginRpc, PluginDependency, PluginInitRequest,
-  PluginInstance, PluginInterfaceVersion, check_interaction_type_capability,
+  PactPlugin, PactPluginManifest, PactPluginRpc, PluginDependency, PluginDependencyType,
+  PluginInitRequest, PluginInstance, PluginInterfaceVersion, check_interaction_type_capability,
 };
 use crate::proto::*;
 use crate::proto_v2;
-use crate::repository::{USER_AGENT, fetch_repository_index};
+use crate::repository::{USER_AGENT, fetch_repository_index, mirrored_plugin_url};
 use crate::utils::{
-  optional_string, proto_value_to_json, to_proto_struct, to_proto_value, versions_compatible,
+  compare_versions, proto_value_to_json, to_proto_struct, to_proto_value, versions_compatible,
 };
 use crate::verification::{InteractionVerificationData, InteractionVerificationResult};
 
+/// Health of a loaded plugin, as last seen by the driver
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginHealth {
+  /// The plugin is running
+  Healthy,
+  /// The plugin process has exited, and has not been restarted. Calls to the plugin will fail.
+  Unhealthy {
+    /// Why the plugin is unhealthy
+    reason: String
+  },
+}
+
+/// What the driver does when the process of an `exec` plugin exits while the plugin is still
+/// loaded. Set the policy for all plugins with [`set_plugin_restart_policy`]. A plugin can
+/// override it with the `restartPolicy` (`"never"` or `"on-failure"`) and `maxRestarts` keys of
+/// the `pluginConfig` section of its manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PluginRestartPolicy {
+  /// Mark the plugin unhealthy and leave it that way (the default)
+  #[default]
+  Never,
+  /// Restart the plugin, at most `max_restarts` times while the driver is running
+  OnFailure {
+    /// Maximum number of times to restart the plugin
+    max_restarts: u32
+  },
+}
+
+/// Number of restarts for a plugin that sets `"restartPolicy": "on-failure"` without `maxRestarts`
+const DEFAULT_MAX_RESTARTS: u32 = 3;
+
+impl PluginRestartPolicy {
+  /// The restart policy for the plugin with the given manifest
+  pub fn for_manifest(manifest: &PactPluginManifest) -> Self {
+    let default_policy = plugin_restart_policy();
+    let max_restarts = manifest.plugin_config.get("maxRestarts")
+      .and_then(Value::as_u64)
+      .map(|value| u32::try_from(value).unwrap_or(u32::MAX));
+    match manifest.plugin_config.get("restartPolicy").and_then(Value::as_str) {
+      Some("never") => PluginRestartPolicy::Never,
+      Some("on-failure") => PluginRestartPolicy::OnFailure {
+        max_restarts: max_restarts.unwrap_or(DEFAULT_MAX_RESTARTS)
+      },
+      Some(other) => {
+        warn!("Plugin {} has an unknown restartPolicy '{}', ignoring it", manifest.name, other);
+        default_policy
+      }
+      None => match default_policy {
+        PluginRestartPolicy::Never => PluginRestartPolicy::Never,
+        PluginRestartPolicy::OnFailure { max_restarts: default_max } => PluginRestartPolicy::OnFailure {
+          max_restarts: max_restarts.unwrap_or(default_max)
+        }
+      }
+    }
+  }
+
+  /// If a plugin that has already been restarted `restarts` times may be restarted again
+  pub fn allows_restart(&self, restarts: u32) -> bool {
+    match self {
+      PluginRestartPolicy::Never => false,
+      PluginRestartPolicy::OnFailure { max_restarts } => restarts < *max_restarts
+    }
+  }
+}
+
 #[derive(Debug, Clone)]
-struct RegisteredPlugin {
+pub(crate) struct RegisteredPlugin {
   /// The running plugin instance, accessed via the PluginInstance trait.
   instance: Arc<dyn PluginInstance + Send + Sync>,
   /// Kept so load_plugin can return PactPlugin without breaking external callers.
   plugin: PactPlugin,
   access_count: Arc<AtomicUsize>,
+  health: PluginHealth,
+  /// Number of times the plugin has been restarted after its process exited
+  restarts: u32,
 }
 
 impl RegisteredPlugin {
-  fn new(instance: Arc<dyn PluginInstance + Send + Sync>, plugin: PactPlugin) -> Self {
+  pub(crate) fn new(instance: Arc<dyn PluginInstance + Send + Sync>, plugin: PactPlugin) -> Self {
     RegisteredPlugin {
       instance,
       plugin,
       access_count: Arc::new(AtomicUsize::new(1)),
+      health: PluginHealth::Healthy,
+      restarts: 0,
     }
   }
 
@@ -96,14 +174,60 @@ impl RegisteredPlugin {
 }
 
 lazy_static! {
-  static ref PLUGIN_MANIFEST_REGISTER: Mutex<HashMap<String, PactPluginManifest>> =
-    Mutex::new(HashMap::new());
-  static ref PLUGIN_REGISTER: Mutex<HashMap<String, RegisteredPlugin>> = Mutex::new(HashMap::new());
   /// Maps plugin_instance_id → plugin_name so the PluginHost Log RPC handler can
-  /// attach the plugin name to forwarded log entries without a proto field for it.
+  /// attach the plugin name to forwarded log entries without a proto field for it. Instance IDs
+  /// are unique, so this is shared by all drivers.
   static ref INSTANCE_NAMES: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
 }
 
+/// How long to wait for a plugin process to output its startup message if nothing else is set
+const DEFAULT_STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Set the restart policy of the current driver for plugins that do not set one in their
+/// manifest. Should be called at startup before any plugins load.
+pub fn set_plugin_restart_policy(policy: PluginRestartPolicy) {
+  PluginDriver::current().update_config(|config| config.restart_policy = Some(policy));
+}
+
+fn plugin_restart_policy() -> PluginRestartPolicy {
+  PluginDriver::current().config().restart_policy.unwrap_or_default()
+}
+
+/// Set how long the current driver waits for a plugin process to output its startup message, for
+/// plugins that do not set one in their manifest. Takes precedence over the
+/// `PACT_PLUGIN_STARTUP_TIMEOUT` environment variable.
+pub fn set_plugin_startup_timeout(timeout: Duration) {
+  PluginDriver::current().update_config(|config| config.startup_timeout = Some(timeout));
+}
+
+/// How long to wait for the plugin process to output its startup message. Taken from the first of
+/// the `startupTimeout` key (in seconds) of the manifest's `pluginConfig`, the timeout configured
+/// for the current driver (see [`set_plugin_startup_timeout`]) and the `PACT_PLUGIN_STARTUP_TIMEOUT` environment variable (in
+/// seconds) that is set, otherwise 60 seconds. A timeout of zero would fail every plugin straight
+/// away, so it is ignored as if it were not set.
+pub(crate) fn plugin_startup_timeout(manifest: &PactPluginManifest) -> Duration {
+  if let Some(value) = manifest.plugin_config.get("startupTimeout") {
+    match value.as_u64() {
+      Some(secs) if secs > 0 => return Duration::from_secs(secs),
+      _ => warn!("Plugin {} has an invalid startupTimeout '{}', it must be a number of seconds greater than zero. Ignoring it",
+        manifest.name, value)
+    }
+  }
+  match PluginDriver::current().config().startup_timeout {
+    Some(timeout) if !timeout.is_zero() => return timeout,
+    Some(_) => warn!("Ignoring the driver's startup timeout of zero"),
+    None => {}
+  }
+  if let Ok(value) = env::var("PACT_PLUGIN_STARTUP_TIMEOUT") {
+    match value.trim().parse::<u64>() {
+      Ok(secs) if secs > 0 => return Duration::from_secs(secs),
+      Ok(_) => warn!("Ignoring PACT_PLUGIN_STARTUP_TIMEOUT of zero"),
+      Err(err) => warn!("Ignoring invalid PACT_PLUGIN_STARTUP_TIMEOUT '{}' - {}", value, err)
+    }
+  }
+  DEFAULT_STARTUP_TIMEOUT
+}
+
 pub(crate) fn plugin_name_for_instance(instance_id: &str) -> Option<String> {
   INSTANCE_NAMES.lock().unwrap().get(instance_id).cloned()
 }
@@ -123,8 +247,8 @@ fn host_capabilities() -> Vec<String> {
     .collect()
 }
 
-/// Load the plugin defined by the dependency information. Will first look in the global
-/// plugin registry.
+/// Load the plugin defined by the dependency information. Will first look in the plugin registry
+/// of the current driver.
 pub async fn load_plugin(plugin: &PluginDependency) -> anyhow::Result<PactPlugin> {
   let thread_id = thread::current().id();
   debug!("Loading plugin {:?}", plugin);
@@ -136,40 +260,10 @@ pub async fn load_plugin(plugin: &PluginDependency) -> anyhow::Result<PactPlugin
     "load_plugin {:?}: Waiting on PLUGIN_REGISTER lock",
     thread_id
   );
-  let mut inner = PLUGIN_REGISTER.lock().unwrap();
+  let driver = PluginDriver::current();
+  let mut inner = driver.inner.plugin_register.lock().unwrap();
   trace!("load_plugin {:?}: Got PLUGIN_REGISTER lock", thread_id);
-  let result = match lookup_plugin_inner(plugin, &inner) {
-    Some(entry) => {
-      debug!("Found running plugin {:?}", entry.plugin.manifest);
-      entry.update_access();
-      Ok(entry.plugin.clone())
-    }
-    None => {
-      debug!("Did not find plugin, will attempt to start it");
-      let manifest = match load_plugin_manifest(plugin) {
-        Ok(manifest) => manifest,
-        Err(err) => {
-          warn!(
-            "Could not load plugin manifest from disk, will try auto install it: {}",
-            err
-          );
-          let http_client = reqwest::ClientBuilder::new()
-            .user_agent(USER_AGENT)
-            .build()?;
-          let index = fetch_repository_index(&http_client, None).await?;
-          match index.lookup_plugin_version(&plugin.name, &plugin.version) {
-            Some(entry) => {
-              info!("Found an entry for the plugin in the plugin index, will try install that");
-              install_plugin_from_url(&http_client, entry.source.value().as_str()).await?
-            }
-            None => Err(err)?,
-          }
-        }
-      };
-      send_metrics(&manifest);
-      initialise_plugin(&manifest, &mut inner).await
-    }
-  };
+  let result = load_plugin_inner(plugin, &mut inner, &mut vec![]).await;
   trace!(
     "load_plugin {:?}: Releasing PLUGIN_REGISTER lock",
     thread_id
@@ -177,6 +271,128 @@ pub async fn load_plugin(plugin: &PluginDependency) -> anyhow::Result<PactPlugin
   result
 }
 
+/// Load the plugin, first loading the plugins it depends on. `dependants` is the chain of plugins
+/// being loaded that (transitively) depend on this one, used to detect dependency cycles.
+fn load_plugin_inner<'a>(
+  plugin: &'a PluginDependency,
+  plugin_register: &'a mut HashMap<String, RegisteredPlugin>,
+  dependants: &'a mut Vec<PactPluginManifest>,
+) -> Pin<Box<dyn Future<Output = anyhow::Result<PactPlugin>> + 'a>> {
+  Box::pin(async move {
+    match lookup_plugin_inner(plugin, plugin_register) {
+      Some(entry) => {
+        debug!("Found running plugin {:?}", entry.plugin.manifest);
+        entry.update_access();
+        Ok(entry.plugin.clone())
+      }
+      None => {
+        debug!("Did not find plugin, will attempt to start it");
+        let lock_file = plugin_lock_file();
+        let locked = match &lock_file {
+          Some(path) => PluginLockFile::load(path)?.find(plugin).cloned(),
+          None => None
+        };
+        let resolved = match &locked {
+          Some(locked) => {
+            debug!("Plugin {} is locked to version {}", plugin, locked.version);
+            load_locked_manifest(locked)
+          }
+          None => load_plugin_manifest(plugin)
+        };
+        let manifest = match resolved {
+          Ok(manifest) => manifest,
+          Err(err) => {
+            warn!(
+              "Could not load plugin manifest from disk, will try auto install it: {}",
+              err
+            );
+            let http_client = reqwest::ClientBuilder::new()
+              .user_agent(USER_AGENT)
+              .build()?;
+            let index = fetch_repository_index(&http_client, None).await?;
+            let version = match &locked {
+              Some(locked) => Some(locked.version.clone()),
+              None => plugin.version.clone()
+            };
+            match index.lookup_plugin_version(&plugin.name, &version) {
+              Some(entry) => {
+                info!("Found an entry for the plugin in the plugin index, will try install that");
+                match mirrored_plugin_url(&plugin.name, &entry.version) {
+                  Some(url) => install_plugin_from_mirror(&http_client, url.as_str()).await?,
+                  None => install_plugin_from_url(&http_client, entry.source.value().as_str()).await?
+                }
+              }
+              None => Err(err)?,
+            }
+          }
+        };
+        if let Some(path) = &lock_file {
+          lock_plugin(path, plugin, &manifest)?;
+        }
+        load_plugin_dependencies(&manifest, plugin_register, dependants).await?;
+        send_metrics(&manifest);
+        initialise_plugin(&manifest, plugin_register).await
+      }
+    }
+  })
+}
+
+/// Load the plugins the manifest declares as dependencies (other dependency types are left to
+/// the plugin), so they are running before the plugin is started. A dependency already running
+/// at a compatible version is used as is. As with [`load_plugin`], each dependency loaded counts
+/// as an access to that plugin.
+async fn load_plugin_dependencies(
+  manifest: &PactPluginManifest,
+  plugin_register: &mut HashMap<String, RegisteredPlugin>,
+  dependants: &mut Vec<PactPluginManifest>,
+) -> anyhow::Result<()> {
+  let dependencies = manifest.dependencies.iter()
+    .flatten()
+    .filter(|dependency| dependency.dependency_type == PluginDependencyType::Plugin)
+    .collect::<Vec<_>>();
+  if dependencies.is_empty() {
+    return Ok(());
+  }
+
+  dependants.push(manifest.clone());
+  for dependency in dependencies {
+    if let Some(index) = dependants.iter().position(|m| m.name == dependency.name) {
+      let cycle = dependants[index..].iter()
+        .map(|m| format!("{}:{}", m.name, m.version))
+        .chain(std::iter::once(dependency.to_string()))
+        .join(" -> ");
+      bail!("Plugin dependency cycle detected: {}", cycle);
+    }
+
+    let running = plugin_register.values()
+      .filter(|entry| entry.plugin.manifest.name == dependency.name)
+      .collect::<Vec<_>>();
+    if let Some(entry) = running.iter()
+      .filter(|entry| versions_compatible(&entry.plugin.manifest.version, &dependency.version))
+      .max_by(|e1, e2| compare_versions(&e1.plugin.manifest.version, &e2.plugin.manifest.version)) {
+      debug!("Dependency {} of plugin {} is already running ({})", dependency, manifest.name,
+        entry.plugin.manifest.version);
+      entry.update_access();
+      continue;
+    }
+    if !running.is_empty() {
+      bail!(
+        "Plugin {}:{} requires plugin {}, but version {} of that plugin is already loaded",
+        manifest.name, manifest.version, dependency,
+        running.iter().map(|entry| entry.plugin.manifest.version.as_str()).sorted().join(", ")
+      );
+    }
+
+    debug!("Loading dependency {} of plugin {}", dependency, manifest.name);
+    load_plugin_inner(dependency, plugin_register, dependants).await
+      .with_context(|| format!("Failed to load plugin {} required by plugin {}:{}", dependency,
+        manifest.name, manifest.version))?;
+  }
+  dependants.pop();
+
+  Ok(())
+}
+
 fn lookup_plugin_inner<'a>(
   plugin: &PluginDependency,
   plugin_register: &'a HashMap<String, RegisteredPlugin>,
@@ -192,14 +408,15 @@ fn lookup_plugin_inner<'a>(
   }
 }
 
-/// Look up the plugin in the global plugin register
+/// Look up the plugin in the plugin register of the current driver
 pub fn lookup_plugin(plugin: &PluginDependency) -> Option<Arc<dyn PluginInstance + Send + Sync>> {
   let thread_id = thread::current().id();
   trace!(
     "lookup_plugin {:?}: Waiting on PLUGIN_REGISTER lock",
     thread_id
   );
-  let inner = PLUGIN_REGISTER.lock().unwrap();
+  let driver = PluginDriver::current();
+  let inner = driver.inner.plugin_register.lock().unwrap();
   trace!("lookup_plugin {:?}: Got PLUGIN_REGISTER lock", thread_id);
   let entry = lookup_plugin_inner(plugin, &inner);
   trace!(
@@ -209,8 +426,15 @@ pub fn lookup_plugin(plugin: &PluginDependency) -> Option<Arc<dyn PluginInstance
   entry.map(|e| e.instance.clone())
 }
 
-/// Return the plugin manifest for the given plugin. Will first look in the global plugin manifest
-/// registry.
+/// Return the health of the plugin in the plugin register of the current driver, if it is loaded
+pub fn plugin_health(plugin: &PluginDependency) -> Option<PluginHealth> {
+  let driver = PluginDriver::current();
+  let inner = driver.inner.plugin_register.lock().unwrap();
+  lookup_plugin_inner(plugin, &inner).map(|entry| entry.health.clone())
+}
+
+/// Return the plugin manifest for the given plugin. Will first look in the plugin manifest
+/// registry of the current driver.
 pub fn load_plugin_manifest(plugin_dep: &PluginDependency) -> anyhow::Result<PactPluginManifest> {
   debug!("Loading plugin manifest for plugin {:?}", plugin_dep);
   match lookup_plugin_manifest(plugin_dep) {
@@ -230,9 +454,34 @@ fn load_manifest_from_disk(plugin_dep: &PluginDependency) -> anyhow::Result<Pact
   }
 }
 
+/// Return the plugin manifest for the exact plugin version locked in the lock file
+fn load_locked_manifest(locked: &LockedPlugin) -> anyhow::Result<PactPluginManifest> {
+  let plugin_dep = locked.as_dependency();
+  match lookup_plugin_manifest(&plugin_dep) {
+    Some(manifest) => Ok(manifest),
+    None => {
+      let plugin_dir = pact_plugin_dir()?;
+      if plugin_dir.exists() {
+        find_manifest_in_dir(&plugin_dep, &plugin_dir, |version| version == locked.version)
+      } else {
+        Err(anyhow!("Plugin directory {:?} does not exist", plugin_dir))
+      }
+    }
+  }
+}
+
 fn load_manifest_from_dir(
   plugin_dep: &PluginDependency,
   plugin_dir: &PathBuf,
+) -> anyhow::Result<PactPluginManifest> {
+  find_manifest_in_dir(plugin_dep, plugin_dir, |version| versions_compatible(version, &plugin_dep.version))
+}
+
+/// Finds the highest version of the plugin in the plugin directory that the predicate accepts
+fn find_manifest_in_dir<F: Fn(&str) -> bool>(
+  plugin_dep: &PluginDependency,
+  plugin_dir: &PathBuf,
+  version_matches: F
 ) -> anyhow::Result<PactPluginManifest> {
   let mut manifests = vec![];
   for entry in fs::read_dir(plugin_dir)? {
@@ -248,9 +497,7 @@ fn load_manifest_from_dir(
         let manifest: PactPluginManifest = serde_json::from_reader(reader)?;
         trace!("Parsed plugin manifest: {:?}", manifest);
         let version = manifest.version.clone();
-        if manifest.name == plugin_dep.name
-          && versions_compatible(version.as_str(), &plugin_dep.version)
-        {
+        if manifest.name == plugin_dep.name && version_matches(version.as_str()) {
           let manifest = PactPluginManifest {
             plugin_dir: path.to_string_lossy().to_string(),
             ..manifest
@@ -269,7 +516,8 @@ fn load_manifest_from_dir(
   if let Some(manifest) = manifest {
     let key = format!("{}/{}", manifest.name, manifest.version);
     {
-      let mut guard = PLUGIN_MANIFEST_REGISTER.lock().unwrap();
+      let driver = PluginDriver::current();
+      let mut guard = driver.inner.plugin_manifest_register.lock().unwrap();
       guard.insert(key.clone(), manifest.clone());
     }
     Ok(manifest.clone())
@@ -281,7 +529,12 @@ fn load_manifest_from_dir(
   }
 }
 
+/// The plugin directory of the current driver, otherwise `$PACT_PLUGIN_DIR` or
+/// `$HOME/.pact/plugins`
 pub(crate) fn pact_plugin_dir() -> anyhow::Result<PathBuf> {
+  if let Some(plugin_dir) = PluginDriver::current().config().plugin_dir {
+    return Ok(plugin_dir);
+  }
   let env_var = env::var_os("PACT_PLUGIN_DIR");
   let plugin_dir = env_var.unwrap_or_default();
   let plugin_dir = plugin_dir.to_string_lossy();
@@ -295,9 +548,10 @@ pub(crate) fn pact_plugin_dir() -> anyhow::Result<PathBuf> {
   })
 }
 
-/// Lookup the plugin manifest in the global plugin manifest registry.
+/// Lookup the plugin manifest in the plugin manifest registry of the current driver.
 pub fn lookup_plugin_manifest(plugin: &PluginDependency) -> Option<PactPluginManifest> {
-  let guard = PLUGIN_MANIFEST_REGISTER.lock().unwrap();
+  let driver = PluginDriver::current();
+  let guard = driver.inner.plugin_manifest_register.lock().unwrap();
   if let Some(version) = &plugin.version {
     let key = format!("{}/{}", plugin.name, version);
     guard.get(&key).cloned()
@@ -321,6 +575,7 @@ async fn initialise_plugin(
         manifest.name, manifest.version
       )
     })?;
+  check_minimum_required_version(manifest)?;
 
   match interface_version {
     PluginInterfaceVersion::V1 | PluginInterfaceVersion::V2 => {
@@ -385,6 +640,76 @@ async fn initialise_plugin(
             ))
           }
         }
+        "wasm" => {
+          #[cfg(feature = "wasm")]
+          {
+            let instance_id = uuid::Uuid::new_v4().to_string();
+            let mut wasm_plugin = crate::wasm_plugin::start_wasm_plugin(manifest, instance_id.clone()).await?;
+            let response = init_handshake(manifest, &mut wasm_plugin, &instance_id).await.map_err(|err| {
+              anyhow!("Failed to send init request to the WASM plugin - {}", err)
+            })?;
+            wasm_plugin.set_plugin_capabilities(response.plugin_capabilities.clone());
+
+            #[allow(deprecated)]
+            let child = crate::child_process::ChildPluginProcess {
+              child_pid: 0,
+              plugin_info: crate::child_process::RunningPluginInfo {
+                port: 0,
+                server_key: String::new(),
+              },
+              instance_id: instance_id.clone(),
+            };
+            let mut pact_plugin = PactPlugin::new(manifest, child)?;
+            pact_plugin.plugin_capabilities = response.plugin_capabilities.clone();
+
+            let key = format!("{}/{}", manifest.name, manifest.version);
+            let instance: Arc<dyn PluginInstance + Send + Sync> = Arc::new(wasm_plugin);
+            plugin_register.insert(key, RegisteredPlugin::new(instance, pact_plugin.clone()));
+
+            Ok(pact_plugin)
+          }
+          #[cfg(not(feature = "wasm"))]
+          {
+            Err(anyhow!(
+              "WASM plugins are not supported (the 'wasm' feature of pact-plugin-driver is not enabled)"
+            ))
+          }
+        }
+        "python" => {
+          #[cfg(feature = "python")]
+          {
+            let instance_id = uuid::Uuid::new_v4().to_string();
+            let mut python_plugin = crate::python_plugin::start_python_plugin(manifest, instance_id.clone())?;
+            let response = init_handshake(manifest, &mut python_plugin, &instance_id).await.map_err(|err| {
+              anyhow!("Failed to send init request to the Python plugin - {}", err)
+            })?;
+            python_plugin.set_plugin_capabilities(response.plugin_capabilities.clone());
+
+            #[allow(deprecated)]
+            let child = crate::child_process::ChildPluginProcess {
+              child_pid: 0,
+              plugin_info: crate::child_process::RunningPluginInfo {
+                port: 0,
+                server_key: String::new(),
+              },
+              instance_id: instance_id.clone(),
+            };
+            let mut pact_plugin = PactPlugin::new(manifest, child)?;
+            pact_plugin.plugin_capabilities = response.plugin_capabilities.clone();
+
+            let key = format!("{}/{}", manifest.name, manifest.version);
+            let instance: Arc<dyn PluginInstance + Send + Sync> = Arc::new(python_plugin);
+            plugin_register.insert(key, RegisteredPlugin::new(instance, pact_plugin.clone()));
+
+            Ok(pact_plugin)
+          }
+          #[cfg(not(feature = "python"))]
+          {
+            Err(anyhow!(
+              "Python plugins are not supported (the 'python' feature of pact-plugin-driver is not enabled)"
+            ))
+          }
+        }
         _ => Err(anyhow!(
           "Plugin executable type of {} is not supported",
           manifest.executable_type
@@ -394,6 +719,55 @@ async fn initialise_plugin(
   }
 }
 
+/// Refuse to start a plugin whose `minimumRequiredVersion` is newer than the runtime its executable
+/// type runs on: the embedded Lua or Python interpreter, or for `exec` and `wasm` plugins (which
+/// talk to the driver directly), the newest plugin interface version the driver supports.
+fn check_minimum_required_version(manifest: &PactPluginManifest) -> anyhow::Result<()> {
+  let Some(required) = &manifest.minimum_required_version else {
+    return Ok(());
+  };
+  let (runtime, runtime_version, remedy) = match manifest.executable_type.as_str() {
+    "exec" | "wasm" => (
+      "plugin interface",
+      format!("{}.0.0", PluginInterfaceVersion::LATEST.version_number()),
+      "Upgrade to a Pact framework that uses a newer version of the plugin driver, or use an older version of the plugin"
+    ),
+    #[cfg(feature = "lua")]
+    "lua" => (
+      "Lua",
+      crate::lua_plugin::lua_version()?,
+      "Use a version of the plugin that supports this version of Lua"
+    ),
+    #[cfg(feature = "python")]
+    "python" => (
+      "Python",
+      crate::python_plugin::python_version(),
+      "Upgrade the Python installation the driver is linked against, or use an older version of the plugin"
+    ),
+    _ => return Ok(())
+  };
+
+  let required_version = lenient_semver::parse(required).map_err(|err| {
+    anyhow!("Plugin {}:{} has an invalid minimumRequiredVersion '{}' - {}", manifest.name,
+      manifest.version, required, err)
+  })?;
+  let available_version = lenient_semver::parse(&runtime_version)
+    .map_err(|err| anyhow!("Could not parse the {} version '{}' - {}", runtime, runtime_version, err))?;
+  // Only compare as many parts of the version as the runtime reports (Lua only has major.minor)
+  let required_version = match runtime_version.split('.').count() {
+    1 => Version::new(required_version.major, 0, 0),
+    2 => Version::new(required_version.major, required_version.minor, 0),
+    _ => Version::new(required_version.major, required_version.minor, required_version.patch)
+  };
+  if available_version < required_version {
+    bail!(
+      "Plugin {}:{} requires {} version {} or later (minimumRequiredVersion), but this driver provides {} {}. {}",
+      manifest.name, manifest.version, runtime, required, runtime, runtime_version, remedy
+    );
+  }
+  Ok(())
+}
+
 /// Internal function: public for testing
 pub async fn init_handshake(
   manifest: &PactPluginManifest,
@@ -412,11 +786,128 @@ pub async fn init_handshake(
     response, manifest.name
   );
   register_plugin_entries(manifest, &response.catalogue);
-  tokio::task::spawn(publish_updated_catalogue());
+  tokio::task::spawn(PluginDriver::current().scope(publish_updated_catalogue()));
   Ok(response)
 }
 
-/// Shut down all plugin processes
+/// Called when the process of an `exec` plugin exits. If the plugin is still in the plugin
+/// register of the current driver (i.e. the driver did not shut it down), it is marked unhealthy
+/// and, if its restart policy allows, restarted. Runs on the thread waiting on the process, so
+/// blocks until the restart is done.
+pub(crate) fn plugin_process_exited(instance_id: &str, exit_status: &str) {
+  let driver = PluginDriver::current();
+  let (manifest, policy, reason, restarts, restart) = {
+    let mut inner = driver.inner.plugin_register.lock().unwrap();
+    let Some((key, entry)) = inner.iter_mut()
+      .find(|(_, entry)| entry.plugin.instance_id == instance_id) else {
+      trace!("Plugin process for instance {} exited after the plugin was shut down", instance_id);
+      return;
+    };
+    let reason = format!("Plugin process exited unexpectedly ({})", exit_status);
+    entry.health = PluginHealth::Unhealthy { reason: reason.clone() };
+    let manifest = entry.plugin.manifest.clone();
+    let policy = PluginRestartPolicy::for_manifest(&manifest);
+    let restart = if policy.allows_restart(entry.restarts) {
+      Some((key.clone(), entry.restarts + 1))
+    } else {
+      None
+    };
+    (manifest, policy, reason, entry.restarts, restart)
+  };
+
+  warn!("Plugin {}/{}: {}", manifest.name, manifest.version, reason);
+  emit_lifecycle_log(&manifest, instance_id, "ERROR", reason);
+  deregister_plugin_instance(instance_id);
+
+  match restart {
+    Some((key, attempt)) => {
+      let max_restarts = match policy {
+        PluginRestartPolicy::OnFailure { max_restarts } => max_restarts,
+        PluginRestartPolicy::Never => 0
+      };
+      emit_lifecycle_log(&manifest, instance_id, "WARN",
+        format!("Restarting plugin (restart {} of {})", attempt, max_restarts));
+      let result = crate::grpc_plugin::channel_runtime()
+        .and_then(|runtime| runtime.block_on(driver.scope(restart_plugin(&key, &manifest, instance_id, attempt))));
+      if let Err(err) = result {
+        error!("Failed to restart plugin {}/{} - {}", manifest.name, manifest.version, err);
+        emit_lifecycle_log(&manifest, instance_id, "ERROR",
+          format!("Failed to restart plugin, it will remain unavailable - {}", err));
+      }
+    }
+    None if policy != PluginRestartPolicy::Never => {
+      emit_lifecycle_log(&manifest, instance_id, "ERROR",
+        format!("Plugin has already been restarted {} times, it will remain unavailable", restarts));
+    }
+    None => {}
+  }
+}
+
+/// Start a new process for a plugin whose process exited, redo the init handshake (which
+/// re-registers its catalogue entries), and swap it into the plugin register in place of the
+/// old instance.
+async fn restart_plugin(
+  key: &str,
+  manifest: &PactPluginManifest,
+  old_instance_id: &str,
+  attempt: u32
+) -> anyhow::Result<()> {
+  let plugin = start_plugin_process(manifest).await?;
+  let instance_id = plugin.instance_id.clone();
+  let mut grpc_plugin = GrpcPactPlugin::new(plugin);
+  // The restarted plugin may not provide the same entries as before
+  remove_plugin_entries(&manifest.name);
+  let response = init_handshake(manifest, &mut grpc_plugin, &instance_id).await.map_err(|err| {
+    deregister_plugin_instance(&instance_id);
+    grpc_plugin.kill();
+    anyhow!("Failed to send init request to the plugin - {}", err)
+  })?;
+  grpc_plugin.plugin.plugin_capabilities = response.plugin_capabilities;
+  let pact_plugin = grpc_plugin.plugin.clone();
+  let instance: Arc<dyn PluginInstance + Send + Sync> = Arc::new(grpc_plugin);
+
+  let replaced = {
+    let driver = PluginDriver::current();
+    let mut inner = driver.inner.plugin_register.lock().unwrap();
+    match inner.get_mut(key) {
+      Some(entry) if entry.plugin.instance_id == old_instance_id => {
+        entry.instance = instance.clone();
+        entry.plugin = pact_plugin;
+        entry.health = PluginHealth::Healthy;
+        entry.restarts = attempt;
+        true
+      }
+      _ => false
+    }
+  };
+
+  if replaced {
+    info!("Restarted plugin {}/{} (instance {})", manifest.name, manifest.version, instance_id);
+    emit_lifecycle_log(manifest, &instance_id, "INFO",
+      format!("Plugin restarted, replacing instance {}", old_instance_id));
+  } else {
+    debug!("Plugin {}/{} was shut down while it was being restarted", manifest.name, manifest.version);
+    deregister_plugin_instance(&instance_id);
+    instance.kill();
+    remove_plugin_entries(&manifest.name);
+  }
+  Ok(())
+}
+
+fn emit_lifecycle_log(manifest: &PactPluginManifest, instance_id: &str, level: &str, message: String) {
+  emit_plugin_log(&PluginLogEntry {
+    plugin_name: manifest.name.clone(),
+    plugin_instance_id: instance_id.to_string(),
+    test_run_id: None,
+    level: level.to_string(),
+    message,
+    target: Some(module_path!().to_string()),
+    timestamp_ms: Utc::now().timestamp_millis(),
+    source: PluginLogSource::Driver,
+  });
+}
+
+/// Shut down all plugin processes of the current driver
 pub fn shutdown_plugins() {
   let thread_id = thread::current().id();
   debug!("Shutting down all plugins");
@@ -424,7 +915,8 @@ pub fn shutdown_plugins() {
     "shutdown_plugins {:?}: Waiting on PLUGIN_REGISTER lock",
     thread_id
   );
-  let mut guard = PLUGIN_REGISTER.lock().unwrap();
+  let driver = PluginDriver::current();
+  let mut guard = driver.inner.plugin_register.lock().unwrap();
   trace!("shutdown_plugins {:?}: Got PLUGIN_REGISTER lock", thread_id);
   for entry in guard.values() {
     debug!("Shutting down plugin {:?}", entry.plugin.manifest);
@@ -439,8 +931,19 @@ pub fn shutdown_plugins() {
   );
 }
 
-/// Shutdown the given plugin
+/// Shutdown the given plugin. It is removed from the plugin register before its process is
+/// killed, so the process exiting is not taken as the plugin failing.
 pub fn shutdown_plugin(plugin: &dyn PluginInstance) {
+  {
+    let driver = PluginDriver::current();
+    let mut inner = driver.inner.plugin_register.lock().unwrap();
+    inner.retain(|_, entry| entry.plugin.instance_id != plugin.instance_id());
+  }
+  kill_plugin(plugin);
+}
+
+/// Kill a plugin that has already been removed from the plugin register
+fn kill_plugin(plugin: &dyn PluginInstance) {
   debug!(
     "Shutting down plugin {}:{}",
     plugin.manifest().name, plugin.manifest().version
@@ -450,7 +953,7 @@ pub fn shutdown_plugin(plugin: &dyn PluginInstance) {
   remove_plugin_entries(&plugin.manifest().name);
 }
 
-/// Publish the current catalogue to all plugins
+/// Publish the catalogue of the current driver to all its plugins
 pub async fn publish_updated_catalogue() {
   let thread_id = thread::current().id();
 
@@ -470,7 +973,8 @@ pub async fn publish_updated_catalogue() {
       "publish_updated_catalogue {:?}: Waiting on PLUGIN_REGISTER lock",
       thread_id
     );
-    let inner = PLUGIN_REGISTER.lock().unwrap();
+    let driver = PluginDriver::current();
+    let inner = driver.inner.plugin_register.lock().unwrap();
     trace!(
       "publish_updated_catalogue {:?}: Got PLUGIN_REGISTER lock",
       thread_id
@@ -502,7 +1006,8 @@ pub fn increment_plugin_access(plugin: &PluginDependency) {
     "increment_plugin_access {:?}: Waiting on PLUGIN_REGISTER lock",
     thread_id
   );
-  let inner = PLUGIN_REGISTER.lock().unwrap();
+  let driver = PluginDriver::current();
+  let inner = driver.inner.plugin_register.lock().unwrap();
   trace!(
     "increment_plugin_access {:?}: Got PLUGIN_REGISTER lock",
     thread_id
@@ -527,7 +1032,8 @@ pub fn drop_plugin_access(plugin: &PluginDependency) {
     "drop_plugin_access {:?}: Waiting on PLUGIN_REGISTER lock",
     thread_id
   );
-  let mut inner = PLUGIN_REGISTER.lock().unwrap();
+  let driver = PluginDriver::current();
+  let mut inner = driver.inner.plugin_register.lock().unwrap();
   trace!(
     "drop_plugin_access {:?}: Got PLUGIN_REGISTER lock",
     thread_id
@@ -543,8 +1049,8 @@ pub fn drop_plugin_access(plugin: &PluginDependency) {
     }
   });
   if let Some((key, instance_ref)) = shutdown_info {
-    shutdown_plugin(instance_ref.as_ref());
     inner.remove(key.as_str());
+    kill_plugin(instance_ref.as_ref());
   }
 
   trace!(
@@ -742,22 +1248,7 @@ pub async fn shutdown_mock_server(
           mismatches: result
             .mismatches
             .iter()
-            .map(|mismatch| ContentMismatch {
-              expected: mismatch
-                .expected
-                .as_ref()
-                .map(|e| from_utf8(&e).unwrap_or_default().to_string())
-                .unwrap_or_default(),
-              actual: mismatch
-                .actual
-                .as_ref()
-                .map(|a| from_utf8(&a).unwrap_or_default().to_string())
-                .unwrap_or_default(),
-              mismatch: mismatch.mismatch.clone(),
-              path: mismatch.path.clone(),
-              diff: optional_string(&mismatch.diff),
-              mismatch_type: optional_string(&mismatch.mismatch_type),
-            })
+            .map(|mismatch| ContentMismatch::from_proto(mismatch, None))
             .collect(),
         })
         .collect(),
@@ -796,22 +1287,7 @@ pub async fn get_mock_server_results(
           mismatches: result
             .mismatches
             .iter()
-            .map(|mismatch| ContentMismatch {
-              expected: mismatch
-                .expected
-                .as_ref()
-                .map(|e| from_utf8(&e).unwrap_or_default().to_string())
-                .unwrap_or_default(),
-              actual: mismatch
-                .actual
-                .as_ref()
-                .map(|a| from_utf8(&a).unwrap_or_default().to_string())
-                .unwrap_or_default(),
-              mismatch: mismatch.mismatch.clone(),
-              path: mismatch.path.clone(),
-              diff: optional_string(&mismatch.diff),
-              mismatch_type: optional_string(&mismatch.mismatch_type),
-            })
+            .map(|mismatch| ContentMismatch::from_proto(mismatch, None))
             .collect(),
         })
         .collect(),
@@ -1020,11 +1496,15 @@ pub(crate) async fn verify_interaction_inner(
 }
 
 /// Tries to download and install the plugin from the given URL, returning the manifest for the
-/// plugin if successful.
+/// plugin if successful. A `file://` URL is installed with [`install_plugin_from_mirror`].
 pub async fn install_plugin_from_url(
   http_client: &Client,
   source_url: &str,
 ) -> anyhow::Result<PactPluginManifest> {
+  if source_url.starts_with("file:") {
+    return install_plugin_from_mirror(http_client, source_url).await;
+  }
+
   let response = fetch_json_from_url(source_url, http_client).await?;
   if let Some(map) = response.as_object() {
     if let Some(tag) = map.get("tag_name") {
@@ -1038,11 +1518,10 @@ pub async fn install_plugin_from_url(
           .strip_suffix(suffix.as_str())
           .unwrap_or(source_url)
       };
-      let manifest_json = download_json_from_github(&http_client, url, &tag, "pact-plugin.json")
+      let location = release_url(url, &tag);
+      let manifest = fetch_plugin_manifest(http_client, &location)
         .await
         .context("Downloading manifest file from GitHub")?;
-      let manifest: PactPluginManifest = serde_json::from_value(manifest_json)
-        .context("Failed to parsing JSON manifest file from GitHub")?;
       debug!(?manifest, "Loaded manifest from GitHub");
 
       debug!(
@@ -1051,7 +1530,7 @@ pub async fn install_plugin_from_url(
       );
       let plugin_dir =
         create_plugin_dir(&manifest).context("Failed to creating plugins directory")?;
-      download_plugin_executable(&manifest, &plugin_dir, &http_client, url, &tag, false).await?;
+      download_plugin_executable_from(&manifest, &plugin_dir, http_client, &location, false).await?;
 
       Ok(PactPluginManifest {
         plugin_dir: plugin_dir.to_string_lossy().to_string(),
@@ -1065,6 +1544,32 @@ pub async fn install_plugin_from_url(
   }
 }
 
+/// Tries to install the plugin from a directory of a plugin repository mirror (see
+/// [`set_repository_url`](crate::repository::set_repository_url)), returning the manifest for the
+/// plugin if successful. The directory URL can be `http(s)://` or `file://`, and must hold the
+/// `pact-plugin.json` manifest and the release files for the plugin.
+pub async fn install_plugin_from_mirror(
+  http_client: &Client,
+  url: &str,
+) -> anyhow::Result<PactPluginManifest> {
+  let url = url.trim_end_matches('/');
+  let manifest = fetch_plugin_manifest(http_client, url).await?;
+  debug!(?manifest, "Loaded manifest from {}", url);
+
+  debug!(
+    "Installing plugin {} version {}",
+    manifest.name, manifest.version
+  );
+  let plugin_dir =
+    create_plugin_dir(&manifest).context("Failed to creating plugins directory")?;
+  download_plugin_executable_from(&manifest, &plugin_dir, http_client, url, false).await?;
+
+  Ok(PactPluginManifest {
+    plugin_dir: plugin_dir.to_string_lossy().to_string(),
+    ..manifest
+  })
+}
+
 fn create_plugin_dir(manifest: &PactPluginManifest) -> anyhow::Result<PathBuf> {
   let plugins_dir = pact_plugin_dir()?;
   if !plugins_dir.exists() {
@@ -1089,8 +1594,11 @@ fn create_plugin_dir(manifest: &PactPluginManifest) -> anyhow::Result<PathBuf> {
 mod tests {
   use std::collections::HashMap;
   use std::fs::{self, File};
+  use std::sync::Arc;
+  use std::time::Duration;
 
   use maplit::hashmap;
+  use serde_json::json;
   use pact_models::prelude::v4::V4Pact;
   use pact_models::v4::interaction::V4Interaction;
   use pact_models::v4::sync_message::SynchronousMessage;
@@ -1100,15 +1608,41 @@ mod tests {
 
   use crate::plugin_manager::prepare_validation_for_interaction_inner;
   use crate::plugin_manager::verify_interaction_inner;
-  use crate::plugin_models::PluginDependency;
+  use crate::child_process::{ChildPluginProcess, RunningPluginInfo};
+  use crate::plugin_models::{PactPlugin, PluginDependency, PluginDependencyType, PluginInstance};
   use crate::plugin_models::tests::{FailingInitPlugin, InitRecordingPlugin, MockPlugin};
   use crate::verification::InteractionVerificationData;
+  use crate::driver::PluginDriver;
 
   use crate::catalogue_manager::{
     CatalogueEntry, CatalogueEntryProviderType, CatalogueEntryType, register_core_entries,
   };
 
-  use super::{PactPluginManifest, init_handshake, initialise_plugin, load_manifest_from_dir};
+  use super::{
+    DEFAULT_STARTUP_TIMEOUT, PactPluginManifest, PluginHealth, plugin_startup_timeout, set_plugin_startup_timeout,
+    PluginRestartPolicy, RegisteredPlugin, check_minimum_required_version, init_handshake, initialise_plugin,
+    find_manifest_in_dir, load_manifest_from_dir, load_plugin_dependencies, plugin_health, plugin_process_exited,
+    shutdown_plugin,
+  };
+
+  #[test]
+  fn plugin_startup_timeout_ignores_a_timeout_of_zero() {
+    let driver = PluginDriver::new(Default::default());
+    let _guard = driver.enter();
+    let manifest = |timeout: Option<serde_json::Value>| PactPluginManifest {
+      name: "startup-timeout-test".to_string(),
+      plugin_config: timeout.map(|timeout| hashmap!{ "startupTimeout".to_string() => timeout }).unwrap_or_default(),
+      ..PactPluginManifest::default()
+    };
+
+    expect!(plugin_startup_timeout(&manifest(Some(json!(5))))).to(be_equal_to(Duration::from_secs(5)));
+    expect!(plugin_startup_timeout(&manifest(Some(json!(0))))).to(be_equal_to(DEFAULT_STARTUP_TIMEOUT));
+
+    set_plugin_startup_timeout(Duration::ZERO);
+    expect!(plugin_startup_timeout(&manifest(None))).to(be_equal_to(DEFAULT_STARTUP_TIMEOUT));
+    set_plugin_startup_timeout(Duration::from_secs(10));
+    expect!(plugin_startup_timeout(&manifest(Some(json!(0))))).to(be_equal_to(Duration::from_secs(10)));
+  }
 
   #[test]
   fn load_manifest_from_dir_test() {
@@ -1172,6 +1706,9 @@ mod tests {
 
     let result = load_manifest_from_dir(&dep, &tmp_dir.path().to_path_buf()).unwrap();
     expect!(result.version).to(be_equal_to("0.1.20"));
+
+    let result = find_manifest_in_dir(&dep, &tmp_dir.path().to_path_buf(), |version| version == "0.1.7").unwrap();
+    expect!(result.version).to(be_equal_to("0.1.7"));
   }
 
   #[test_log::test(tokio::test)]
@@ -1194,6 +1731,247 @@ mod tests {
     ));
   }
 
+  #[test]
+  fn restart_policy_for_manifest() {
+    let manifest = |config: serde_json::Value| PactPluginManifest {
+      name: "test-plugin".to_string(),
+      plugin_config: serde_json::from_value(config).unwrap(),
+      ..PactPluginManifest::default()
+    };
+
+    expect!(PluginRestartPolicy::for_manifest(&manifest(json!({}))))
+      .to(be_equal_to(PluginRestartPolicy::Never));
+    expect!(PluginRestartPolicy::for_manifest(&manifest(json!({"restartPolicy": "never"}))))
+      .to(be_equal_to(PluginRestartPolicy::Never));
+    expect!(PluginRestartPolicy::for_manifest(&manifest(json!({"restartPolicy": "on-failure"}))))
+      .to(be_equal_to(PluginRestartPolicy::OnFailure { max_restarts: 3 }));
+    expect!(PluginRestartPolicy::for_manifest(&manifest(json!({"restartPolicy": "on-failure", "maxRestarts": 5}))))
+      .to(be_equal_to(PluginRestartPolicy::OnFailure { max_restarts: 5 }));
+    expect!(PluginRestartPolicy::for_manifest(&manifest(json!({"restartPolicy": "always"}))))
+      .to(be_equal_to(PluginRestartPolicy::Never));
+  }
+
+  #[test]
+  fn restart_policy_allows_restart() {
+    expect!(PluginRestartPolicy::Never.allows_restart(0)).to(be_false());
+    expect!(PluginRestartPolicy::OnFailure { max_restarts: 2 }.allows_restart(0)).to(be_true());
+    expect!(PluginRestartPolicy::OnFailure { max_restarts: 2 }.allows_restart(1)).to(be_true());
+    expect!(PluginRestartPolicy::OnFailure { max_restarts: 2 }.allows_restart(2)).to(be_false());
+  }
+
+  #[test]
+  #[allow(deprecated)]
+  fn plugin_process_exited_marks_a_loaded_plugin_unhealthy() {
+    let manifest = PactPluginManifest {
+      name: "plugin-process-exited-test".to_string(),
+      version: "0.0.0".to_string(),
+      plugin_config: hashmap!{ "restartPolicy".to_string() => json!("never") },
+      ..PactPluginManifest::default()
+    };
+    let child = ChildPluginProcess {
+      child_pid: 0,
+      plugin_info: RunningPluginInfo { port: 0, server_key: String::new() },
+      instance_id: "plugin-process-exited-test-instance".to_string(),
+    };
+    let plugin = PactPlugin::new(&manifest, child).unwrap();
+    let instance: Arc<dyn PluginInstance + Send + Sync> = Arc::new(MockPlugin {
+      manifest: manifest.clone(),
+      ..MockPlugin::default()
+    });
+    let key = "plugin-process-exited-test/0.0.0".to_string();
+    PluginDriver::current().inner.plugin_register.lock().unwrap().insert(key.clone(), RegisteredPlugin::new(instance, plugin));
+
+    plugin_process_exited("some-other-instance", "exit status: 1");
+    expect!(plugin_health(&manifest.as_dependency())).to(be_some().value(PluginHealth::Healthy));
+
+    plugin_process_exited("plugin-process-exited-test-instance", "exit status: 1");
+    expect!(plugin_health(&manifest.as_dependency())).to(be_some().value(PluginHealth::Unhealthy {
+      reason: "Plugin process exited unexpectedly (exit status: 1)".to_string()
+    }));
+
+    PluginDriver::current().inner.plugin_register.lock().unwrap().remove(&key);
+  }
+
+  #[test]
+  #[allow(deprecated)]
+  fn a_plugin_that_is_shut_down_is_not_restarted_when_its_process_exits() {
+    let driver = PluginDriver::new(Default::default());
+    let _guard = driver.enter();
+    let manifest = PactPluginManifest {
+      name: "plugin-shutdown-test".to_string(),
+      version: "0.0.0".to_string(),
+      plugin_config: hashmap!{ "restartPolicy".to_string() => json!("on-failure") },
+      ..PactPluginManifest::default()
+    };
+    let child = ChildPluginProcess {
+      child_pid: 0,
+      plugin_info: RunningPluginInfo { port: 0, server_key: String::new() },
+      instance_id: "test-instance".to_string(),
+    };
+    let plugin = PactPlugin::new(&manifest, child).unwrap();
+    let instance: Arc<dyn PluginInstance + Send + Sync> = Arc::new(MockPlugin {
+      manifest: manifest.clone(),
+      ..MockPlugin::default()
+    });
+    driver.inner.plugin_register.lock().unwrap()
+      .insert("plugin-shutdown-test/0.0.0".to_string(), RegisteredPlugin::new(instance.clone(), plugin));
+
+    shutdown_plugin(instance.as_ref());
+    plugin_process_exited("test-instance", "signal: 9 (SIGKILL)");
+
+    expect!(plugin_health(&manifest.as_dependency())).to(be_none());
+    expect!(driver.inner.plugin_register.lock().unwrap().is_empty()).to(be_true());
+  }
+
+  fn manifest_with_dependencies(name: &str, version: &str, dependencies: &[(&str, &str)]) -> PactPluginManifest {
+    PactPluginManifest {
+      name: name.to_string(),
+      version: version.to_string(),
+      dependencies: Some(dependencies.iter().map(|(name, version)| PluginDependency {
+        name: name.to_string(),
+        version: Some(version.to_string()),
+        dependency_type: PluginDependencyType::Plugin,
+      }).collect()),
+      ..PactPluginManifest::default()
+    }
+  }
+
+  #[allow(deprecated)]
+  fn registered_mock_plugin(name: &str, version: &str) -> RegisteredPlugin {
+    let manifest = PactPluginManifest {
+      name: name.to_string(),
+      version: version.to_string(),
+      ..PactPluginManifest::default()
+    };
+    let child = ChildPluginProcess {
+      child_pid: 0,
+      plugin_info: RunningPluginInfo { port: 0, server_key: String::new() },
+      instance_id: format!("{}-instance", name),
+    };
+    let plugin = PactPlugin::new(&manifest, child).unwrap();
+    RegisteredPlugin::new(Arc::new(MockPlugin { manifest, ..MockPlugin::default() }), plugin)
+  }
+
+  #[test_log::test(tokio::test)]
+  async fn load_plugin_dependencies_detects_cycles() {
+    let manifest_a = manifest_with_dependencies("dependency-cycle-a", "1.0.0", &[("dependency-cycle-b", "1.0.0")]);
+    let manifest_b = manifest_with_dependencies("dependency-cycle-b", "1.0.0", &[("dependency-cycle-a", "1.0.0")]);
+    PluginDriver::current().inner.plugin_manifest_register.lock().unwrap()
+      .insert("dependency-cycle-b/1.0.0".to_string(), manifest_b);
+
+    let err = load_plugin_dependencies(&manifest_a, &mut HashMap::new(), &mut vec![])
+      .await
+      .unwrap_err();
+
+    expect!(format!("{:#}", err)).to(be_equal_to(
+      "Failed to load plugin dependency-cycle-b:1.0.0 required by plugin dependency-cycle-a:1.0.0: \
+      Plugin dependency cycle detected: dependency-cycle-a:1.0.0 -> dependency-cycle-b:1.0.0 -> dependency-cycle-a:1.0.0"
+    ));
+  }
+
+  #[test_log::test(tokio::test)]
+  async fn load_plugin_dependencies_uses_a_running_plugin_with_a_compatible_version() {
+    let manifest = manifest_with_dependencies("dependant", "1.0.0", &[("dependency", "2.0.0")]);
+    let dependency = registered_mock_plugin("dependency", "2.1.0");
+    let access_count = dependency.access_count.clone();
+    let mut plugin_register = hashmap!{ "dependency/2.1.0".to_string() => dependency };
+
+    load_plugin_dependencies(&manifest, &mut plugin_register, &mut vec![]).await.unwrap();
+
+    expect!(access_count.load(std::sync::atomic::Ordering::SeqCst)).to(be_equal_to(2));
+  }
+
+  #[test_log::test(tokio::test)]
+  async fn load_plugin_dependencies_uses_the_newest_running_version() {
+    let manifest = manifest_with_dependencies("dependant", "1.0.0", &[("dependency", "0.9.0")]);
+    let older = registered_mock_plugin("dependency", "0.9.0");
+    let newer = registered_mock_plugin("dependency", "0.10.0");
+    let older_access_count = older.access_count.clone();
+    let newer_access_count = newer.access_count.clone();
+    let mut plugin_register = hashmap!{
+      "dependency/0.9.0".to_string() => older,
+      "dependency/0.10.0".to_string() => newer
+    };
+
+    load_plugin_dependencies(&manifest, &mut plugin_register, &mut vec![]).await.unwrap();
+
+    expect!(older_access_count.load(std::sync::atomic::Ordering::SeqCst)).to(be_equal_to(1));
+    expect!(newer_access_count.load(std::sync::atomic::Ordering::SeqCst)).to(be_equal_to(2));
+  }
+
+  #[test_log::test(tokio::test)]
+  async fn load_plugin_dependencies_fails_on_a_version_conflict() {
+    let manifest = manifest_with_dependencies("dependant", "1.0.0", &[("dependency", "2.0.0")]);
+    let mut plugin_register = hashmap!{
+      "dependency/1.4.2".to_string() => registered_mock_plugin("dependency", "1.4.2")
+    };
+
+    let err = load_plugin_dependencies(&manifest, &mut plugin_register, &mut vec![])
+      .await
+      .unwrap_err();
+
+    expect!(err.to_string()).to(be_equal_to(
+      "Plugin dependant:1.0.0 requires plugin dependency:2.0.0, but version 1.4.2 of that plugin is already loaded"
+    ));
+  }
+
+  fn manifest_with_minimum_version(executable_type: &str, minimum_required_version: Option<&str>) -> PactPluginManifest {
+    PactPluginManifest {
+      name: "test-plugin".to_string(),
+      version: "0.0.0".to_string(),
+      executable_type: executable_type.to_string(),
+      minimum_required_version: minimum_required_version.map(|v| v.to_string()),
+      ..PactPluginManifest::default()
+    }
+  }
+
+  #[test]
+  fn check_minimum_required_version_for_exec_plugins() {
+    expect!(check_minimum_required_version(&manifest_with_minimum_version("exec", None))).to(be_ok());
+    expect!(check_minimum_required_version(&manifest_with_minimum_version("exec", Some("2.0.0")))).to(be_ok());
+    expect!(check_minimum_required_version(&manifest_with_minimum_version("exec", Some("1")))).to(be_ok());
+
+    let err = check_minimum_required_version(&manifest_with_minimum_version("exec", Some("3.0.0")))
+      .unwrap_err();
+    expect!(err.to_string()).to(be_equal_to(
+      "Plugin test-plugin:0.0.0 requires plugin interface version 3.0.0 or later (minimumRequiredVersion), \
+      but this driver provides plugin interface 2.0.0. Upgrade to a Pact framework that uses a newer version \
+      of the plugin driver, or use an older version of the plugin"
+    ));
+
+    let err = check_minimum_required_version(&manifest_with_minimum_version("exec", Some("not a version")))
+      .unwrap_err();
+    expect!(err.to_string().starts_with(
+      "Plugin test-plugin:0.0.0 has an invalid minimumRequiredVersion 'not a version'"
+    )).to(be_true());
+  }
+
+  #[test]
+  #[cfg(feature = "lua")]
+  fn check_minimum_required_version_for_lua_plugins() {
+    expect!(check_minimum_required_version(&manifest_with_minimum_version("lua", Some("5.4.6")))).to(be_ok());
+    expect!(check_minimum_required_version(&manifest_with_minimum_version("lua", Some("5.1")))).to(be_ok());
+
+    let err = check_minimum_required_version(&manifest_with_minimum_version("lua", Some("5.5.0")))
+      .unwrap_err();
+    expect!(err.to_string()).to(be_equal_to(
+      "Plugin test-plugin:0.0.0 requires Lua version 5.5.0 or later (minimumRequiredVersion), \
+      but this driver provides Lua 5.4. Use a version of the plugin that supports this version of Lua"
+    ));
+  }
+
+  #[test]
+  #[cfg(feature = "python")]
+  fn check_minimum_required_version_for_python_plugins() {
+    expect!(check_minimum_required_version(&manifest_with_minimum_version("python", Some("3.0")))).to(be_ok());
+
+    let err = check_minimum_required_version(&manifest_with_minimum_version("python", Some("99.0")))
+      .unwrap_err();
+    expect!(err.to_string().starts_with(
+      "Plugin test-plugin:0.0.0 requires Python version 99.0 or later (minimumRequiredVersion), but this driver provides Python 3."
+    )).to(be_true());
+  }
+
   #[test_log::test(tokio::test)]
   async fn init_handshake_sends_host_capabilities_and_returns_plugin_capabilities() {
     register_core_entries(&vec![CatalogueEntry {
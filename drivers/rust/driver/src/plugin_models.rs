@@ -147,6 +147,19 @@ pub struct PluginInitResponse {
   pub plugin_capabilities: Vec<String>,
 }
 
+impl PluginInterfaceVersion {
+  /// The newest plugin interface version this driver supports
+  pub const LATEST: PluginInterfaceVersion = PluginInterfaceVersion::V2;
+
+  /// The interface version number, as used in the plugin manifest
+  pub fn version_number(&self) -> u8 {
+    match self {
+      PluginInterfaceVersion::V1 => 1,
+      PluginInterfaceVersion::V2 => 2,
+    }
+  }
+}
+
 impl TryFrom<u8> for PluginInterfaceVersion {
   type Error = anyhow::Error;
 
@@ -216,6 +229,12 @@ pub fn check_interaction_type_capability(
   }
 }
 
+/// Capability a plugin declares when it accepts field-level calls in batches (`MatchFieldsBatch`
+/// and `GenerateFieldsBatch`), so a body with many values handled by the plugin needs one call
+/// rather than one per value. This is an optional capability: for a plugin that does not declare
+/// it, the driver makes a call for each value.
+pub const FIELD_BATCH_CAPABILITY: &str = "field/batch";
+
 /// Trait for the plugin init handshake only (used by anything that can handle the init message)
 #[async_trait]
 pub trait PactPluginRpc {
@@ -252,9 +271,10 @@ pub trait PluginInstance: std::fmt::Debug + Send + Sync {
   /// Send a compare contents request to the plugin process, propagating call-chain cycle
   /// detection and deadline metadata (see [`crate::call_chain`]) for transports that support it.
   /// The default implementation ignores `chain_id`/`deadline_ms` and delegates to
-  /// [`PluginInstance::compare_contents`], which suits in-process runtimes (Lua, WASM) where a
-  /// cycle is already caught by the native call stack; [`crate::grpc_plugin::GrpcPactPlugin`]
-  /// overrides this to send the metadata over gRPC.
+  /// [`PluginInstance::compare_contents`], which suits in-process runtimes (Lua) where a cycle is
+  /// already caught by the native call stack; [`crate::grpc_plugin::GrpcPactPlugin`] overrides
+  /// this to send the metadata over gRPC, and the WASM runtime to check for a call back into the
+  /// same instance.
   async fn compare_contents_with_chain(
     &self,
     request: CompareContentsRequest,
@@ -336,6 +356,66 @@ pub trait PluginInstance: std::fmt::Debug + Send + Sync {
     self.generate_field(request).await
   }
 
+  /// Apply plugin-provided matching rules to several values in one call. Only used for plugins
+  /// that declare the [`FIELD_BATCH_CAPABILITY`] capability; otherwise the driver calls
+  /// [`PluginInstance::match_field`] for each value. See [`PluginInstance::match_field`].
+  ///
+  /// The default calls [`PluginInstance::match_field`] for each request in turn, so a plugin
+  /// runtime that can't batch the calls still works with a plugin that declares the capability. A
+  /// failure for one value is reported in that value's response.
+  async fn match_fields_batch(
+    &self,
+    request: proto_v2::MatchFieldsBatchRequest,
+  ) -> anyhow::Result<proto_v2::MatchFieldsBatchResponse> {
+    let mut responses = Vec::with_capacity(request.requests.len());
+    for field_request in request.requests {
+      let response = self.match_field(field_request).await
+        .unwrap_or_else(|err| proto_v2::MatchFieldResponse { error: err.to_string(), mismatches: vec![] });
+      responses.push(response);
+    }
+    Ok(proto_v2::MatchFieldsBatchResponse { error: String::new(), responses })
+  }
+
+  /// Apply plugin-provided matching rules to several values in one call, propagating call-chain
+  /// cycle detection and deadline metadata. See [`PluginInstance::match_field_with_chain`].
+  async fn match_fields_batch_with_chain(
+    &self,
+    request: proto_v2::MatchFieldsBatchRequest,
+    chain_id: &str,
+    deadline_ms: u64,
+  ) -> anyhow::Result<proto_v2::MatchFieldsBatchResponse> {
+    let _ = (chain_id, deadline_ms);
+    self.match_fields_batch(request).await
+  }
+
+  /// Apply plugin-provided generators to several values in one call. See
+  /// [`PluginInstance::match_fields_batch`], the default likewise calls
+  /// [`PluginInstance::generate_field`] for each request.
+  async fn generate_fields_batch(
+    &self,
+    request: proto_v2::GenerateFieldsBatchRequest,
+  ) -> anyhow::Result<proto_v2::GenerateFieldsBatchResponse> {
+    let mut responses = Vec::with_capacity(request.requests.len());
+    for field_request in request.requests {
+      let response = self.generate_field(field_request).await
+        .unwrap_or_else(|err| proto_v2::GenerateFieldResponse { error: err.to_string(), value: None });
+      responses.push(response);
+    }
+    Ok(proto_v2::GenerateFieldsBatchResponse { error: String::new(), responses })
+  }
+
+  /// Apply plugin-provided generators to several values in one call, propagating call-chain cycle
+  /// detection and deadline metadata. See [`PluginInstance::match_field_with_chain`].
+  async fn generate_fields_batch_with_chain(
+    &self,
+    request: proto_v2::GenerateFieldsBatchRequest,
+    chain_id: &str,
+    deadline_ms: u64,
+  ) -> anyhow::Result<proto_v2::GenerateFieldsBatchResponse> {
+    let _ = (chain_id, deadline_ms);
+    self.generate_fields_batch(request).await
+  }
+
   /// Start a mock server
   async fn start_mock_server(
     &self,
@@ -521,8 +601,9 @@ pub(crate) mod tests {
   use pact_models::v4::V4InteractionType;
 
   use crate::plugin_models::{
-    ALL_INTERACTION_TYPES, PactPluginManifest, PluginInitRequest, PluginInitResponse,
-    PluginInstance, check_interaction_type_capability, interaction_type_capability,
+    ALL_INTERACTION_TYPES, FIELD_BATCH_CAPABILITY, PactPluginManifest, PluginInitRequest,
+    PluginInitResponse, PluginInstance, check_interaction_type_capability,
+    interaction_type_capability,
   };
   use crate::proto::verification_preparation_response::Response;
   use crate::proto::*;
@@ -535,6 +616,8 @@ pub(crate) mod tests {
     pub verify_request: RwLock<VerifyInteractionRequest>,
     pub prepare_request_v2: RwLock<Option<proto_v2::VerificationPreparationRequest>>,
     pub verify_request_v2: RwLock<Option<proto_v2::VerifyInteractionRequest>>,
+    /// Field-level calls received, as `<operation>:<path or batch size>`
+    pub field_calls: RwLock<Vec<String>>,
   }
 
   impl std::fmt::Debug for MockPlugin {
@@ -554,6 +637,7 @@ pub(crate) mod tests {
         verify_request: RwLock::new(VerifyInteractionRequest::default()),
         prepare_request_v2: RwLock::new(None),
         verify_request_v2: RwLock::new(None),
+        field_calls: RwLock::new(vec![]),
       }
     }
   }
@@ -681,6 +765,173 @@ pub(crate) mod tests {
     async fn update_catalogue(&self, _request: Catalogue) -> anyhow::Result<()> {
       unimplemented!()
     }
+
+    async fn match_field(
+      &self,
+      request: proto_v2::MatchFieldRequest,
+    ) -> anyhow::Result<proto_v2::MatchFieldResponse> {
+      self.field_calls.write().unwrap().push(format!("match_field:{}", request.path));
+      Ok(mock_match_field(&request))
+    }
+
+    async fn generate_field(
+      &self,
+      request: proto_v2::GenerateFieldRequest,
+    ) -> anyhow::Result<proto_v2::GenerateFieldResponse> {
+      self.field_calls.write().unwrap().push(format!("generate_field:{}", request.path));
+      Ok(mock_generate_field(&request))
+    }
+
+    async fn match_fields_batch(
+      &self,
+      request: proto_v2::MatchFieldsBatchRequest,
+    ) -> anyhow::Result<proto_v2::MatchFieldsBatchResponse> {
+      if !self.has_capability(FIELD_BATCH_CAPABILITY) {
+        return Err(anyhow::anyhow!("match_fields_batch called without the capability"));
+      }
+      self.field_calls.write().unwrap().push(format!("match_fields_batch:{}", request.requests.len()));
+      Ok(proto_v2::MatchFieldsBatchResponse {
+        error: String::new(),
+        responses: request.requests.iter().map(mock_match_field).collect(),
+      })
+    }
+
+    async fn generate_fields_batch(
+      &self,
+      request: proto_v2::GenerateFieldsBatchRequest,
+    ) -> anyhow::Result<proto_v2::GenerateFieldsBatchResponse> {
+      if !self.has_capability(FIELD_BATCH_CAPABILITY) {
+        return Err(anyhow::anyhow!("generate_fields_batch called without the capability"));
+      }
+      self.field_calls.write().unwrap().push(format!("generate_fields_batch:{}", request.requests.len()));
+      Ok(proto_v2::GenerateFieldsBatchResponse {
+        error: String::new(),
+        responses: request.requests.iter().map(mock_generate_field).collect(),
+      })
+    }
+  }
+
+  /// Mismatches when the actual value differs from the expected one
+  fn mock_match_field(request: &proto_v2::MatchFieldRequest) -> proto_v2::MatchFieldResponse {
+    let mismatches = if request.expected == request.actual {
+      vec![]
+    } else {
+      vec![proto_v2::ContentMismatch {
+        mismatch: "Values differ".to_string(),
+        path: request.path.clone(),
+        ..proto_v2::ContentMismatch::default()
+      }]
+    };
+    proto_v2::MatchFieldResponse { error: String::new(), mismatches }
+  }
+
+  /// Generates a string naming the path it was generated for
+  fn mock_generate_field(request: &proto_v2::GenerateFieldRequest) -> proto_v2::GenerateFieldResponse {
+    proto_v2::GenerateFieldResponse {
+      error: String::new(),
+      value: Some(proto_v2::FieldValue {
+        value: Some(proto_v2::field_value::Value::StringValue(format!("generated {}", request.path))),
+      }),
+    }
+  }
+
+  /// Declares [`FIELD_BATCH_CAPABILITY`] but, like a script plugin, only implements the
+  /// single-value field calls, so the batch calls are the [`PluginInstance`] defaults
+  #[derive(Debug)]
+  struct PerFieldPlugin {
+    mock: MockPlugin,
+  }
+
+  #[async_trait]
+  impl PluginInstance for PerFieldPlugin {
+    fn manifest(&self) -> &PactPluginManifest {
+      self.mock.manifest()
+    }
+
+    fn instance_id(&self) -> &str {
+      self.mock.instance_id()
+    }
+
+    fn has_capability(&self, capability: &str) -> bool {
+      self.mock.has_capability(capability)
+    }
+
+    async fn compare_contents(
+      &self,
+      request: CompareContentsRequest,
+    ) -> anyhow::Result<CompareContentsResponse> {
+      self.mock.compare_contents(request).await
+    }
+
+    async fn configure_interaction(
+      &self,
+      request: ConfigureInteractionRequest,
+    ) -> anyhow::Result<ConfigureInteractionResponse> {
+      self.mock.configure_interaction(request).await
+    }
+
+    async fn generate_content(
+      &self,
+      request: GenerateContentRequest,
+    ) -> anyhow::Result<GenerateContentResponse> {
+      self.mock.generate_content(request).await
+    }
+
+    async fn start_mock_server(
+      &self,
+      request: StartMockServerRequest,
+    ) -> anyhow::Result<StartMockServerResponse> {
+      self.mock.start_mock_server(request).await
+    }
+
+    async fn shutdown_mock_server(
+      &self,
+      request: ShutdownMockServerRequest,
+    ) -> anyhow::Result<ShutdownMockServerResponse> {
+      self.mock.shutdown_mock_server(request).await
+    }
+
+    async fn get_mock_server_results(
+      &self,
+      request: MockServerRequest,
+    ) -> anyhow::Result<MockServerResults> {
+      self.mock.get_mock_server_results(request).await
+    }
+
+    async fn prepare_interaction_for_verification(
+      &self,
+      request: VerificationPreparationRequest,
+    ) -> anyhow::Result<VerificationPreparationResponse> {
+      self.mock.prepare_interaction_for_verification(request).await
+    }
+
+    async fn verify_interaction(
+      &self,
+      request: VerifyInteractionRequest,
+    ) -> anyhow::Result<VerifyInteractionResponse> {
+      self.mock.verify_interaction(request).await
+    }
+
+    async fn update_catalogue(&self, request: Catalogue) -> anyhow::Result<()> {
+      self.mock.update_catalogue(request).await
+    }
+
+    async fn match_field(
+      &self,
+      request: proto_v2::MatchFieldRequest,
+    ) -> anyhow::Result<proto_v2::MatchFieldResponse> {
+      if request.path == "$.error" {
+        return Err(anyhow::anyhow!("could not check {}", request.path));
+      }
+      self.mock.match_field(request).await
+    }
+
+    async fn generate_field(
+      &self,
+      request: proto_v2::GenerateFieldRequest,
+    ) -> anyhow::Result<proto_v2::GenerateFieldResponse> {
+      self.mock.generate_field(request).await
+    }
   }
 
   pub(crate) struct FailingInitPlugin {
@@ -775,4 +1026,67 @@ pub(crate) mod tests {
     )
     .to(be_true());
   }
+
+  #[tokio::test]
+  async fn batch_calls_default_to_one_call_per_field() {
+    let plugin = PerFieldPlugin {
+      mock: MockPlugin {
+        capabilities: vec![FIELD_BATCH_CAPABILITY.to_string()],
+        ..MockPlugin::default()
+      },
+    };
+    let match_request = |path: &str, expected: &str, actual: &str| proto_v2::MatchFieldRequest {
+      path: path.to_string(),
+      expected: Some(proto_v2::FieldValue {
+        value: Some(proto_v2::field_value::Value::StringValue(expected.to_string())),
+      }),
+      actual: Some(proto_v2::FieldValue {
+        value: Some(proto_v2::field_value::Value::StringValue(actual.to_string())),
+      }),
+      ..proto_v2::MatchFieldRequest::default()
+    };
+
+    let response = plugin
+      .match_fields_batch(proto_v2::MatchFieldsBatchRequest {
+        requests: vec![
+          match_request("$.a", "1", "1"),
+          match_request("$.error", "2", "2"),
+          match_request("$.b", "3", "4"),
+        ],
+      })
+      .await
+      .unwrap();
+
+    expect!(response.error.as_str()).to(be_equal_to(""));
+    expect!(response.responses.len()).to(be_equal_to(3));
+    expect!(response.responses[0].mismatches.is_empty()).to(be_true());
+    expect!(response.responses[1].error.as_str()).to(be_equal_to("could not check $.error"));
+    expect!(response.responses[2].mismatches.len()).to(be_equal_to(1));
+
+    let response = plugin
+      .generate_fields_batch(proto_v2::GenerateFieldsBatchRequest {
+        requests: vec![
+          proto_v2::GenerateFieldRequest { path: "$.a".to_string(), ..Default::default() },
+          proto_v2::GenerateFieldRequest { path: "$.b".to_string(), ..Default::default() },
+        ],
+      })
+      .await
+      .unwrap();
+
+    expect!(response.error.as_str()).to(be_equal_to(""));
+    expect!(response.responses.iter().map(|r| r.value.clone()).collect::<Vec<_>>()).to(be_equal_to(vec![
+      Some(proto_v2::FieldValue {
+        value: Some(proto_v2::field_value::Value::StringValue("generated $.a".to_string())),
+      }),
+      Some(proto_v2::FieldValue {
+        value: Some(proto_v2::field_value::Value::StringValue("generated $.b".to_string())),
+      }),
+    ]));
+    expect!(plugin.mock.field_calls.read().unwrap().clone()).to(be_equal_to(vec![
+      "match_field:$.a".to_string(),
+      "match_field:$.b".to_string(),
+      "generate_field:$.a".to_string(),
+      "generate_field:$.b".to_string(),
+    ]));
+  }
 }
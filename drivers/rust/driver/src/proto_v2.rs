@@ -257,6 +257,72 @@ pub struct ContentMismatch {
     /// Part of the interaction that the mismatch is for: body, headers, metadata, etc.
     #[prost(string, tag = "6")]
     pub mismatch_type: ::prost::alloc::string::String,
+    /// Optional structured diff of the contents. The driver renders this the same way for all plugins, and uses it for
+    /// the diff if one is not set.
+    #[prost(message, repeated, tag = "7")]
+    pub structured_diff: ::prost::alloc::vec::Vec<DiffEntry>,
+}
+/// A change in a structured diff of the expected and actual contents
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct DiffEntry {
+    /// Type of change
+    #[prost(enumeration = "diff_entry::ChangeType", tag = "1")]
+    pub change_type: i32,
+    /// Path to the value that changed. This is the value as per the documented Pact matching rule expressions.
+    #[prost(string, tag = "2")]
+    pub path: ::prost::alloc::string::String,
+    /// Expected value as JSON. Not set for added values.
+    #[prost(message, optional, tag = "3")]
+    pub expected: ::core::option::Option<::prost::alloc::string::String>,
+    /// Actual value as JSON. Not set for removed values.
+    #[prost(message, optional, tag = "4")]
+    pub actual: ::core::option::Option<::prost::alloc::string::String>,
+}
+/// Nested message and enum types in `DiffEntry`.
+pub mod diff_entry {
+    /// Type of change
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum ChangeType {
+        /// The value at the path is different
+        Changed = 0,
+        /// The value at the path is in the actual contents but not the expected contents
+        Added = 1,
+        /// The value at the path is in the expected contents but not the actual contents
+        Removed = 2,
+    }
+    impl ChangeType {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Self::Changed => "CHANGED",
+                Self::Added => "ADDED",
+                Self::Removed => "REMOVED",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "CHANGED" => Some(Self::Changed),
+                "ADDED" => Some(Self::Added),
+                "REMOVED" => Some(Self::Removed),
+                _ => None,
+            }
+        }
+    }
 }
 /// List of content mismatches
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -685,6 +751,44 @@ pub struct GenerateFieldResponse {
     #[prost(message, optional, tag = "2")]
     pub value: ::core::option::Option<FieldValue>,
 }
+/// Request to apply plugin-provided matching rules to several values in one call, rather than making a call for each
+/// value. Only sent to plugins that declare the "field/batch" capability. See MatchFieldRequest.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MatchFieldsBatchRequest {
+    /// Request for each value to be matched
+    #[prost(message, repeated, tag = "1")]
+    pub requests: ::prost::alloc::vec::Vec<MatchFieldRequest>,
+}
+/// Response to the MatchFieldsBatchRequest
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MatchFieldsBatchResponse {
+    /// Error message if the batch could not be processed at all. If this field is set, the responses will be ignored and
+    /// every value in the batch marked as failed
+    #[prost(string, tag = "1")]
+    pub error: ::prost::alloc::string::String,
+    /// Response for each request in the batch, in the same order as the requests
+    #[prost(message, repeated, tag = "2")]
+    pub responses: ::prost::alloc::vec::Vec<MatchFieldResponse>,
+}
+/// Request to generate several values in one call using plugin-provided generators, rather than making a call for
+/// each value. Only sent to plugins that declare the "field/batch" capability. See GenerateFieldRequest.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GenerateFieldsBatchRequest {
+    /// Request for each value to be generated
+    #[prost(message, repeated, tag = "1")]
+    pub requests: ::prost::alloc::vec::Vec<GenerateFieldRequest>,
+}
+/// Response to the GenerateFieldsBatchRequest
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GenerateFieldsBatchResponse {
+    /// Error message if the batch could not be processed at all. If this field is set, the responses will be ignored and
+    /// every value in the batch marked as failed
+    #[prost(string, tag = "1")]
+    pub error: ::prost::alloc::string::String,
+    /// Response for each request in the batch, in the same order as the requests
+    #[prost(message, repeated, tag = "2")]
+    pub responses: ::prost::alloc::vec::Vec<GenerateFieldResponse>,
+}
 /// Request to start a mock server
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct StartMockServerRequest {
@@ -1862,6 +1966,61 @@ pub mod pact_plugin_client {
                 );
             self.inner.unary(req, path, codec).await
         }
+        /// Apply plugin-provided matching rules to several values in one call. Only called for plugins that declare the
+        /// "field/batch" capability, otherwise MatchField is called for each value.
+        pub async fn match_fields_batch(
+            &mut self,
+            request: impl tonic::IntoRequest<super::MatchFieldsBatchRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MatchFieldsBatchResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/io.pact.plugin.v2.PactPlugin/MatchFieldsBatch",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("io.pact.plugin.v2.PactPlugin", "MatchFieldsBatch"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        /// Apply plugin-provided generators to several values in one call. See MatchFieldsBatch.
+        pub async fn generate_fields_batch(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GenerateFieldsBatchRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GenerateFieldsBatchResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/io.pact.plugin.v2.PactPlugin/GenerateFieldsBatch",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("io.pact.plugin.v2.PactPlugin", "GenerateFieldsBatch"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
         /// Start a mock server
         pub async fn start_mock_server(
             &mut self,
@@ -2074,6 +2233,23 @@ pub mod pact_plugin_server {
             tonic::Response<super::GenerateFieldResponse>,
             tonic::Status,
         >;
+        /// Apply plugin-provided matching rules to several values in one call. Only called for plugins that declare the
+        /// "field/batch" capability, otherwise MatchField is called for each value.
+        async fn match_fields_batch(
+            &self,
+            request: tonic::Request<super::MatchFieldsBatchRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MatchFieldsBatchResponse>,
+            tonic::Status,
+        >;
+        /// Apply plugin-provided generators to several values in one call. See MatchFieldsBatch.
+        async fn generate_fields_batch(
+            &self,
+            request: tonic::Request<super::GenerateFieldsBatchRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GenerateFieldsBatchResponse>,
+            tonic::Status,
+        >;
         /// Start a mock server
         async fn start_mock_server(
             &self,
@@ -2506,6 +2682,96 @@ pub mod pact_plugin_server {
                     };
                     Box::pin(fut)
                 }
+                "/io.pact.plugin.v2.PactPlugin/MatchFieldsBatch" => {
+                    #[allow(non_camel_case_types)]
+                    struct MatchFieldsBatchSvc<T: PactPlugin>(pub Arc<T>);
+                    impl<
+                        T: PactPlugin,
+                    > tonic::server::UnaryService<super::MatchFieldsBatchRequest>
+                    for MatchFieldsBatchSvc<T> {
+                        type Response = super::MatchFieldsBatchResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::MatchFieldsBatchRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as PactPlugin>::match_fields_batch(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = MatchFieldsBatchSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/io.pact.plugin.v2.PactPlugin/GenerateFieldsBatch" => {
+                    #[allow(non_camel_case_types)]
+                    struct GenerateFieldsBatchSvc<T: PactPlugin>(pub Arc<T>);
+                    impl<
+                        T: PactPlugin,
+                    > tonic::server::UnaryService<super::GenerateFieldsBatchRequest>
+                    for GenerateFieldsBatchSvc<T> {
+                        type Response = super::GenerateFieldsBatchResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GenerateFieldsBatchRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as PactPlugin>::generate_fields_batch(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GenerateFieldsBatchSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/io.pact.plugin.v2.PactPlugin/StartMockServer" => {
                     #[allow(non_camel_case_types)]
                     struct StartMockServerSvc<T: PactPlugin>(pub Arc<T>);
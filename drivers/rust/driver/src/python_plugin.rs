@@ -0,0 +1,1832 @@
+//! Support for Pact plugins written in Python (proposal 010).
+//!
+//! A Python plugin is loaded into an embedded CPython interpreter (via [`pyo3`]) running in the
+//! driver's own process (`executableType: "python"` in `pact-plugin.json`), instead of a separate
+//! child process speaking gRPC. It is the Python counterpart of [`crate::lua_plugin`], and the
+//! plugin script defines the same functions a Lua plugin does, as top-level functions of the
+//! script:
+//!
+//! - `init(implementation, version) -> list` - returns a list of catalogue entries, each shaped
+//!   as `{"entryType": "CONTENT_MATCHER", "key": "...", "values": {...}}`.
+//! - `configure_interaction(content_type, config) -> dict` - see [`PluginInstance::configure_interaction`].
+//! - `match_contents(request) -> dict` - see [`PluginInstance::compare_contents`].
+//! - `generate_content(contents, generators, test_mode)` (optional) - see [`PluginInstance::generate_content`].
+//! - `update_catalogue(catalogue)` (optional) - see [`PluginInstance::update_catalogue`].
+//! - `match_field(request)`/`generate_field(request)` - for `MATCHER`/`GENERATOR` entries.
+//! - `start_mock_server`, `shutdown_mock_server`, `get_mock_server_results`,
+//!   `prepare_interaction_for_verification` and `verify_interaction` - for `TRANSPORT` entries,
+//!   called with a V1 or V2 shaped request depending on the manifest's `pluginInterfaceVersion`.
+//!
+//! Requests and responses are plain dicts, lists and scalars with the same keys and nesting as the
+//! tables the Lua runtime uses, so a plugin can be ported between the two languages function by
+//! function. The one difference is binary data: Lua strings are byte strings, so the Lua runtime
+//! wraps a binary metadata or field value in a `{ binary = ... }` table to tell it apart from text.
+//! Python has a separate `bytes` type, so binary values (and body contents) cross as plain `bytes`.
+//!
+//! The script can also call `logger(message)`, and its `print` output is captured into the
+//! per-instance plugin log file rather than the driver's stdout. From any of the functions above
+//! it can call back into a host-provided or another plugin's capability, named by catalogue entry
+//! key - `host_compare_contents(entry_key, request)`,
+//! `host_generate_content(entry_key, contents, generators, test_mode)`,
+//! `host_match_field(entry_key, request)` and `host_generate_field(entry_key, request)` - with the
+//! same shapes as the Lua host functions of the same names.
+//!
+//! There is a single interpreter per process, shared by all Python plugins. Each plugin instance
+//! runs its script in its own module namespace, but `sys.path` and anything imported through it
+//! (`sys.modules`) are process wide.
+
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use pyo3::types::{
+  PyBool, PyBytes, PyCFunction, PyCode, PyCodeInput, PyCodeMethods, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple,
+};
+use tracing::{debug, warn};
+
+use crate::catalogue_manager::CatalogueEntryType;
+use crate::host_callbacks::{
+  call_host_compare_contents, call_host_generate_content, call_host_generate_field, call_host_match_field,
+};
+use crate::plugin_models::{
+  PactPluginManifest, PactPluginRpc, PluginInitRequest, PluginInitResponse, PluginInstance,
+};
+use crate::plugin_conversions::{
+  as_json_map, content_type_hint_to_str, str_to_content_type_hint, str_to_test_mode, test_mode_to_str,
+  v1_content_mismatch_to_v2, v1_plugin_configuration_to_v2, v2_content_mismatch_to_v1, v2_interaction_data_to_v1,
+  v2_plugin_configuration_to_v1,
+};
+use crate::proto::*;
+use crate::proto_v2;
+use crate::utils::{proto_struct_to_json, proto_value_to_json, to_proto_struct, to_proto_value};
+
+/// A running Python plugin instance: the module namespace its script was executed in.
+///
+/// Every call into the script runs on a blocking thread (`spawn_blocking`) while holding the GIL,
+/// so a slow script does not stall the async runtime, and so the `host_*` functions can block on
+/// the async dispatch in [`crate::host_callbacks`] (releasing the GIL while they wait). The mutex
+/// keeps calls into one instance sequential, as they are for a Lua plugin - which also means a
+/// callback chain that loops back into the same instance deadlocks rather than re-entering it.
+pub struct PythonPactPlugin {
+  runtime: Arc<tokio::sync::Mutex<Py<PyModule>>>,
+  manifest: PactPluginManifest,
+  instance_id: String,
+  plugin_capabilities: Vec<String>,
+}
+
+impl std::fmt::Debug for PythonPactPlugin {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("PythonPactPlugin")
+      .field("manifest", &self.manifest)
+      .field("instance_id", &self.instance_id)
+      .field("plugin_capabilities", &self.plugin_capabilities)
+      .finish()
+  }
+}
+
+/// Start a Python plugin: resolve the entry point script, add the plugin's package directories to
+/// `sys.path`, create a module namespace with the host functions the plugin can call, and execute
+/// the script in it.
+pub(crate) fn start_python_plugin(
+  manifest: &PactPluginManifest,
+  instance_id: String,
+) -> anyhow::Result<PythonPactPlugin> {
+  let script_path = resolve_entry_point(manifest)?;
+  debug!("Loading Python plugin {} from {:?}", manifest.name, script_path);
+
+  let log = Arc::new(PythonPluginLog::open(&manifest.name, &instance_id));
+  let script = std::fs::read_to_string(&script_path)?;
+  let module = Python::attach(|py| -> anyhow::Result<Py<PyModule>> {
+    add_search_paths(py, manifest)?;
+    let module = PyModule::new(py, &module_name(&manifest.name, &instance_id))?;
+    module.add("__file__", script_path.to_string_lossy().to_string())?;
+    register_host_functions(&module, &manifest.name, &log)?;
+
+    let code = CString::new(script)
+      .map_err(|_| anyhow!("Python plugin script {:?} contains a NUL byte", script_path))?;
+    let file_name = CString::new(script_path.to_string_lossy().to_string())?;
+    PyCode::compile(py, &code, &file_name, PyCodeInput::File)
+      .and_then(|code| code.run(Some(&module.dict()), None))
+      .map_err(|err| anyhow!("Failed to load Python plugin script {:?} - {}", script_path, err))?;
+    Ok(module.unbind())
+  })?;
+
+  Ok(PythonPactPlugin {
+    runtime: Arc::new(tokio::sync::Mutex::new(module)),
+    manifest: manifest.clone(),
+    instance_id,
+    plugin_capabilities: vec![],
+  })
+}
+
+impl PythonPactPlugin {
+  /// Set the capabilities negotiated for this plugin instance (called once, after the init
+  /// handshake, before the instance is shared behind an `Arc`).
+  pub(crate) fn set_plugin_capabilities(&mut self, capabilities: Vec<String>) {
+    self.plugin_capabilities = capabilities;
+  }
+
+  /// Runs `f` against the plugin's module on a blocking thread, holding the instance lock and the
+  /// GIL - see [`PythonPactPlugin`]. The current driver is carried over to the blocking thread for
+  /// any host callbacks the script makes.
+  async fn with_module<T, F>(&self, f: F) -> anyhow::Result<T>
+  where
+    T: Send + 'static,
+    F: FnOnce(&Bound<'_, PyModule>) -> anyhow::Result<T> + Send + 'static,
+  {
+    let module = self.runtime.clone().lock_owned().await;
+    let driver = crate::driver::PluginDriver::current();
+    tokio::task::spawn_blocking(move || {
+      let _guard = driver.enter();
+      Python::attach(|py| f(module.bind(py)))
+    })
+      .await
+      .map_err(|err| anyhow!("Python plugin call did not complete - {}", err))?
+  }
+}
+
+/// Version of the embedded Python interpreter (e.g. `3.12.3`).
+pub(crate) fn python_version() -> String {
+  Python::attach(|py| {
+    let version = py.version_info();
+    format!("{}.{}.{}", version.major, version.minor, version.patch)
+  })
+}
+
+/// The name of the module an instance's script runs in. It is never added to `sys.modules`, so
+/// this is only what the script sees as `__name__`.
+fn module_name(plugin_name: &str, instance_id: &str) -> String {
+  let plugin_name: String = plugin_name.chars()
+    .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '_' })
+    .collect();
+  format!("pact_plugin_{}_{}", plugin_name, instance_id.replace('-', "_"))
+}
+
+/// Captures a Python plugin's diagnostic output (`print` and `logger()` calls) into the same
+/// per-instance log file a gRPC plugin's stderr is captured to (see
+/// `child_process::open_plugin_log_file`), the same way the Lua runtime does.
+struct PythonPluginLog {
+  file: Mutex<Option<File>>,
+}
+
+impl PythonPluginLog {
+  fn open(plugin_name: &str, instance_id: &str) -> Self {
+    PythonPluginLog {
+      file: Mutex::new(crate::child_process::open_plugin_log_file(plugin_name, instance_id)),
+    }
+  }
+
+  fn write_line(&self, line: &str) {
+    if let Ok(mut guard) = self.file.lock()
+      && let Some(file) = guard.as_mut() {
+      let _ = writeln!(file, "{}", line);
+      let _ = file.flush();
+    }
+  }
+}
+
+fn resolve_entry_point(manifest: &PactPluginManifest) -> anyhow::Result<PathBuf> {
+  let entry_point = PathBuf::from(&manifest.entry_point);
+  let path = if entry_point.is_absolute() && entry_point.exists() {
+    entry_point
+  } else {
+    PathBuf::from(&manifest.plugin_dir).join(&manifest.entry_point)
+  };
+  if !path.exists() {
+    return Err(anyhow!("Python plugin entry point {:?} does not exist", path));
+  }
+  Ok(path)
+}
+
+/// Makes the plugin's own modules and packages importable, in order of precedence:
+///
+/// - the plugin directory itself, for modules vendored next to the entry point (the equivalent of
+///   the plugin directory on a Lua plugin's `package.path`);
+/// - `<plugin_dir>/site-packages`, for pure-Python packages bundled with the plugin using
+///   `pip install --target <plugin_dir>/site-packages`;
+/// - a shared packages directory, for dependencies installed once for several plugins - the
+///   `pythonPackagesDir` key of the manifest's `pluginConfig`, or `~/.pact/python-packages` by
+///   default (the Python equivalent of `luaRocksDir`).
+///
+/// Directories that do not exist are skipped, as is any already on `sys.path`.
+fn add_search_paths(py: Python<'_>, manifest: &PactPluginManifest) -> anyhow::Result<()> {
+  let plugin_dir = PathBuf::from(&manifest.plugin_dir);
+  let configured = manifest.plugin_config.get("pythonPackagesDir").and_then(|v| v.as_str());
+  let packages_dir = match configured {
+    Some(dir) => Some(PathBuf::from(dir)),
+    None => home::home_dir().map(|home| home.join(".pact").join("python-packages")),
+  };
+  if let Some(dir) = configured && !PathBuf::from(dir).exists() {
+    debug!("Configured pythonPackagesDir '{}' does not exist, ignoring", dir);
+  }
+
+  let sys_path = py.import("sys")?.getattr("path")?;
+  let candidates = [Some(plugin_dir.clone()), Some(plugin_dir.join("site-packages")), packages_dir];
+  for dir in candidates.into_iter().flatten().rev() {
+    let dir = dir.to_string_lossy().to_string();
+    if PathBuf::from(&dir).is_dir() && !sys_path.contains(&dir)? {
+      sys_path.call_method1("insert", (0, &dir))?;
+      debug!("Added {} to the Python path for plugin {}", dir, manifest.name);
+    }
+  }
+  Ok(())
+}
+
+/// Adds the host (Rust) functions a Python plugin script can call to its module namespace: a
+/// logger, a `print` that writes to the plugin's log file, and the `host_*` callbacks.
+fn register_host_functions(
+  module: &Bound<'_, PyModule>,
+  plugin_name: &str,
+  log: &Arc<PythonPluginLog>
+) -> anyhow::Result<()> {
+  let py = module.py();
+
+  let name = plugin_name.to_string();
+  let logger_log = log.clone();
+  module.add("logger", PyCFunction::new_closure(py, Some(c"logger"), None,
+    move |args: &Bound<'_, PyTuple>, _kwargs: Option<&Bound<'_, PyDict>>| -> PyResult<()> {
+      let message: String = args.get_item(0)?.str()?.extract()?;
+      debug!(plugin = name.as_str(), "{}", message);
+      logger_log.write_line(&message);
+      Ok(())
+    })?)?;
+
+  // Shadows the built-in `print` for this module only, so the script's output goes to the
+  // per-instance log file instead of the driver's own stdout. `sep` is honoured; `end` and
+  // `file` are not, as each call is written as one line of the log.
+  let print_log = log.clone();
+  module.add("print", PyCFunction::new_closure(py, Some(c"print"), None,
+    move |args: &Bound<'_, PyTuple>, kwargs: Option<&Bound<'_, PyDict>>| -> PyResult<()> {
+      let separator = match kwargs.map(|kwargs| kwargs.get_item("sep")).transpose()?.flatten() {
+        Some(sep) if !sep.is_none() => sep.extract::<String>()?,
+        _ => " ".to_string(),
+      };
+      let mut parts = Vec::with_capacity(args.len());
+      for arg in args.iter() {
+        parts.push(arg.str()?.to_string());
+      }
+      print_log.write_line(&parts.join(&separator));
+      Ok(())
+    })?)?;
+
+  // Callback host functions (proposals 006 and 007): let a plugin script delegate to a
+  // host-provided or another plugin's capability, named by catalogue entry key. The script is
+  // running on a blocking thread (see `PythonPactPlugin::with_module`), so the async dispatch is
+  // driven with `block_on`, with the GIL released so other Python plugins can run meanwhile.
+  module.add("host_compare_contents", PyCFunction::new_closure(py, Some(c"host_compare_contents"), None,
+    |args: &Bound<'_, PyTuple>, _kwargs: Option<&Bound<'_, PyDict>>| -> PyResult<Py<PyAny>> {
+      let py = args.py();
+      let entry_key: String = args.get_item(0)?.extract()?;
+      let request = py_to_compare_request(&args.get_item(1)?).map_err(to_py_err)?;
+      let response = block_on_host(py, call_host_compare_contents(&entry_key, request, None))?;
+      Ok(compare_response_to_py(py, &response)?.into_any().unbind())
+    })?)?;
+
+  module.add("host_generate_content", PyCFunction::new_closure(py, Some(c"host_generate_content"), None,
+    |args: &Bound<'_, PyTuple>, _kwargs: Option<&Bound<'_, PyDict>>| -> PyResult<Py<PyAny>> {
+      let py = args.py();
+      let entry_key: String = args.get_item(0)?.extract()?;
+      let request = py_to_generate_request(
+        args.get_item(1).ok(), args.get_item(2).ok(), args.get_item(3).ok()
+      ).map_err(to_py_err)?;
+      let response = block_on_host(py, call_host_generate_content(&entry_key, request, None))?;
+      Ok(body_to_py(py, &response.contents)?.unbind())
+    })?)?;
+
+  module.add("host_match_field", PyCFunction::new_closure(py, Some(c"host_match_field"), None,
+    |args: &Bound<'_, PyTuple>, _kwargs: Option<&Bound<'_, PyDict>>| -> PyResult<Py<PyAny>> {
+      let py = args.py();
+      let entry_key: String = args.get_item(0)?.extract()?;
+      let request = py_to_match_field_request(&args.get_item(1)?).map_err(to_py_err)?;
+      let response = block_on_host(py, call_host_match_field(&entry_key, request, None))?;
+      Ok(match_field_response_to_py(py, &response)?.into_any().unbind())
+    })?)?;
+
+  module.add("host_generate_field", PyCFunction::new_closure(py, Some(c"host_generate_field"), None,
+    |args: &Bound<'_, PyTuple>, _kwargs: Option<&Bound<'_, PyDict>>| -> PyResult<Py<PyAny>> {
+      let py = args.py();
+      let entry_key: String = args.get_item(0)?.extract()?;
+      let request = py_to_generate_field_request(&args.get_item(1)?).map_err(to_py_err)?;
+      let response = block_on_host(py, call_host_generate_field(&entry_key, request, None))?;
+      Ok(generate_field_response_to_py(py, &response)?.into_any().unbind())
+    })?)?;
+
+  Ok(())
+}
+
+/// Drives a host callback to completion from the blocking thread a script is running on, with
+/// the GIL released. Fails (rather than panicking) if the script somehow runs outside the
+/// driver's Tokio runtime.
+fn block_on_host<T: Send>(
+  py: Python<'_>,
+  future: impl Future<Output = anyhow::Result<T>> + Send
+) -> PyResult<T> {
+  let handle = tokio::runtime::Handle::try_current()
+    .map_err(|_| PyRuntimeError::new_err("host functions can only be called while the driver is calling the plugin"))?;
+  py.detach(|| handle.block_on(future)).map_err(to_py_err)
+}
+
+fn to_py_err(err: anyhow::Error) -> PyErr {
+  PyRuntimeError::new_err(err.to_string())
+}
+
+/// Looks up a top level function of the plugin script.
+fn function<'py>(module: &Bound<'py, PyModule>, name: &str) -> anyhow::Result<Bound<'py, PyAny>> {
+  optional_function(module, name)?
+    .ok_or_else(|| anyhow!("Python plugin does not define a '{}' function", name))
+}
+
+/// Looks up a top level function the plugin script is not required to define.
+fn optional_function<'py>(module: &Bound<'py, PyModule>, name: &str) -> anyhow::Result<Option<Bound<'py, PyAny>>> {
+  Ok(module.dict().get_item(name)?.filter(|function| !function.is_none()))
+}
+
+fn call_failed(name: &str, err: PyErr) -> anyhow::Error {
+  anyhow!("Python {}() function failed - {}", name, err)
+}
+
+fn call_init(
+  module: &Bound<'_, PyModule>,
+  implementation: &str,
+  version: &str,
+) -> anyhow::Result<Vec<CatalogueEntry>> {
+  let result = function(module, "init")?
+    .call1((implementation, version))
+    .map_err(|err| call_failed("init", err))?;
+  py_to_catalogue_entries(&result)
+}
+
+fn py_to_catalogue_entries(value: &Bound<'_, PyAny>) -> anyhow::Result<Vec<CatalogueEntry>> {
+  let mut entries = vec![];
+  for entry in value.try_iter()? {
+    let entry = as_dict(entry?, "catalogue entry")?;
+    let entry_type_str = string_item(&entry, "entryType")?
+      .ok_or_else(|| anyhow!("Catalogue entry from Python is missing 'entryType'"))?;
+    let key = string_item(&entry, "key")?
+      .ok_or_else(|| anyhow!("Catalogue entry from Python is missing 'key'"))?;
+    let values: Option<HashMap<String, String>> = item(&entry, "values")?.map(|v| v.extract()).transpose()?;
+    let entry_type = CatalogueEntryType::from_proto_name(&entry_type_str)
+      .ok_or_else(|| anyhow!("Unknown catalogue entry type '{}'", entry_type_str))?;
+    entries.push(CatalogueEntry {
+      r#type: entry_type.to_proto_value(),
+      key,
+      values: values.unwrap_or_default(),
+    });
+  }
+  Ok(entries)
+}
+
+// ---- Generic Python value helpers ----
+
+fn type_name(value: &Bound<'_, PyAny>) -> String {
+  value.get_type().name().map(|name| name.to_string()).unwrap_or_else(|_| "unknown".to_string())
+}
+
+fn as_dict<'py>(value: Bound<'py, PyAny>, what: &str) -> anyhow::Result<Bound<'py, PyDict>> {
+  let name = type_name(&value);
+  value.cast_into::<PyDict>()
+    .map_err(|_| anyhow!("Expected a {} dict from Python, got {}", what, name))
+}
+
+/// Looks up `key` in a dict from the plugin, treating a `None` value the same as a missing key.
+fn item<'py>(dict: &Bound<'py, PyDict>, key: &str) -> PyResult<Option<Bound<'py, PyAny>>> {
+  Ok(dict.get_item(key)?.filter(|value| !value.is_none()))
+}
+
+fn string_item(dict: &Bound<'_, PyDict>, key: &str) -> anyhow::Result<Option<String>> {
+  Ok(item(dict, key)?.map(|value| value.extract::<String>()).transpose()?)
+}
+
+fn bool_item(dict: &Bound<'_, PyDict>, key: &str) -> anyhow::Result<Option<bool>> {
+  Ok(item(dict, key)?.map(|value| value.extract::<bool>()).transpose()?)
+}
+
+fn dict_item<'py>(dict: &Bound<'py, PyDict>, key: &str) -> anyhow::Result<Option<Bound<'py, PyDict>>> {
+  item(dict, key)?.map(|value| as_dict(value, key)).transpose()
+}
+
+/// Converts JSON (the form the driver holds `google.protobuf.Struct` values in) to plain Python
+/// dicts, lists and scalars. Whole numbers become `int`s, so a script sees `1` rather than `1.0`
+/// for a value that was written as an integer.
+fn json_to_py<'py>(py: Python<'py>, value: &serde_json::Value) -> PyResult<Bound<'py, PyAny>> {
+  Ok(match value {
+    serde_json::Value::Null => py.None().into_bound(py),
+    serde_json::Value::Bool(b) => PyBool::new(py, *b).to_owned().into_any(),
+    serde_json::Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+      (Some(i), _) => i.into_pyobject(py)?.into_any(),
+      (None, Some(u)) => u.into_pyobject(py)?.into_any(),
+      _ => n.as_f64().unwrap_or_default().into_pyobject(py)?.into_any(),
+    },
+    serde_json::Value::String(s) => PyString::new(py, s).into_any(),
+    serde_json::Value::Array(items) => {
+      let list = PyList::empty(py);
+      for item in items {
+        list.append(json_to_py(py, item)?)?;
+      }
+      list.into_any()
+    }
+    serde_json::Value::Object(map) => {
+      let dict = PyDict::new(py);
+      for (key, value) in map {
+        dict.set_item(key, json_to_py(py, value)?)?;
+      }
+      dict.into_any()
+    }
+  })
+}
+
+/// Reverse of [`json_to_py`]. Dict keys must be strings, and anything that has no JSON form
+/// (bytes, sets, arbitrary objects) is an error.
+fn py_to_json(value: &Bound<'_, PyAny>) -> anyhow::Result<serde_json::Value> {
+  if value.is_none() {
+    Ok(serde_json::Value::Null)
+  } else if let Ok(b) = value.cast::<PyBool>() {
+    Ok(serde_json::Value::Bool(b.is_true()))
+  } else if let Ok(i) = value.cast::<PyInt>() {
+    match i.extract::<i64>() {
+      Ok(i) => Ok(serde_json::Value::from(i)),
+      Err(_) => Ok(serde_json::Value::from(i.extract::<f64>()?)),
+    }
+  } else if let Ok(f) = value.cast::<PyFloat>() {
+    Ok(serde_json::Value::from(f.value()))
+  } else if let Ok(s) = value.cast::<PyString>() {
+    Ok(serde_json::Value::String(s.to_str()?.to_string()))
+  } else if let Ok(dict) = value.cast::<PyDict>() {
+    let mut map = serde_json::Map::new();
+    for (key, value) in dict.iter() {
+      let key: String = key.extract()
+        .map_err(|_| anyhow!("Expected string dict keys from Python, got {}", type_name(&key)))?;
+      map.insert(key, py_to_json(&value)?);
+    }
+    Ok(serde_json::Value::Object(map))
+  } else if value.is_instance_of::<PyList>() || value.is_instance_of::<PyTuple>() {
+    let items = value.try_iter()?
+      .map(|item| py_to_json(&item?))
+      .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(serde_json::Value::Array(items))
+  } else {
+    Err(anyhow!("Expected a JSON compatible value from Python, got {}", type_name(value)))
+  }
+}
+
+/// Converts a `google.protobuf.Struct` to a dict, `None` if not set.
+fn struct_to_py<'py>(py: Python<'py>, value: &Option<prost_types::Struct>) -> PyResult<Bound<'py, PyAny>> {
+  match value {
+    Some(value) => json_to_py(py, &proto_struct_to_json(value)),
+    None => Ok(py.None().into_bound(py)),
+  }
+}
+
+/// Reverse of [`struct_to_py`]. `None` (or a non-dict value, which a `Struct` cannot represent)
+/// becomes no struct at all rather than an error.
+fn py_to_struct(value: Option<Bound<'_, PyAny>>) -> anyhow::Result<Option<prost_types::Struct>> {
+  match value {
+    None => Ok(None),
+    Some(value) if value.is_none() => Ok(None),
+    Some(value) => Ok(Some(to_proto_struct(&as_json_map(py_to_json(&value)?)))),
+  }
+}
+
+/// Accepts either `bytes` or `str` (encoded as UTF-8) where the driver needs raw bytes.
+fn py_to_bytes(value: &Bound<'_, PyAny>) -> anyhow::Result<Vec<u8>> {
+  if let Ok(bytes) = value.cast::<PyBytes>() {
+    Ok(bytes.as_bytes().to_vec())
+  } else if let Ok(s) = value.cast::<PyString>() {
+    Ok(s.to_str()?.as_bytes().to_vec())
+  } else {
+    Err(anyhow!("Expected bytes or str from Python, got {}", type_name(value)))
+  }
+}
+
+// ---- Body <-> Python ----
+
+fn body_to_py<'py>(py: Python<'py>, body: &Option<Body>) -> PyResult<Bound<'py, PyAny>> {
+  match body {
+    None => Ok(py.None().into_bound(py)),
+    Some(body) => {
+      let dict = PyDict::new(py);
+      dict.set_item("content_type", &body.content_type)?;
+      match &body.content {
+        Some(bytes) => dict.set_item("contents", PyBytes::new(py, bytes))?,
+        None => dict.set_item("contents", py.None())?,
+      }
+      dict.set_item("content_type_hint", content_type_hint_to_str(body.content_type_hint))?;
+      Ok(dict.into_any())
+    }
+  }
+}
+
+fn py_to_body(value: Option<Bound<'_, PyAny>>) -> anyhow::Result<Option<Body>> {
+  match value {
+    None => Ok(None),
+    Some(value) if value.is_none() => Ok(None),
+    Some(value) => {
+      let dict = as_dict(value, "body")?;
+      let content_type = string_item(&dict, "content_type")?
+        .ok_or_else(|| anyhow!("Body from Python is missing 'content_type'"))?;
+      let content = item(&dict, "contents")?.map(|value| py_to_bytes(&value)).transpose()?;
+      let content_type_hint = string_item(&dict, "content_type_hint")?;
+      Ok(Some(Body {
+        content_type,
+        content,
+        content_type_hint: content_type_hint
+          .map(|h| str_to_content_type_hint(&h))
+          .unwrap_or(body::ContentTypeHint::Default as i32),
+      }))
+    }
+  }
+}
+
+// ---- Matching rules / generators / plugin configuration <-> Python ----
+
+/// Converts a matching rule or a generator - a name plus an optional struct of configured values -
+/// into a `{"type": "...", "values": {...}}` dict.
+fn typed_values_to_py<'py>(
+  py: Python<'py>,
+  r#type: &str,
+  values: &Option<prost_types::Struct>
+) -> PyResult<Bound<'py, PyDict>> {
+  let dict = PyDict::new(py);
+  dict.set_item("type", r#type)?;
+  if let Some(values) = values {
+    dict.set_item("values", json_to_py(py, &proto_struct_to_json(values))?)?;
+  }
+  Ok(dict)
+}
+
+/// Reverse of [`typed_values_to_py`], returning the name and values separately so the caller can
+/// build either a `MatchingRule` or a `Generator` from them.
+fn py_to_typed_values(
+  value: Option<Bound<'_, PyAny>>,
+  what: &str
+) -> anyhow::Result<(String, Option<prost_types::Struct>)> {
+  let value = value.ok_or_else(|| anyhow!("Expected a '{}' dict from Python", what))?;
+  let dict = as_dict(value, what)?;
+  let r#type = string_item(&dict, "type")?
+    .ok_or_else(|| anyhow!("Expected the '{}' dict from Python to have a 'type'", what))?;
+  Ok((r#type, py_to_struct(item(&dict, "values")?)?))
+}
+
+fn matching_rules_to_py<'py>(py: Python<'py>, rules: &HashMap<String, MatchingRules>) -> PyResult<Bound<'py, PyDict>> {
+  let dict = PyDict::new(py);
+  for (path, rule_list) in rules {
+    let list = PyList::empty(py);
+    for rule in &rule_list.rule {
+      list.append(typed_values_to_py(py, &rule.r#type, &rule.values)?)?;
+    }
+    dict.set_item(path, list)?;
+  }
+  Ok(dict)
+}
+
+fn py_to_matching_rules(value: Option<Bound<'_, PyAny>>) -> anyhow::Result<HashMap<String, MatchingRules>> {
+  let mut result = HashMap::new();
+  if let Some(value) = value {
+    for (path, rules) in as_dict(value, "rules")?.iter() {
+      let mut rule = vec![];
+      for rule_value in rules.try_iter()? {
+        let (r#type, values) = py_to_typed_values(Some(rule_value?), "rule")?;
+        rule.push(MatchingRule { r#type, values });
+      }
+      result.insert(path.extract()?, MatchingRules { rule });
+    }
+  }
+  Ok(result)
+}
+
+fn generators_to_py<'py>(py: Python<'py>, generators: &HashMap<String, Generator>) -> PyResult<Bound<'py, PyDict>> {
+  let dict = PyDict::new(py);
+  for (path, generator) in generators {
+    dict.set_item(path, typed_values_to_py(py, &generator.r#type, &generator.values)?)?;
+  }
+  Ok(dict)
+}
+
+fn py_to_generators(value: Option<Bound<'_, PyAny>>) -> anyhow::Result<HashMap<String, Generator>> {
+  let mut result = HashMap::new();
+  if let Some(value) = value.filter(|value| !value.is_none()) {
+    for (path, generator) in as_dict(value, "generators")?.iter() {
+      let (r#type, values) = py_to_typed_values(Some(generator), "generator")?;
+      result.insert(path.extract()?, Generator { r#type, values });
+    }
+  }
+  Ok(result)
+}
+
+fn plugin_configuration_to_py<'py>(
+  py: Python<'py>,
+  config: &Option<PluginConfiguration>
+) -> PyResult<Bound<'py, PyAny>> {
+  match config {
+    None => Ok(py.None().into_bound(py)),
+    Some(config) => {
+      let dict = PyDict::new(py);
+      if let Some(interaction_configuration) = &config.interaction_configuration {
+        dict.set_item("interaction_configuration", json_to_py(py, &proto_struct_to_json(interaction_configuration))?)?;
+      }
+      if let Some(pact_configuration) = &config.pact_configuration {
+        dict.set_item("pact_configuration", json_to_py(py, &proto_struct_to_json(pact_configuration))?)?;
+      }
+      Ok(dict.into_any())
+    }
+  }
+}
+
+fn py_to_plugin_configuration(value: Option<Bound<'_, PyAny>>) -> anyhow::Result<Option<PluginConfiguration>> {
+  match value {
+    None => Ok(None),
+    Some(value) => {
+      let dict = as_dict(value, "plugin configuration")?;
+      Ok(Some(PluginConfiguration {
+        interaction_configuration: py_to_struct(item(&dict, "interaction_configuration")?)?,
+        pact_configuration: py_to_struct(item(&dict, "pact_configuration")?)?,
+      }))
+    }
+  }
+}
+
+// ---- CompareContents <-> Python ----
+
+fn compare_request_to_py<'py>(py: Python<'py>, request: &CompareContentsRequest) -> PyResult<Bound<'py, PyDict>> {
+  let dict = PyDict::new(py);
+  dict.set_item("expected", body_to_py(py, &request.expected)?)?;
+  dict.set_item("actual", body_to_py(py, &request.actual)?)?;
+  dict.set_item("allow_unexpected_keys", request.allow_unexpected_keys)?;
+  dict.set_item("rules", matching_rules_to_py(py, &request.rules)?)?;
+  dict.set_item("plugin_configuration", plugin_configuration_to_py(py, &request.plugin_configuration)?)?;
+  Ok(dict)
+}
+
+/// Reverse of [`compare_request_to_py`] - the request a script passes to `host_compare_contents`
+/// is the same shape its own `match_contents` receives.
+fn py_to_compare_request(value: &Bound<'_, PyAny>) -> anyhow::Result<CompareContentsRequest> {
+  let dict = as_dict(value.clone(), "compare contents request")?;
+  Ok(CompareContentsRequest {
+    expected: py_to_body(item(&dict, "expected")?)?,
+    actual: py_to_body(item(&dict, "actual")?)?,
+    allow_unexpected_keys: bool_item(&dict, "allow_unexpected_keys")?.unwrap_or(false),
+    rules: py_to_matching_rules(item(&dict, "rules")?)?,
+    plugin_configuration: py_to_plugin_configuration(item(&dict, "plugin_configuration")?)?,
+  })
+}
+
+fn py_to_compare_response(value: Bound<'_, PyAny>) -> anyhow::Result<CompareContentsResponse> {
+  let dict = as_dict(value, "match_contents result")?;
+  if let Some(error) = string_item(&dict, "error")? {
+    return Ok(CompareContentsResponse { error, type_mismatch: None, results: HashMap::new() });
+  }
+
+  if let Some(type_mismatch) = dict_item(&dict, "type-mismatch")? {
+    return Ok(CompareContentsResponse {
+      error: String::new(),
+      type_mismatch: Some(ContentTypeMismatch {
+        expected: string_item(&type_mismatch, "expected")?.unwrap_or_default(),
+        actual: string_item(&type_mismatch, "actual")?.unwrap_or_default(),
+      }),
+      results: HashMap::new(),
+    });
+  }
+
+  let mut results = HashMap::new();
+  if let Some(mismatches) = dict_item(&dict, "mismatches")? {
+    for (path, value) in mismatches.iter() {
+      let path: String = path.extract()?;
+      let mismatch_list = py_to_content_mismatches(&path, &value)?;
+      if !mismatch_list.is_empty() {
+        results.insert(path, ContentMismatches { mismatches: mismatch_list });
+      }
+    }
+  }
+  Ok(CompareContentsResponse { error: String::new(), type_mismatch: None, results })
+}
+
+/// Converts the `expected`/`actual` value of a mismatch to bytes. `bytes` are passed through as
+/// they are, and any other scalar is stringified, as a value being compared could just as easily
+/// be a number or boolean as a string.
+fn py_scalar_to_bytes(value: Option<Bound<'_, PyAny>>) -> anyhow::Result<Option<Vec<u8>>> {
+  match value {
+    None => Ok(None),
+    Some(value) if value.is_instance_of::<PyBytes>() || value.is_instance_of::<PyString>() =>
+      Ok(Some(py_to_bytes(&value)?)),
+    Some(value) => Ok(Some(py_to_json(&value)?.to_string().into_bytes())),
+  }
+}
+
+/// Parses a mismatch dict (`{"mismatch": ..., "expected": ..., "actual": ..., "path": ...,
+/// "diff": ..., "mismatch_type": ...}`), as used by match, mock server and verification results.
+fn py_to_content_mismatch(dict: &Bound<'_, PyDict>, path: &str) -> anyhow::Result<ContentMismatch> {
+  Ok(ContentMismatch {
+    expected: py_scalar_to_bytes(item(dict, "expected")?)?,
+    actual: py_scalar_to_bytes(item(dict, "actual")?)?,
+    mismatch: string_item(dict, "mismatch")?.unwrap_or_default(),
+    path: string_item(dict, "path")?.unwrap_or_else(|| path.to_string()),
+    diff: string_item(dict, "diff")?.unwrap_or_default(),
+    mismatch_type: string_item(dict, "mismatch_type")?.unwrap_or_default(),
+  })
+}
+
+/// Parses the mismatches for a path: a description string, a mismatch dict, or a list of either.
+fn py_to_content_mismatches(path: &str, value: &Bound<'_, PyAny>) -> anyhow::Result<Vec<ContentMismatch>> {
+  if value.is_none() {
+    Ok(vec![])
+  } else if let Ok(s) = value.cast::<PyString>() {
+    Ok(vec![ContentMismatch {
+      expected: None,
+      actual: None,
+      mismatch: s.to_str()?.to_string(),
+      path: path.to_string(),
+      diff: String::new(),
+      mismatch_type: String::new(),
+    }])
+  } else if let Ok(dict) = value.cast::<PyDict>() {
+    Ok(vec![py_to_content_mismatch(dict, path)?])
+  } else if value.is_instance_of::<PyList>() || value.is_instance_of::<PyTuple>() {
+    let mut result = vec![];
+    for entry in value.try_iter()? {
+      result.extend(py_to_content_mismatches(path, &entry?)?);
+    }
+    Ok(result)
+  } else {
+    Err(anyhow!("Expected a mismatch string, dict or list from Python, got {}", type_name(value)))
+  }
+}
+
+/// Converts a path's mismatches into the list-of-dicts shape [`py_to_content_mismatches`] parses,
+/// so a host callback's response can be passed straight through.
+fn content_mismatches_to_py<'py>(py: Python<'py>, mismatches: &[ContentMismatch]) -> PyResult<Bound<'py, PyList>> {
+  let list = PyList::empty(py);
+  for mismatch in mismatches {
+    let dict = PyDict::new(py);
+    dict.set_item("mismatch", &mismatch.mismatch)?;
+    if let Some(expected) = &mismatch.expected {
+      dict.set_item("expected", mismatch_value_to_py(py, expected))?;
+    }
+    if let Some(actual) = &mismatch.actual {
+      dict.set_item("actual", mismatch_value_to_py(py, actual))?;
+    }
+    dict.set_item("path", &mismatch.path)?;
+    if !mismatch.diff.is_empty() {
+      dict.set_item("diff", &mismatch.diff)?;
+    }
+    if !mismatch.mismatch_type.is_empty() {
+      dict.set_item("mismatch_type", &mismatch.mismatch_type)?;
+    }
+    list.append(dict)?;
+  }
+  Ok(list)
+}
+
+/// Mismatch values are usually text, so they are given to the script as `str` when they are
+/// valid UTF-8 and as `bytes` otherwise.
+fn mismatch_value_to_py<'py>(py: Python<'py>, value: &[u8]) -> Bound<'py, PyAny> {
+  match std::str::from_utf8(value) {
+    Ok(s) => PyString::new(py, s).into_any(),
+    Err(_) => PyBytes::new(py, value).into_any(),
+  }
+}
+
+/// Reverse of [`py_to_compare_response`], so a script can return the result of
+/// `host_compare_contents` straight through as its own `match_contents` result.
+fn compare_response_to_py<'py>(py: Python<'py>, response: &CompareContentsResponse) -> PyResult<Bound<'py, PyDict>> {
+  let dict = PyDict::new(py);
+  if !response.error.is_empty() {
+    dict.set_item("error", &response.error)?;
+  } else if let Some(type_mismatch) = &response.type_mismatch {
+    let mismatch = PyDict::new(py);
+    mismatch.set_item("expected", &type_mismatch.expected)?;
+    mismatch.set_item("actual", &type_mismatch.actual)?;
+    dict.set_item("type-mismatch", mismatch)?;
+  } else if !response.results.is_empty() {
+    let mismatches = PyDict::new(py);
+    for (path, content_mismatches) in &response.results {
+      mismatches.set_item(path, content_mismatches_to_py(py, &content_mismatches.mismatches)?)?;
+    }
+    dict.set_item("mismatches", mismatches)?;
+  }
+  Ok(dict)
+}
+
+// ---- GenerateContent <-> Python ----
+
+/// Converts the `(contents, generators, test_mode)` arguments a script passes to
+/// `host_generate_content` - the same arguments its own `generate_content` receives.
+fn py_to_generate_request(
+  contents: Option<Bound<'_, PyAny>>,
+  generators: Option<Bound<'_, PyAny>>,
+  test_mode: Option<Bound<'_, PyAny>>
+) -> anyhow::Result<GenerateContentRequest> {
+  let test_mode: Option<String> = test_mode.filter(|mode| !mode.is_none()).map(|mode| mode.extract()).transpose()?;
+  Ok(GenerateContentRequest {
+    contents: py_to_body(contents)?,
+    generators: py_to_generators(generators)?,
+    test_mode: str_to_test_mode(test_mode.as_deref()),
+    .. GenerateContentRequest::default()
+  })
+}
+
+// ---- MatchField / GenerateField <-> Python ----
+
+/// Converts a single field value to a plain Python value: `None`, `bool`, `int`, `float`, `str`,
+/// `bytes`, or a dict/list for a structured value. Python's separate `int` and `float` types keep
+/// the integer/decimal distinction the `integer`, `decimal` and `type` rules rely on.
+fn field_value_to_py<'py>(py: Python<'py>, value: &Option<proto_v2::FieldValue>) -> PyResult<Bound<'py, PyAny>> {
+  use proto_v2::field_value::Value as FieldValue;
+  Ok(match value.as_ref().and_then(|value| value.value.as_ref()) {
+    None | Some(FieldValue::NullValue(_)) => py.None().into_bound(py),
+    Some(FieldValue::BooleanValue(value)) => PyBool::new(py, *value).to_owned().into_any(),
+    Some(FieldValue::StringValue(value)) => PyString::new(py, value).into_any(),
+    Some(FieldValue::IntegerValue(value)) => value.into_pyobject(py)?.into_any(),
+    Some(FieldValue::DecimalValue(value)) => value.into_pyobject(py)?.into_any(),
+    Some(FieldValue::BinaryValue(bytes)) => PyBytes::new(py, bytes).into_any(),
+    Some(FieldValue::StructuredValue(value)) => json_to_py(py, &proto_value_to_json(value))?,
+  })
+}
+
+/// Reverse of [`field_value_to_py`].
+fn py_to_field_value(value: Option<Bound<'_, PyAny>>) -> anyhow::Result<proto_v2::FieldValue> {
+  use proto_v2::field_value::Value as FieldValue;
+  let value = match value {
+    None => FieldValue::NullValue(0),
+    Some(value) if value.is_instance_of::<PyBool>() => FieldValue::BooleanValue(value.extract()?),
+    Some(value) if value.is_instance_of::<PyInt>() => FieldValue::IntegerValue(value.extract()?),
+    Some(value) if value.is_instance_of::<PyFloat>() => FieldValue::DecimalValue(value.extract()?),
+    Some(value) if value.is_instance_of::<PyString>() => FieldValue::StringValue(value.extract()?),
+    Some(value) if value.is_instance_of::<PyBytes>() => FieldValue::BinaryValue(py_to_bytes(&value)?),
+    Some(value) => FieldValue::StructuredValue(to_proto_value(&py_to_json(&value)?)),
+  };
+  Ok(proto_v2::FieldValue { value: Some(value) })
+}
+
+/// Builds the request dict a plugin's own `match_field(request)` function receives.
+fn match_field_request_to_py<'py>(py: Python<'py>, request: &proto_v2::MatchFieldRequest) -> PyResult<Bound<'py, PyDict>> {
+  let dict = PyDict::new(py);
+  dict.set_item("key", &request.key)?;
+  let rule = request.rule.clone().unwrap_or_default();
+  dict.set_item("rule", typed_values_to_py(py, &rule.r#type, &rule.values)?)?;
+  dict.set_item("path", &request.path)?;
+  dict.set_item("mismatch_type", &request.mismatch_type)?;
+  dict.set_item("expected", field_value_to_py(py, &request.expected)?)?;
+  dict.set_item("actual", field_value_to_py(py, &request.actual)?)?;
+  dict.set_item(
+    "plugin_configuration",
+    plugin_configuration_to_py(py, &v2_plugin_configuration_to_v1(&request.plugin_configuration))?,
+  )?;
+  dict.set_item("test_context", struct_to_py(py, &request.test_context)?)?;
+  Ok(dict)
+}
+
+/// Reverse of [`match_field_request_to_py`] - the request a script passes to `host_match_field`.
+fn py_to_match_field_request(value: &Bound<'_, PyAny>) -> anyhow::Result<proto_v2::MatchFieldRequest> {
+  let dict = as_dict(value.clone(), "match field request")?;
+  let (r#type, values) = py_to_typed_values(item(&dict, "rule")?, "rule")?;
+  Ok(proto_v2::MatchFieldRequest {
+    key: string_item(&dict, "key")?.unwrap_or_default(),
+    rule: Some(proto_v2::MatchingRule { r#type, values }),
+    path: string_item(&dict, "path")?.unwrap_or_default(),
+    mismatch_type: string_item(&dict, "mismatch_type")?.unwrap_or_default(),
+    expected: Some(py_to_field_value(item(&dict, "expected")?)?),
+    actual: Some(py_to_field_value(item(&dict, "actual")?)?),
+    plugin_configuration: v1_plugin_configuration_to_v2(
+      py_to_plugin_configuration(item(&dict, "plugin_configuration")?)?
+    ),
+    test_context: py_to_struct(item(&dict, "test_context")?)?,
+  })
+}
+
+/// Parses the dict a plugin's `match_field` function returns: `{"error": "..."}` or
+/// `{"mismatches": [...]}`. An absent or empty list means the value matched.
+fn py_to_match_field_response(value: Bound<'_, PyAny>, path: &str) -> anyhow::Result<proto_v2::MatchFieldResponse> {
+  let dict = as_dict(value, "match_field result")?;
+  if let Some(error) = string_item(&dict, "error")? {
+    return Ok(proto_v2::MatchFieldResponse { error, mismatches: vec![] });
+  }
+  let mismatches = match item(&dict, "mismatches")? {
+    Some(value) => py_to_content_mismatches(path, &value)?
+      .into_iter()
+      .map(v1_content_mismatch_to_v2)
+      .collect(),
+    None => vec![],
+  };
+  Ok(proto_v2::MatchFieldResponse { error: String::new(), mismatches })
+}
+
+/// Reverse of [`py_to_match_field_response`], so a script can return the result of
+/// `host_match_field` straight through.
+fn match_field_response_to_py<'py>(py: Python<'py>, response: &proto_v2::MatchFieldResponse) -> PyResult<Bound<'py, PyDict>> {
+  let dict = PyDict::new(py);
+  if !response.error.is_empty() {
+    dict.set_item("error", &response.error)?;
+  } else {
+    let mismatches: Vec<ContentMismatch> = response.mismatches.iter()
+      .map(v2_content_mismatch_to_v1)
+      .collect();
+    dict.set_item("mismatches", content_mismatches_to_py(py, &mismatches)?)?;
+  }
+  Ok(dict)
+}
+
+/// Builds the request dict a plugin's own `generate_field(request)` function receives.
+fn generate_field_request_to_py<'py>(py: Python<'py>, request: &proto_v2::GenerateFieldRequest) -> PyResult<Bound<'py, PyDict>> {
+  let dict = PyDict::new(py);
+  dict.set_item("key", &request.key)?;
+  let generator = request.generator.clone().unwrap_or_default();
+  dict.set_item("generator", typed_values_to_py(py, &generator.r#type, &generator.values)?)?;
+  dict.set_item("path", &request.path)?;
+  dict.set_item("example_value", field_value_to_py(py, &request.example_value)?)?;
+  dict.set_item(
+    "plugin_configuration",
+    plugin_configuration_to_py(py, &v2_plugin_configuration_to_v1(&request.plugin_configuration))?,
+  )?;
+  dict.set_item("test_context", struct_to_py(py, &request.test_context)?)?;
+  dict.set_item("test_mode", test_mode_to_str(request.test_mode))?;
+  Ok(dict)
+}
+
+/// Reverse of [`generate_field_request_to_py`] - the request a script passes to
+/// `host_generate_field`.
+fn py_to_generate_field_request(value: &Bound<'_, PyAny>) -> anyhow::Result<proto_v2::GenerateFieldRequest> {
+  let dict = as_dict(value.clone(), "generate field request")?;
+  let (r#type, values) = py_to_typed_values(item(&dict, "generator")?, "generator")?;
+  let test_mode = string_item(&dict, "test_mode")?;
+  Ok(proto_v2::GenerateFieldRequest {
+    key: string_item(&dict, "key")?.unwrap_or_default(),
+    generator: Some(proto_v2::Generator { r#type, values }),
+    path: string_item(&dict, "path")?.unwrap_or_default(),
+    example_value: Some(py_to_field_value(item(&dict, "example_value")?)?),
+    plugin_configuration: v1_plugin_configuration_to_v2(
+      py_to_plugin_configuration(item(&dict, "plugin_configuration")?)?
+    ),
+    test_context: py_to_struct(item(&dict, "test_context")?)?,
+    test_mode: str_to_test_mode(test_mode.as_deref()),
+  })
+}
+
+/// Parses the dict a plugin's `generate_field` function returns: `{"value": ...}` or
+/// `{"error": "..."}`.
+fn py_to_generate_field_response(value: Bound<'_, PyAny>) -> anyhow::Result<proto_v2::GenerateFieldResponse> {
+  let dict = as_dict(value, "generate_field result")?;
+  if let Some(error) = string_item(&dict, "error")? {
+    return Ok(proto_v2::GenerateFieldResponse { error, value: None });
+  }
+  Ok(proto_v2::GenerateFieldResponse {
+    error: String::new(),
+    value: Some(py_to_field_value(item(&dict, "value")?)?),
+  })
+}
+
+/// Reverse of [`py_to_generate_field_response`], so a script can return the result of
+/// `host_generate_field` straight through.
+fn generate_field_response_to_py<'py>(py: Python<'py>, response: &proto_v2::GenerateFieldResponse) -> PyResult<Bound<'py, PyDict>> {
+  let dict = PyDict::new(py);
+  if !response.error.is_empty() {
+    dict.set_item("error", &response.error)?;
+  } else {
+    dict.set_item("value", field_value_to_py(py, &response.value)?)?;
+  }
+  Ok(dict)
+}
+
+// ---- ConfigureInteraction <-> Python ----
+
+/// Converts a single interaction dict, shaped as
+/// `{"contents": <body>, "rules": {...}, "part_name": "...", "plugin_config": {...}}`, into an
+/// `InteractionResponse`. See the Lua runtime's `lua_to_interaction_response` for what the rules
+/// are used for.
+fn py_to_interaction_response(value: Bound<'_, PyAny>) -> anyhow::Result<InteractionResponse> {
+  let dict = as_dict(value, "interaction")?;
+  Ok(InteractionResponse {
+    contents: py_to_body(item(&dict, "contents")?)?,
+    rules: py_to_matching_rules(item(&dict, "rules")?)?,
+    generators: HashMap::new(),
+    message_metadata: None,
+    plugin_configuration: py_to_plugin_configuration(item(&dict, "plugin_config")?)?,
+    interaction_markup: String::new(),
+    interaction_markup_type: 0,
+    part_name: string_item(&dict, "part_name")?.unwrap_or_default(),
+    metadata_rules: HashMap::new(),
+    metadata_generators: HashMap::new(),
+  })
+}
+
+/// Converts the dict returned by `configure_interaction`, shaped as
+/// `{"interactions": [...], "plugin_config": {...}}`, into a `ConfigureInteractionResponse`.
+fn py_to_configure_response(value: Bound<'_, PyAny>) -> anyhow::Result<ConfigureInteractionResponse> {
+  let dict = as_dict(value, "configure_interaction result")?;
+  let mut interactions = vec![];
+  if let Some(items) = item(&dict, "interactions")? {
+    for entry in items.try_iter()? {
+      interactions.push(py_to_interaction_response(entry?)?);
+    }
+  }
+  Ok(ConfigureInteractionResponse {
+    error: string_item(&dict, "error")?.unwrap_or_default(),
+    interaction: interactions,
+    plugin_configuration: py_to_plugin_configuration(item(&dict, "plugin_config")?)?,
+  })
+}
+
+// ---- TRANSPORT plugin support: mock server / verification <-> Python ----
+
+/// Converts V2 `InteractionContents` into a dict shaped as
+/// `{"interaction_type", "consumer", "provider", "plugin_configuration": {...}}`.
+fn interaction_contents_to_py<'py>(py: Python<'py>, contents: &proto_v2::InteractionContents) -> PyResult<Bound<'py, PyDict>> {
+  let dict = PyDict::new(py);
+  dict.set_item("interaction_type", &contents.interaction_type)?;
+  dict.set_item("consumer", &contents.consumer)?;
+  dict.set_item("provider", &contents.provider)?;
+  if contents.plugin_configuration.is_some() {
+    dict.set_item(
+      "plugin_configuration",
+      plugin_configuration_to_py(py, &v2_plugin_configuration_to_v1(&contents.plugin_configuration))?,
+    )?;
+  }
+  Ok(dict)
+}
+
+/// Converts request/response metadata to a dict. Binary values are `bytes`, everything else a
+/// plain Python value.
+fn metadata_to_py<'py>(py: Python<'py>, metadata: &HashMap<String, MetadataValue>) -> PyResult<Bound<'py, PyDict>> {
+  let dict = PyDict::new(py);
+  for (key, value) in metadata {
+    let value = match &value.value {
+      Some(metadata_value::Value::NonBinaryValue(value)) => json_to_py(py, &proto_value_to_json(value))?,
+      Some(metadata_value::Value::BinaryValue(bytes)) => PyBytes::new(py, bytes).into_any(),
+      None => py.None().into_bound(py),
+    };
+    dict.set_item(key, value)?;
+  }
+  Ok(dict)
+}
+
+/// Reverse of [`metadata_to_py`].
+fn py_to_metadata(value: Option<Bound<'_, PyAny>>) -> anyhow::Result<HashMap<String, MetadataValue>> {
+  let mut metadata = HashMap::new();
+  if let Some(value) = value {
+    for (key, value) in as_dict(value, "metadata")?.iter() {
+      let metadata_value = if value.is_instance_of::<PyBytes>() {
+        metadata_value::Value::BinaryValue(py_to_bytes(&value)?)
+      } else {
+        metadata_value::Value::NonBinaryValue(to_proto_value(&py_to_json(&value)?))
+      };
+      metadata.insert(key.extract()?, MetadataValue { value: Some(metadata_value) });
+    }
+  }
+  Ok(metadata)
+}
+
+/// Converts `InteractionData` to a dict shaped as `{"body": <body>, "metadata": {...}}`, or
+/// `None` if not set.
+fn interaction_data_to_py<'py>(py: Python<'py>, data: &Option<InteractionData>) -> PyResult<Bound<'py, PyAny>> {
+  match data {
+    None => Ok(py.None().into_bound(py)),
+    Some(data) => {
+      let dict = PyDict::new(py);
+      dict.set_item("body", body_to_py(py, &data.body)?)?;
+      dict.set_item("metadata", metadata_to_py(py, &data.metadata)?)?;
+      Ok(dict.into_any())
+    }
+  }
+}
+
+/// Reverse of [`interaction_data_to_py`].
+fn py_to_interaction_data(value: Option<Bound<'_, PyAny>>) -> anyhow::Result<Option<InteractionData>> {
+  match value {
+    None => Ok(None),
+    Some(value) => {
+      let dict = as_dict(value, "interaction data")?;
+      Ok(Some(InteractionData {
+        body: py_to_body(item(&dict, "body")?)?,
+        metadata: py_to_metadata(item(&dict, "metadata")?)?,
+      }))
+    }
+  }
+}
+
+/// Converts the dict returned by `start_mock_server`, shaped as either `{"error": "..."}` or
+/// `{"details": {"key", "port", "address"}}`.
+fn py_to_start_mock_server_response(value: Bound<'_, PyAny>) -> anyhow::Result<StartMockServerResponse> {
+  let dict = as_dict(value, "start_mock_server result")?;
+  if let Some(error) = string_item(&dict, "error")? {
+    return Ok(StartMockServerResponse {
+      response: Some(start_mock_server_response::Response::Error(error)),
+    });
+  }
+
+  let details = dict_item(&dict, "details")?.ok_or_else(|| {
+    anyhow!("Python start_mock_server() must return either an 'error' or 'details' field")
+  })?;
+  Ok(StartMockServerResponse {
+    response: Some(start_mock_server_response::Response::Details(MockServerDetails {
+      key: string_item(&details, "key")?.unwrap_or_default(),
+      port: item(&details, "port")?.map(|port| port.extract()).transpose()?.unwrap_or_default(),
+      address: string_item(&details, "address")?.unwrap_or_default(),
+    })),
+  })
+}
+
+/// Converts the dict returned by `shutdown_mock_server`/`get_mock_server_results`, shaped as
+/// `{"ok": bool, "results": [{"path", "error", "mismatches": [...]}, ...]}`.
+fn py_to_mock_server_results(value: Bound<'_, PyAny>) -> anyhow::Result<MockServerResults> {
+  let dict = as_dict(value, "mock server results")?;
+  let ok = bool_item(&dict, "ok")?.unwrap_or(true);
+  let mut results = vec![];
+  if let Some(entries) = item(&dict, "results")? {
+    for entry in entries.try_iter()? {
+      let entry = as_dict(entry?, "mock server result")?;
+      let path = string_item(&entry, "path")?.unwrap_or_default();
+      let mismatches = match item(&entry, "mismatches")? {
+        Some(value) => py_to_content_mismatches(&path, &value)?,
+        None => vec![],
+      };
+      results.push(MockServerResult {
+        error: string_item(&entry, "error")?.unwrap_or_default(),
+        path,
+        mismatches,
+      });
+    }
+  }
+  Ok(MockServerResults { ok, results })
+}
+
+/// Converts the dict returned by `prepare_interaction_for_verification`, shaped as either
+/// `{"error": "..."}` or `{"interaction_data": {"body", "metadata"}}`.
+fn py_to_verification_preparation_response(value: Bound<'_, PyAny>) -> anyhow::Result<VerificationPreparationResponse> {
+  let dict = as_dict(value, "prepare_interaction_for_verification result")?;
+  if let Some(error) = string_item(&dict, "error")? {
+    return Ok(VerificationPreparationResponse {
+      response: Some(verification_preparation_response::Response::Error(error)),
+    });
+  }
+
+  let data = item(&dict, "interaction_data")?.ok_or_else(|| {
+    anyhow!("Python prepare_interaction_for_verification() must return either an 'error' or 'interaction_data' field")
+  })?;
+  let interaction_data = py_to_interaction_data(Some(data))?
+    .unwrap_or_else(|| InteractionData { body: None, metadata: HashMap::new() });
+  Ok(VerificationPreparationResponse {
+    response: Some(verification_preparation_response::Response::InteractionData(interaction_data)),
+  })
+}
+
+/// Converts a single verification mismatch (an error string, or a mismatch dict) into a
+/// `VerificationResultItem`.
+fn py_to_verification_result_item(value: &Bound<'_, PyAny>) -> anyhow::Result<VerificationResultItem> {
+  if let Ok(s) = value.cast::<PyString>() {
+    Ok(VerificationResultItem {
+      result: Some(verification_result_item::Result::Error(s.to_str()?.to_string())),
+    })
+  } else if let Ok(dict) = value.cast::<PyDict>() {
+    Ok(VerificationResultItem {
+      result: Some(verification_result_item::Result::Mismatch(py_to_content_mismatch(dict, "")?)),
+    })
+  } else {
+    Err(anyhow!("Expected a mismatch string or dict from Python, got {}", type_name(value)))
+  }
+}
+
+/// Converts the dict returned by `verify_interaction`, shaped as either `{"error": "..."}` or
+/// `{"result": {"success", "response_data", "mismatches": [...], "output": [...]}}`.
+fn py_to_verify_interaction_response(value: Bound<'_, PyAny>) -> anyhow::Result<VerifyInteractionResponse> {
+  let dict = as_dict(value, "verify_interaction result")?;
+  if let Some(error) = string_item(&dict, "error")? {
+    return Ok(VerifyInteractionResponse {
+      response: Some(verify_interaction_response::Response::Error(error)),
+    });
+  }
+
+  let result = dict_item(&dict, "result")?
+    .ok_or_else(|| anyhow!("Python verify_interaction() must return either an 'error' or 'result' field"))?;
+  let mut mismatches = vec![];
+  if let Some(entries) = item(&result, "mismatches")? {
+    for entry in entries.try_iter()? {
+      mismatches.push(py_to_verification_result_item(&entry?)?);
+    }
+  }
+  let output: Option<Vec<String>> = item(&result, "output")?.map(|output| output.extract()).transpose()?;
+
+  Ok(VerifyInteractionResponse {
+    response: Some(verify_interaction_response::Response::Result(VerificationResult {
+      success: bool_item(&result, "success")?.unwrap_or(false),
+      response_data: py_to_interaction_data(item(&result, "response_data")?)?,
+      mismatches,
+      output: output.unwrap_or_default(),
+    })),
+  })
+}
+
+#[async_trait]
+impl PactPluginRpc for PythonPactPlugin {
+  async fn init_plugin(&mut self, request: PluginInitRequest) -> anyhow::Result<PluginInitResponse> {
+    let catalogue = self.with_module(move |module| {
+      call_init(module, &request.implementation, &request.version)
+    }).await?;
+    Ok(PluginInitResponse {
+      catalogue,
+      plugin_capabilities: vec![],
+    })
+  }
+}
+
+#[async_trait]
+impl PluginInstance for PythonPactPlugin {
+  fn manifest(&self) -> &PactPluginManifest {
+    &self.manifest
+  }
+
+  fn instance_id(&self) -> &str {
+    &self.instance_id
+  }
+
+  fn has_capability(&self, capability: &str) -> bool {
+    self.plugin_capabilities.iter().any(|c| c == capability)
+  }
+
+  async fn compare_contents(
+    &self,
+    request: CompareContentsRequest,
+  ) -> anyhow::Result<CompareContentsResponse> {
+    self.with_module(move |module| {
+      let match_fn = function(module, "match_contents")?;
+      let request = compare_request_to_py(module.py(), &request)?;
+      let result = match_fn.call1((request,)).map_err(|err| call_failed("match_contents", err))?;
+      py_to_compare_response(result)
+    }).await
+  }
+
+  async fn configure_interaction(
+    &self,
+    request: ConfigureInteractionRequest,
+  ) -> anyhow::Result<ConfigureInteractionResponse> {
+    self.with_module(move |module| {
+      let configure_fn = function(module, "configure_interaction")?;
+      let config = struct_to_py(module.py(), &request.contents_config)?;
+      let result = configure_fn
+        .call1((request.content_type.as_str(), config))
+        .map_err(|err| call_failed("configure_interaction", err))?;
+      py_to_configure_response(result)
+    }).await
+  }
+
+  async fn generate_content(
+    &self,
+    request: GenerateContentRequest,
+  ) -> anyhow::Result<GenerateContentResponse> {
+    self.with_module(move |module| {
+      let Some(generate_fn) = optional_function(module, "generate_content")? else {
+        return Ok(GenerateContentResponse { contents: request.contents });
+      };
+      let py = module.py();
+      let contents = body_to_py(py, &request.contents)?;
+      let generators = generators_to_py(py, &request.generators)?;
+      let result = generate_fn
+        .call1((contents, generators, test_mode_to_str(request.test_mode)))
+        .map_err(|err| call_failed("generate_content", err))?;
+      Ok(GenerateContentResponse { contents: py_to_body(Some(result))? })
+    }).await
+  }
+
+  async fn match_field(
+    &self,
+    request: proto_v2::MatchFieldRequest,
+  ) -> anyhow::Result<proto_v2::MatchFieldResponse> {
+    self.with_module(move |module| {
+      let match_fn = function(module, "match_field")?;
+      let request_dict = match_field_request_to_py(module.py(), &request)?;
+      let result = match_fn.call1((request_dict,)).map_err(|err| call_failed("match_field", err))?;
+      py_to_match_field_response(result, &request.path)
+    }).await
+  }
+
+  async fn generate_field(
+    &self,
+    request: proto_v2::GenerateFieldRequest,
+  ) -> anyhow::Result<proto_v2::GenerateFieldResponse> {
+    self.with_module(move |module| {
+      let generate_fn = function(module, "generate_field")?;
+      let request = generate_field_request_to_py(module.py(), &request)?;
+      let result = generate_fn.call1((request,)).map_err(|err| call_failed("generate_field", err))?;
+      py_to_generate_field_response(result)
+    }).await
+  }
+
+  async fn start_mock_server(
+    &self,
+    request: StartMockServerRequest,
+  ) -> anyhow::Result<StartMockServerResponse> {
+    self.with_module(move |module| {
+      let start_fn = function(module, "start_mock_server")?;
+      let py = module.py();
+      let request_dict = PyDict::new(py);
+      request_dict.set_item("host_interface", &request.host_interface)?;
+      request_dict.set_item("port", request.port)?;
+      request_dict.set_item("tls", request.tls)?;
+      request_dict.set_item("pact", &request.pact)?;
+      request_dict.set_item("test_context", struct_to_py(py, &request.test_context)?)?;
+      let result = start_fn.call1((request_dict,)).map_err(|err| call_failed("start_mock_server", err))?;
+      py_to_start_mock_server_response(result)
+    }).await
+  }
+
+  async fn start_mock_server_v2(
+    &self,
+    request: proto_v2::StartMockServerRequest,
+  ) -> anyhow::Result<StartMockServerResponse> {
+    self.with_module(move |module| {
+      let start_fn = function(module, "start_mock_server")?;
+      let py = module.py();
+      let request_dict = PyDict::new(py);
+      request_dict.set_item("host_interface", &request.host_interface)?;
+      request_dict.set_item("port", request.port)?;
+      request_dict.set_item("tls", request.tls)?;
+      let interactions = PyList::empty(py);
+      for interaction in &request.interactions {
+        interactions.append(interaction_contents_to_py(py, interaction)?)?;
+      }
+      request_dict.set_item("interactions", interactions)?;
+      request_dict.set_item("test_context", struct_to_py(py, &request.test_context)?)?;
+      let result = start_fn.call1((request_dict,)).map_err(|err| call_failed("start_mock_server", err))?;
+      py_to_start_mock_server_response(result)
+    }).await
+  }
+
+  async fn shutdown_mock_server(
+    &self,
+    request: ShutdownMockServerRequest,
+  ) -> anyhow::Result<ShutdownMockServerResponse> {
+    let results = self.with_module(move |module| {
+      let result = function(module, "shutdown_mock_server")?
+        .call1((request.server_key.as_str(),))
+        .map_err(|err| call_failed("shutdown_mock_server", err))?;
+      py_to_mock_server_results(result)
+    }).await?;
+    Ok(ShutdownMockServerResponse { ok: results.ok, results: results.results })
+  }
+
+  async fn get_mock_server_results(
+    &self,
+    request: MockServerRequest,
+  ) -> anyhow::Result<MockServerResults> {
+    self.with_module(move |module| {
+      let result = function(module, "get_mock_server_results")?
+        .call1((request.server_key.as_str(),))
+        .map_err(|err| call_failed("get_mock_server_results", err))?;
+      py_to_mock_server_results(result)
+    }).await
+  }
+
+  async fn prepare_interaction_for_verification(
+    &self,
+    request: VerificationPreparationRequest,
+  ) -> anyhow::Result<VerificationPreparationResponse> {
+    self.with_module(move |module| {
+      let prepare_fn = function(module, "prepare_interaction_for_verification")?;
+      let py = module.py();
+      let request_dict = PyDict::new(py);
+      request_dict.set_item("pact", &request.pact)?;
+      request_dict.set_item("interaction_key", &request.interaction_key)?;
+      request_dict.set_item("config", struct_to_py(py, &request.config)?)?;
+      let result = prepare_fn
+        .call1((request_dict,))
+        .map_err(|err| call_failed("prepare_interaction_for_verification", err))?;
+      py_to_verification_preparation_response(result)
+    }).await
+  }
+
+  async fn prepare_interaction_for_verification_v2(
+    &self,
+    request: proto_v2::VerificationPreparationRequest,
+  ) -> anyhow::Result<VerificationPreparationResponse> {
+    self.with_module(move |module| {
+      let prepare_fn = function(module, "prepare_interaction_for_verification")?;
+      let py = module.py();
+      let request_dict = PyDict::new(py);
+      if let Some(interaction_contents) = &request.interaction_contents {
+        request_dict.set_item("interaction_contents", interaction_contents_to_py(py, interaction_contents)?)?;
+      }
+      request_dict.set_item("config", struct_to_py(py, &request.config)?)?;
+      request_dict.set_item("test_context", struct_to_py(py, &request.test_context)?)?;
+      let result = prepare_fn
+        .call1((request_dict,))
+        .map_err(|err| call_failed("prepare_interaction_for_verification", err))?;
+      py_to_verification_preparation_response(result)
+    }).await
+  }
+
+  async fn verify_interaction(
+    &self,
+    request: VerifyInteractionRequest,
+  ) -> anyhow::Result<VerifyInteractionResponse> {
+    self.with_module(move |module| {
+      let verify_fn = function(module, "verify_interaction")?;
+      let py = module.py();
+      let request_dict = PyDict::new(py);
+      request_dict.set_item("interaction_data", interaction_data_to_py(py, &request.interaction_data)?)?;
+      request_dict.set_item("config", struct_to_py(py, &request.config)?)?;
+      request_dict.set_item("pact", &request.pact)?;
+      request_dict.set_item("interaction_key", &request.interaction_key)?;
+      let result = verify_fn.call1((request_dict,)).map_err(|err| call_failed("verify_interaction", err))?;
+      py_to_verify_interaction_response(result)
+    }).await
+  }
+
+  async fn verify_interaction_v2(
+    &self,
+    request: proto_v2::VerifyInteractionRequest,
+  ) -> anyhow::Result<VerifyInteractionResponse> {
+    self.with_module(move |module| {
+      let verify_fn = function(module, "verify_interaction")?;
+      let py = module.py();
+      let interaction_data = request.interaction_data.as_ref()
+        .map(v2_interaction_data_to_v1)
+        .transpose()?;
+      let request_dict = PyDict::new(py);
+      request_dict.set_item("interaction_data", interaction_data_to_py(py, &interaction_data)?)?;
+      request_dict.set_item("config", struct_to_py(py, &request.config)?)?;
+      if let Some(interaction_contents) = &request.interaction_contents {
+        request_dict.set_item("interaction_contents", interaction_contents_to_py(py, interaction_contents)?)?;
+      }
+      request_dict.set_item("test_context", struct_to_py(py, &request.test_context)?)?;
+      let result = verify_fn.call1((request_dict,)).map_err(|err| call_failed("verify_interaction", err))?;
+      py_to_verify_interaction_response(result)
+    }).await
+  }
+
+  async fn update_catalogue(&self, request: Catalogue) -> anyhow::Result<()> {
+    self.with_module(move |module| {
+      let Some(update_fn) = optional_function(module, "update_catalogue")? else {
+        return Ok(());
+      };
+      let entries = PyList::empty(module.py());
+      for entry in &request.catalogue {
+        // An entry type this driver doesn't understand is skipped rather than passed to the
+        // script as some other type it isn't - see `register_plugin_entries`.
+        let Some(entry_type) = CatalogueEntryType::from_proto_value(entry.r#type) else {
+          warn!("Not passing catalogue entry '{}' to the plugin: {} is not a catalogue entry type this driver understands",
+            entry.key, entry.r#type);
+          continue;
+        };
+        let entry_dict = PyDict::new(module.py());
+        entry_dict.set_item("entryType", entry_type.as_proto_name())?;
+        entry_dict.set_item("key", &entry.key)?;
+        entry_dict.set_item("values", &entry.values)?;
+        entries.append(entry_dict)?;
+      }
+      update_fn.call1((entries,)).map_err(|err| call_failed("update_catalogue", err))?;
+      Ok(())
+    }).await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+  use std::path::Path;
+
+  use expectest::prelude::*;
+  use maplit::hashmap;
+
+  use super::*;
+
+  fn python_manifest(plugin_dir: &Path, name: &str) -> PactPluginManifest {
+    PactPluginManifest {
+      plugin_dir: plugin_dir.to_string_lossy().to_string(),
+      plugin_interface_version: 1,
+      name: name.to_string(),
+      version: "0.0.0".to_string(),
+      executable_type: "python".to_string(),
+      minimum_required_version: None,
+      entry_point: "entry.py".to_string(),
+      entry_points: HashMap::new(),
+      args: None,
+      dependencies: None,
+      plugin_config: HashMap::new(),
+    }
+  }
+
+  fn init_request() -> PluginInitRequest {
+    PluginInitRequest {
+      implementation: "test".to_string(),
+      version: "0.0.0".to_string(),
+      host_capabilities: vec![],
+      plugin_instance_id: "test-instance".to_string(),
+    }
+  }
+
+  fn start_test_plugin(script: &str, name: &str) -> PythonPactPlugin {
+    let plugin_dir = tempdir::TempDir::new("python-plugin-test").unwrap();
+    std::fs::write(plugin_dir.path().join("entry.py"), script).unwrap();
+    // The script is fully executed by `start_python_plugin`, so the tempdir doesn't need to
+    // outlive this call.
+    start_python_plugin(&python_manifest(plugin_dir.path(), name), "test-instance".to_string()).unwrap()
+  }
+
+  fn text_body(contents: &str) -> Option<Body> {
+    Some(Body {
+      content_type: "text/plain".to_string(),
+      content: Some(contents.as_bytes().to_vec()),
+      content_type_hint: body::ContentTypeHint::Default as i32,
+    })
+  }
+
+  #[tokio::test]
+  async fn init_returns_the_catalogue_entries_from_the_script() {
+    let mut plugin = start_test_plugin(r#"
+def init(implementation, version):
+    return [
+        {"entryType": "CONTENT_MATCHER", "key": "text", "values": {"content-types": "text/plain"}},
+        {"entryType": "GENERATOR", "key": "creditcard"},
+    ]
+"#, "init-test");
+
+    let response = plugin.init_plugin(init_request()).await.unwrap();
+
+    expect!(response.catalogue.len()).to(be_equal_to(2));
+    expect!(response.catalogue[0].key.as_str()).to(be_equal_to("text"));
+    expect!(response.catalogue[0].r#type).to(be_equal_to(CatalogueEntryType::CONTENT_MATCHER.to_proto_value()));
+    expect!(response.catalogue[0].values.get("content-types")).to(be_some().value(&"text/plain".to_string()));
+    expect!(response.catalogue[1].r#type).to(be_equal_to(CatalogueEntryType::GENERATOR.to_proto_value()));
+  }
+
+  #[tokio::test]
+  async fn init_rejects_an_unknown_catalogue_entry_type() {
+    let mut plugin = start_test_plugin(r#"
+def init(implementation, version):
+    return [{"entryType": "NOT_AN_ENTRY_TYPE", "key": "nope"}]
+"#, "bad-entry-type-test");
+
+    let error = plugin.init_plugin(init_request()).await.unwrap_err().to_string();
+    expect!(error.contains("NOT_AN_ENTRY_TYPE")).to(be_true());
+  }
+
+  #[test]
+  fn reports_an_error_in_the_script_when_loading_it() {
+    let plugin_dir = tempdir::TempDir::new("python-plugin-test").unwrap();
+    std::fs::write(plugin_dir.path().join("entry.py"), "def init(:\n").unwrap();
+    let manifest = python_manifest(plugin_dir.path(), "syntax-error-test");
+
+    let error = start_python_plugin(&manifest, "test-instance".to_string()).unwrap_err().to_string();
+    expect!(error.contains("Failed to load Python plugin script")).to(be_true());
+    expect!(error.contains("SyntaxError")).to(be_true());
+  }
+
+  #[tokio::test]
+  async fn reports_a_missing_function_and_a_failing_one() {
+    let plugin = start_test_plugin(r#"
+def match_contents(request):
+    raise ValueError("something went wrong")
+"#, "missing-function-test");
+
+    let error = plugin.configure_interaction(ConfigureInteractionRequest::default()).await.unwrap_err();
+    expect!(error.to_string()).to(be_equal_to("Python plugin does not define a 'configure_interaction' function"));
+    let error = plugin.compare_contents(CompareContentsRequest::default()).await.unwrap_err();
+    expect!(error.to_string()).to(be_equal_to("Python match_contents() function failed - ValueError: something went wrong"));
+  }
+
+  #[test]
+  fn imports_modules_from_the_plugin_directory_and_package_directories() {
+    let plugin_dir = tempdir::TempDir::new("python-plugin-test").unwrap();
+    std::fs::write(plugin_dir.path().join("py_plugin_test_local.py"), "VALUE = 'local'\n").unwrap();
+    let site_packages = plugin_dir.path().join("site-packages").join("py_plugin_test_bundled");
+    std::fs::create_dir_all(&site_packages).unwrap();
+    std::fs::write(site_packages.join("__init__.py"), "VALUE = 'bundled'\n").unwrap();
+    let packages_dir = tempdir::TempDir::new("python-packages-test").unwrap();
+    std::fs::write(packages_dir.path().join("py_plugin_test_shared.py"), "VALUE = 'shared'\n").unwrap();
+    std::fs::write(plugin_dir.path().join("entry.py"), r#"
+import py_plugin_test_local, py_plugin_test_bundled, py_plugin_test_shared
+RESULT = [py_plugin_test_local.VALUE, py_plugin_test_bundled.VALUE, py_plugin_test_shared.VALUE]
+"#).unwrap();
+
+    let mut manifest = python_manifest(plugin_dir.path(), "search-path-test");
+    manifest.plugin_config.insert(
+      "pythonPackagesDir".to_string(),
+      serde_json::Value::String(packages_dir.path().to_string_lossy().to_string()),
+    );
+    let plugin = start_python_plugin(&manifest, "test-instance".to_string()).unwrap();
+
+    let result: Vec<String> = Python::attach(|py| {
+      let module = plugin.runtime.blocking_lock();
+      module.bind(py).getattr("RESULT").unwrap().extract().unwrap()
+    });
+    expect!(result).to(be_equal_to(vec!["local".to_string(), "bundled".to_string(), "shared".to_string()]));
+  }
+
+  #[tokio::test]
+  async fn configure_interaction_then_match_contents_round_trip() {
+    let plugin = start_test_plugin(r#"
+def configure_interaction(content_type, config):
+    return {
+        "interactions": [{
+            "contents": {"content_type": content_type, "contents": config["text"].encode()},
+            "rules": {"$": [{"type": "regex", "values": {"regex": "\\w+"}}]},
+        }],
+        "plugin_config": {"pact_configuration": {"version": 1}},
+    }
+
+def match_contents(request):
+    expected = request["expected"]["contents"]
+    actual = request["actual"]["contents"]
+    if expected == actual:
+        return {}
+    return {"mismatches": {"$": {"mismatch": "Text differs", "expected": len(expected), "actual": actual}}}
+"#, "round-trip-test");
+
+    let response = plugin.configure_interaction(ConfigureInteractionRequest {
+      content_type: "text/plain".to_string(),
+      contents_config: Some(to_proto_struct(&hashmap!{ "text".to_string() => serde_json::json!("hello") })),
+    }).await.unwrap();
+    let interaction = &response.interaction[0];
+    expect!(interaction.contents.clone()).to(be_equal_to(text_body("hello")));
+    expect!(interaction.rules["$"].rule[0].r#type.as_str()).to(be_equal_to("regex"));
+    expect!(response.plugin_configuration.unwrap().pact_configuration.is_some()).to(be_true());
+
+    let matched = plugin.compare_contents(CompareContentsRequest {
+      expected: text_body("hello"),
+      actual: text_body("hello"),
+      .. CompareContentsRequest::default()
+    }).await.unwrap();
+    expect!(matched.results.is_empty()).to(be_true());
+
+    let mismatched = plugin.compare_contents(CompareContentsRequest {
+      expected: text_body("hello"),
+      actual: text_body("goodbye"),
+      .. CompareContentsRequest::default()
+    }).await.unwrap();
+    let mismatch = &mismatched.results["$"].mismatches[0];
+    expect!(mismatch.mismatch.as_str()).to(be_equal_to("Text differs"));
+    expect!(mismatch.expected.clone()).to(be_some().value(b"5".to_vec()));
+    expect!(mismatch.actual.clone()).to(be_some().value(b"goodbye".to_vec()));
+    expect!(mismatch.path.as_str()).to(be_equal_to("$"));
+  }
+
+  #[tokio::test]
+  async fn generate_content_is_optional() {
+    let plugin = start_test_plugin("", "no-generate-test");
+    let response = plugin.generate_content(GenerateContentRequest {
+      contents: text_body("unchanged"),
+      .. GenerateContentRequest::default()
+    }).await.unwrap();
+    expect!(response.contents).to(be_equal_to(text_body("unchanged")));
+  }
+
+  #[tokio::test]
+  async fn field_values_keep_their_python_types() {
+    let plugin = start_test_plugin(r#"
+def match_field(request):
+    expected, actual = request["expected"], request["actual"]
+    if type(expected) is not type(actual):
+        return {"mismatches": ["Expected %r but got %r" % (expected, actual)]}
+    return {}
+
+def generate_field(request):
+    return {"value": request["example_value"] + b"!"}
+"#, "field-test");
+
+    let field = |value| Some(proto_v2::FieldValue { value: Some(value) });
+    use proto_v2::field_value::Value as FieldValue;
+    let response = plugin.match_field(proto_v2::MatchFieldRequest {
+      path: "$.amount".to_string(),
+      expected: field(FieldValue::IntegerValue(100)),
+      actual: field(FieldValue::DecimalValue(100.5)),
+      .. proto_v2::MatchFieldRequest::default()
+    }).await.unwrap();
+    expect!(response.mismatches[0].mismatch.as_str()).to(be_equal_to("Expected 100 but got 100.5"));
+    expect!(response.mismatches[0].path.as_str()).to(be_equal_to("$.amount"));
+
+    let response = plugin.generate_field(proto_v2::GenerateFieldRequest {
+      example_value: field(FieldValue::BinaryValue(vec![0, 1])),
+      .. proto_v2::GenerateFieldRequest::default()
+    }).await.unwrap();
+    expect!(response.value).to(be_equal_to(field(FieldValue::BinaryValue(vec![0, 1, b'!']))));
+  }
+
+  struct FixedErrorCoreMatcher;
+
+  #[async_trait]
+  impl crate::core_capabilities::CoreContentMatcher for FixedErrorCoreMatcher {
+    async fn compare_contents(&self, _request: CompareContentsRequest) -> anyhow::Result<CompareContentsResponse> {
+      Ok(CompareContentsResponse {
+        error: "core matcher says no".to_string(),
+        type_mismatch: None,
+        results: HashMap::new(),
+      })
+    }
+  }
+
+  #[tokio::test]
+  async fn match_contents_calls_host_compare_contents_for_a_registered_core_capability() {
+    let key = "python_match_contents_calls_host_compare_contents_for_a_registered_core_capability";
+    crate::catalogue_manager::register_core_entries(&vec![crate::catalogue_manager::CatalogueEntry {
+      entry_type: CatalogueEntryType::CONTENT_MATCHER,
+      provider_type: crate::catalogue_manager::CatalogueEntryProviderType::CORE,
+      plugin: None,
+      key: key.to_string(),
+      values: HashMap::new()
+    }]);
+    crate::core_capabilities::register_core_content_matcher(key, Arc::new(FixedErrorCoreMatcher));
+
+    let plugin = start_test_plugin(&format!(r#"
+def match_contents(request):
+    return host_compare_contents("{key}", request)
+"#), "host-compare-contents-test");
+    let response = plugin.compare_contents(CompareContentsRequest::default()).await.unwrap();
+
+    crate::core_capabilities::deregister_core_content_matcher(key);
+
+    expect!(response.error.as_str()).to(be_equal_to("core matcher says no"));
+  }
+
+  #[tokio::test]
+  async fn host_match_field_raises_an_exception_the_script_can_handle() {
+    let plugin = start_test_plugin(r#"
+def match_field(request):
+    try:
+        return host_match_field("python-no-such-matcher", request)
+    except RuntimeError as err:
+        return {"error": str(err)}
+"#, "host-match-field-test");
+
+    let response = plugin.match_field(proto_v2::MatchFieldRequest {
+      rule: Some(proto_v2::MatchingRule { r#type: "regex".to_string(), values: None }),
+      .. proto_v2::MatchFieldRequest::default()
+    }).await.unwrap();
+    expect!(response.error.contains("No catalogue entry found")).to(be_true());
+  }
+
+  const TRANSPORT_PLUGIN_SCRIPT: &str = r#"
+def start_mock_server(request):
+    if request["port"] == 0:
+        return {"error": "could not bind a mock server"}
+    return {"details": {"key": "mock-server-1", "port": 12345, "address": "127.0.0.1:12345"}}
+
+def shutdown_mock_server(server_key):
+    return {"ok": False, "results": [{"path": "/foo", "error": "did not match", "mismatches": ["simple string mismatch"]}]}
+
+def verify_interaction(request):
+    if (request["config"] or {}).get("fail"):
+        return {"error": "verification failed"}
+    return {
+        "result": {
+            "success": True,
+            "response_data": {
+                "body": {"content_type": "application/json", "contents": b"response-body"},
+                "metadata": {"tag": b"raw-bytes", "path": request["interaction_data"]["metadata"]["path"]},
+            },
+            "mismatches": ["a plain mismatch", {"mismatch": "a dict mismatch", "path": "$.foo", "expected": 1, "actual": 2}],
+            "output": ["POST /foo", "200 OK"],
+        }
+    }
+"#;
+
+  #[tokio::test]
+  async fn transport_functions_round_trip() {
+    let plugin = start_test_plugin(TRANSPORT_PLUGIN_SCRIPT, "transport-test");
+
+    let response = plugin.start_mock_server(StartMockServerRequest {
+      host_interface: "127.0.0.1".to_string(),
+      port: 8080,
+      .. StartMockServerRequest::default()
+    }).await.unwrap();
+    expect!(response.response).to(be_some().value(start_mock_server_response::Response::Details(MockServerDetails {
+      key: "mock-server-1".to_string(),
+      port: 12345,
+      address: "127.0.0.1:12345".to_string(),
+    })));
+
+    let response = plugin.shutdown_mock_server(ShutdownMockServerRequest {
+      server_key: "mock-server-1".to_string(),
+    }).await.unwrap();
+    expect!(response.ok).to(be_false());
+    expect!(response.results[0].mismatches[0].mismatch.as_str()).to(be_equal_to("simple string mismatch"));
+
+    let response = plugin.verify_interaction(VerifyInteractionRequest {
+      interaction_data: Some(InteractionData {
+        body: None,
+        metadata: hashmap!{
+          "path".to_string() => MetadataValue {
+            value: Some(metadata_value::Value::NonBinaryValue(to_proto_value(&serde_json::json!("/foo"))))
+          }
+        },
+      }),
+      .. VerifyInteractionRequest::default()
+    }).await.unwrap();
+    let Some(verify_interaction_response::Response::Result(result)) = response.response else {
+      panic!("expected a verification result, got {:?}", response.response);
+    };
+    expect!(result.success).to(be_true());
+    let response_data = result.response_data.unwrap();
+    expect!(response_data.body.unwrap().content).to(be_some().value(b"response-body".to_vec()));
+    expect!(response_data.metadata["tag"].value.clone())
+      .to(be_some().value(metadata_value::Value::BinaryValue(b"raw-bytes".to_vec())));
+    expect!(response_data.metadata["path"].value.clone())
+      .to(be_some().value(metadata_value::Value::NonBinaryValue(to_proto_value(&serde_json::json!("/foo")))));
+    expect!(result.mismatches.len()).to(be_equal_to(2));
+    expect!(result.output).to(be_equal_to(vec!["POST /foo".to_string(), "200 OK".to_string()]));
+  }
+}
@@ -1,24 +1,70 @@
 //! Module for dealing with the plugin repository
 
 use std::collections::HashMap;
+use std::env;
 use std::fs;
 use std::fs::File;
 use std::io::{BufReader, Read, Write};
 use std::path::PathBuf;
+use std::sync::RwLock;
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use minisign_verify::PublicKey;
 use reqwest::Client;
 use semver::Version;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use tracing::{debug, info, warn};
 
+use crate::download::fetch_text_file;
 use crate::plugin_manager::pact_plugin_dir;
 use crate::plugin_models::PactPluginManifest;
+use crate::signatures::{SIGNATURE_EXTENSION, trusted_keys, verify_signature};
 
 pub const DEFAULT_INDEX: &str = include_str!("../repository.index");
 pub const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+/// URL of the public plugin repository
+pub const DEFAULT_REPOSITORY_URL: &str = "https://raw.githubusercontent.com/pact-foundation/pact-plugins/main/repository";
+
+lazy_static! {
+  static ref REPOSITORY_URL: RwLock<Option<String>> = RwLock::new(None);
+}
+
+/// Set the URL of the plugin repository to fetch the index and install plugins from. This can be
+/// an internal HTTP server or a `file://` URL of a directory mirroring the repository. Takes
+/// precedence over the `PACT_PLUGIN_REPOSITORY_URL` environment variable.
+///
+/// A mirror holds the `repository.index` and `repository.index.sha256` files, and a
+/// `<name>/<version>` directory for each plugin version with its `pact-plugin.json` manifest and
+/// release files (i.e. `pact-<name>-plugin-<os>-<arch>.gz`).
+pub fn set_repository_url(url: &str) {
+  *REPOSITORY_URL.write().unwrap() = Some(url.trim_end_matches('/').to_string());
+}
+
+/// URL of the plugin repository. Taken from the first of the value set with [`set_repository_url`]
+/// and the `PACT_PLUGIN_REPOSITORY_URL` environment variable that is set, otherwise the public
+/// repository.
+pub fn repository_url() -> String {
+  configured_repository_url().unwrap_or_else(|| DEFAULT_REPOSITORY_URL.to_string())
+}
+
+fn configured_repository_url() -> Option<String> {
+  REPOSITORY_URL.read().unwrap().clone()
+    .or_else(|| env::var("PACT_PLUGIN_REPOSITORY_URL").ok()
+      .map(|url| url.trim().trim_end_matches('/').to_string())
+      .filter(|url| !url.is_empty()))
+}
+
+/// If a plugin repository other than the public one has been configured, the URL of the directory
+/// in it holding the manifest and release files for the given plugin version. The public
+/// repository only holds the index, so plugins are installed from their release source instead.
+pub fn mirrored_plugin_url(name: &str, version: &str) -> Option<String> {
+  configured_repository_url()
+    .filter(|url| url != DEFAULT_REPOSITORY_URL)
+    .map(|url| format!("{}/{}/{}", url, name, version))
+}
 
 /// Struct representing the plugin repository index file
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -180,32 +226,60 @@ impl Default for PluginRepositoryIndex {
   }
 }
 
-/// Retrieves the latest repository index, first from GitHub, and if not able to, then any locally
-/// cached index, otherwise defaults to the version compiled into the library.
+/// Retrieves the latest repository index, first from the plugin repository (see [`repository_url`]),
+/// and if not able to, then any locally cached index, otherwise defaults to the version compiled
+/// into the library.
+///
+/// If trusted signing keys are configured (see [`crate::signatures`]), a fetched or cached index
+/// must have a valid signature to be used. An index whose signature is missing or does not verify
+/// is an error, rather than a reason to fall back to another index.
 pub async fn fetch_repository_index(
   http_client: &Client,
   default_index: Option<&str>
 ) -> anyhow::Result<PluginRepositoryIndex> {
-  fetch_index_from_github(http_client)
-    .await
-    .or_else(|err| {
-      warn!("Was not able to load index from GitHub - {}", err);
-      load_local_index()
-    })
-    .or_else(|err| {
-      warn!("Was not able to load local index, will use built in one - {}", err);
-      toml::from_str::<PluginRepositoryIndex>(default_index.unwrap_or(DEFAULT_INDEX))
-        .map_err(|err| anyhow!(err))
-    })
+  fetch_repository_index_from(http_client, repository_url().as_str(), &trusted_keys()?, default_index).await
 }
 
-fn load_local_index() -> anyhow::Result<PluginRepositoryIndex> {
-  let plugin_dir = pact_plugin_dir()?;
-  if !plugin_dir.exists() {
-    return Err(anyhow!("Plugin directory does not exist"));
+async fn fetch_repository_index_from(
+  http_client: &Client,
+  url: &str,
+  keys: &[PublicKey],
+  default_index: Option<&str>
+) -> anyhow::Result<PluginRepositoryIndex> {
+  match fetch_index_from_repository(http_client, url).await {
+    Ok((index_contents, index_sha, index)) => {
+      let signature = if keys.is_empty() {
+        None
+      } else {
+        Some(fetch_index_signature(http_client, url, &index_contents, keys).await
+          .with_context(|| format!("Refusing to use the index from {}", url))?)
+      };
+      if let Err(err) = cache_index(&index_contents, &index_sha, signature.as_ref()) {
+        warn!("Could not cache index to local file - {}", err);
+      }
+      return Ok(index);
+    }
+    Err(err) => warn!("Was not able to load index from {} - {}", url, err)
   }
 
+  match load_local_index(keys) {
+    Ok(Some(index)) => return Ok(index),
+    Ok(None) => warn!("There is no locally cached index, will use the built in one"),
+    Err(err) if !keys.is_empty() => return Err(err.context("Refusing to use the locally cached index")),
+    Err(err) => warn!("Was not able to load local index, will use built in one - {}", err)
+  }
+
+  toml::from_str::<PluginRepositoryIndex>(default_index.unwrap_or(DEFAULT_INDEX))
+    .map_err(|err| anyhow!(err))
+}
+
+/// Loads the locally cached index, if there is one
+fn load_local_index(keys: &[PublicKey]) -> anyhow::Result<Option<PluginRepositoryIndex>> {
+  let plugin_dir = pact_plugin_dir()?;
   let repository_file = plugin_dir.join("repository.index");
+  if !repository_file.exists() {
+    return Ok(None);
+  }
 
   let sha = calculate_sha(&repository_file)?;
   let expected_sha = load_sha(&repository_file)?;
@@ -213,39 +287,54 @@ fn load_local_index() -> anyhow::Result<PluginRepositoryIndex> {
     return Err(anyhow!("Error: SHA256 digest does not match: expected {} but got {}", expected_sha, sha));
   }
 
-  load_index_file(&repository_file)
+  if !keys.is_empty() {
+    let signature_file = plugin_dir.join(format!("repository.index.{}", SIGNATURE_EXTENSION));
+    let signature = fs::read_to_string(&signature_file)
+      .map_err(|err| anyhow!("Could not read the signature file for the cached index - {}", err))?;
+    verify_signature("repository.index", &fs::read(&repository_file)?, signature.as_str(), keys)?;
+  }
+
+  load_index_file(&repository_file).map(Some)
 }
 
-async fn fetch_index_from_github(http_client: &Client) -> anyhow::Result<PluginRepositoryIndex> {
-  info!("Fetching index from github");
-  let index_contents = http_client.get("https://raw.githubusercontent.com/pact-foundation/pact-plugins/main/repository/repository.index")
-    .send()
-    .await?
-    .text()
-    .await?;
-
-  let index_sha = http_client.get("https://raw.githubusercontent.com/pact-foundation/pact-plugins/main/repository/repository.index.sha256")
-    .send()
-    .await?
-    .text()
-    .await?;
+/// Fetches the index and its SHA256 digest from the repository, checking the digest
+async fn fetch_index_from_repository(
+  http_client: &Client,
+  url: &str
+) -> anyhow::Result<(String, String, PluginRepositoryIndex)> {
+  info!("Fetching index from {}", url);
+  let index_contents = fetch_text_file(http_client, url, "repository.index").await?;
+  let index_sha = fetch_text_file(http_client, url, "repository.index.sha256").await?;
+  let index_sha = index_sha.trim().to_string();
   let mut hasher = Sha256::new();
   hasher.update(index_contents.as_bytes());
   let result = hasher.finalize();
   let calculated = format!("{:x}", result);
 
   if calculated != index_sha {
-    return Err(anyhow!("Error: SHA256 digest from GitHub does not match: expected {} but got {}", index_sha, calculated));
+    return Err(anyhow!("Error: SHA256 digest from the plugin repository does not match: expected {} but got {}", index_sha, calculated));
   }
 
-  if let Err(err) = cache_index(&index_contents, &index_sha) {
-    warn!("Could not cache index to local file - {}", err);
-  }
+  let index = toml::from_str(index_contents.as_str())?;
+  Ok((index_contents, index_sha, index))
+}
 
-  Ok(toml::from_str(index_contents.as_str())?)
+/// Fetches the signature of the index from the repository and verifies the index with it
+async fn fetch_index_signature(
+  http_client: &Client,
+  url: &str,
+  index_contents: &str,
+  keys: &[PublicKey]
+) -> anyhow::Result<String> {
+  let signature_file = format!("repository.index.{}", SIGNATURE_EXTENSION);
+  let signature = fetch_text_file(http_client, url, signature_file.as_str())
+    .await
+    .map_err(|err| anyhow!("Could not get the signature file {} - {}", signature_file, err))?;
+  verify_signature("repository.index", index_contents.as_bytes(), signature.as_str(), keys)?;
+  Ok(signature)
 }
 
-fn cache_index(index_contents: &String, sha: &String) -> anyhow::Result<()> {
+fn cache_index(index_contents: &String, sha: &String, signature: Option<&String>) -> anyhow::Result<()> {
   let plugin_dir = pact_plugin_dir()?;
   if !plugin_dir.exists() {
     fs::create_dir_all(&plugin_dir)?;
@@ -256,6 +345,13 @@ fn cache_index(index_contents: &String, sha: &String) -> anyhow::Result<()> {
   let sha_file = plugin_dir.join("repository.index.sha256");
   let mut f2 = File::create(sha_file)?;
   f2.write_all(sha.as_bytes())?;
+  let signature_file = plugin_dir.join(format!("repository.index.{}", SIGNATURE_EXTENSION));
+  match signature {
+    Some(signature) => fs::write(signature_file, signature)?,
+    None => if signature_file.exists() {
+      fs::remove_file(signature_file)?;
+    }
+  }
   Ok(())
 }
 
@@ -317,11 +413,15 @@ pub fn load_sha(repository_file: &PathBuf) -> anyhow::Result<String> {
 
 #[cfg(test)]
 mod tests {
+  use std::fs;
   use std::time::{SystemTime, UNIX_EPOCH};
 
   use expectest::prelude::*;
+  use reqwest::{Client, Url};
+  use sha2::{Digest, Sha256};
 
-  use crate::repository::PluginRepositoryIndex;
+  use crate::repository::{DEFAULT_INDEX, PluginRepositoryIndex, fetch_repository_index_from};
+  use crate::signatures::tests::{PUBLIC_KEY, SIGNATURE};
 
   #[test]
   fn plugin_repository_index_default() {
@@ -338,4 +438,27 @@ mod tests {
     let ts = index.timestamp.naive_utc().and_utc().timestamp() as u64;
     expect!(ts / 3600).to(be_equal_to(now / 3600));
   }
+
+  #[tokio::test]
+  async fn fetch_repository_index_refuses_an_index_without_a_valid_signature() {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let mirror = tempdir::TempDir::new("plugin-repository").unwrap();
+    fs::write(mirror.path().join("repository.index"), DEFAULT_INDEX).unwrap();
+    fs::write(mirror.path().join("repository.index.sha256"), format!("{:x}", Sha256::digest(DEFAULT_INDEX))).unwrap();
+    let url = Url::from_directory_path(mirror.path()).unwrap().to_string();
+    let url = url.trim_end_matches('/');
+    let keys = [minisign_verify::PublicKey::from_base64(PUBLIC_KEY).unwrap()];
+
+    let result = fetch_repository_index_from(&Client::new(), url, &keys, None).await;
+    expect!(format!("{:#}", result.unwrap_err()).starts_with(
+      format!("Refusing to use the index from {}: Could not get the signature file repository.index.minisig", url).as_str()
+    )).to(be_true());
+
+    // A signature of some other file
+    fs::write(mirror.path().join("repository.index.minisig"), SIGNATURE).unwrap();
+    let result = fetch_repository_index_from(&Client::new(), url, &keys, None).await;
+    expect!(format!("{:#}", result.unwrap_err())).to(be_equal_to(
+      format!("Refusing to use the index from {}: The signature for repository.index was not made with any of the trusted signing keys", url)
+    ));
+  }
 }
@@ -0,0 +1,135 @@
+//! Verification of the signatures of the plugin repository index and downloaded plugin files.
+//!
+//! The SHA256 digest files only detect corrupted downloads, as they are fetched from the same place
+//! as the files they cover. When trusted signing keys are configured (with [`set_trusted_keys`] or
+//! the `PACT_PLUGIN_TRUSTED_KEYS` environment variable), each file must also have a
+//! [minisign](https://jedisct1.github.io/minisign/) signature (`<file>.minisig`) made with one of
+//! those keys, otherwise it is refused.
+
+use std::env;
+use std::sync::RwLock;
+
+use anyhow::{anyhow, bail};
+use lazy_static::lazy_static;
+use minisign_verify::{PublicKey, Signature};
+use tracing::{debug, warn};
+
+/// Extension of signature files
+pub const SIGNATURE_EXTENSION: &str = "minisig";
+
+lazy_static! {
+  static ref TRUSTED_KEYS: RwLock<Option<Vec<String>>> = RwLock::new(None);
+}
+
+/// Set the minisign public keys trusted to sign the plugin repository index and plugin files.
+/// Each key is either the base64 encoded key (the second line of a minisign `.pub` file) or the
+/// contents of a `.pub` file. Takes precedence over the `PACT_PLUGIN_TRUSTED_KEYS` environment
+/// variable (a comma separated list of base64 encoded keys). Setting no keys turns off signature
+/// verification.
+pub fn set_trusted_keys(keys: &[&str]) -> anyhow::Result<()> {
+  for key in keys {
+    parse_key(key)?;
+  }
+  *TRUSTED_KEYS.write().unwrap() = Some(keys.iter().map(|key| key.to_string()).collect());
+  Ok(())
+}
+
+/// The trusted signing keys, taken from the first of the keys set with [`set_trusted_keys`] and
+/// the `PACT_PLUGIN_TRUSTED_KEYS` environment variable that is set. If there are no keys,
+/// signatures are not checked.
+pub(crate) fn trusted_keys() -> anyhow::Result<Vec<PublicKey>> {
+  let keys = match &*TRUSTED_KEYS.read().unwrap() {
+    Some(keys) => keys.clone(),
+    None => env::var("PACT_PLUGIN_TRUSTED_KEYS")
+      .map(|value| value.split(',')
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+        .collect())
+      .unwrap_or_default()
+  };
+  keys.iter().map(|key| parse_key(key)).collect()
+}
+
+fn parse_key(key: &str) -> anyhow::Result<PublicKey> {
+  let key = key.trim();
+  let result = if key.starts_with("untrusted comment:") {
+    PublicKey::decode(key)
+  } else {
+    PublicKey::from_base64(key)
+  };
+  result.map_err(|err| anyhow!("'{}' is not a valid minisign public key - {}", key, err))
+}
+
+/// Verifies the data of the named file against a minisign signature. The signature must have been
+/// made with one of the keys.
+pub(crate) fn verify_signature(
+  name: &str,
+  data: &[u8],
+  signature: &str,
+  keys: &[PublicKey]
+) -> anyhow::Result<()> {
+  let signature = Signature::decode(signature)
+    .map_err(|err| anyhow!("The signature for {} is not a valid minisign signature - {}", name, err))?;
+  for key in keys {
+    match key.verify(data, &signature, false) {
+      Ok(_) => {
+        debug!(%name, trusted_comment = signature.trusted_comment(), "Signature verified OK");
+        return Ok(());
+      }
+      Err(minisign_verify::Error::UnexpectedKeyId) => {}
+      Err(err) => warn!("Signature for {} failed to verify - {}", name, err)
+    }
+  }
+  bail!("The signature for {} was not made with any of the trusted signing keys", name)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use expectest::prelude::*;
+  use minisign_verify::PublicKey;
+
+  use super::{parse_key, verify_signature};
+
+  pub(crate) const PUBLIC_KEY: &str = "RWRuNzMEnqpMM0DD6KEBRVLWSZgirKz//ff5m8uEW3OTl0QcQORwqzoQ";
+  const OTHER_PUBLIC_KEY: &str = "RWQrCGIIjJT/Y4sZJFogIA0r9oN+k/2QiXoq/qiY56ygk7jajsZKCUG1";
+  /// Signature of "plugin executable" made with the secret key for `PUBLIC_KEY`
+  pub(crate) const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RURuNzMEnqpMM10/b0r6skS2TQ8ASwAMXsgFE03tONtIQ5SB3McVWYsDwvztuKZITuve3Wc+TABVTdFN76uSJevKLfMK2nYylAk=
+trusted comment: timestamp:1792300000\tfile:pact-test-plugin-linux-x86_64.gz
+AElcuXxfOqD7CpOqKEZgbUZvGPoyC9x+nCYueUQ4rrsNsyvj99GkAD+H3U3jHW0iz+f8FwfLVJm/xYvmPEJXDg==
+";
+
+  fn key(key: &str) -> PublicKey {
+    parse_key(key).unwrap()
+  }
+
+  #[test]
+  fn parse_key_accepts_base64_keys_and_public_key_files() {
+    expect!(parse_key(PUBLIC_KEY)).to(be_ok());
+    expect!(parse_key(format!("untrusted comment: minisign public key\n{}\n", PUBLIC_KEY).as_str())).to(be_ok());
+    expect!(parse_key("not a key")).to(be_err());
+  }
+
+  #[test]
+  fn verify_signature_accepts_a_signature_from_a_trusted_key() {
+    expect!(verify_signature("test", b"plugin executable", SIGNATURE, &[key(PUBLIC_KEY)])).to(be_ok());
+    expect!(verify_signature("test", b"plugin executable", SIGNATURE, &[key(OTHER_PUBLIC_KEY), key(PUBLIC_KEY)])).to(be_ok());
+  }
+
+  #[test]
+  fn verify_signature_rejects_modified_data() {
+    expect!(verify_signature("test", b"plugin executable!", SIGNATURE, &[key(PUBLIC_KEY)])).to(be_err());
+  }
+
+  #[test]
+  fn verify_signature_rejects_a_signature_from_an_untrusted_key() {
+    let result = verify_signature("test", b"plugin executable", SIGNATURE, &[key(OTHER_PUBLIC_KEY)]);
+    expect!(result.unwrap_err().to_string()).to(be_equal_to(
+      "The signature for test was not made with any of the trusted signing keys"));
+  }
+
+  #[test]
+  fn verify_signature_rejects_an_invalid_signature() {
+    expect!(verify_signature("test", b"plugin executable", "not a signature", &[key(PUBLIC_KEY)])).to(be_err());
+  }
+}
@@ -1,5 +1,6 @@
 //! Utils for dealing with protobufs structs
 
+use std::cmp::Ordering;
 use std::collections::{BTreeMap, HashMap};
 use anyhow::bail;
 use itertools::Itertools;
@@ -117,6 +118,15 @@ pub fn versions_compatible(version: &str, required: &Option<String>) -> bool {
   }
 }
 
+/// Orders two plugin versions as semantic versions, so `0.10.0` comes after `0.9.0`. Falls back
+/// to comparing them as strings if either is not a valid version.
+pub(crate) fn compare_versions(a: &str, b: &str) -> Ordering {
+  match (Version::parse(a), Version::parse(b)) {
+    (Ok(a), Ok(b)) => a.cmp(&b),
+    _ => a.cmp(b)
+  }
+}
+
 fn drop_pre_release(version: Version) -> Version {
   Version::new(version.major, version.minor, version.patch)
 }
@@ -151,9 +161,11 @@ pub fn os_and_arch() -> anyhow::Result<(&'static str, &'static str)> {
 
 #[cfg(test)]
 mod tests {
+  use std::cmp::Ordering;
+
   use expectest::prelude::*;
 
-  use super::versions_compatible;
+  use super::{compare_versions, versions_compatible};
 
   #[test]
   fn versions_compatible_test() {
@@ -167,6 +179,14 @@ mod tests {
     expect!(versions_compatible("0.1.0", &Some("0.0.3".to_string()))).to(be_true());
   }
 
+  #[test]
+  fn compare_versions_test() {
+    expect!(compare_versions("0.10.0", "0.9.0")).to(be_equal_to(Ordering::Greater));
+    expect!(compare_versions("1.0.0", "1.0.0")).to(be_equal_to(Ordering::Equal));
+    expect!(compare_versions("1.0.0-beta.1", "1.0.0")).to(be_equal_to(Ordering::Less));
+    expect!(compare_versions("0.10", "0.9")).to(be_equal_to(Ordering::Less));
+  }
+
   #[test]
   fn versions_compatible_accepts_future_dev_versions() {
     expect!(versions_compatible("0.1", &Some("0.1.0-beta.1".to_string()))).to(be_true());
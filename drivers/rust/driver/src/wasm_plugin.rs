@@ -0,0 +1,887 @@
+//! Support for Pact plugins compiled to WebAssembly (proposal 003).
+//!
+//! A WASM plugin is a core WebAssembly module loaded into an embedded [`wasmtime`] engine running
+//! in the driver's own process (`executableType: "wasm"` in `pact-plugin.json`, with `entryPoint`
+//! naming the `.wasm` file), instead of a separate child process speaking gRPC. There is no WASI:
+//! the module only gets the host functions listed below.
+//!
+//! Every call crosses the module boundary as serialised protobuf bytes in the module's linear
+//! memory - the request and response messages of the matching `PactPlugin` RPC in the V2 interface
+//! (`proto/plugin_v2.proto`). This keeps the WASM interface exactly as expressive as the gRPC one,
+//! including `google.protobuf.Value` data, which a WIT record has no way to describe. The module
+//! must export:
+//!
+//! - `memory` - its linear memory.
+//! - `pact_alloc(len: i32) -> i32` - allocate `len` bytes and return a pointer to them. The driver
+//!   uses it to hand the module a request, and to hand back the result of a host function.
+//! - `pact_free(ptr: i32, len: i32)` (optional) - release a response buffer once the driver has
+//!   read it. A request buffer belongs to the module as soon as it has been passed in.
+//!
+//! Each operation is an export taking `(ptr: i32, len: i32)` - the encoded request - and returning
+//! an `i64` that packs the pointer to the encoded response into its upper 32 bits and the length
+//! into its lower 32 bits. They are named after the RPCs they stand in for:
+//!
+//! - `init_plugin` - `InitPluginRequest` -> `InitPluginResponse`.
+//! - `update_catalogue` (optional) - `Catalogue` -> nothing (return `0`).
+//! - `compare_contents`, `configure_interaction`, `generate_content` (optional - the contents are
+//!   returned unchanged without it), `match_field`, `generate_field`.
+//! - `start_mock_server`, `shutdown_mock_server`, `get_mock_server_results`,
+//!   `prepare_interaction_for_verification`, `verify_interaction` - for a plugin that registers a
+//!   `TRANSPORT` catalogue entry.
+//!
+//! The module can import these host functions from the `pact` module. Each `host_*` function
+//! takes the encoded `PluginHost` request and returns the encoded response packed the same way,
+//! in a buffer allocated with the module's own `pact_alloc` (proposal 007, "WASM transport" - the
+//! in-process equivalent of the gRPC `PluginHost` callback service):
+//!
+//! - `log(ptr: i32, len: i32)` - an encoded `LogMessage`, written to the plugin's log file and
+//!   forwarded to the registered [`crate::plugin_log_sink::PluginLogSink`].
+//! - `host_compare_contents` - `HostCompareContentsRequest` -> `CompareContentsResponse`.
+//! - `host_generate_content` - `HostGenerateContentRequest` -> `GenerateContentResponse`.
+//! - `host_match_field` - `HostMatchFieldRequest` -> `MatchFieldResponse`.
+//! - `host_generate_field` - `HostGenerateFieldRequest` -> `GenerateFieldResponse`.
+//!
+//! A callback that can not be dispatched (an unknown entry key, a failing handler) comes back as
+//! the response's `error`, so the module can decide what to do about it. `GenerateContentResponse`
+//! has no error field, so a failed `host_generate_content` traps instead, failing the call that
+//! made it.
+//!
+//! Each instance runs one call at a time, and a callback is made while that call is still running,
+//! so a module can not call back into a capability it provides itself. Callbacks are made under
+//! the call chain of the call that made them (see [`crate::call_chain`]), and a call that arrives
+//! at an instance already on its chain - directly, or through a plugin that passes the chain on -
+//! fails instead of waiting on itself. The callback gets the failure as its response's `error`.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use prost::Message;
+use tracing::debug;
+use wasmtime::{AsContextMut, Caller, Config, Engine, Extern, Instance, Linker, Memory, Module, Store, TypedFunc};
+
+use crate::call_chain;
+use crate::grpc_plugin::PluginClient;
+use crate::host_callbacks::{
+  call_host_compare_contents, call_host_generate_content, call_host_generate_field, call_host_match_field,
+};
+use crate::plugin_log_sink::{PluginLogEntry, PluginLogSource, emit_plugin_log};
+use crate::plugin_models::{
+  PactPluginManifest, PactPluginRpc, PluginInitRequest, PluginInitResponse, PluginInstance,
+};
+use crate::proto::*;
+use crate::proto_v2;
+
+/// A running WASM plugin instance. Each instance owns its own engine, store and module instance.
+///
+/// The mutex is `tokio::sync::Mutex` for the same reason as the Lua runtime's (`LuaPactPlugin`):
+/// a call into the module is held across the `.await` of any host function it makes.
+pub struct WasmPactPlugin {
+  runtime: Arc<tokio::sync::Mutex<WasmRuntime>>,
+  manifest: PactPluginManifest,
+  instance_id: String,
+  plugin_capabilities: Vec<String>,
+}
+
+impl std::fmt::Debug for WasmPactPlugin {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("WasmPactPlugin")
+      .field("manifest", &self.manifest)
+      .field("instance_id", &self.instance_id)
+      .field("plugin_capabilities", &self.plugin_capabilities)
+      .finish()
+  }
+}
+
+/// Data the host functions have access to through the store.
+struct WasmPluginState {
+  plugin_name: String,
+  instance_id: String,
+  /// Per-instance log file, the same one a gRPC plugin's stderr is captured to (see
+  /// `child_process::open_plugin_log_file`)
+  log: Option<File>,
+  /// Call chain ID and deadline of the call the module is running, which its callbacks are made
+  /// under
+  call_chain: Option<(String, u64)>,
+}
+
+struct WasmRuntime {
+  store: Store<WasmPluginState>,
+  instance: Instance,
+}
+
+/// Start a WASM plugin: resolve the entry point module, compile it, link the host functions the
+/// plugin can import, and instantiate it.
+pub(crate) async fn start_wasm_plugin(
+  manifest: &PactPluginManifest,
+  instance_id: String,
+) -> anyhow::Result<WasmPactPlugin> {
+  let module_path = resolve_entry_point(manifest)?;
+  debug!("Loading WASM plugin {} from {:?}", manifest.name, module_path);
+
+  let mut config = Config::new();
+  config.async_support(true);
+  let engine = Engine::new(&config)?;
+  let module = Module::from_file(&engine, &module_path)
+    .map_err(|err| anyhow!("Failed to load WASM plugin module {:?} - {}", module_path, err))?;
+
+  let mut linker = Linker::new(&engine);
+  register_host_functions(&mut linker)?;
+
+  let mut store = Store::new(&engine, WasmPluginState {
+    plugin_name: manifest.name.clone(),
+    instance_id: instance_id.clone(),
+    log: crate::child_process::open_plugin_log_file(&manifest.name, &instance_id),
+    call_chain: None,
+  });
+  let instance = linker.instantiate_async(&mut store, &module).await
+    .map_err(|err| anyhow!("Failed to instantiate WASM plugin module {:?} - {}", module_path, err))?;
+
+  Ok(WasmPactPlugin {
+    runtime: Arc::new(tokio::sync::Mutex::new(WasmRuntime { store, instance })),
+    manifest: manifest.clone(),
+    instance_id,
+    plugin_capabilities: vec![],
+  })
+}
+
+impl WasmPactPlugin {
+  /// Set the capabilities negotiated for this plugin instance (called once, after the init
+  /// handshake, before the instance is shared behind an `Arc`).
+  pub(crate) fn set_plugin_capabilities(&mut self, capabilities: Vec<String>) {
+    self.plugin_capabilities = capabilities;
+  }
+
+  /// Call one of the module's operation exports with an encoded request, decoding the response.
+  /// The call starts a new call chain.
+  ///
+  /// The V1 and V2 messages for an operation are wire compatible (the same thing
+  /// `PluginClient::convert_message` relies on), so a request that arrives in its V1 shape is
+  /// encoded as it is.
+  async fn call<Req: Message, Resp: Message + Default>(&self, name: &str, request: &Req) -> anyhow::Result<Resp> {
+    self.call_with_chain(name, request, &call_chain::new_call_chain_id(), call_chain::default_deadline_ms()).await
+  }
+
+  /// Call one of the module's operation exports as part of the given call chain. See
+  /// [`WasmPactPlugin::call`].
+  async fn call_with_chain<Req: Message, Resp: Message + Default>(
+    &self,
+    name: &str,
+    request: &Req,
+    chain_id: &str,
+    deadline_ms: u64
+  ) -> anyhow::Result<Resp> {
+    self.call_if_exported(name, request, chain_id, deadline_ms).await?
+      .ok_or_else(|| anyhow!("WASM plugin does not export a '{}' function", name))
+  }
+
+  /// Call one of the module's operation exports as part of the given call chain, if the module
+  /// exports it. See [`WasmPactPlugin::call`].
+  async fn call_if_exported<Req: Message, Resp: Message + Default>(
+    &self,
+    name: &str,
+    request: &Req,
+    chain_id: &str,
+    deadline_ms: u64
+  ) -> anyhow::Result<Option<Resp>> {
+    // Checked before waiting for the instance, as a call from a callback this instance made would
+    // otherwise wait for itself to finish
+    let _guard = call_chain::push_call(chain_id, &format!("{}/{}", self.manifest.name, self.instance_id))
+      .map_err(|err| anyhow!(err))?;
+    let mut runtime = self.runtime.lock().await;
+    let WasmRuntime { store, instance } = &mut *runtime;
+    if instance.get_func(&mut *store, name).is_none() {
+      return Ok(None);
+    }
+
+    store.data_mut().call_chain = Some((chain_id.to_string(), deadline_ms));
+    let result = runtime.call(name, request.encode_to_vec()).await;
+    runtime.store.data_mut().call_chain = None;
+    let bytes = result?;
+    Resp::decode(bytes.as_slice())
+      .map(Some)
+      .map_err(|err| anyhow!("WASM plugin {}() returned an invalid response - {}", name, err))
+  }
+}
+
+impl WasmRuntime {
+  async fn call(&mut self, name: &str, request: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    let func = self.instance.get_func(&mut self.store, name)
+      .ok_or_else(|| anyhow!("WASM plugin does not export a '{}' function", name))?
+      .typed::<(i32, i32), i64>(&self.store)
+      .map_err(|err| anyhow!("WASM plugin export '{}' does not have the expected signature - {}", name, err))?;
+    let memory = self.instance.get_memory(&mut self.store, "memory")
+      .ok_or_else(|| anyhow!("WASM plugin does not export its memory"))?;
+    let alloc = self.instance.get_typed_func::<i32, i32>(&mut self.store, "pact_alloc")
+      .map_err(|err| anyhow!("WASM plugin does not export a usable 'pact_alloc' function - {}", err))?;
+
+    let (ptr, len) = write_to_guest(&mut self.store, &alloc, &memory, &request).await?;
+    let packed = func.call_async(&mut self.store, (ptr, len)).await
+      .map_err(|err| anyhow!("WASM plugin {}() function failed - {}", name, err))?;
+    let (response_ptr, response_len) = unpack(packed);
+    let response = read_from_guest(&mut self.store, &memory, response_ptr, response_len)?;
+
+    if let Ok(free) = self.instance.get_typed_func::<(i32, i32), ()>(&mut self.store, "pact_free") {
+      free.call_async(&mut self.store, (response_ptr, response_len)).await
+        .map_err(|err| anyhow!("WASM plugin pact_free() function failed - {}", err))?;
+    }
+    Ok(response)
+  }
+}
+
+fn resolve_entry_point(manifest: &PactPluginManifest) -> anyhow::Result<PathBuf> {
+  let entry_point = PathBuf::from(&manifest.entry_point);
+  let path = if entry_point.is_absolute() && entry_point.exists() {
+    entry_point
+  } else {
+    PathBuf::from(&manifest.plugin_dir).join(&manifest.entry_point)
+  };
+  if !path.exists() {
+    return Err(anyhow!("WASM plugin entry point {:?} does not exist", path));
+  }
+  Ok(path)
+}
+
+fn pack(ptr: i32, len: i32) -> i64 {
+  (((ptr as u32 as u64) << 32) | (len as u32 as u64)) as i64
+}
+
+fn unpack(packed: i64) -> (i32, i32) {
+  let packed = packed as u64;
+  ((packed >> 32) as u32 as i32, packed as u32 as i32)
+}
+
+fn read_from_guest(
+  store: impl AsContextMut,
+  memory: &Memory,
+  ptr: i32,
+  len: i32
+) -> anyhow::Result<Vec<u8>> {
+  let mut buffer = vec![0; len as u32 as usize];
+  memory.read(store, ptr as u32 as usize, &mut buffer)
+    .map_err(|_| anyhow!("WASM plugin returned a buffer ({} bytes at {}) outside its memory", len, ptr))?;
+  Ok(buffer)
+}
+
+async fn write_to_guest(
+  mut store: impl AsContextMut<Data = WasmPluginState>,
+  alloc: &TypedFunc<i32, i32>,
+  memory: &Memory,
+  bytes: &[u8]
+) -> anyhow::Result<(i32, i32)> {
+  let len = i32::try_from(bytes.len())
+    .map_err(|_| anyhow!("Message of {} bytes is too large to pass to a WASM plugin", bytes.len()))?;
+  let ptr = alloc.call_async(&mut store, len).await
+    .map_err(|err| anyhow!("WASM plugin pact_alloc() function failed - {}", err))?;
+  memory.write(&mut store, ptr as u32 as usize, bytes)
+    .map_err(|_| anyhow!("WASM plugin pact_alloc() returned a buffer outside its memory"))?;
+  Ok((ptr, len))
+}
+
+/// The memory and allocator of the module making a host function call.
+fn caller_exports(caller: &mut Caller<'_, WasmPluginState>) -> anyhow::Result<(Memory, TypedFunc<i32, i32>)> {
+  let memory = caller.get_export("memory")
+    .and_then(Extern::into_memory)
+    .ok_or_else(|| anyhow!("WASM plugin does not export its memory"))?;
+  let alloc = caller.get_export("pact_alloc")
+    .and_then(Extern::into_func)
+    .ok_or_else(|| anyhow!("WASM plugin does not export a 'pact_alloc' function"))?
+    .typed::<i32, i32>(&caller)?;
+  Ok((memory, alloc))
+}
+
+/// Read the request a host function was called with out of the caller's memory.
+fn read_request<T: Message + Default>(
+  caller: &mut Caller<'_, WasmPluginState>,
+  name: &str,
+  ptr: i32,
+  len: i32
+) -> anyhow::Result<T> {
+  let (memory, _) = caller_exports(caller)?;
+  let bytes = read_from_guest(caller, &memory, ptr, len)?;
+  T::decode(bytes.as_slice()).map_err(|err| anyhow!("Invalid request passed to {}() - {}", name, err))
+}
+
+/// Hand a host function's response back to the caller, in a buffer allocated with its own
+/// `pact_alloc`.
+async fn write_response<T: Message>(caller: &mut Caller<'_, WasmPluginState>, response: &T) -> anyhow::Result<i64> {
+  let (memory, alloc) = caller_exports(caller)?;
+  let (ptr, len) = write_to_guest(caller, &alloc, &memory, &response.encode_to_vec()).await?;
+  Ok(pack(ptr, len))
+}
+
+fn chain_ref((chain_id, deadline_ms): &(String, u64)) -> (&str, u64) {
+  (chain_id.as_str(), *deadline_ms)
+}
+
+fn to_anyhow(status: tonic::Status) -> anyhow::Error {
+  anyhow!(status.message().to_string())
+}
+
+/// Links the host functions a WASM plugin can import from the `pact` module: a logger, and the
+/// `PluginHost` callbacks, which go through the same dispatch as the Lua host functions (see
+/// [`crate::host_callbacks`]).
+fn register_host_functions(linker: &mut Linker<WasmPluginState>) -> anyhow::Result<()> {
+  linker.func_wrap("pact", "log", |mut caller: Caller<'_, WasmPluginState>, ptr: i32, len: i32| -> anyhow::Result<()> {
+    let message: proto_v2::LogMessage = read_request(&mut caller, "log", ptr, len)?;
+    let state = caller.data_mut();
+    debug!(plugin = state.plugin_name.as_str(), "{}", message.message);
+    if let Some(file) = state.log.as_mut() {
+      let _ = writeln!(file, "{} {}", message.level, message.message);
+      let _ = file.flush();
+    }
+    emit_plugin_log(&PluginLogEntry {
+      plugin_name: state.plugin_name.clone(),
+      plugin_instance_id: state.instance_id.clone(),
+      test_run_id: if message.test_run_id.is_empty() { None } else { Some(message.test_run_id) },
+      level: message.level,
+      message: message.message,
+      target: if message.target.is_empty() { None } else { Some(message.target) },
+      timestamp_ms: message.timestamp_ms,
+      source: PluginLogSource::LogRpc,
+    });
+    Ok(())
+  })?;
+
+  linker.func_wrap_async("pact", "host_compare_contents",
+    |mut caller: Caller<'_, WasmPluginState>, (ptr, len): (i32, i32)| Box::new(async move {
+      let request: proto_v2::HostCompareContentsRequest = read_request(&mut caller, "host_compare_contents", ptr, len)?;
+      let chain = caller.data().call_chain.clone();
+      let response = match request.request {
+        Some(inner) => match PluginClient::convert_message(inner) {
+          Ok(inner) => call_host_compare_contents(&request.entry_key, inner, chain.as_ref().map(chain_ref)).await,
+          Err(status) => Err(to_anyhow(status))
+        },
+        None => Err(anyhow!("HostCompareContentsRequest.request is required"))
+      }.unwrap_or_else(|err| CompareContentsResponse { error: err.to_string(), .. CompareContentsResponse::default() });
+      write_response(&mut caller, &response).await
+    }))?;
+
+  linker.func_wrap_async("pact", "host_generate_content",
+    |mut caller: Caller<'_, WasmPluginState>, (ptr, len): (i32, i32)| Box::new(async move {
+      let request: proto_v2::HostGenerateContentRequest = read_request(&mut caller, "host_generate_content", ptr, len)?;
+      let chain = caller.data().call_chain.clone();
+      let inner = request.request
+        .ok_or_else(|| anyhow!("HostGenerateContentRequest.request is required"))?;
+      let inner = PluginClient::convert_message(inner).map_err(to_anyhow)?;
+      let response = call_host_generate_content(&request.entry_key, inner, chain.as_ref().map(chain_ref)).await?;
+      write_response(&mut caller, &response).await
+    }))?;
+
+  linker.func_wrap_async("pact", "host_match_field",
+    |mut caller: Caller<'_, WasmPluginState>, (ptr, len): (i32, i32)| Box::new(async move {
+      let request: proto_v2::HostMatchFieldRequest = read_request(&mut caller, "host_match_field", ptr, len)?;
+      let chain = caller.data().call_chain.clone();
+      let response = match request.request {
+        Some(inner) => call_host_match_field(&request.entry_key, inner, chain.as_ref().map(chain_ref)).await,
+        None => Err(anyhow!("HostMatchFieldRequest.request is required"))
+      }.unwrap_or_else(|err| proto_v2::MatchFieldResponse { error: err.to_string(), .. proto_v2::MatchFieldResponse::default() });
+      write_response(&mut caller, &response).await
+    }))?;
+
+  linker.func_wrap_async("pact", "host_generate_field",
+    |mut caller: Caller<'_, WasmPluginState>, (ptr, len): (i32, i32)| Box::new(async move {
+      let request: proto_v2::HostGenerateFieldRequest = read_request(&mut caller, "host_generate_field", ptr, len)?;
+      let chain = caller.data().call_chain.clone();
+      let response = match request.request {
+        Some(inner) => call_host_generate_field(&request.entry_key, inner, chain.as_ref().map(chain_ref)).await,
+        None => Err(anyhow!("HostGenerateFieldRequest.request is required"))
+      }.unwrap_or_else(|err| proto_v2::GenerateFieldResponse { error: err.to_string(), .. proto_v2::GenerateFieldResponse::default() });
+      write_response(&mut caller, &response).await
+    }))?;
+
+  Ok(())
+}
+
+#[async_trait]
+impl PactPluginRpc for WasmPactPlugin {
+  async fn init_plugin(&mut self, request: PluginInitRequest) -> anyhow::Result<PluginInitResponse> {
+    let response: proto_v2::InitPluginResponse = self.call("init_plugin", &proto_v2::InitPluginRequest {
+      implementation: request.implementation,
+      version: request.version,
+      host_capabilities: request.host_capabilities,
+      plugin_instance_id: request.plugin_instance_id,
+    }).await?;
+    match response.response {
+      Some(proto_v2::init_plugin_response::Response::Success(success)) => Ok(PluginInitResponse {
+        catalogue: success.catalogue
+          .into_iter()
+          .map(PluginClient::convert_message)
+          .collect::<Result<Vec<CatalogueEntry>, _>>()
+          .map_err(to_anyhow)?,
+        plugin_capabilities: success.plugin_capabilities,
+      }),
+      Some(proto_v2::init_plugin_response::Response::Failure(failure)) => {
+        let mut error = failure.error;
+        if !failure.missing_host_capabilities.is_empty() {
+          error.push_str(" (missing host capabilities: ");
+          error.push_str(failure.missing_host_capabilities.join(", ").as_str());
+          error.push(')');
+        }
+        Err(anyhow!(error))
+      }
+      None => Err(anyhow!("WASM plugin returned an invalid InitPlugin response")),
+    }
+  }
+}
+
+#[async_trait]
+impl PluginInstance for WasmPactPlugin {
+  fn manifest(&self) -> &PactPluginManifest {
+    &self.manifest
+  }
+
+  fn instance_id(&self) -> &str {
+    &self.instance_id
+  }
+
+  fn has_capability(&self, capability: &str) -> bool {
+    self.plugin_capabilities.iter().any(|c| c == capability)
+  }
+
+  async fn compare_contents(
+    &self,
+    request: CompareContentsRequest,
+  ) -> anyhow::Result<CompareContentsResponse> {
+    self.call("compare_contents", &request).await
+  }
+
+  async fn compare_contents_with_chain(
+    &self,
+    request: CompareContentsRequest,
+    chain_id: &str,
+    deadline_ms: u64,
+  ) -> anyhow::Result<CompareContentsResponse> {
+    self.call_with_chain("compare_contents", &request, chain_id, deadline_ms).await
+  }
+
+  async fn configure_interaction(
+    &self,
+    request: ConfigureInteractionRequest,
+  ) -> anyhow::Result<ConfigureInteractionResponse> {
+    self.call("configure_interaction", &request).await
+  }
+
+  async fn generate_content(
+    &self,
+    request: GenerateContentRequest,
+  ) -> anyhow::Result<GenerateContentResponse> {
+    self.generate_content_with_chain(request, &call_chain::new_call_chain_id(), call_chain::default_deadline_ms()).await
+  }
+
+  async fn generate_content_with_chain(
+    &self,
+    request: GenerateContentRequest,
+    chain_id: &str,
+    deadline_ms: u64,
+  ) -> anyhow::Result<GenerateContentResponse> {
+    let response = self.call_if_exported("generate_content", &request, chain_id, deadline_ms).await?;
+    Ok(response.unwrap_or(GenerateContentResponse {
+      contents: request.contents,
+    }))
+  }
+
+  async fn match_field(
+    &self,
+    request: proto_v2::MatchFieldRequest,
+  ) -> anyhow::Result<proto_v2::MatchFieldResponse> {
+    self.call("match_field", &request).await
+  }
+
+  async fn match_field_with_chain(
+    &self,
+    request: proto_v2::MatchFieldRequest,
+    chain_id: &str,
+    deadline_ms: u64,
+  ) -> anyhow::Result<proto_v2::MatchFieldResponse> {
+    self.call_with_chain("match_field", &request, chain_id, deadline_ms).await
+  }
+
+  async fn generate_field(
+    &self,
+    request: proto_v2::GenerateFieldRequest,
+  ) -> anyhow::Result<proto_v2::GenerateFieldResponse> {
+    self.call("generate_field", &request).await
+  }
+
+  async fn generate_field_with_chain(
+    &self,
+    request: proto_v2::GenerateFieldRequest,
+    chain_id: &str,
+    deadline_ms: u64,
+  ) -> anyhow::Result<proto_v2::GenerateFieldResponse> {
+    self.call_with_chain("generate_field", &request, chain_id, deadline_ms).await
+  }
+
+  async fn start_mock_server(
+    &self,
+    request: StartMockServerRequest,
+  ) -> anyhow::Result<StartMockServerResponse> {
+    self.call("start_mock_server", &request).await
+  }
+
+  async fn start_mock_server_v2(
+    &self,
+    request: proto_v2::StartMockServerRequest,
+  ) -> anyhow::Result<StartMockServerResponse> {
+    self.call("start_mock_server", &request).await
+  }
+
+  async fn shutdown_mock_server(
+    &self,
+    request: ShutdownMockServerRequest,
+  ) -> anyhow::Result<ShutdownMockServerResponse> {
+    self.call("shutdown_mock_server", &request).await
+  }
+
+  async fn get_mock_server_results(
+    &self,
+    request: MockServerRequest,
+  ) -> anyhow::Result<MockServerResults> {
+    self.call("get_mock_server_results", &request).await
+  }
+
+  async fn prepare_interaction_for_verification(
+    &self,
+    request: VerificationPreparationRequest,
+  ) -> anyhow::Result<VerificationPreparationResponse> {
+    self.call("prepare_interaction_for_verification", &request).await
+  }
+
+  async fn prepare_interaction_for_verification_v2(
+    &self,
+    request: proto_v2::VerificationPreparationRequest,
+  ) -> anyhow::Result<VerificationPreparationResponse> {
+    self.call("prepare_interaction_for_verification", &request).await
+  }
+
+  async fn verify_interaction(
+    &self,
+    request: VerifyInteractionRequest,
+  ) -> anyhow::Result<VerifyInteractionResponse> {
+    self.call("verify_interaction", &request).await
+  }
+
+  async fn verify_interaction_v2(
+    &self,
+    request: proto_v2::VerifyInteractionRequest,
+  ) -> anyhow::Result<VerifyInteractionResponse> {
+    self.call("verify_interaction", &request).await
+  }
+
+  async fn update_catalogue(&self, request: Catalogue) -> anyhow::Result<()> {
+    self.call_if_exported::<_, ()>("update_catalogue", &request, &call_chain::new_call_chain_id(),
+      call_chain::default_deadline_ms()).await?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+
+  use expectest::prelude::*;
+
+  use crate::catalogue_manager::{
+    CatalogueEntryProviderType, CatalogueEntryType, register_core_entries, register_plugin_entries, remove_plugin_entries,
+  };
+  use crate::child_process::{ChildPluginProcess, RunningPluginInfo};
+  use crate::core_capabilities::{CoreContentMatcher, register_core_content_matcher};
+  use crate::driver::PluginDriver;
+  use crate::plugin_manager::RegisteredPlugin;
+  use crate::plugin_models::PactPlugin;
+
+  use super::*;
+
+  /// Builds the text of a test module: a bump allocator, the host function imports, and exports
+  /// that answer with responses encoded up front and placed in the module's data segment.
+  #[derive(Default)]
+  struct TestModule {
+    data: Vec<u8>,
+    functions: Vec<String>,
+  }
+
+  const DATA_OFFSET: usize = 1024;
+
+  impl TestModule {
+    /// Place the bytes in the module's memory, returning where they are
+    fn data(&mut self, bytes: &[u8]) -> (i32, i32) {
+      let offset = DATA_OFFSET + self.data.len();
+      self.data.extend_from_slice(bytes);
+      (offset as i32, bytes.len() as i32)
+    }
+
+    /// Export an operation that always answers with the given message
+    fn returning<T: Message>(mut self, name: &str, response: &T) -> Self {
+      let (ptr, len) = self.data(&response.encode_to_vec());
+      self.functions.push(format!(
+        r#"(func (export "{}") (param i32 i32) (result i64) (i64.const {}))"#, name, pack(ptr, len)));
+      self
+    }
+
+    /// Export an operation that answers with whatever the host function returns for the given
+    /// request
+    fn calling<T: Message>(mut self, name: &str, host_function: &str, request: &T) -> Self {
+      let (ptr, len) = self.data(&request.encode_to_vec());
+      self.functions.push(format!(
+        r#"(func (export "{}") (param i32 i32) (result i64) (call ${} (i32.const {}) (i32.const {})))"#,
+        name, host_function, ptr, len));
+      self
+    }
+
+    fn function(mut self, function: &str) -> Self {
+      self.functions.push(function.to_string());
+      self
+    }
+
+    fn wat(&self) -> String {
+      let data: String = self.data.iter().map(|byte| format!("\\{:02x}", byte)).collect();
+      format!(r#"(module
+        (import "pact" "log" (func $log (param i32 i32)))
+        (import "pact" "host_compare_contents" (func $host_compare_contents (param i32 i32) (result i64)))
+        (import "pact" "host_match_field" (func $host_match_field (param i32 i32) (result i64)))
+        (memory (export "memory") 1)
+        (global $heap (mut i32) (i32.const 32768))
+        (func $pact_alloc (export "pact_alloc") (param $len i32) (result i32)
+          (global.get $heap)
+          (global.set $heap (i32.add (global.get $heap) (local.get $len))))
+        (data (i32.const {}) "{}")
+        {})"#, DATA_OFFSET, data, self.functions.join("\n"))
+    }
+  }
+
+  fn wasm_manifest(plugin_dir: &std::path::Path, name: &str) -> PactPluginManifest {
+    PactPluginManifest {
+      plugin_dir: plugin_dir.to_string_lossy().to_string(),
+      plugin_interface_version: 2,
+      name: name.to_string(),
+      version: "0.0.0".to_string(),
+      executable_type: "wasm".to_string(),
+      minimum_required_version: None,
+      entry_point: "plugin.wat".to_string(),
+      entry_points: HashMap::new(),
+      args: None,
+      dependencies: None,
+      plugin_config: HashMap::new(),
+    }
+  }
+
+  async fn start_test_plugin(name: &str, module: &TestModule) -> (tempdir::TempDir, WasmPactPlugin) {
+    let plugin_dir = tempdir::TempDir::new("wasm-plugin-test").unwrap();
+    std::fs::write(plugin_dir.path().join("plugin.wat"), module.wat()).unwrap();
+    let manifest = wasm_manifest(plugin_dir.path(), name);
+    let plugin = start_wasm_plugin(&manifest, "test-instance".to_string()).await.unwrap();
+    // The temp dir is returned so it outlives the plugin
+    (plugin_dir, plugin)
+  }
+
+  fn init_request() -> PluginInitRequest {
+    PluginInitRequest {
+      implementation: "plugin-driver-rust".to_string(),
+      version: "0.0.0".to_string(),
+      host_capabilities: vec![],
+      plugin_instance_id: "test-instance".to_string(),
+    }
+  }
+
+  #[tokio::test]
+  async fn init_returns_the_catalogue_and_capabilities_the_module_declares() {
+    let mut module = TestModule::default();
+    let (log_ptr, log_len) = module.data(&proto_v2::LogMessage {
+      level: "INFO".to_string(),
+      message: "Initialising".to_string(),
+      .. proto_v2::LogMessage::default()
+    }.encode_to_vec());
+    let (response_ptr, response_len) = module.data(&proto_v2::InitPluginResponse {
+      response: Some(proto_v2::init_plugin_response::Response::Success(proto_v2::InitPluginSuccess {
+        catalogue: vec![proto_v2::CatalogueEntry {
+          r#type: proto_v2::catalogue_entry::EntryType::ContentMatcher as i32,
+          key: "test".to_string(),
+          values: [("content-types".to_string(), "application/test".to_string())].into(),
+        }],
+        plugin_capabilities: vec!["interaction/synchronous-messages".to_string()],
+      })),
+    }.encode_to_vec());
+    let module = module.function(&format!(
+      r#"(func (export "init_plugin") (param i32 i32) (result i64)
+        (call $log (i32.const {}) (i32.const {}))
+        (i64.const {}))"#, log_ptr, log_len, pack(response_ptr, response_len)));
+
+    let (_dir, mut plugin) = start_test_plugin("wasm-init-test", &module).await;
+    let response = plugin.init_plugin(init_request()).await.unwrap();
+
+    expect!(response.catalogue.len()).to(be_equal_to(1));
+    expect!(response.catalogue[0].key.as_str()).to(be_equal_to("test"));
+    expect!(response.catalogue[0].r#type).to(be_equal_to(CatalogueEntryType::CONTENT_MATCHER.to_proto_value()));
+    expect!(response.catalogue[0].values.get("content-types").cloned())
+      .to(be_some().value("application/test".to_string()));
+    expect!(response.plugin_capabilities).to(be_equal_to(vec!["interaction/synchronous-messages".to_string()]));
+  }
+
+  #[tokio::test]
+  async fn init_reports_the_failure_the_module_returns() {
+    let module = TestModule::default().returning("init_plugin", &proto_v2::InitPluginResponse {
+      response: Some(proto_v2::init_plugin_response::Response::Failure(proto_v2::InitPluginFailure {
+        error: "Host is too old".to_string(),
+        missing_host_capabilities: vec!["matcher/type".to_string()],
+      })),
+    });
+
+    let (_dir, mut plugin) = start_test_plugin("wasm-init-failure-test", &module).await;
+    let err = plugin.init_plugin(init_request()).await.unwrap_err();
+
+    expect!(err.to_string()).to(be_equal_to("Host is too old (missing host capabilities: matcher/type)".to_string()));
+  }
+
+  #[tokio::test]
+  async fn a_request_reaches_the_module_intact() {
+    // Echoes the request back as the response's `error` string (field 1), which is enough to
+    // prove the bytes the module received are the ones the driver encoded
+    let module = TestModule::default().function(
+      r#"(func (export "match_field") (param $ptr i32) (param $len i32) (result i64)
+        (local $out i32)
+        (local.set $out (call $pact_alloc (i32.add (local.get $len) (i32.const 2))))
+        (i32.store8 (local.get $out) (i32.const 0x0a))
+        (i32.store8 (i32.add (local.get $out) (i32.const 1)) (local.get $len))
+        (memory.copy (i32.add (local.get $out) (i32.const 2)) (local.get $ptr) (local.get $len))
+        (i64.or
+          (i64.shl (i64.extend_i32_u (local.get $out)) (i64.const 32))
+          (i64.extend_i32_u (i32.add (local.get $len) (i32.const 2)))))"#);
+    let request = proto_v2::MatchFieldRequest {
+      key: "creditcard".to_string(),
+      path: "$.card.number".to_string(),
+      mismatch_type: "body".to_string(),
+      .. proto_v2::MatchFieldRequest::default()
+    };
+
+    let (_dir, plugin) = start_test_plugin("wasm-request-test", &module).await;
+    let response = plugin.match_field(request.clone()).await.unwrap();
+
+    expect!(response.error.into_bytes()).to(be_equal_to(request.encode_to_vec()));
+  }
+
+  #[tokio::test]
+  async fn an_operation_the_module_does_not_export_is_reported() {
+    let (_dir, plugin) = start_test_plugin("wasm-missing-export-test", &TestModule::default()).await;
+    let err = plugin.verify_interaction(VerifyInteractionRequest::default()).await.unwrap_err();
+
+    expect!(err.to_string()).to(be_equal_to("WASM plugin does not export a 'verify_interaction' function".to_string()));
+  }
+
+  #[tokio::test]
+  async fn optional_operations_can_be_left_out() {
+    let (_dir, plugin) = start_test_plugin("wasm-optional-export-test", &TestModule::default()).await;
+    let contents = Body {
+      content_type: "application/test".to_string(),
+      content: Some(b"1234".to_vec()),
+      content_type_hint: 0,
+    };
+
+    let response = plugin.generate_content(GenerateContentRequest {
+      contents: Some(contents.clone()),
+      .. GenerateContentRequest::default()
+    }).await.unwrap();
+
+    expect!(response.contents).to(be_some().value(contents));
+    expect!(plugin.update_catalogue(Catalogue::default()).await).to(be_ok());
+  }
+
+  #[tokio::test]
+  async fn a_module_that_does_not_exist_is_reported() {
+    let plugin_dir = tempdir::TempDir::new("wasm-plugin-test").unwrap();
+    let manifest = wasm_manifest(plugin_dir.path(), "wasm-no-module-test");
+
+    let err = start_wasm_plugin(&manifest, "test-instance".to_string()).await.unwrap_err();
+
+    expect!(err.to_string().contains("does not exist")).to(be_true());
+  }
+
+  #[derive(Debug)]
+  struct FixedErrorCoreMatcher;
+
+  #[async_trait]
+  impl CoreContentMatcher for FixedErrorCoreMatcher {
+    async fn compare_contents(&self, _request: CompareContentsRequest) -> anyhow::Result<CompareContentsResponse> {
+      Ok(CompareContentsResponse {
+        error: "core matcher says no".to_string(),
+        .. CompareContentsResponse::default()
+      })
+    }
+  }
+
+  #[tokio::test]
+  async fn host_compare_contents_dispatches_to_a_registered_core_capability() {
+    let key = "wasm_host_compare_contents_dispatches_to_a_registered_core_capability";
+    register_core_entries(&vec![crate::catalogue_manager::CatalogueEntry {
+      entry_type: CatalogueEntryType::CONTENT_MATCHER,
+      provider_type: CatalogueEntryProviderType::CORE,
+      plugin: None,
+      key: key.to_string(),
+      values: HashMap::new()
+    }]);
+    register_core_content_matcher(key, Arc::new(FixedErrorCoreMatcher));
+    let module = TestModule::default().calling("compare_contents", "host_compare_contents",
+      &proto_v2::HostCompareContentsRequest {
+        entry_key: key.to_string(),
+        request: Some(proto_v2::CompareContentsRequest::default()),
+      });
+
+    let (_dir, plugin) = start_test_plugin("wasm-host-compare-test", &module).await;
+    let response = plugin.compare_contents(CompareContentsRequest::default()).await.unwrap();
+
+    expect!(response.error.as_str()).to(be_equal_to("core matcher says no"));
+  }
+
+  #[tokio::test]
+  #[allow(deprecated)]
+  async fn a_module_that_calls_back_into_itself_gets_an_error() {
+    let name = "wasm-self-callback-test";
+    let module = TestModule::default().calling("match_field", "host_match_field",
+      &proto_v2::HostMatchFieldRequest {
+        entry_key: name.to_string(),
+        request: Some(proto_v2::MatchFieldRequest::default()),
+      });
+    let (_dir, plugin) = start_test_plugin(name, &module).await;
+    let manifest = plugin.manifest.clone();
+    register_plugin_entries(&manifest, &vec![CatalogueEntry {
+      r#type: CatalogueEntryType::MATCHER.to_proto_value(),
+      key: name.to_string(),
+      values: HashMap::new(),
+    }]);
+    let child = ChildPluginProcess {
+      child_pid: 0,
+      plugin_info: RunningPluginInfo { port: 0, server_key: String::new() },
+      instance_id: "test-instance".to_string(),
+    };
+    let plugin: Arc<dyn PluginInstance + Send + Sync> = Arc::new(plugin);
+    let key = format!("{}/{}", manifest.name, manifest.version);
+    PluginDriver::current().inner.plugin_register.lock().unwrap()
+      .insert(key.clone(), RegisteredPlugin::new(plugin.clone(), PactPlugin::new(&manifest, child).unwrap()));
+
+    let response = tokio::time::timeout(std::time::Duration::from_secs(10),
+      plugin.match_field(proto_v2::MatchFieldRequest::default())).await;
+
+    PluginDriver::current().inner.plugin_register.lock().unwrap().remove(&key);
+    remove_plugin_entries(name);
+    let response = response.expect("the call back into the module should not wait for itself").unwrap();
+    expect!(response.error.contains("Cycle detected")).to(be_true());
+  }
+
+  #[tokio::test]
+  async fn a_host_callback_that_cannot_be_resolved_comes_back_as_an_error() {
+    let key = "wasm_a_host_callback_that_cannot_be_resolved_comes_back_as_an_error";
+    let module = TestModule::default().calling("match_field", "host_match_field",
+      &proto_v2::HostMatchFieldRequest {
+        entry_key: key.to_string(),
+        request: Some(proto_v2::MatchFieldRequest::default()),
+      });
+
+    let (_dir, plugin) = start_test_plugin("wasm-host-unresolved-test", &module).await;
+    let response = plugin.match_field(proto_v2::MatchFieldRequest::default()).await.unwrap();
+
+    expect!(response.error.contains(key)).to(be_true());
+  }
+}